@@ -49,7 +49,8 @@ fn real_main() -> Result<()> {
     let mapping_root = rclib::mapping::parse_mapping_root(&mapping_yaml)?;
 
     // Build CLI
-    let (app, _) = rclib::cli::build_cli(&mapping_root, &default_base_url);
+    let (app, _) =
+        rclib::cli::build_cli_with_openapi(&mapping_root, &default_base_url, Some(&openapi));
     let matches = app.get_matches();
 
     // Global options are handled by rclib::cli::drive_command
@@ -68,14 +69,23 @@ fn real_main() -> Result<()> {
     // Validate mapping vs handlers
     rclib::cli::validate_handlers(&mapping_root, &reg)?;
 
+    // Ctrl-C stops an event stream cleanly; anywhere else it exits as usual
+    ctrlc::set_handler(|| {
+        if !rclib::sse::interrupt() {
+            std::process::exit(130);
+        }
+    })
+    .context("Failed to install the Ctrl-C handler")?;
+
     // Delegate command driving to rclib
     let user_agent = format!("{}/{}", APP_NAME, env!("CARGO_PKG_VERSION"));
-    let exit_code = rclib::cli::drive_command(
+    let exit_code = rclib::cli::drive_command_with_openapi(
         &mapping_root,
         &default_base_url,
         &matches,
         &reg,
         &user_agent,
+        Some(&openapi),
     )?;
     std::process::exit(exit_code);
 }
@@ -97,6 +107,10 @@ fn handle_export_users(
         .unwrap_or(false);
     let limit = vars.get("limit").map(|s| s.as_str()).unwrap_or("100");
     let skip = vars.get("skip").map(|s| s.as_str()).unwrap_or("0");
+    rclib::journal::note(
+        "export_users",
+        serde_json::json!({ "format": format, "output_file": output_file, "limit": limit }),
+    );
 
     if json_output {
         let response = serde_json::json!({
@@ -0,0 +1,1826 @@
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, Instant};
+
+use anyhow::{bail, Context, Result};
+use openapiv3::{
+    APIKeyLocation, OAuth2Flow, OpenAPI, ReferenceOr, SecurityRequirement, SecurityScheme,
+};
+use reqwest::blocking::{Client, RequestBuilder};
+use serde::{Deserialize, Serialize};
+
+use crate::journal::{fetch, Fetched};
+use crate::mapping::AuthSettings;
+
+// =====================
+// Schemes and credentials
+// =====================
+
+/// Where an API key is sent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApiKeyIn {
+    Header,
+    Query,
+    Cookie,
+}
+
+/// A security scheme rclib knows how to apply, converted from `components.securitySchemes`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AuthScheme {
+    /// `type: http, scheme: bearer`
+    Bearer,
+    /// `type: http, scheme: basic`
+    Basic,
+    /// `type: apiKey`
+    ApiKey { location: ApiKeyIn, name: String },
+    /// `type: oauth2` with a token endpoint
+    OAuth2(OAuth2Endpoints),
+}
+
+/// Token endpoint of a `type: oauth2` scheme, taken from its flows.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OAuth2Endpoints {
+    pub token_url: String,
+    /// Where refresh tokens are exchanged, when it differs from `token_url`
+    pub refresh_url: Option<String>,
+    /// Whether the scheme declares the client-credentials flow
+    pub client_credentials: bool,
+}
+
+/// Credentials given by the user. The credentials file maps scheme names (or `default`)
+/// to this structure.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Credentials {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,
+    /// OAuth2 client id
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+    /// OAuth2 client secret, for the client-credentials flow
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_secret: Option<String>,
+}
+
+impl Credentials {
+    /// Fill fields missing here from `other`.
+    fn or(self, other: &Credentials) -> Credentials {
+        Credentials {
+            token: self.token.or_else(|| other.token.clone()),
+            username: self.username.or_else(|| other.username.clone()),
+            password: self.password.or_else(|| other.password.clone()),
+            api_key: self.api_key.or_else(|| other.api_key.clone()),
+            client_id: self.client_id.or_else(|| other.client_id.clone()),
+            client_secret: self.client_secret.or_else(|| other.client_secret.clone()),
+        }
+    }
+}
+
+/// A credential applied to one request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RequestAuth {
+    Bearer(String),
+    Basic {
+        username: String,
+        password: Option<String>,
+    },
+    ApiKey {
+        location: ApiKeyIn,
+        name: String,
+        value: String,
+    },
+    /// A cached OAuth2 token, renewed when it expires or is rejected
+    OAuth2(Box<OAuth2Session>),
+}
+
+impl RequestAuth {
+    /// Add this credential to a request.
+    pub fn apply(&self, req: RequestBuilder) -> RequestBuilder {
+        match self {
+            RequestAuth::Bearer(token) => req.bearer_auth(token),
+            RequestAuth::Basic { username, password } => {
+                req.basic_auth(username, password.as_ref())
+            }
+            RequestAuth::ApiKey {
+                location: ApiKeyIn::Header,
+                name,
+                value,
+            } => req.header(name.as_str(), value.as_str()),
+            RequestAuth::ApiKey {
+                location: ApiKeyIn::Query,
+                name,
+                value,
+            } => req.query(&[(name, value)]),
+            RequestAuth::ApiKey {
+                location: ApiKeyIn::Cookie,
+                name,
+                value,
+            } => req.header("Cookie", format!("{}={}", name, value)),
+            RequestAuth::OAuth2(session) => match &session.token {
+                Some(token) => req.bearer_auth(&token.access_token),
+                None => req,
+            },
+        }
+    }
+
+    /// Name of the header or query parameter carrying an API key, which the journal and
+    /// dry runs mask whatever it is called.
+    pub fn secret_name(&self) -> Option<&str> {
+        match self {
+            RequestAuth::ApiKey {
+                location: ApiKeyIn::Header | ApiKeyIn::Query,
+                name,
+                ..
+            } => Some(name),
+            _ => None,
+        }
+    }
+
+    /// One-line description with the secret masked, for `--verbose`.
+    pub fn describe(&self) -> String {
+        match self {
+            RequestAuth::Bearer(_) => "Authorization: Bearer ****".to_string(),
+            RequestAuth::Basic { username, .. } => {
+                format!("Authorization: Basic ({}:****)", username)
+            }
+            RequestAuth::ApiKey { location, name, .. } => match location {
+                ApiKeyIn::Header => format!("{}: ****", name),
+                ApiKeyIn::Query => format!("query {}=****", name),
+                ApiKeyIn::Cookie => format!("Cookie: {}=****", name),
+            },
+            RequestAuth::OAuth2(session) => {
+                format!("Authorization: Bearer **** (OAuth2 '{}')", session.scheme)
+            }
+        }
+    }
+}
+
+/// Names of the headers and query parameters carrying API keys among `auth`.
+pub fn secret_names(auth: &[RequestAuth]) -> Vec<&str> {
+    auth.iter().filter_map(RequestAuth::secret_name).collect()
+}
+
+/// Add every credential to a request.
+pub fn apply_auth(mut req: RequestBuilder, auth: &[RequestAuth]) -> RequestBuilder {
+    for a in auth {
+        req = a.apply(req);
+    }
+    req
+}
+
+impl AuthScheme {
+    /// Convert an OpenAPI scheme; `None` for kinds rclib cannot apply.
+    pub fn from_openapi(scheme: &SecurityScheme) -> Option<AuthScheme> {
+        match scheme {
+            SecurityScheme::HTTP { scheme, .. } if scheme.eq_ignore_ascii_case("bearer") => {
+                Some(AuthScheme::Bearer)
+            }
+            SecurityScheme::HTTP { scheme, .. } if scheme.eq_ignore_ascii_case("basic") => {
+                Some(AuthScheme::Basic)
+            }
+            SecurityScheme::APIKey { location, name, .. } => Some(AuthScheme::ApiKey {
+                location: match location {
+                    APIKeyLocation::Header => ApiKeyIn::Header,
+                    APIKeyLocation::Query => ApiKeyIn::Query,
+                    APIKeyLocation::Cookie => ApiKeyIn::Cookie,
+                },
+                name: name.clone(),
+            }),
+            SecurityScheme::OAuth2 { flows, .. } => {
+                // The flows deserialize into whichever field comes first, so look at the
+                // variants rather than the field names
+                let flows: Vec<&OAuth2Flow> = [
+                    &flows.implicit,
+                    &flows.password,
+                    &flows.client_credentials,
+                    &flows.authorization_code,
+                ]
+                .into_iter()
+                .flatten()
+                .collect();
+                let client_credentials = flows
+                    .iter()
+                    .any(|f| matches!(f, OAuth2Flow::ClientCredentials { .. }));
+                // Prefer the client-credentials endpoint; implicit flows have none
+                let mut with_token = flows.iter().filter_map(|f| match f {
+                    OAuth2Flow::ClientCredentials {
+                        token_url,
+                        refresh_url,
+                        ..
+                    } => Some((0, token_url, refresh_url)),
+                    OAuth2Flow::AuthorizationCode {
+                        token_url,
+                        refresh_url,
+                        ..
+                    } => Some((1, token_url, refresh_url)),
+                    OAuth2Flow::Password {
+                        token_url,
+                        refresh_url,
+                        ..
+                    } => Some((2, token_url, refresh_url)),
+                    OAuth2Flow::Implicit { .. } => None,
+                });
+                let first = with_token.next()?;
+                let (_, token_url, refresh_url) =
+                    with_token.fold(first, |best, f| if f.0 < best.0 { f } else { best });
+                Some(AuthScheme::OAuth2(OAuth2Endpoints {
+                    token_url: token_url.clone(),
+                    refresh_url: refresh_url.clone(),
+                    client_credentials,
+                }))
+            }
+            _ => None,
+        }
+    }
+
+    /// Build the request credential, if the needed values are present. For OAuth2 this
+    /// is an explicitly given token; cached tokens come from [`CredentialSources::oauth2_session`].
+    pub fn credential(&self, creds: &Credentials) -> Option<RequestAuth> {
+        match self {
+            AuthScheme::Bearer | AuthScheme::OAuth2(_) => {
+                creds.token.clone().map(RequestAuth::Bearer)
+            }
+            AuthScheme::Basic => creds.username.clone().map(|username| RequestAuth::Basic {
+                username,
+                password: creds.password.clone(),
+            }),
+            AuthScheme::ApiKey { location, name } => {
+                creds.api_key.clone().map(|value| RequestAuth::ApiKey {
+                    location: *location,
+                    name: name.clone(),
+                    value,
+                })
+            }
+        }
+    }
+
+    /// How a user provides credentials for this scheme, for error messages.
+    fn hint(&self, env_prefix: Option<&str>) -> String {
+        let (flag, var) = match self {
+            AuthScheme::Bearer => ("--token", "TOKEN"),
+            AuthScheme::Basic => ("--username/--password", "USERNAME"),
+            AuthScheme::ApiKey { .. } => ("--api-key", "API_KEY"),
+            AuthScheme::OAuth2(endpoints) if endpoints.client_credentials => {
+                ("login, --client-id/--client-secret, --token", "TOKEN")
+            }
+            AuthScheme::OAuth2(_) => ("login, --token", "TOKEN"),
+        };
+        match env_prefix {
+            Some(prefix) => format!("{}, {}_{} or the credentials file", flag, prefix, var),
+            None => format!("{} or the credentials file", flag),
+        }
+    }
+}
+
+// =====================
+// Credential sources
+// =====================
+
+/// Credentials from every source, looked up field by field in order: CLI flags,
+/// environment variables, then the credentials file (the scheme's entry, then `default`).
+/// OAuth2 tokens come from the token cache.
+#[derive(Debug, Clone, Default)]
+pub struct CredentialSources {
+    pub flags: Credentials,
+    pub env: Credentials,
+    pub file: HashMap<String, Credentials>,
+    /// Kept for error hints
+    pub env_prefix: Option<String>,
+    /// OAuth2 client id from the mapping, used when no other source has one
+    pub client_id: Option<String>,
+    /// OAuth2 scopes to request
+    pub scopes: Vec<String>,
+    pub token_cache: TokenCache,
+    /// Cached OAuth2 tokens by scheme name
+    pub tokens: BTreeMap<String, CachedToken>,
+}
+
+impl CredentialSources {
+    /// Read environment variables and the credentials file named by `credentials_file`
+    /// (which must exist) or, failing that, by the mapping settings (which may be absent).
+    pub fn load(
+        flags: Credentials,
+        settings: &AuthSettings,
+        credentials_file: Option<&str>,
+    ) -> Result<Self> {
+        let env = match &settings.env_prefix {
+            Some(prefix) => env_credentials(prefix),
+            None => Credentials::default(),
+        };
+        let file = match (credentials_file, &settings.credentials_file) {
+            (Some(path), _) => read_credentials_file(&expand_home(path))?,
+            (None, Some(path)) => {
+                let path = expand_home(path);
+                if std::path::Path::new(&path).exists() {
+                    read_credentials_file(&path)?
+                } else {
+                    HashMap::new()
+                }
+            }
+            (None, None) => HashMap::new(),
+        };
+        let token_cache = TokenCache::for_settings(settings);
+        let tokens = token_cache.load()?;
+        Ok(Self {
+            flags,
+            env,
+            file,
+            env_prefix: settings.env_prefix.clone(),
+            client_id: settings.client_id.clone(),
+            scopes: settings.scopes.clone(),
+            token_cache,
+            tokens,
+        })
+    }
+
+    /// Merged credentials for one scheme.
+    pub fn for_scheme(&self, scheme: &str) -> Credentials {
+        let none = Credentials::default();
+        self.flags
+            .clone()
+            .or(&self.env)
+            .or(self.file.get(scheme).unwrap_or(&none))
+            .or(self.file.get("default").unwrap_or(&none))
+            .or(&Credentials {
+                client_id: self.client_id.clone(),
+                ..Default::default()
+            })
+    }
+
+    /// An OAuth2 credential for `scheme`: its cached token, or a session that fetches one
+    /// with the client-credentials flow when a client id and secret are available.
+    pub fn oauth2_session(&self, name: &str, scheme: &AuthScheme) -> Option<RequestAuth> {
+        let AuthScheme::OAuth2(endpoints) = scheme else {
+            return None;
+        };
+        let creds = self.for_scheme(name);
+        let token = self
+            .tokens
+            .get(name)
+            .filter(|t| t.token_url == endpoints.token_url)
+            .cloned();
+        let can_fetch = endpoints.client_credentials
+            && creds.client_id.is_some()
+            && creds.client_secret.is_some();
+        if token.is_none() && !can_fetch {
+            return None;
+        }
+        Some(RequestAuth::OAuth2(Box::new(OAuth2Session {
+            scheme: name.to_string(),
+            endpoints: endpoints.clone(),
+            client: OAuth2Client {
+                client_id: creds.client_id,
+                client_secret: creds.client_secret,
+                scopes: self.scopes.clone(),
+            },
+            cache: self.token_cache.clone(),
+            token,
+        })))
+    }
+}
+
+/// Read `{PREFIX}_TOKEN`, `{PREFIX}_USERNAME`, `{PREFIX}_PASSWORD`, `{PREFIX}_API_KEY`,
+/// `{PREFIX}_CLIENT_ID` and `{PREFIX}_CLIENT_SECRET`.
+pub fn env_credentials(prefix: &str) -> Credentials {
+    let var = |name: &str| {
+        std::env::var(format!("{}_{}", prefix, name))
+            .ok()
+            .filter(|v| !v.is_empty())
+    };
+    Credentials {
+        token: var("TOKEN"),
+        username: var("USERNAME"),
+        password: var("PASSWORD"),
+        api_key: var("API_KEY"),
+        client_id: var("CLIENT_ID"),
+        client_secret: var("CLIENT_SECRET"),
+    }
+}
+
+/// Parse a YAML (or JSON) credentials file keyed by scheme name.
+pub fn read_credentials_file(path: &str) -> Result<HashMap<String, Credentials>> {
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read credentials file: {}", path))?;
+    serde_yaml::from_str(&text)
+        .with_context(|| format!("Failed to parse credentials file: {}", path))
+}
+
+/// Expand a leading `~/` to `$HOME`.
+pub fn expand_home(path: &str) -> String {
+    match (path.strip_prefix("~/"), std::env::var("HOME")) {
+        (Some(rest), Ok(home)) => format!("{}/{}", home.trim_end_matches('/'), rest),
+        _ => path.to_string(),
+    }
+}
+
+// =====================
+// Resolving what a request needs
+// =====================
+
+/// Look up a scheme in `components.securitySchemes`, following references.
+pub fn security_scheme<'a>(api: &'a OpenAPI, name: &str) -> Option<&'a SecurityScheme> {
+    let schemes = &api.components.as_ref()?.security_schemes;
+    match schemes.get(name)? {
+        ReferenceOr::Item(scheme) => Some(scheme),
+        ReferenceOr::Reference { reference } => {
+            let target = reference.strip_prefix("#/components/securitySchemes/")?;
+            match schemes.get(target)? {
+                ReferenceOr::Item(scheme) => Some(scheme),
+                ReferenceOr::Reference { .. } => None,
+            }
+        }
+    }
+}
+
+/// Security requirements of a request: its operation's when declared, else the document's.
+pub fn security_requirements<'a>(
+    api: &'a OpenAPI,
+    method: &str,
+    endpoint: &str,
+) -> &'a [SecurityRequirement] {
+    crate::openapi::find_operation(api, method, endpoint)
+        .and_then(|op| op.operation.security.as_deref())
+        .or(api.security.as_deref())
+        .unwrap_or(&[])
+}
+
+/// Work out the credentials for one request.
+///
+/// `choice` is the mapping's `auth:` value: `none` disables auth, a scheme name forces that
+/// scheme. Otherwise the spec's requirements are tried in order and the first one whose
+/// credentials are all available wins; `settings.default_scheme` covers requests the spec
+/// does not describe. Errors when auth is required but no credentials were given.
+pub fn resolve_auth(
+    api: Option<&OpenAPI>,
+    choice: Option<&str>,
+    method: &str,
+    endpoint: &str,
+    settings: &AuthSettings,
+    sources: &CredentialSources,
+) -> Result<Vec<RequestAuth>> {
+    let requirements: Vec<Vec<String>> = match choice {
+        Some("none") => return Ok(Vec::new()),
+        Some(scheme) => vec![vec![scheme.to_string()]],
+        None => {
+            let declared = api
+                .map(|api| security_requirements(api, method, endpoint))
+                .unwrap_or(&[]);
+            if declared.is_empty() {
+                match &settings.default_scheme {
+                    Some(scheme)
+                        if api.is_some_and(|api| {
+                            crate::openapi::find_operation(api, method, endpoint).is_none()
+                        }) =>
+                    {
+                        vec![vec![scheme.clone()]]
+                    }
+                    _ => return Ok(Vec::new()),
+                }
+            } else {
+                declared
+                    .iter()
+                    .map(|req| req.keys().cloned().collect())
+                    .collect()
+            }
+        }
+    };
+
+    let mut missing: Vec<String> = Vec::new();
+    // An empty requirement object makes auth optional: the other alternatives are tried
+    // first, and the request goes out without credentials only if none can be met
+    let optional = requirements.iter().any(|r| r.is_empty());
+    for requirement in requirements.iter().filter(|r| !r.is_empty()) {
+        let mut auth = Vec::new();
+        for name in requirement {
+            let Some(spec_scheme) = api.and_then(|api| security_scheme(api, name)) else {
+                bail!(
+                    "auth scheme '{}' is not defined in the OpenAPI document",
+                    name
+                );
+            };
+            let Some(scheme) = AuthScheme::from_openapi(spec_scheme) else {
+                if choice.is_some() {
+                    bail!("auth scheme '{}' is of a kind rclib cannot apply", name);
+                }
+                missing.push(format!("{} (unsupported)", name));
+                break;
+            };
+            let found = scheme
+                .credential(&sources.for_scheme(name))
+                .or_else(|| sources.oauth2_session(name, &scheme));
+            match found {
+                Some(a) => auth.push(a),
+                None => {
+                    missing.push(format!(
+                        "{} (use {})",
+                        name,
+                        scheme.hint(sources.env_prefix.as_deref())
+                    ));
+                    break;
+                }
+            }
+        }
+        if auth.len() == requirement.len() {
+            return Ok(auth);
+        }
+    }
+    if optional {
+        return Ok(Vec::new());
+    }
+    bail!("missing credentials for {}", missing.join(" or "));
+}
+
+/// OAuth2 schemes of the document that rclib can log in to, in declaration order.
+pub fn oauth2_schemes(api: &OpenAPI) -> Vec<(String, OAuth2Endpoints)> {
+    let Some(components) = &api.components else {
+        return Vec::new();
+    };
+    components
+        .security_schemes
+        .keys()
+        .filter_map(
+            |name| match security_scheme(api, name).and_then(AuthScheme::from_openapi) {
+                Some(AuthScheme::OAuth2(endpoints)) => Some((name.clone(), endpoints)),
+                _ => None,
+            },
+        )
+        .collect()
+}
+
+// =====================
+// OAuth2 token cache
+// =====================
+
+/// Tokens are renewed this many seconds before they expire.
+const EXPIRY_SKEW_SECS: i64 = 30;
+
+fn now_secs() -> i64 {
+    chrono::Utc::now().timestamp()
+}
+
+/// An OAuth2 token as stored in the token cache.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CachedToken {
+    pub access_token: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refresh_token: Option<String>,
+    /// Unix time the access token expires, when the server said
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scope: Option<String>,
+    /// Token endpoint that issued it; entries for another endpoint are ignored
+    pub token_url: String,
+    /// `client-credentials` or `device`
+    pub flow: String,
+}
+
+impl CachedToken {
+    /// Seconds until the access token expires (negative once it has).
+    pub fn seconds_left(&self) -> Option<i64> {
+        self.expires_at.map(|at| at - now_secs())
+    }
+
+    /// Whether the token expires within the renewal margin of `now`.
+    pub fn is_expired(&self, now: i64) -> bool {
+        self.expires_at
+            .is_some_and(|at| at - EXPIRY_SKEW_SECS <= now)
+    }
+}
+
+/// JSON file of OAuth2 tokens keyed by scheme name, readable only by the user.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TokenCache {
+    pub path: String,
+}
+
+impl TokenCache {
+    pub fn new(path: &str) -> Self {
+        Self {
+            path: expand_home(path),
+        }
+    }
+
+    /// The mapping's `token_cache`, else `tokens.json` in a per-app directory under
+    /// `$XDG_CACHE_HOME` (or `~/.cache`) named after the env prefix.
+    pub fn for_settings(settings: &AuthSettings) -> Self {
+        if let Some(path) = &settings.token_cache {
+            return Self::new(path);
+        }
+        let app = settings
+            .env_prefix
+            .as_deref()
+            .map(|p| p.to_ascii_lowercase().replace('_', "-"))
+            .unwrap_or_else(|| "rclib".to_string());
+        match std::env::var("XDG_CACHE_HOME") {
+            Ok(dir) if !dir.is_empty() => Self::new(&format!("{}/{}/tokens.json", dir, app)),
+            _ => Self::new(&format!("~/.cache/{}/tokens.json", app)),
+        }
+    }
+
+    /// Every cached token; empty when the file does not exist.
+    pub fn load(&self) -> Result<BTreeMap<String, CachedToken>> {
+        if self.path.is_empty() || !std::path::Path::new(&self.path).exists() {
+            return Ok(BTreeMap::new());
+        }
+        let text = std::fs::read_to_string(&self.path)
+            .with_context(|| format!("Failed to read token cache: {}", self.path))?;
+        serde_json::from_str(&text)
+            .with_context(|| format!("Failed to parse token cache: {}", self.path))
+    }
+
+    pub fn get(&self, scheme: &str) -> Result<Option<CachedToken>> {
+        Ok(self.load()?.remove(scheme))
+    }
+
+    pub fn store(&self, scheme: &str, token: &CachedToken) -> Result<()> {
+        let mut tokens = self.load()?;
+        tokens.insert(scheme.to_string(), token.clone());
+        self.save(&tokens)
+    }
+
+    /// Drop one scheme's token, or all of them; returns the schemes removed.
+    pub fn remove(&self, scheme: Option<&str>) -> Result<Vec<String>> {
+        let mut tokens = self.load()?;
+        let removed: Vec<String> = match scheme {
+            Some(name) => tokens
+                .remove(name)
+                .map(|_| name.to_string())
+                .into_iter()
+                .collect(),
+            None => std::mem::take(&mut tokens).into_keys().collect(),
+        };
+        if !removed.is_empty() {
+            self.save(&tokens)?;
+        }
+        Ok(removed)
+    }
+
+    fn save(&self, tokens: &BTreeMap<String, CachedToken>) -> Result<()> {
+        if self.path.is_empty() {
+            bail!("no token cache file configured");
+        }
+        let path = std::path::Path::new(&self.path);
+        if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+        let text = serde_json::to_string_pretty(tokens)?;
+        write_private(path, text.as_bytes())
+            .with_context(|| format!("Failed to write token cache: {}", self.path))
+    }
+}
+
+/// Write a file only the owner can read, tightening the mode of an existing one.
+#[cfg(unix)]
+fn write_private(path: &std::path::Path, contents: &[u8]) -> std::io::Result<()> {
+    use std::io::Write;
+    use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(path)?;
+    file.set_permissions(std::fs::Permissions::from_mode(0o600))?;
+    file.write_all(contents)
+}
+
+#[cfg(not(unix))]
+fn write_private(path: &std::path::Path, contents: &[u8]) -> std::io::Result<()> {
+    std::fs::write(path, contents)
+}
+
+// =====================
+// OAuth2 flows
+// =====================
+
+/// The client rclib acts as when talking to the token endpoint.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct OAuth2Client {
+    pub client_id: Option<String>,
+    /// Sent with HTTP basic auth when set; public clients send only `client_id`
+    pub client_secret: Option<String>,
+    pub scopes: Vec<String>,
+}
+
+/// Flows `login` can run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoginFlow {
+    ClientCredentials,
+    Device,
+}
+
+impl LoginFlow {
+    pub fn name(self) -> &'static str {
+        match self {
+            LoginFlow::ClientCredentials => "client-credentials",
+            LoginFlow::Device => "device",
+        }
+    }
+}
+
+/// Response of the device authorization endpoint (RFC 8628).
+#[derive(Debug, Clone, Deserialize)]
+pub struct DeviceCode {
+    pub device_code: String,
+    pub user_code: String,
+    #[serde(alias = "verification_url")]
+    pub verification_uri: String,
+    #[serde(default)]
+    pub verification_uri_complete: Option<String>,
+    #[serde(default)]
+    pub expires_in: Option<u64>,
+    #[serde(default)]
+    pub interval: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    #[serde(default)]
+    expires_in: Option<i64>,
+    #[serde(default)]
+    refresh_token: Option<String>,
+    #[serde(default)]
+    scope: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenError {
+    error: String,
+    #[serde(default)]
+    error_description: Option<String>,
+}
+
+impl std::fmt::Display for TokenError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.error_description {
+            Some(desc) => write!(f, "{}: {}", self.error, desc),
+            None => write!(f, "{}", self.error),
+        }
+    }
+}
+
+/// POST a form to an OAuth2 endpoint. OAuth2 error responses come back as the inner `Err`.
+fn post_form<T: serde::de::DeserializeOwned>(
+    http: &Client,
+    url: &str,
+    client: &OAuth2Client,
+    mut params: Vec<(&str, String)>,
+) -> Result<std::result::Result<T, TokenError>> {
+    let mut req = http.post(url).header("Accept", "application/json");
+    match (&client.client_id, &client.client_secret) {
+        (Some(id), Some(secret)) => req = req.basic_auth(id, Some(secret)),
+        (Some(id), None) => params.push(("client_id", id.clone())),
+        (None, _) => {}
+    }
+    let resp = fetch(http, req.form(&params), None, &[])
+        .with_context(|| format!("OAuth2 request to {} failed", url))?;
+    let status = resp.status;
+    let text = resp.body;
+    if status.is_success() {
+        return serde_json::from_str(&text)
+            .map(Ok)
+            .with_context(|| format!("Unexpected OAuth2 response from {}: {}", url, text));
+    }
+    match serde_json::from_str::<TokenError>(&text) {
+        Ok(err) => Ok(Err(err)),
+        Err(_) => bail!(
+            "OAuth2 request to {} failed with status {}: {}",
+            url,
+            status,
+            text
+        ),
+    }
+}
+
+fn scope_param(params: &mut Vec<(&str, String)>, scopes: &[String]) {
+    if !scopes.is_empty() {
+        params.push(("scope", scopes.join(" ")));
+    }
+}
+
+fn cached_token(
+    resp: TokenResponse,
+    endpoints: &OAuth2Endpoints,
+    flow: &str,
+    previous_refresh: Option<&str>,
+) -> CachedToken {
+    CachedToken {
+        access_token: resp.access_token,
+        // Servers may keep the refresh token and not send it again
+        refresh_token: resp
+            .refresh_token
+            .or_else(|| previous_refresh.map(str::to_string)),
+        expires_at: resp.expires_in.map(|secs| now_secs() + secs),
+        scope: resp.scope,
+        token_url: endpoints.token_url.clone(),
+        flow: flow.to_string(),
+    }
+}
+
+/// Get a token with the client-credentials grant.
+pub fn client_credentials_token(
+    http: &Client,
+    endpoints: &OAuth2Endpoints,
+    client: &OAuth2Client,
+) -> Result<CachedToken> {
+    if client.client_id.is_none() || client.client_secret.is_none() {
+        bail!("the client-credentials flow needs a client id and secret (--client-id/--client-secret)");
+    }
+    let mut params = vec![("grant_type", "client_credentials".to_string())];
+    scope_param(&mut params, &client.scopes);
+    match post_form(http, &endpoints.token_url, client, params)? {
+        Ok(resp) => Ok(cached_token(
+            resp,
+            endpoints,
+            LoginFlow::ClientCredentials.name(),
+            None,
+        )),
+        Err(err) => bail!("client-credentials login failed: {}", err),
+    }
+}
+
+/// Exchange a refresh token for a new access token.
+pub fn refresh_token(
+    http: &Client,
+    endpoints: &OAuth2Endpoints,
+    client: &OAuth2Client,
+    token: &CachedToken,
+) -> Result<CachedToken> {
+    let Some(refresh) = &token.refresh_token else {
+        bail!("no refresh token");
+    };
+    let url = endpoints
+        .refresh_url
+        .as_ref()
+        .unwrap_or(&endpoints.token_url);
+    let params = vec![
+        ("grant_type", "refresh_token".to_string()),
+        ("refresh_token", refresh.clone()),
+    ];
+    match post_form(http, url, client, params)? {
+        Ok(resp) => Ok(cached_token(resp, endpoints, &token.flow, Some(refresh))),
+        Err(err) => bail!("token refresh failed: {}", err),
+    }
+}
+
+/// Run the device authorization flow: request a code, hand it to `notify` to show the
+/// user, then poll the token endpoint until the login is approved, denied or expires.
+pub fn device_token(
+    http: &Client,
+    device_authorization_url: &str,
+    endpoints: &OAuth2Endpoints,
+    client: &OAuth2Client,
+    notify: impl FnOnce(&DeviceCode),
+) -> Result<CachedToken> {
+    if client.client_id.is_none() {
+        bail!("the device flow needs a client id (--client-id or `client_id` in the mapping)");
+    }
+    let mut params = Vec::new();
+    scope_param(&mut params, &client.scopes);
+    let code: DeviceCode = match post_form(http, device_authorization_url, client, params)? {
+        Ok(code) => code,
+        Err(err) => bail!("device authorization failed: {}", err),
+    };
+    notify(&code);
+
+    let deadline = Instant::now() + Duration::from_secs(code.expires_in.unwrap_or(600));
+    let mut interval = code.interval.unwrap_or(5);
+    loop {
+        if Instant::now() >= deadline {
+            bail!("the device code expired before the login was approved");
+        }
+        let params = vec![
+            (
+                "grant_type",
+                "urn:ietf:params:oauth:grant-type:device_code".to_string(),
+            ),
+            ("device_code", code.device_code.clone()),
+        ];
+        match post_form(http, &endpoints.token_url, client, params)? {
+            Ok(resp) => {
+                return Ok(cached_token(
+                    resp,
+                    endpoints,
+                    LoginFlow::Device.name(),
+                    None,
+                ))
+            }
+            Err(err) if err.error == "authorization_pending" => {}
+            Err(err) if err.error == "slow_down" => interval += 5,
+            Err(err) => bail!("device login failed: {}", err),
+        }
+        std::thread::sleep(Duration::from_secs(interval));
+    }
+}
+
+/// Log in to `scheme` and store the token in the cache.
+///
+/// Without an explicit `flow`, client credentials are used when the scheme declares that
+/// flow and a secret is available, else the device flow when its endpoint is configured.
+pub fn login(
+    http: &Client,
+    scheme: &str,
+    endpoints: &OAuth2Endpoints,
+    sources: &CredentialSources,
+    flow: Option<LoginFlow>,
+    device_authorization_url: Option<&str>,
+    notify: impl FnOnce(&DeviceCode),
+) -> Result<CachedToken> {
+    let creds = sources.for_scheme(scheme);
+    let client = OAuth2Client {
+        client_id: creds.client_id,
+        client_secret: creds.client_secret,
+        scopes: sources.scopes.clone(),
+    };
+    let flow = match flow {
+        Some(flow) => flow,
+        None if endpoints.client_credentials && client.client_secret.is_some() => {
+            LoginFlow::ClientCredentials
+        }
+        None if device_authorization_url.is_some() => LoginFlow::Device,
+        None if endpoints.client_credentials => LoginFlow::ClientCredentials,
+        None => bail!(
+            "scheme '{}' has no client-credentials flow and no `device_authorization_url` is configured",
+            scheme
+        ),
+    };
+    let token = match flow {
+        LoginFlow::ClientCredentials => client_credentials_token(http, endpoints, &client)?,
+        LoginFlow::Device => {
+            let Some(url) = device_authorization_url else {
+                bail!("the device flow needs `device_authorization_url` in the mapping's auth settings");
+            };
+            device_token(http, url, endpoints, &client, notify)?
+        }
+    };
+    sources.token_cache.store(scheme, &token)?;
+    Ok(token)
+}
+
+/// Claims of a JWT access token, for `whoami`; `None` for opaque tokens.
+pub fn jwt_claims(token: &str) -> Option<serde_json::Value> {
+    use base64::Engine;
+    let payload = token.split('.').nth(1)?;
+    let bytes = base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(payload.trim_end_matches('='))
+        .ok()?;
+    serde_json::from_slice::<serde_json::Value>(&bytes)
+        .ok()
+        .filter(|v| v.is_object())
+}
+
+// =====================
+// Sending with renewal
+// =====================
+
+/// An OAuth2 token in use by a request, with what is needed to renew it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OAuth2Session {
+    pub scheme: String,
+    pub endpoints: OAuth2Endpoints,
+    pub client: OAuth2Client,
+    pub cache: TokenCache,
+    /// `None` until the first client-credentials token is fetched
+    pub token: Option<CachedToken>,
+}
+
+impl OAuth2Session {
+    /// Get a fresh token: one another process already stored in the cache, else by
+    /// refreshing, else with client credentials. Returns `false` when no fresh token can
+    /// be had; errors when a renewal was attempted and failed.
+    pub fn renew(&mut self, http: &Client) -> Result<bool> {
+        let now = now_secs();
+        let current = self.token.as_ref().map(|t| t.access_token.clone());
+        if let Some(cached) = self.cache.get(&self.scheme)? {
+            if cached.token_url == self.endpoints.token_url
+                && Some(&cached.access_token) != current.as_ref()
+                && !cached.is_expired(now)
+            {
+                self.token = Some(cached);
+                return Ok(true);
+            }
+        }
+        let refreshed = match self.token.as_ref().filter(|t| t.refresh_token.is_some()) {
+            Some(token) => Some(refresh_token(http, &self.endpoints, &self.client, token)),
+            None => None,
+        };
+        let can_fetch = self.endpoints.client_credentials
+            && self.client.client_id.is_some()
+            && self.client.client_secret.is_some();
+        let token = match refreshed {
+            Some(Ok(token)) => token,
+            Some(Err(_)) | None if can_fetch => {
+                client_credentials_token(http, &self.endpoints, &self.client)?
+            }
+            Some(Err(err)) => {
+                return Err(err.context(format!(
+                    "cannot renew the OAuth2 token for '{}'; run login again",
+                    self.scheme
+                )))
+            }
+            None => return Ok(false),
+        };
+        self.cache.store(&self.scheme, &token)?;
+        self.token = Some(token);
+        Ok(true)
+    }
+
+    fn needs_renewal(&self) -> bool {
+        self.token.as_ref().is_none_or(|t| t.is_expired(now_secs()))
+    }
+}
+
+/// Renew OAuth2 tokens that are missing or about to expire.
+pub fn prepare_auth(http: &Client, auth: &mut [RequestAuth]) -> Result<()> {
+    for a in auth.iter_mut() {
+        if let RequestAuth::OAuth2(session) = a {
+            if session.needs_renewal() && !session.renew(http)? {
+                bail!(
+                    "the OAuth2 token for '{}' has expired; run login again",
+                    session.scheme
+                );
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Renew the OAuth2 tokens of a request the server answered with 401. Returns whether
+/// any was renewed, i.e. whether sending the request again can help.
+pub fn renew_rejected(http: &Client, auth: &mut [RequestAuth]) -> Result<bool> {
+    let mut renewed = false;
+    for a in auth.iter_mut() {
+        if let RequestAuth::OAuth2(session) = a {
+            renewed |= session.renew(http)?;
+        }
+    }
+    Ok(renewed)
+}
+
+/// Send the request `build` makes with the given credentials and read the response; every
+/// attempt is journaled. Expired OAuth2 tokens are renewed first, and a 401 response is
+/// retried once after renewing them.
+pub fn send_with_auth<F>(
+    http: &Client,
+    auth: &[RequestAuth],
+    verbose: bool,
+    step: Option<&str>,
+    build: F,
+) -> Result<Fetched>
+where
+    F: Fn(&[RequestAuth]) -> Result<RequestBuilder>,
+{
+    let mut auth = auth.to_vec();
+    prepare_auth(http, &mut auth)?;
+    let resp = fetch(http, build(&auth)?, step, &secret_names(&auth))?;
+    if resp.status != reqwest::StatusCode::UNAUTHORIZED || !renew_rejected(http, &mut auth)? {
+        return Ok(resp);
+    }
+    if verbose {
+        eprintln!("<- 401; retrying with a renewed OAuth2 token");
+    }
+    fetch(http, build(&auth)?, step, &secret_names(&auth))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn api() -> OpenAPI {
+        crate::parse_openapi(
+            r#"
+openapi: 3.0.3
+info:
+  title: Test API
+  version: 1.0.0
+security:
+  - bearerAuth: []
+paths:
+  /public:
+    get:
+      security: []
+      responses:
+        '200':
+          description: ok
+  /items:
+    get:
+      security:
+        - keyHeader: []
+        - basicAuth: []
+      responses:
+        '200':
+          description: ok
+  /me:
+    get:
+      responses:
+        '200':
+          description: ok
+  /orders:
+    get:
+      security:
+        - oauth: [read]
+      responses:
+        '200':
+          description: ok
+  /maybe:
+    get:
+      security:
+        - {}
+        - bearerAuth: []
+      responses:
+        '200':
+          description: ok
+components:
+  securitySchemes:
+    bearerAuth:
+      type: http
+      scheme: bearer
+    basicAuth:
+      type: http
+      scheme: Basic
+    keyHeader:
+      type: apiKey
+      in: header
+      name: X-API-Key
+    keyQuery:
+      type: apiKey
+      in: query
+      name: api_key
+    keyCookie:
+      type: apiKey
+      in: cookie
+      name: session
+    alias:
+      $ref: '#/components/securitySchemes/keyQuery'
+    oauth:
+      type: oauth2
+      flows:
+        authorizationCode:
+          authorizationUrl: https://auth.example.com/authorize
+          tokenUrl: https://auth.example.com/token
+          scopes:
+            read: Read access
+        clientCredentials:
+          tokenUrl: https://auth.example.com/cc-token
+          refreshUrl: https://auth.example.com/refresh
+          scopes:
+            read: Read access
+    implicitOnly:
+      type: oauth2
+      flows:
+        implicit:
+          authorizationUrl: https://auth.example.com/authorize
+          scopes: {}
+    oidc:
+      type: openIdConnect
+      openIdConnectUrl: https://example.com/.well-known/openid-configuration
+"#,
+        )
+        .unwrap()
+    }
+
+    fn sources(flags: Credentials) -> CredentialSources {
+        CredentialSources {
+            flags,
+            ..Default::default()
+        }
+    }
+
+    fn token(t: &str) -> Credentials {
+        Credentials {
+            token: Some(t.to_string()),
+            ..Default::default()
+        }
+    }
+
+    // ==================== scheme tests ====================
+
+    #[test]
+    fn test_auth_scheme_from_openapi() {
+        let api = api();
+        let scheme = |name: &str| AuthScheme::from_openapi(security_scheme(&api, name).unwrap());
+        assert_eq!(scheme("bearerAuth"), Some(AuthScheme::Bearer));
+        assert_eq!(scheme("basicAuth"), Some(AuthScheme::Basic));
+        assert_eq!(
+            scheme("keyHeader"),
+            Some(AuthScheme::ApiKey {
+                location: ApiKeyIn::Header,
+                name: "X-API-Key".to_string()
+            })
+        );
+        assert_eq!(
+            scheme("alias"),
+            Some(AuthScheme::ApiKey {
+                location: ApiKeyIn::Query,
+                name: "api_key".to_string()
+            })
+        );
+        assert_eq!(scheme("oidc"), None);
+        assert!(security_scheme(&api, "nope").is_none());
+    }
+
+    #[test]
+    fn test_request_auth_describe_masks_secrets() {
+        let basic = RequestAuth::Basic {
+            username: "ann".to_string(),
+            password: Some("pw".to_string()),
+        };
+        assert_eq!(basic.describe(), "Authorization: Basic (ann:****)");
+        let cookie = RequestAuth::ApiKey {
+            location: ApiKeyIn::Cookie,
+            name: "session".to_string(),
+            value: "secret".to_string(),
+        };
+        assert_eq!(cookie.describe(), "Cookie: session=****");
+        assert!(!RequestAuth::Bearer("secret".to_string())
+            .describe()
+            .contains("secret"));
+    }
+
+    #[test]
+    fn test_request_auth_apply() {
+        let client = reqwest::blocking::Client::new();
+        let auth = vec![
+            RequestAuth::Basic {
+                username: "ann".to_string(),
+                password: Some("pw".to_string()),
+            },
+            RequestAuth::ApiKey {
+                location: ApiKeyIn::Query,
+                name: "api_key".to_string(),
+                value: "a b".to_string(),
+            },
+            RequestAuth::ApiKey {
+                location: ApiKeyIn::Cookie,
+                name: "session".to_string(),
+                value: "s1".to_string(),
+            },
+        ];
+        let req = apply_auth(client.get("http://localhost/items?x=1"), &auth)
+            .build()
+            .unwrap();
+        assert_eq!(req.url().as_str(), "http://localhost/items?x=1&api_key=a+b");
+        assert_eq!(req.headers()["authorization"], "Basic YW5uOnB3");
+        assert_eq!(req.headers()["cookie"], "session=s1");
+    }
+
+    // ==================== credential source tests ====================
+
+    #[test]
+    fn test_credential_sources_precedence() {
+        let mut file = HashMap::new();
+        file.insert(
+            "basicAuth".to_string(),
+            Credentials {
+                username: Some("file-user".to_string()),
+                password: Some("file-pw".to_string()),
+                ..Default::default()
+            },
+        );
+        file.insert("default".to_string(), token("file-token"));
+        let sources = CredentialSources {
+            flags: Credentials {
+                username: Some("flag-user".to_string()),
+                ..Default::default()
+            },
+            env: token("env-token"),
+            file,
+            env_prefix: None,
+            ..Default::default()
+        };
+        let basic = sources.for_scheme("basicAuth");
+        assert_eq!(basic.username.as_deref(), Some("flag-user"));
+        assert_eq!(basic.password.as_deref(), Some("file-pw"));
+        assert_eq!(basic.token.as_deref(), Some("env-token"));
+        let other = sources.for_scheme("other");
+        assert_eq!(other.password, None);
+    }
+
+    #[test]
+    fn test_credential_sources_load_file() {
+        let dir = std::env::temp_dir().join(format!("rclib-auth-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("credentials.yaml");
+        std::fs::write(&path, "bearerAuth:\n  token: abc\n").unwrap();
+        let path = path.to_str().unwrap();
+
+        let settings = AuthSettings {
+            credentials_file: Some(path.to_string()),
+            ..Default::default()
+        };
+        let sources = CredentialSources::load(Credentials::default(), &settings, None).unwrap();
+        assert_eq!(
+            sources.for_scheme("bearerAuth").token.as_deref(),
+            Some("abc")
+        );
+
+        // A file named in the mapping may be missing; one given explicitly may not
+        let settings = AuthSettings {
+            credentials_file: Some(format!("{}.missing", path)),
+            ..Default::default()
+        };
+        assert!(CredentialSources::load(Credentials::default(), &settings, None).is_ok());
+        let missing = format!("{}.missing", path);
+        assert!(
+            CredentialSources::load(Credentials::default(), &settings, Some(&missing)).is_err()
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    // ==================== resolve_auth tests ====================
+
+    #[test]
+    fn test_resolve_auth_from_spec() {
+        let api = api();
+        let settings = AuthSettings::default();
+        let resolve =
+            |method: &str, endpoint: &str, choice: Option<&str>, s: &CredentialSources| {
+                resolve_auth(Some(&api), choice, method, endpoint, &settings, s)
+            };
+        let with_token = sources(token("t1"));
+
+        // Global requirement applies to operations without their own
+        assert_eq!(
+            resolve("GET", "/me", None, &with_token).unwrap(),
+            vec![RequestAuth::Bearer("t1".to_string())]
+        );
+        // `security: []` and `auth: none` turn it off
+        assert!(resolve("GET", "/public", None, &with_token)
+            .unwrap()
+            .is_empty());
+        assert!(resolve("GET", "/me", Some("none"), &with_token)
+            .unwrap()
+            .is_empty());
+        // An empty requirement object makes auth optional: credentials are still sent
+        // when they meet another alternative
+        assert!(
+            resolve("GET", "/maybe", None, &sources(Credentials::default()))
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(
+            resolve("GET", "/maybe", None, &with_token).unwrap(),
+            vec![RequestAuth::Bearer("t1".to_string())]
+        );
+
+        // Alternatives are tried in order
+        let basic = sources(Credentials {
+            username: Some("ann".to_string()),
+            ..Default::default()
+        });
+        assert_eq!(
+            resolve("GET", "/items", None, &basic).unwrap(),
+            vec![RequestAuth::Basic {
+                username: "ann".to_string(),
+                password: None
+            }]
+        );
+
+        // A forced scheme replaces the spec's requirements
+        let key = sources(Credentials {
+            api_key: Some("k".to_string()),
+            ..Default::default()
+        });
+        assert_eq!(
+            resolve("GET", "/me", Some("keyCookie"), &key).unwrap(),
+            vec![RequestAuth::ApiKey {
+                location: ApiKeyIn::Cookie,
+                name: "session".to_string(),
+                value: "k".to_string()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_resolve_auth_errors() {
+        let api = api();
+        let settings = AuthSettings {
+            env_prefix: Some("TESTAPI".to_string()),
+            ..Default::default()
+        };
+        let empty = CredentialSources {
+            env_prefix: Some("TESTAPI".to_string()),
+            ..Default::default()
+        };
+        let err = resolve_auth(Some(&api), None, "GET", "/items", &settings, &empty).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "missing credentials for keyHeader (use --api-key, TESTAPI_API_KEY or the credentials file) \
+             or basicAuth (use --username/--password, TESTAPI_USERNAME or the credentials file)"
+        );
+        let err =
+            resolve_auth(Some(&api), Some("nope"), "GET", "/me", &settings, &empty).unwrap_err();
+        assert!(err.to_string().contains("'nope' is not defined"));
+        let err =
+            resolve_auth(Some(&api), Some("oidc"), "GET", "/me", &settings, &empty).unwrap_err();
+        assert!(err.to_string().contains("cannot apply"));
+        // Without a spec, only an explicit scheme needs one
+        assert!(resolve_auth(None, None, "GET", "/me", &settings, &empty)
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_resolve_auth_default_scheme() {
+        let mut api = api();
+        api.security = None;
+        let settings = AuthSettings {
+            default_scheme: Some("bearerAuth".to_string()),
+            ..Default::default()
+        };
+        let with_token = sources(token("t1"));
+        // Used for requests the spec does not describe
+        assert_eq!(
+            resolve_auth(Some(&api), None, "GET", "/unknown", &settings, &with_token).unwrap(),
+            vec![RequestAuth::Bearer("t1".to_string())]
+        );
+        assert!(
+            resolve_auth(Some(&api), None, "GET", "/me", &settings, &with_token)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    // ==================== OAuth2 tests ====================
+
+    fn temp_cache() -> (std::path::PathBuf, TokenCache) {
+        let dir = std::env::temp_dir().join(format!("rclib-oauth-{}", uuid::Uuid::new_v4()));
+        let cache = TokenCache::new(dir.join("app/tokens.json").to_str().unwrap());
+        (dir, cache)
+    }
+
+    fn endpoints(server: &crate::test_server::TestServer) -> OAuth2Endpoints {
+        OAuth2Endpoints {
+            token_url: format!("{}/token", server.url),
+            refresh_url: None,
+            client_credentials: true,
+        }
+    }
+
+    fn cached(
+        access: &str,
+        refresh: Option<&str>,
+        expires_at: Option<i64>,
+        url: &str,
+    ) -> CachedToken {
+        CachedToken {
+            access_token: access.to_string(),
+            refresh_token: refresh.map(str::to_string),
+            expires_at,
+            scope: None,
+            token_url: url.to_string(),
+            flow: "device".to_string(),
+        }
+    }
+
+    /// A form field of a request body.
+    fn form_value(body: &str, key: &str) -> Option<String> {
+        body.split('&').find_map(|pair| {
+            let (k, v) = pair.split_once('=')?;
+            (k == key).then(|| {
+                percent_encoding::percent_decode_str(&v.replace('+', " "))
+                    .decode_utf8_lossy()
+                    .into_owned()
+            })
+        })
+    }
+
+    #[test]
+    fn test_oauth2_scheme_from_openapi() {
+        let api = api();
+        assert_eq!(
+            AuthScheme::from_openapi(security_scheme(&api, "oauth").unwrap()),
+            Some(AuthScheme::OAuth2(OAuth2Endpoints {
+                token_url: "https://auth.example.com/cc-token".to_string(),
+                refresh_url: Some("https://auth.example.com/refresh".to_string()),
+                client_credentials: true,
+            }))
+        );
+        // Implicit flows have no token endpoint
+        assert_eq!(
+            AuthScheme::from_openapi(security_scheme(&api, "implicitOnly").unwrap()),
+            None
+        );
+        let names: Vec<String> = oauth2_schemes(&api).into_iter().map(|(n, _)| n).collect();
+        assert_eq!(names, vec!["oauth"]);
+    }
+
+    #[test]
+    fn test_token_cache_round_trip() {
+        let (dir, cache) = temp_cache();
+        assert!(cache.load().unwrap().is_empty());
+        let token = cached("a1", Some("r1"), Some(100), "http://x/token");
+        cache.store("oauth", &token).unwrap();
+        cache.store("other", &token).unwrap();
+        assert_eq!(cache.get("oauth").unwrap(), Some(token));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&cache.path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        assert_eq!(cache.remove(Some("oauth")).unwrap(), vec!["oauth"]);
+        assert!(cache.remove(Some("oauth")).unwrap().is_empty());
+        assert_eq!(cache.remove(None).unwrap(), vec!["other"]);
+        assert!(cache.load().unwrap().is_empty());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_token_cache_default_path() {
+        let settings = AuthSettings {
+            env_prefix: Some("MY_API".to_string()),
+            ..Default::default()
+        };
+        assert!(TokenCache::for_settings(&settings)
+            .path
+            .ends_with("/my-api/tokens.json"));
+        let settings = AuthSettings {
+            token_cache: Some("/tmp/x/tokens.json".to_string()),
+            ..settings
+        };
+        assert_eq!(
+            TokenCache::for_settings(&settings).path,
+            "/tmp/x/tokens.json"
+        );
+    }
+
+    #[test]
+    fn test_client_credentials_login() {
+        use crate::test_server::{Reply, TestServer};
+        let server = TestServer::start(|_| {
+            Reply::json(
+                200,
+                r#"{"access_token":"cc-1","token_type":"Bearer","expires_in":3600,"scope":"read"}"#,
+            )
+        });
+        let (dir, cache) = temp_cache();
+        let sources = CredentialSources {
+            flags: Credentials {
+                client_id: Some("app".to_string()),
+                client_secret: Some("s3".to_string()),
+                ..Default::default()
+            },
+            scopes: vec!["read".to_string(), "write".to_string()],
+            token_cache: cache.clone(),
+            ..Default::default()
+        };
+        let http = Client::new();
+        let token = login(
+            &http,
+            "oauth",
+            &endpoints(&server),
+            &sources,
+            None,
+            None,
+            |_| panic!("not a device login"),
+        )
+        .unwrap();
+        assert_eq!(token.access_token, "cc-1");
+        assert_eq!(token.flow, "client-credentials");
+        assert!(token.seconds_left().unwrap() > 3500);
+        assert_eq!(cache.get("oauth").unwrap(), Some(token));
+
+        let req = &server.requests()[0];
+        assert_eq!(req.target, "/token");
+        assert_eq!(req.header("authorization"), Some("Basic YXBwOnMz"));
+        assert_eq!(
+            form_value(&req.body, "grant_type").as_deref(),
+            Some("client_credentials")
+        );
+        assert_eq!(
+            form_value(&req.body, "scope").as_deref(),
+            Some("read write")
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_device_login_polls_until_approved() {
+        use crate::test_server::{Reply, TestServer};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        let polls = AtomicUsize::new(0);
+        let server = TestServer::start(move |req| match req.target.as_str() {
+            "/device" => Reply::json(
+                200,
+                r#"{"device_code":"dc","user_code":"WXYZ-1234","verification_uri":"https://example.com/activate","expires_in":60,"interval":0}"#,
+            ),
+            _ if polls.fetch_add(1, Ordering::SeqCst) == 0 => {
+                Reply::json(400, r#"{"error":"authorization_pending"}"#)
+            }
+            _ => Reply::json(
+                200,
+                r#"{"access_token":"dev-1","refresh_token":"ref-1","expires_in":600}"#,
+            ),
+        });
+        let (dir, cache) = temp_cache();
+        let sources = CredentialSources {
+            client_id: Some("cli".to_string()),
+            token_cache: cache.clone(),
+            ..Default::default()
+        };
+        let mut shown = None;
+        let device_url = format!("{}/device", server.url);
+        let token = login(
+            &Client::new(),
+            "oauth",
+            &endpoints(&server),
+            &sources,
+            None,
+            Some(&device_url),
+            |code| shown = Some(code.user_code.clone()),
+        )
+        .unwrap();
+        assert_eq!(shown.as_deref(), Some("WXYZ-1234"));
+        assert_eq!(token.flow, "device");
+        assert_eq!(token.refresh_token.as_deref(), Some("ref-1"));
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 3);
+        // Public client: no secret, so the client id goes in the form
+        assert_eq!(
+            form_value(&requests[0].body, "client_id").as_deref(),
+            Some("cli")
+        );
+        assert_eq!(
+            form_value(&requests[2].body, "grant_type").as_deref(),
+            Some("urn:ietf:params:oauth:grant-type:device_code")
+        );
+        assert_eq!(
+            form_value(&requests[2].body, "device_code").as_deref(),
+            Some("dc")
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_device_login_denied() {
+        use crate::test_server::{Reply, TestServer};
+        let server = TestServer::start(|req| match req.target.as_str() {
+            "/device" => Reply::json(
+                200,
+                r#"{"device_code":"dc","user_code":"U","verification_uri":"https://e/a","interval":0}"#,
+            ),
+            _ => Reply::json(
+                400,
+                r#"{"error":"access_denied","error_description":"user said no"}"#,
+            ),
+        });
+        let client = OAuth2Client {
+            client_id: Some("cli".to_string()),
+            ..Default::default()
+        };
+        let err = device_token(
+            &Client::new(),
+            &format!("{}/device", server.url),
+            &endpoints(&server),
+            &client,
+            |_| {},
+        )
+        .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "device login failed: access_denied: user said no"
+        );
+    }
+
+    #[test]
+    fn test_expired_token_is_refreshed_before_sending() {
+        use crate::test_server::{Reply, TestServer};
+        let server = TestServer::start(|req| match req.target.as_str() {
+            "/token" => Reply::json(200, r#"{"access_token":"new","expires_in":600}"#),
+            _ => Reply::json(200, "{}"),
+        });
+        let (dir, cache) = temp_cache();
+        let endpoints = endpoints(&server);
+        let old = cached(
+            "old",
+            Some("ref-1"),
+            Some(now_secs() - 10),
+            &endpoints.token_url,
+        );
+        cache.store("oauth", &old).unwrap();
+        let auth = vec![RequestAuth::OAuth2(Box::new(OAuth2Session {
+            scheme: "oauth".to_string(),
+            endpoints,
+            client: OAuth2Client::default(),
+            cache: cache.clone(),
+            token: Some(old),
+        }))];
+        let http = Client::new();
+        let url = format!("{}/items", server.url);
+        let resp = send_with_auth(&http, &auth, false, None, |a| {
+            Ok(apply_auth(http.get(&url), a))
+        })
+        .unwrap();
+        assert_eq!(resp.status, 200);
+
+        let requests = server.requests();
+        assert_eq!(requests[0].target, "/token");
+        assert_eq!(
+            form_value(&requests[0].body, "refresh_token").as_deref(),
+            Some("ref-1")
+        );
+        assert_eq!(requests[1].header("authorization"), Some("Bearer new"));
+        // The new token is cached and keeps the old refresh token
+        let stored = cache.get("oauth").unwrap().unwrap();
+        assert_eq!(stored.access_token, "new");
+        assert_eq!(stored.refresh_token.as_deref(), Some("ref-1"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_unauthorized_response_is_retried_with_renewed_token() {
+        use crate::test_server::{Reply, TestServer};
+        let server = TestServer::start(|req| match req.target.as_str() {
+            "/token" => Reply::json(200, r#"{"access_token":"fresh"}"#),
+            _ if req.header("authorization") == Some("Bearer fresh") => Reply::json(200, "{}"),
+            _ => Reply::json(401, r#"{"error":"invalid_token"}"#),
+        });
+        let (dir, cache) = temp_cache();
+        let endpoints = endpoints(&server);
+        let session = |token: Option<CachedToken>, secret: Option<&str>| {
+            vec![RequestAuth::OAuth2(Box::new(OAuth2Session {
+                scheme: "oauth".to_string(),
+                endpoints: endpoints.clone(),
+                client: OAuth2Client {
+                    client_id: Some("app".to_string()),
+                    client_secret: secret.map(str::to_string),
+                    scopes: Vec::new(),
+                },
+                cache: cache.clone(),
+                token,
+            }))]
+        };
+        let http = Client::new();
+        let url = format!("{}/items", server.url);
+        let send = |auth: &[RequestAuth]| {
+            send_with_auth(&http, auth, false, None, |a| {
+                Ok(apply_auth(http.get(&url), a))
+            })
+            .unwrap()
+        };
+
+        // Nothing to renew with: the 401 is returned as is
+        let stale = cached("stale", None, None, &endpoints.token_url);
+        assert_eq!(send(&session(Some(stale.clone()), None)).status, 401);
+        // Client credentials get a new token and the request is sent again
+        assert_eq!(send(&session(Some(stale), Some("s3"))).status, 200);
+
+        let targets: Vec<String> = server.requests().into_iter().map(|r| r.target).collect();
+        assert_eq!(targets, vec!["/items", "/items", "/token", "/items"]);
+        assert_eq!(cache.get("oauth").unwrap().unwrap().access_token, "fresh");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_resolve_auth_oauth2() {
+        let api = api();
+        let settings = AuthSettings {
+            env_prefix: Some("TESTAPI".to_string()),
+            ..Default::default()
+        };
+        let mut sources = CredentialSources {
+            env_prefix: Some("TESTAPI".to_string()),
+            ..Default::default()
+        };
+        let err =
+            resolve_auth(Some(&api), None, "GET", "/orders", &settings, &sources).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "missing credentials for oauth (use login, --client-id/--client-secret, --token, \
+             TESTAPI_TOKEN or the credentials file)"
+        );
+
+        // A cached token is used; one from another token endpoint is not
+        sources.tokens.insert(
+            "oauth".to_string(),
+            cached("a1", None, None, "https://auth.example.com/cc-token"),
+        );
+        let auth = resolve_auth(Some(&api), None, "GET", "/orders", &settings, &sources).unwrap();
+        assert!(
+            matches!(&auth[0], RequestAuth::OAuth2(s) if s.token.as_ref().unwrap().access_token == "a1")
+        );
+        sources.tokens.get_mut("oauth").unwrap().token_url = "https://elsewhere/token".to_string();
+        assert!(resolve_auth(Some(&api), None, "GET", "/orders", &settings, &sources).is_err());
+
+        // Client credentials are enough to fetch one when sending
+        sources.env.client_id = Some("app".to_string());
+        sources.env.client_secret = Some("s3".to_string());
+        let auth = resolve_auth(Some(&api), None, "GET", "/orders", &settings, &sources).unwrap();
+        assert!(matches!(&auth[0], RequestAuth::OAuth2(s) if s.token.is_none()));
+        // An explicit token wins over the cache
+        sources.flags.token = Some("given".to_string());
+        assert_eq!(
+            resolve_auth(Some(&api), None, "GET", "/orders", &settings, &sources).unwrap(),
+            vec![RequestAuth::Bearer("given".to_string())]
+        );
+    }
+}
@@ -1,11 +1,26 @@
 use std::collections::{HashMap, HashSet};
 
+use anyhow::Context;
 use clap::{Arg, ArgAction, ArgMatches, Command};
+use openapiv3::OpenAPI;
 
+use crate::auth::{
+    login, oauth2_schemes, resolve_auth, CachedToken, CredentialSources, Credentials, LoginFlow,
+    RequestAuth,
+};
+use crate::curl::CurlCommand;
+use crate::dry_run::{self, DryRunFormat};
+use crate::journal::{self, Journal};
 use crate::mapping::*;
+use crate::openapi::{apply_openapi_arg_types, check_mapping_against_openapi};
+use crate::output::{ResponseFilter, RowCondition};
+use crate::pagination::execute_paginated;
+use crate::sse::execute_sse;
+use crate::table::{self, TableSettings, TableStyle};
 use crate::{
-    build_request_from_command, execute_requests_loop, ExecutionConfig, OutputFormat,
-    RawRequestSpec, RequestSpec,
+    append_query, execute_requests_loop, json_body_from_pairs, parse_openapi,
+    percent_encode_component, try_build_request_from_command, ExecutionConfig, OutputFormat,
+    RawRequestSpec, RequestSpec, MULTI_VALUE_SEPARATOR,
 };
 
 #[derive(Default)]
@@ -35,10 +50,52 @@ fn leak_str<S: Into<String>>(s: S) -> &'static str {
     Box::leak(s.into().into_boxed_str())
 }
 
+/// Attach a value parser enforcing the arg's `type`, `choices`, `regex` and `min`/`max`.
+///
+/// Parsed values stay `String`s so `collect_vars_from_matches` reads every arg the same way.
+fn with_value_parser(a: Arg, arg: &ArgSpec) -> Arg {
+    let typed = matches!(arg.arg_type.as_deref(), Some("int" | "float"));
+    if let Some(choices) = arg.choices.as_ref().filter(|_| !typed) {
+        let values: Vec<&'static str> = choices.iter().map(|c| leak_str(c.clone())).collect();
+        return a.value_parser(clap::builder::PossibleValuesParser::new(values));
+    }
+    if !typed && arg.choices.is_none() && arg.regex.is_none() {
+        return a;
+    }
+    let spec = arg.clone();
+    a.value_parser(move |v: &str| check_arg_value(&spec, v).map(|_| v.to_string()))
+}
+
+/// Attach the arg's `default`; a `multiple` arg gets each of its values.
+fn with_default(a: Arg, arg: &ArgSpec) -> Arg {
+    let values: Vec<&'static str> = arg
+        .default_values()
+        .into_iter()
+        .map(|v| leak_str(v.to_string()))
+        .collect();
+    if values.is_empty() {
+        a
+    } else {
+        a.default_values(values)
+    }
+}
+
 pub fn build_cli(
     mapping_root: &MappingRoot,
     default_base_url: &str,
 ) -> (Command, HashMap<Vec<String>, CommandSpec>) {
+    build_cli_with_openapi(mapping_root, default_base_url, None)
+}
+
+/// Same as [`build_cli`]; with `openapi_arg_types: true` in the mapping, arg types and
+/// constraints are also derived from the operation's parameter schemas in `openapi`.
+pub fn build_cli_with_openapi(
+    mapping_root: &MappingRoot,
+    default_base_url: &str,
+    openapi: Option<&OpenAPI>,
+) -> (Command, HashMap<Vec<String>, CommandSpec>) {
+    let typing_api = openapi.filter(|_| mapping_root.openapi_arg_types());
+
     // Build a tree of commands from mapping patterns
     let mut root = TreeNode::default();
     let mut leaf_map: HashMap<Vec<String>, CommandSpec> = HashMap::new();
@@ -61,9 +118,14 @@ pub fn build_cli(
                 } else {
                     cmd.args.clone()
                 };
+                let mut leaf_cmd = cmd.clone();
+                if let Some(api) = typing_api {
+                    apply_openapi_arg_types(api, cmd, &mut node.args);
+                    leaf_cmd.args = node.args.clone();
+                }
                 leaf_map.insert(
                     path_tokens.iter().map(|s| s.to_string()).collect(),
-                    cmd.clone(),
+                    leaf_cmd,
                 );
             }
         }
@@ -74,47 +136,13 @@ pub fn build_cli(
                 }
                 node
             }
-            fn merge_arg_specs(base: &ArgSpec, override_spec: &ArgSpec) -> ArgSpec {
-                ArgSpec {
-                    name: override_spec.name.clone().or_else(|| base.name.clone()),
-                    inherit: None,
-                    help: override_spec.help.clone().or_else(|| base.help.clone()),
-                    positional: override_spec.positional.or(base.positional),
-                    long: override_spec.long.clone().or_else(|| base.long.clone()),
-                    short: override_spec.short.clone().or_else(|| base.short.clone()),
-                    required: override_spec.required.or(base.required),
-                    default: override_spec
-                        .default
-                        .clone()
-                        .or_else(|| base.default.clone()),
-                    arg_type: override_spec
-                        .arg_type
-                        .clone()
-                        .or_else(|| base.arg_type.clone()),
-                    value: override_spec.value.clone().or_else(|| base.value.clone()),
-                    file_upload: override_spec.file_upload || base.file_upload,
-                    endpoint: override_spec
-                        .endpoint
-                        .clone()
-                        .or_else(|| base.endpoint.clone()),
-                    method: override_spec.method.clone().or_else(|| base.method.clone()),
-                    headers: override_spec
-                        .headers
-                        .clone()
-                        .or_else(|| base.headers.clone()),
-                    body: override_spec.body.clone().or_else(|| base.body.clone()),
-                    file_overrides_value_of: override_spec
-                        .file_overrides_value_of
-                        .clone()
-                        .or_else(|| base.file_overrides_value_of.clone()),
-                }
-            }
             fn walk_group(
                 root: &mut TreeNode,
                 leaf_map: &mut HashMap<Vec<String>, CommandSpec>,
                 path: &mut Vec<String>,
                 group: &CommandGroup,
                 top_level_common_args: &HashMap<String, ArgSpec>,
+                typing_api: Option<&OpenAPI>,
             ) {
                 path.push(group.name.clone());
                 // Ensure group node exists and set its about
@@ -126,57 +154,24 @@ pub fn build_cli(
                 for node in &group.subcommands {
                     match node {
                         CommandNode::Group(g) => {
-                            walk_group(root, leaf_map, path, g, top_level_common_args);
+                            walk_group(root, leaf_map, path, g, top_level_common_args, typing_api);
                         }
                         CommandNode::Command(cmd) => {
                             let mut insert_path = path.clone();
-                            let cmd_name = cmd.name.clone().unwrap_or_else(|| {
-                                if !cmd.pattern.is_empty() {
-                                    cmd.pattern
-                                        .split_whitespace()
-                                        .rfind(|t| !is_placeholder(t))
-                                        .unwrap_or("")
-                                        .to_string()
-                                } else {
-                                    String::new()
-                                }
-                            });
+                            let cmd_name = command_name(cmd);
                             if !cmd_name.is_empty() {
                                 insert_path.push(cmd_name);
                             }
                             let node_ref = ensure_path(root, &insert_path);
 
                             // Resolve args with inheritance from group's common_args
-                            let mut resolved_args: Vec<ArgSpec> = Vec::new();
-                            if !cmd.args.is_empty() {
-                                for a in &cmd.args {
-                                    if let Some(inherit_key) = &a.inherit {
-                                        // First check group-level common_args, then top-level
-                                        let base = group
-                                            .common_args
-                                            .get(inherit_key)
-                                            .or_else(|| top_level_common_args.get(inherit_key));
-
-                                        if let Some(base) = base {
-                                            let merged = merge_arg_specs(base, a);
-                                            resolved_args.push(merged);
-                                        } else {
-                                            // No base found; use override as-is
-                                            resolved_args.push(a.clone());
-                                        }
-                                    } else {
-                                        resolved_args.push(a.clone());
-                                    }
-                                }
-                            } else {
-                                resolved_args = derive_args_from_pattern(&cmd.pattern);
-                            }
-
-                            // Add common args requested via legacy use_common_args
-                            for common_arg_name in &cmd.use_common_args {
-                                if let Some(common_arg) = group.common_args.get(common_arg_name) {
-                                    resolved_args.push(common_arg.clone());
-                                }
+                            let (mut resolved_args, _) = resolve_command_args(
+                                cmd,
+                                &group.common_args,
+                                top_level_common_args,
+                            );
+                            if let Some(api) = typing_api {
+                                apply_openapi_arg_types(api, cmd, &mut resolved_args);
                             }
 
                             node_ref.args = resolved_args;
@@ -200,6 +195,7 @@ pub fn build_cli(
                     &mut Vec::new(),
                     g,
                     &hier.common_args,
+                    typing_api,
                 );
             }
         }
@@ -214,7 +210,7 @@ pub fn build_cli(
             Arg::new("log-file")
                 .long("log-file")
                 .short('l')
-                .help("Path to log file (JSON format)")
+                .help("Append every HTTP exchange to this file as JSON lines")
                 .num_args(1),
         )
         .arg(
@@ -225,12 +221,130 @@ pub fn build_cli(
                 .num_args(1)
                 .default_value(leak_str(default_base_url.to_string())),
         )
+        .arg(
+            Arg::new("output")
+                .long("output")
+                .short('o')
+                .value_name("FORMAT")
+                .help("Output format (default: table)")
+                .num_args(1)
+                .value_parser(clap::builder::PossibleValuesParser::new(
+                    OutputFormat::NAMES,
+                )),
+        )
+        .arg(
+            Arg::new(QUERY_ID)
+                .long("query")
+                .value_name("JSONPATH")
+                .help("Print only this part of the response, e.g. '$.items[*].id'")
+                .num_args(1)
+                .global(true),
+        )
+        .arg(
+            Arg::new(RAW_VALUE_ID)
+                .long("raw-value")
+                .help("Print strings without quotes and array items one per line")
+                .action(ArgAction::SetTrue)
+                .global(true),
+        )
+        .arg(
+            Arg::new("table-style")
+                .long("table-style")
+                .value_name("STYLE")
+                .help("Table layout: boxed (default) or compact, without borders")
+                .num_args(1)
+                .value_parser(clap::builder::PossibleValuesParser::new(TableStyle::NAMES)),
+        )
+        .arg(
+            Arg::new("wrap")
+                .long("wrap")
+                .help("Wrap long table cells instead of cutting them to the terminal width")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("column-depth")
+                .long("column-depth")
+                .value_name("N")
+                .help("Levels of nested objects shown as table columns when there is no table view")
+                .num_args(1)
+                .value_parser(clap::value_parser!(usize)),
+        )
+        .arg(
+            Arg::new("no-color")
+                .long("no-color")
+                .help(
+                    "Disable colors (also disabled by NO_COLOR or when not writing to a terminal)",
+                )
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("columns")
+                .long("columns")
+                .value_name("COLUMNS")
+                .help("Table columns to show, overriding the table view, e.g. 'id,title,price'")
+                .num_args(1)
+                .value_delimiter(','),
+        )
+        .arg(
+            Arg::new("where")
+                .long("where")
+                .value_name("CONDITION")
+                .help("Keep rows matching path=value (also !=, >, >=, <, <=, ~); repeatable")
+                .num_args(1)
+                .action(ArgAction::Append),
+        )
+        .arg(
+            Arg::new("sort-by")
+                .long("sort-by")
+                .value_name("PATH")
+                .help("Sort rows by this column")
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("desc")
+                .long("desc")
+                .help("Sort in descending order")
+                .requires("sort-by")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("limit-rows")
+                .long("limit-rows")
+                .value_name("N")
+                .help("Show at most N rows")
+                .num_args(1)
+                .value_parser(clap::value_parser!(usize)),
+        )
+        .arg(
+            Arg::new("all")
+                .long("all")
+                .help("Fetch every page of a list command with a pagination: block")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("max-pages")
+                .long("max-pages")
+                .value_name("N")
+                .help("With --all, stop after N pages")
+                .num_args(1)
+                .requires("all")
+                .value_parser(clap::value_parser!(u32).range(1..)),
+        )
+        .arg(
+            Arg::new("max-events")
+                .long("max-events")
+                .value_name("N")
+                .help("For stream: sse commands, stop after N events")
+                .num_args(1)
+                .value_parser(clap::value_parser!(u64).range(1..)),
+        )
         .arg(
             Arg::new("json-output")
                 .long("json-output")
                 .short('j')
-                .help("Output in JSON format")
-                .action(ArgAction::SetTrue),
+                .help("Output in JSON format (same as --output json)")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("output"),
         )
         .arg(
             Arg::new("verbose")
@@ -266,6 +380,71 @@ pub fn build_cli(
                 .help("Path to mapping YAML file")
                 .num_args(1),
         )
+        // Show requests instead of sending them
+        .arg(
+            Arg::new("dry-run")
+                .long("dry-run")
+                .help("Print the resolved request(s) without sending them")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(
+            Arg::new("as-curl")
+                .long("as-curl")
+                .help("Print the request(s) as curl commands without sending them")
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["dry-run", "as-httpie"]),
+        )
+        .arg(
+            Arg::new("as-httpie")
+                .long("as-httpie")
+                .help("Print the request(s) as HTTPie commands without sending them")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("dry-run"),
+        )
+        // Credentials for the OpenAPI security schemes
+        .next_help_heading("Authentication")
+        .arg(
+            Arg::new("token")
+                .long("token")
+                .help("Bearer token")
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("username")
+                .long("username")
+                .help("Username for HTTP basic auth")
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("password")
+                .long("password")
+                .help("Password for HTTP basic auth")
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("api-key")
+                .long("api-key")
+                .help("API key (sent where the spec's apiKey scheme says)")
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("client-id")
+                .long("client-id")
+                .help("OAuth2 client id")
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("client-secret")
+                .long("client-secret")
+                .help("OAuth2 client secret (client-credentials flow)")
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("credentials-file")
+                .long("credentials-file")
+                .help("YAML file with credentials keyed by security scheme name")
+                .num_args(1),
+        )
         // Performance testing options
         .next_help_heading("Perf tests options")
         .arg(
@@ -302,14 +481,21 @@ pub fn build_cli(
             Arg::new("method")
                 .long("method")
                 .help("HTTP method")
-                .required(true)
+                .required_unless_present("from-curl")
                 .num_args(1),
         )
         .arg(
             Arg::new("endpoint")
                 .long("endpoint")
                 .help("Endpoint path or absolute URL")
-                .required(true)
+                .required_unless_present("from-curl")
+                .num_args(1),
+        )
+        .arg(
+            Arg::new("from-curl")
+                .long("from-curl")
+                .value_name("COMMAND")
+                .help("Build the request from a curl command line ('-' reads it from stdin)")
                 .num_args(1),
         )
         .arg(
@@ -323,17 +509,171 @@ pub fn build_cli(
         .arg(
             Arg::new("body")
                 .long("body")
-                .help("Request body")
+                .help("Request body ('-' reads it from stdin)")
+                .num_args(1)
+                .conflicts_with_all(["body-file", "json", "form"]),
+        )
+        .arg(
+            Arg::new("body-file")
+                .long("body-file")
+                .value_name("PATH")
+                .help("Read the request body from a file")
+                .num_args(1)
+                .conflicts_with_all(["json", "form"]),
+        )
+        .arg(
+            Arg::new("param")
+                .long("param")
+                .short('p')
+                .value_name("KEY=VALUE")
+                .help("Query parameter, URL-encoded (repeatable)")
+                .num_args(1)
+                .action(ArgAction::Append),
+        )
+        .arg(
+            Arg::new("form")
+                .long("form")
+                .short('F')
+                .value_name("FIELD=VALUE|FIELD=@PATH")
+                .help("Multipart form field or file (repeatable)")
+                .num_args(1)
+                .action(ArgAction::Append)
+                .conflicts_with("json"),
+        )
+        .arg(
+            Arg::new("json")
+                .long("json")
+                .value_name("KEY=VALUE|KEY:=JSON")
+                .help("JSON body field; dotted keys nest (repeatable)")
+                .num_args(1)
+                .action(ArgAction::Append),
+        )
+        .arg(
+            Arg::new("table-view")
+                .long("table-view")
+                .value_name("COLUMNS")
+                .help("Columns for array responses in human output (comma-separated)")
+                .num_args(1)
+                .value_delimiter(',')
+                .action(ArgAction::Append),
+        )
+        .arg(
+            Arg::new("auth")
+                .long("auth")
+                .help("Security scheme to use instead of the spec's, or 'none'")
                 .num_args(1),
         );
     app = app.subcommand(raw_cmd);
 
+    // Add OAuth2 session commands, unless the mapping uses those names itself
+    for session_cmd in session_commands() {
+        if !root.children.contains_key(session_cmd.get_name()) {
+            app = app.subcommand(session_cmd);
+        }
+    }
+
+    // Add 'mapping' command with maintenance tools for the mapping file itself
+    let mapping_cmd = Command::new("mapping")
+        .about("Inspect and validate the command mapping")
+        .subcommand_required(true)
+        .subcommand(Command::new("lint").about(
+            "Check the mapping for unknown placeholders, broken inheritance and flag clashes",
+        ))
+        .subcommand(
+            Command::new("check-openapi")
+                .about("Check that every mapped method and endpoint exists in the OpenAPI spec"),
+        )
+        .subcommand(
+            Command::new("generate")
+                .about("Print a starter mapping YAML generated from the OpenAPI spec"),
+        )
+        .subcommand(
+            Command::new("add-from-curl")
+                .about("Print a mapping entry for the request of a curl command line")
+                .arg(
+                    Arg::new("curl")
+                        .help("curl command line, or '-' to read it from stdin")
+                        .required(true)
+                        .num_args(1),
+                )
+                .arg(
+                    Arg::new("name")
+                        .long("name")
+                        .help("Command name (default: method and last path segment)")
+                        .num_args(1),
+                ),
+        );
+    app = app.subcommand(mapping_cmd);
+
     // Add hierarchical commands
     app = add_children_commands(app, Vec::new(), &root);
 
     (app, leaf_map)
 }
 
+/// The `--output` format; `--json-output` is an alias for `--output json`.
+fn output_format(matches: &ArgMatches) -> OutputFormat {
+    match matches.get_one::<String>("output") {
+        Some(name) => OutputFormat::from_name(name).unwrap_or(OutputFormat::Human),
+        None if matches.get_flag("json-output") => OutputFormat::Json,
+        None => OutputFormat::Human,
+    }
+}
+
+/// An argument value, or all of stdin when the value is `-`.
+fn read_arg_or_stdin(value: &str) -> anyhow::Result<String> {
+    if value != "-" {
+        return Ok(value.to_string());
+    }
+    let mut input = String::new();
+    std::io::Read::read_to_string(&mut std::io::stdin(), &mut input)
+        .context("Failed to read stdin")?;
+    Ok(input)
+}
+
+/// Names of the generated OAuth2 session commands.
+const SESSION_COMMANDS: &[&str] = &["login", "logout", "whoami"];
+
+/// Ids of the global `--query` and `--raw-value` options. clap propagates global args to
+/// every subcommand under their id, so these must not be valid mapping arg ids.
+const QUERY_ID: &str = "global:query";
+const RAW_VALUE_ID: &str = "global:raw-value";
+
+/// `login`, `logout` and `whoami`, which manage the OAuth2 token cache.
+fn session_commands() -> Vec<Command> {
+    let scheme = || {
+        Arg::new("scheme")
+            .long("scheme")
+            .help("OAuth2 security scheme (default: the only one, or the mapping's default_scheme)")
+            .num_args(1)
+    };
+    vec![
+        Command::new("login")
+            .about("Log in with OAuth2 and cache the token")
+            .arg(scheme())
+            .arg(
+                Arg::new("flow")
+                    .long("flow")
+                    .help("Grant to use (default: client-credentials when a secret is given, else device)")
+                    .value_parser(["client-credentials", "device"])
+                    .num_args(1),
+            )
+            .arg(
+                Arg::new("scope")
+                    .long("scope")
+                    .help("Scope to request instead of the mapping's (repeatable)")
+                    .num_args(1)
+                    .action(ArgAction::Append),
+            ),
+        Command::new("logout")
+            .about("Remove cached OAuth2 tokens")
+            .arg(scheme()),
+        Command::new("whoami")
+            .about("Show the cached OAuth2 login")
+            .arg(scheme()),
+    ]
+}
+
 fn add_children_commands(mut app: Command, path: Vec<String>, node: &TreeNode) -> Command {
     // Add children of current node under the app
     for (name, child) in &node.children {
@@ -354,9 +694,11 @@ fn add_children_commands(mut app: Command, path: Vec<String>, node: &TreeNode) -
                             .required(arg.required.unwrap_or(false))
                             .num_args(1)
                             .index(pos_index);
-                        if let Some(def) = &arg.default {
-                            a = a.default_value(leak_str(def.clone()));
+                        if arg.multiple {
+                            a = a.num_args(1..);
                         }
+                        a = with_value_parser(a, arg);
+                        a = with_default(a, arg);
                         cmd = cmd.arg(a);
                         pos_index += 1;
                     }
@@ -374,10 +716,11 @@ fn add_children_commands(mut app: Command, path: Vec<String>, node: &TreeNode) -
                         if arg.arg_type.as_deref() == Some("bool") {
                             a = a.action(ArgAction::SetTrue);
                         } else {
-                            a = a.num_args(1);
-                            if let Some(def) = &arg.default {
-                                a = a.default_value(leak_str(def.clone()));
+                            a = with_value_parser(a.num_args(1), arg);
+                            if arg.multiple {
+                                a = a.action(ArgAction::Append);
                             }
+                            a = with_default(a, arg);
                         }
 
                         if let Some(l) = arg.long.as_deref() {
@@ -420,9 +763,11 @@ fn add_children_subcommands(mut cmd: Command, path: Vec<String>, node: &TreeNode
                             .required(arg.required.unwrap_or(false))
                             .num_args(1)
                             .index(pos_index);
-                        if let Some(def) = &arg.default {
-                            a = a.default_value(leak_str(def.clone()));
+                        if arg.multiple {
+                            a = a.num_args(1..);
                         }
+                        a = with_value_parser(a, arg);
+                        a = with_default(a, arg);
                         sub = sub.arg(a);
                         pos_index += 1;
                     }
@@ -440,10 +785,11 @@ fn add_children_subcommands(mut cmd: Command, path: Vec<String>, node: &TreeNode
                         if arg.arg_type.as_deref() == Some("bool") {
                             a = a.action(ArgAction::SetTrue);
                         } else {
-                            a = a.num_args(1);
-                            if let Some(def) = &arg.default {
-                                a = a.default_value(leak_str(def.clone()));
+                            a = with_value_parser(a.num_args(1), arg);
+                            if arg.multiple {
+                                a = a.action(ArgAction::Append);
                             }
+                            a = with_default(a, arg);
                         }
 
                         if let Some(l) = arg.long.as_deref() {
@@ -471,7 +817,7 @@ pub fn collect_subcommand_path(matches: &ArgMatches) -> (Vec<String>, &ArgMatche
     let mut path: Vec<String> = Vec::new();
     let mut current = matches;
     while let Some((name, sub_m)) = current.subcommand() {
-        if name == "raw" {
+        if name == "raw" || name == "mapping" {
             break;
         }
         path.push(name.to_string());
@@ -668,14 +1014,27 @@ pub fn collect_vars_from_matches(
                     );
                 }
             }
+        } else if let Some(vals) = leaf.get_many::<String>(&name).filter(|_| arg.multiple) {
+            if let Some(var_name) = arg.name.clone() {
+                let values: Vec<&str> = vals.map(|v| v.as_str()).collect();
+                vars.insert(
+                    var_name.clone(),
+                    values.join(&MULTI_VALUE_SEPARATOR.to_string()),
+                );
+                selected.insert(var_name);
+            }
         } else if let Some(val) = leaf.get_one::<String>(&name) {
             if let Some(var_name) = arg.name.clone() {
                 vars.insert(var_name.clone(), val.clone());
                 selected.insert(var_name);
             }
-        } else if let Some(def) = &arg.default {
+        } else if arg.default.is_some() {
             if let Some(var_name) = arg.name.clone() {
-                vars.insert(var_name, def.clone());
+                vars.insert(
+                    var_name,
+                    arg.default_values()
+                        .join(&MULTI_VALUE_SEPARATOR.to_string()),
+                );
             }
         } else if arg.required.unwrap_or(false) {
             missing_required = true;
@@ -684,26 +1043,436 @@ pub fn collect_vars_from_matches(
     (vars, selected, missing_required)
 }
 
+/// Print mapping diagnostics; exit code is 1 when any error was found.
+fn report_diagnostics(diags: &[Diagnostic], json_output: bool) -> anyhow::Result<i32> {
+    let errors = diags
+        .iter()
+        .filter(|d| d.severity == Severity::Error)
+        .count();
+    if json_output {
+        println!("{}", serde_json::to_string_pretty(&diags)?);
+    } else if diags.is_empty() {
+        println!("Mapping OK");
+    } else {
+        for d in diags {
+            println!("{}", d);
+        }
+        println!("\n{} error(s), {} warning(s)", errors, diags.len() - errors);
+    }
+    Ok(if errors > 0 { 1 } else { 0 })
+}
+
+/// Collect credentials from the global auth flags, env vars and the credentials file.
+fn credential_sources(
+    root: &MappingRoot,
+    matches: &ArgMatches,
+) -> anyhow::Result<CredentialSources> {
+    let flag = |name: &str| matches.get_one::<String>(name).cloned();
+    let flags = Credentials {
+        token: flag("token"),
+        username: flag("username"),
+        password: flag("password"),
+        api_key: flag("api-key"),
+        client_id: flag("client-id"),
+        client_secret: flag("client-secret"),
+    };
+    CredentialSources::load(
+        flags,
+        &root.auth_settings(),
+        matches
+            .get_one::<String>("credentials-file")
+            .map(|s| s.as_str()),
+    )
+}
+
+/// Credentials for one request. Absolute URLs outside the base URL get none unless a
+/// scheme is chosen explicitly, so credentials never leak to other hosts.
+fn request_auth(
+    openapi: Option<&OpenAPI>,
+    root: &MappingRoot,
+    sources: &CredentialSources,
+    choice: Option<&str>,
+    method: &str,
+    endpoint: &str,
+    base_url: &str,
+) -> anyhow::Result<Vec<RequestAuth>> {
+    let absolute = endpoint.starts_with("http://") || endpoint.starts_with("https://");
+    let relative = if absolute {
+        path_under_base(endpoint, base_url)
+    } else {
+        Some(endpoint.to_string())
+    };
+    if choice.is_none() && relative.is_none() {
+        return Ok(Vec::new());
+    }
+    resolve_auth(
+        openapi,
+        choice,
+        method,
+        relative.as_deref().unwrap_or(endpoint),
+        &root.auth_settings(),
+        sources,
+    )
+}
+
+/// The path and query of an absolute `endpoint` relative to `base_url`, or `None` when
+/// it is on another scheme, host or port, or outside the base URL's path.
+fn path_under_base(endpoint: &str, base_url: &str) -> Option<String> {
+    let url = reqwest::Url::parse(endpoint).ok()?;
+    let base = reqwest::Url::parse(base_url).ok()?;
+    let same_origin = url.scheme() == base.scheme()
+        && url.host_str() == base.host_str()
+        && url.port_or_known_default() == base.port_or_known_default();
+    if !same_origin {
+        return None;
+    }
+    // "/v1" covers "/v1" and "/v1/users", not "/v10"
+    let rest = url.path().strip_prefix(base.path().trim_end_matches('/'))?;
+    if !rest.is_empty() && !rest.starts_with('/') {
+        return None;
+    }
+    Some(match url.query() {
+        Some(query) => format!("{}?{}", rest, query),
+        None => rest.to_string(),
+    })
+}
+
+/// Fill in the credentials of a built request (every step, for scenarios).
+fn attach_auth(
+    spec: &mut RequestSpec,
+    cmd: &CommandSpec,
+    openapi: Option<&OpenAPI>,
+    root: &MappingRoot,
+    matches: &ArgMatches,
+    base_url: &str,
+) -> anyhow::Result<()> {
+    if matches!(spec, RequestSpec::CustomHandler { .. }) || cmd.auth.as_deref() == Some("none") {
+        return Ok(());
+    }
+    let sources = credential_sources(root, matches)?;
+    let choice = cmd.auth.as_deref();
+    match spec {
+        RequestSpec::Simple(raw) => {
+            raw.auth = request_auth(
+                openapi,
+                root,
+                &sources,
+                choice,
+                &raw.method,
+                &raw.endpoint,
+                base_url,
+            )?;
+        }
+        RequestSpec::Scenario(scenario) => {
+            for step in &scenario.scenario.steps {
+                let auth = request_auth(
+                    openapi,
+                    root,
+                    &sources,
+                    choice,
+                    &step.method,
+                    &step.endpoint,
+                    base_url,
+                )
+                .with_context(|| format!("step '{}'", step.name))?;
+                scenario.auth.insert(step.name.clone(), auth);
+            }
+        }
+        RequestSpec::CustomHandler { .. } => {}
+    }
+    Ok(())
+}
+
+/// The dry-run mode chosen by `--dry-run`, `--as-curl` or `--as-httpie`, if any.
+fn dry_run_format(matches: &ArgMatches, json_output: bool) -> Option<DryRunFormat> {
+    if matches.get_flag("as-curl") {
+        Some(DryRunFormat::Curl)
+    } else if matches.get_flag("as-httpie") {
+        Some(DryRunFormat::Httpie)
+    } else if matches.get_flag("dry-run") {
+        Some(if json_output {
+            DryRunFormat::Json
+        } else {
+            DryRunFormat::Plain
+        })
+    } else {
+        None
+    }
+}
+
+/// Subcommand names of the invocation, e.g. "users get", for the journal.
+fn command_label(matches: &ArgMatches) -> String {
+    let mut names = Vec::new();
+    let mut current = matches;
+    while let Some((name, sub_m)) = current.subcommand() {
+        names.push(name);
+        current = sub_m;
+    }
+    names.join(" ")
+}
+
+/// The OpenAPI document given by the application, else the one `--openapi-file` names.
+fn required_openapi<'a>(
+    openapi: Option<&'a OpenAPI>,
+    matches: &ArgMatches,
+    command: &str,
+    loaded: &'a mut Option<OpenAPI>,
+) -> anyhow::Result<&'a OpenAPI> {
+    if let Some(api) = openapi {
+        return Ok(api);
+    }
+    let Some(path) = matches.get_one::<String>("openapi-file") else {
+        anyhow::bail!("{} requires --openapi-file", command);
+    };
+    let text = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read openapi file: {}", path))?;
+    Ok(loaded.insert(parse_openapi(&text)?))
+}
+
+/// "in 5m", "2h ago" and so on.
+fn describe_expiry(token: &CachedToken) -> String {
+    let Some(left) = token.seconds_left() else {
+        return "no expiry".to_string();
+    };
+    let span = |secs: i64| match secs {
+        s if s < 60 => format!("{}s", s),
+        s if s < 3600 => format!("{}m", s / 60),
+        s if s < 86400 => format!("{}h{:02}m", s / 3600, s % 3600 / 60),
+        s => format!("{}d", s / 86400),
+    };
+    if left > 0 {
+        format!("expires in {}", span(left))
+    } else {
+        format!("expired {} ago", span(-left))
+    }
+}
+
+/// Run `login`, `logout` or `whoami`.
+fn run_session_command(
+    name: &str,
+    sub_m: &ArgMatches,
+    root: &MappingRoot,
+    openapi: Option<&OpenAPI>,
+    matches: &ArgMatches,
+    config: &ExecutionConfig<'_>,
+) -> anyhow::Result<i32> {
+    let json_output = config.output.is_machine_readable();
+    let mut sources = credential_sources(root, matches)?;
+    let scheme = sub_m.get_one::<String>("scheme").map(|s| s.as_str());
+
+    match name {
+        "login" => {
+            let mut loaded = None;
+            let api = required_openapi(openapi, matches, "login", &mut loaded)?;
+            let schemes = oauth2_schemes(api);
+            let names = || {
+                schemes
+                    .iter()
+                    .map(|(n, _)| n.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            };
+            let settings = root.auth_settings();
+            let wanted = scheme.or(settings.default_scheme.as_deref());
+            let (scheme, endpoints) = match wanted {
+                Some(wanted) => schemes.iter().find(|(n, _)| n == wanted).ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "'{}' is not an OAuth2 scheme of the OpenAPI document (OAuth2 schemes: {})",
+                        wanted,
+                        names()
+                    )
+                })?,
+                None => match schemes.as_slice() {
+                    [only] => only,
+                    [] => anyhow::bail!(
+                        "the OpenAPI document declares no OAuth2 scheme with a token endpoint"
+                    ),
+                    _ => anyhow::bail!(
+                        "several OAuth2 schemes ({}); choose one with --scheme",
+                        names()
+                    ),
+                },
+            };
+            if let Some(scopes) = sub_m.get_many::<String>("scope") {
+                sources.scopes = scopes.cloned().collect();
+            }
+            let flow = sub_m.get_one::<String>("flow").map(|f| match f.as_str() {
+                "device" => LoginFlow::Device,
+                _ => LoginFlow::ClientCredentials,
+            });
+            let mut builder = reqwest::blocking::Client::builder().user_agent(config.user_agent);
+            if let Some(secs) = config.conn_timeout_secs {
+                builder = builder.connect_timeout(std::time::Duration::from_secs_f64(secs));
+            }
+            if let Some(secs) = config.request_timeout_secs {
+                builder = builder.timeout(std::time::Duration::from_secs_f64(secs));
+            }
+            let http = builder.build().context("Failed to build HTTP client")?;
+            let token = login(
+                &http,
+                scheme,
+                endpoints,
+                &sources,
+                flow,
+                settings.device_authorization_url.as_deref(),
+                |code| {
+                    eprintln!(
+                        "To log in, open {} and enter the code {}",
+                        code.verification_uri, code.user_code
+                    );
+                    if let Some(url) = &code.verification_uri_complete {
+                        eprintln!("(or open {})", url);
+                    }
+                    eprintln!("Waiting for approval...");
+                },
+            )
+            .with_context(|| format!("Login to '{}' failed", scheme))?;
+            if json_output {
+                let summary = serde_json::json!({
+                    "scheme": scheme,
+                    "flow": token.flow,
+                    "expires_at": token.expires_at,
+                    "scope": token.scope,
+                    "token_cache": sources.token_cache.path,
+                });
+                println!("{}", serde_json::to_string_pretty(&summary)?);
+            } else {
+                println!(
+                    "Logged in to '{}' with {} ({})",
+                    scheme,
+                    token.flow,
+                    describe_expiry(&token)
+                );
+            }
+            Ok(0)
+        }
+        "logout" => {
+            let removed = sources.token_cache.remove(scheme)?;
+            if json_output {
+                println!("{}", serde_json::json!({ "logged_out": removed }));
+            } else if removed.is_empty() {
+                println!("Not logged in");
+            } else {
+                for name in &removed {
+                    println!("Logged out of '{}'", name);
+                }
+            }
+            Ok(0)
+        }
+        _ => {
+            let tokens: Vec<(&String, &CachedToken)> = sources
+                .tokens
+                .iter()
+                .filter(|(n, _)| scheme.is_none_or(|s| s == n.as_str()))
+                .collect();
+            let subject = |token: &CachedToken| {
+                let claims = crate::auth::jwt_claims(&token.access_token)?;
+                ["email", "preferred_username", "name", "sub"]
+                    .iter()
+                    .find_map(|k| claims.get(*k).and_then(|v| v.as_str()).map(str::to_string))
+            };
+            if json_output {
+                let list: Vec<serde_json::Value> = tokens
+                    .iter()
+                    .map(|(name, token)| {
+                        serde_json::json!({
+                            "scheme": name,
+                            "flow": token.flow,
+                            "token_url": token.token_url,
+                            "expires_at": token.expires_at,
+                            "expired": token.seconds_left().is_some_and(|s| s <= 0),
+                            "refresh_token": token.refresh_token.is_some(),
+                            "scope": token.scope,
+                            "claims": crate::auth::jwt_claims(&token.access_token),
+                        })
+                    })
+                    .collect();
+                println!("{}", serde_json::to_string_pretty(&list)?);
+            } else if tokens.is_empty() {
+                println!("Not logged in (token cache: {})", sources.token_cache.path);
+            } else {
+                for (name, token) in &tokens {
+                    println!("{}: logged in with {}", name, token.flow);
+                    if let Some(subject) = subject(token) {
+                        println!("  subject:       {}", subject);
+                    }
+                    println!("  token:         {}", describe_expiry(token));
+                    println!(
+                        "  refresh token: {}",
+                        if token.refresh_token.is_some() {
+                            "yes"
+                        } else {
+                            "no"
+                        }
+                    );
+                    if let Some(scope) = &token.scope {
+                        println!("  scope:         {}", scope);
+                    }
+                    println!("  token url:     {}", token.token_url);
+                }
+            }
+            Ok(if tokens.is_empty() { 1 } else { 0 })
+        }
+    }
+}
+
 pub fn drive_command(
     root: &MappingRoot,
     default_base_url: &str,
     matches: &ArgMatches,
     handlers: &HandlerRegistry,
     user_agent: &str,
+) -> anyhow::Result<i32> {
+    drive_command_with_openapi(root, default_base_url, matches, handlers, user_agent, None)
+}
+
+/// Same as [`drive_command`], with the OpenAPI document used by `mapping check-openapi`
+/// and `mapping generate`.
+///
+/// Without a document, those commands fall back to `--openapi-file`.
+pub fn drive_command_with_openapi(
+    root: &MappingRoot,
+    default_base_url: &str,
+    matches: &ArgMatches,
+    handlers: &HandlerRegistry,
+    user_agent: &str,
+    openapi: Option<&OpenAPI>,
 ) -> anyhow::Result<i32> {
     let base_url = matches
         .get_one::<String>("base-url")
         .cloned()
         .unwrap_or_else(|| default_base_url.to_string());
-    let json_output = matches.get_flag("json-output");
+    let output = output_format(matches);
+    // Diagnostics, previews and custom handlers print JSON for any machine-readable format
+    let json_output = output.is_machine_readable();
     let verbose = matches.get_flag("verbose");
+    // Only this command's exchanges go to its --log-file
+    let _journal = match matches.get_one::<String>("log-file") {
+        Some(path) => Some(journal::install_scoped(Journal::open(
+            path,
+            &command_label(matches),
+        )?)),
+        None => None,
+    };
+    let mut table_settings = TableSettings::for_stdout(
+        matches
+            .get_one::<String>("table-style")
+            .and_then(|name| TableStyle::from_name(name))
+            .unwrap_or_default(),
+        matches.get_flag("wrap"),
+        matches.get_flag("no-color"),
+    );
+    if let Some(depth) = matches
+        .get_one::<usize>("column-depth")
+        .copied()
+        .or_else(|| root.column_depth())
+    {
+        table_settings.column_depth = depth;
+    }
+    let _table_settings = table::install_scoped(table_settings);
 
     let config = ExecutionConfig {
-        output: if json_output {
-            OutputFormat::Json
-        } else {
-            OutputFormat::Human
-        },
+        output,
         conn_timeout_secs: parse_timeout(matches, "conn-timeout"),
         request_timeout_secs: parse_timeout(matches, "timeout"),
         user_agent,
@@ -711,42 +1480,187 @@ pub fn drive_command(
         count: matches.get_one::<u32>("count").copied(),
         duration_secs: matches.get_one::<u32>("duration").copied().unwrap_or(0),
         concurrency: matches.get_one::<u32>("concurrency").copied().unwrap_or(1),
+        filter: ResponseFilter {
+            query: matches.get_one::<String>(QUERY_ID).cloned(),
+            raw_value: matches.get_flag(RAW_VALUE_ID),
+            columns: matches
+                .get_many::<String>("columns")
+                .map(|cols| cols.cloned().collect()),
+            conditions: matches
+                .get_many::<String>("where")
+                .into_iter()
+                .flatten()
+                .map(|expr| RowCondition::parse(expr))
+                .collect::<anyhow::Result<_>>()?,
+            sort_by: matches.get_one::<String>("sort-by").cloned(),
+            descending: matches.get_flag("desc"),
+            limit_rows: matches.get_one::<usize>("limit-rows").copied(),
+        },
     };
 
     // RAW subcommand handled here
     if let Some(("raw", raw_m)) = matches.subcommand() {
-        let method = raw_m
-            .get_one::<String>("method")
-            .cloned()
-            .unwrap_or_else(|| "GET".to_string());
-        let endpoint = raw_m
-            .get_one::<String>("endpoint")
-            .cloned()
-            .unwrap_or_default();
-        let headers: Vec<String> = raw_m
-            .get_many::<String>("header")
-            .map(|v| v.cloned().collect())
-            .unwrap_or_default();
-        let body = raw_m.get_one::<String>("body").cloned();
-        let raw_spec = RawRequestSpec {
-            base_url: Some(base_url.clone()),
-            method,
-            endpoint,
-            headers,
-            body,
-            multipart: false,
-            file_fields: HashMap::new(),
-            table_view: None,
+        // Flags override what the curl command says; headers add to it
+        let mut raw_spec = match raw_m.get_one::<String>("from-curl") {
+            Some(command) => {
+                CurlCommand::parse(&read_arg_or_stdin(command)?)?.to_raw_spec(&base_url)
+            }
+            None => RawRequestSpec {
+                base_url: Some(base_url.clone()),
+                method: "GET".to_string(),
+                endpoint: String::new(),
+                headers: Vec::new(),
+                body: None,
+                multipart: false,
+                file_fields: HashMap::new(),
+                form_fields: Vec::new(),
+                table_view: None,
+                list_path: None,
+                table_views: Default::default(),
+                detail_view: None,
+                output_template: None,
+                auth: Vec::new(),
+            },
         };
-        return execute_requests_loop(&RequestSpec::Simple(raw_spec), &config);
-    }
-
-    // Build path->command map and current path
-    let (mut app2, path_map) = build_cli(root, default_base_url);
-    let (path, leaf) = collect_subcommand_path(matches);
-    if path.is_empty() {
-        let _ = app2.print_help();
-        return Ok(0);
+        if let Some(method) = raw_m.get_one::<String>("method") {
+            raw_spec.method = method.clone();
+        }
+        if let Some(endpoint) = raw_m.get_one::<String>("endpoint") {
+            raw_spec.endpoint = endpoint.clone();
+        }
+        if let Some(headers) = raw_m.get_many::<String>("header") {
+            raw_spec.headers.extend(headers.cloned());
+        }
+        if let Some(pairs) = raw_m.get_many::<String>("param") {
+            let mut query = Vec::new();
+            for pair in pairs {
+                let (key, value) = pair
+                    .split_once('=')
+                    .with_context(|| format!("--param '{}': expected key=value", pair))?;
+                query.push(format!(
+                    "{}={}",
+                    percent_encode_component(key),
+                    percent_encode_component(value)
+                ));
+            }
+            raw_spec.endpoint = append_query(&raw_spec.endpoint, &query.join("&"));
+        }
+        if let Some(body) = raw_m.get_one::<String>("body") {
+            raw_spec.body = Some(read_arg_or_stdin(body)?);
+        }
+        if let Some(path) = raw_m.get_one::<String>("body-file") {
+            raw_spec.body = Some(
+                std::fs::read_to_string(path)
+                    .with_context(|| format!("Failed to read body file: {}", path))?,
+            );
+        }
+        if let Some(pairs) = raw_m.get_many::<String>("json") {
+            let pairs: Vec<String> = pairs.cloned().collect();
+            raw_spec.body = Some(json_body_from_pairs(&pairs)?.to_string());
+            let has_content_type = raw_spec.headers.iter().any(|h| {
+                h.split_once(':')
+                    .is_some_and(|(k, _)| k.trim().eq_ignore_ascii_case("content-type"))
+            });
+            if !has_content_type {
+                raw_spec
+                    .headers
+                    .push("Content-Type: application/json".to_string());
+            }
+        }
+        if let Some(fields) = raw_m.get_many::<String>("form") {
+            for field in fields {
+                let (name, value) = field
+                    .split_once('=')
+                    .with_context(|| format!("--form '{}': expected field=value", field))?;
+                match value.strip_prefix('@') {
+                    Some(path) => {
+                        raw_spec
+                            .file_fields
+                            .insert(name.to_string(), path.to_string());
+                    }
+                    None => raw_spec
+                        .form_fields
+                        .push((name.to_string(), value.to_string())),
+                }
+            }
+            raw_spec.multipart = true;
+            raw_spec.body = None;
+        }
+        if let Some(columns) = raw_m.get_many::<String>("table-view") {
+            raw_spec.table_view = Some(columns.map(|c| c.trim().to_string()).collect());
+        }
+        // A pasted Authorization header (or curl -u) is used as-is unless --auth is given
+        let choice = raw_m.get_one::<String>("auth").map(|s| s.as_str());
+        let own_credentials = raw_spec.headers.iter().any(|h| {
+            h.split_once(':')
+                .is_some_and(|(k, _)| k.trim().eq_ignore_ascii_case("authorization"))
+        });
+        if choice.is_some() || !own_credentials {
+            let sources = credential_sources(root, matches)?;
+            raw_spec.auth = request_auth(
+                openapi,
+                root,
+                &sources,
+                choice,
+                &raw_spec.method,
+                &raw_spec.endpoint,
+                &base_url,
+            )?;
+        }
+        let spec = RequestSpec::Simple(raw_spec);
+        if let Some(format) = dry_run_format(matches, json_output) {
+            print!("{}", dry_run::render(&spec, format)?);
+            return Ok(0);
+        }
+        return execute_requests_loop(&spec, &config);
+    }
+
+    if let Some(("mapping", mapping_m)) = matches.subcommand() {
+        return match mapping_m.subcommand() {
+            Some(("lint", _)) => report_diagnostics(&validate_mapping(root), json_output),
+            Some((sub @ ("check-openapi" | "generate"), _)) => {
+                let mut loaded = None;
+                let api =
+                    required_openapi(openapi, matches, &format!("mapping {}", sub), &mut loaded)?;
+                if sub == "generate" {
+                    let spec = generate_from_openapi(api, &GenerateOptions::default());
+                    print!("{}", serde_yaml::to_string(&spec)?);
+                    Ok(0)
+                } else {
+                    report_diagnostics(&check_mapping_against_openapi(root, api), json_output)
+                }
+            }
+            Some(("add-from-curl", add_m)) => {
+                let command = add_m
+                    .get_one::<String>("curl")
+                    .map(|s| s.as_str())
+                    .unwrap_or_default();
+                let curl = CurlCommand::parse(&read_arg_or_stdin(command)?)?;
+                let name = add_m.get_one::<String>("name").map(|s| s.as_str());
+                let spec = curl.to_command_spec(name, &base_url)?;
+                print!("{}", serde_yaml::to_string(&vec![spec])?);
+                Ok(0)
+            }
+            _ => Ok(2),
+        };
+    }
+
+    // Build path->command map and current path
+    let (mut app2, path_map) = build_cli_with_openapi(root, default_base_url, openapi);
+    if let Some((name, sub_m)) = matches.subcommand() {
+        let generated = SESSION_COMMANDS.contains(&name)
+            && !path_map
+                .keys()
+                .any(|p| p.first().map(|s| s.as_str()) == Some(name));
+        if generated {
+            return run_session_command(name, sub_m, root, openapi, matches, &config);
+        }
+    }
+
+    let (path, leaf) = collect_subcommand_path(matches);
+    if path.is_empty() {
+        let _ = app2.print_help();
+        return Ok(0);
     }
 
     if let Some(cmd) = path_map.get(&path) {
@@ -755,7 +1669,49 @@ pub fn drive_command(
             print_manual_help(&path, cmd);
             return Ok(2);
         }
-        let spec = build_request_from_command(Some(base_url.clone()), cmd, &vars, &selected);
+        let spec = try_build_request_from_command(
+            Some(base_url.clone()),
+            cmd,
+            &vars,
+            &selected,
+            root.strict_templates(),
+        )
+        .with_context(|| format!("Cannot build request for '{}'", path.join(" ")))?;
+        let mut spec = spec;
+        attach_auth(&mut spec, cmd, openapi, root, matches, &base_url)
+            .with_context(|| format!("Cannot authenticate '{}'", path.join(" ")))?;
+        if let Some(format) = dry_run_format(matches, json_output) {
+            print!("{}", dry_run::render(&spec, format)?);
+            return Ok(0);
+        }
+        if matches.get_flag("all") {
+            let (Some(pagination), RequestSpec::Simple(first)) = (&cmd.pagination, &spec) else {
+                anyhow::bail!(
+                    "'{}' has no pagination: block, which --all needs",
+                    path.join(" ")
+                );
+            };
+            let build_page = |vars: &HashMap<String, String>| {
+                let spec = try_build_request_from_command(
+                    Some(base_url.clone()),
+                    cmd,
+                    vars,
+                    &selected,
+                    root.strict_templates(),
+                )?;
+                let RequestSpec::Simple(mut raw) = spec else {
+                    anyhow::bail!("--all only pages single requests");
+                };
+                raw.auth = first.auth.clone();
+                Ok(raw)
+            };
+            let max_pages = matches.get_one::<u32>("max-pages").copied();
+            return execute_paginated(first, pagination, &vars, build_page, &config, max_pages);
+        }
+        if let (Some(StreamType::Sse), RequestSpec::Simple(raw)) = (cmd.stream, &spec) {
+            let max_events = matches.get_one::<u64>("max-events").copied();
+            return execute_sse(raw, &config, max_events);
+        }
         match &spec {
             RequestSpec::CustomHandler { handler_name, vars } => {
                 let h = handlers
@@ -1023,6 +1979,7 @@ commands:
                 ..Default::default()
             }],
             use_common_args: vec![],
+            ..Default::default()
         };
 
         let yaml = r#"
@@ -1068,6 +2025,7 @@ commands:
                 ..Default::default()
             }],
             use_common_args: vec![],
+            ..Default::default()
         };
 
         let yaml = r#"
@@ -1222,6 +2180,7 @@ commands:
                 ..Default::default()
             }],
             use_common_args: vec![],
+            ..Default::default()
         };
         // Just verify it doesn't panic
         print_manual_help(&["users".to_string(), "list".to_string()], &cmd);
@@ -1243,6 +2202,7 @@ commands:
             custom_handler: None,
             args: vec![],
             use_common_args: vec![],
+            ..Default::default()
         };
         // Just verify it doesn't panic
         print_manual_help(&["users".to_string(), "list".to_string()], &cmd);
@@ -1270,6 +2230,7 @@ commands:
                 ..Default::default()
             }],
             use_common_args: vec![],
+            ..Default::default()
         };
         // Just verify it doesn't panic
         print_manual_help(&["users".to_string(), "get".to_string()], &cmd);
@@ -1339,6 +2300,7 @@ commands:
                 ..Default::default()
             }],
             use_common_args: vec![],
+            ..Default::default()
         };
 
         // Build CLI with the arg defined but not required by clap
@@ -1387,6 +2349,7 @@ commands:
                 ..Default::default()
             }],
             use_common_args: vec![],
+            ..Default::default()
         };
 
         let yaml = r#"
@@ -1440,6 +2403,7 @@ commands:
             custom_handler: None,
             args: vec![], // Empty - will derive from pattern
             use_common_args: vec![],
+            ..Default::default()
         };
 
         // Use flat spec with pattern which derives args automatically
@@ -1579,6 +2543,168 @@ commands:
         assert_eq!(leaf.get_one::<String>("offset"), Some(&"0".to_string()));
     }
 
+    // ==================== typed args tests ====================
+
+    #[test]
+    fn test_typed_args_reject_invalid_values() {
+        let yaml = r#"
+commands:
+  - name: items
+    subcommands:
+      - name: list
+        method: GET
+        endpoint: /items?limit={limit}&order={order}&sku={sku}
+        args:
+          - name: limit
+            long: limit
+            type: int
+            min: 1
+            max: 100
+          - name: order
+            long: order
+            type: enum
+            choices: [asc, desc]
+          - name: sku
+            long: sku
+            regex: "^[A-Z]{3}-[0-9]+$"
+"#;
+        let root = parse_mapping_root(yaml).unwrap();
+        let parse = |extra: &[&str]| {
+            let (app, _) = build_cli(&root, "https://api.example.com");
+            let mut argv = vec!["cli", "items", "list"];
+            argv.extend_from_slice(extra);
+            app.try_get_matches_from(argv)
+        };
+
+        let matches = parse(&["--limit", "10", "--order", "desc", "--sku", "ABC-1"]).unwrap();
+        let (_, leaf) = collect_subcommand_path(&matches);
+        // Typed values are still exposed as strings
+        assert_eq!(leaf.get_one::<String>("limit"), Some(&"10".to_string()));
+        assert_eq!(leaf.get_one::<String>("order"), Some(&"desc".to_string()));
+
+        for bad in [
+            vec!["--limit", "abc"],
+            vec!["--limit", "0"],
+            vec!["--limit", "101"],
+            vec!["--order", "random"],
+            vec!["--sku", "abc-1"],
+        ] {
+            let err = parse(&bad).unwrap_err();
+            assert!(
+                matches!(
+                    err.kind(),
+                    clap::error::ErrorKind::ValueValidation | clap::error::ErrorKind::InvalidValue
+                ),
+                "{:?}",
+                bad
+            );
+        }
+    }
+
+    #[test]
+    fn test_build_cli_with_openapi_derives_arg_types() {
+        let api = crate::parse_openapi(
+            r#"
+openapi: 3.0.3
+info:
+  title: Test API
+  version: 1.0.0
+paths:
+  /items/{id}:
+    get:
+      parameters:
+        - in: path
+          name: id
+          required: true
+          schema:
+            type: integer
+        - in: query
+          name: view
+          schema:
+            type: string
+            enum: [short, full]
+      responses:
+        '200':
+          description: ok
+"#,
+        )
+        .unwrap();
+        let mapping = |opt_in: bool| {
+            format!(
+                r#"
+openapi_arg_types: {}
+commands:
+  - name: items
+    subcommands:
+      - name: get
+        method: GET
+        endpoint: /items/{{id}}?view={{view}}
+        args:
+          - name: id
+            positional: true
+            required: true
+          - name: view
+            long: view
+"#,
+                opt_in
+            )
+        };
+
+        let root = parse_mapping_root(&mapping(true)).unwrap();
+        let (app, path_map) = build_cli_with_openapi(&root, "https://x", Some(&api));
+        let leaf = &path_map[&vec!["items".to_string(), "get".to_string()]];
+        assert_eq!(leaf.args[0].arg_type.as_deref(), Some("int"));
+        assert_eq!(leaf.args[1].arg_type.as_deref(), Some("enum"));
+        assert!(app
+            .try_get_matches_from(["cli", "items", "get", "abc"])
+            .is_err());
+        let (app, _) = build_cli_with_openapi(&root, "https://x", Some(&api));
+        assert!(app
+            .try_get_matches_from(["cli", "items", "get", "7", "--view", "wide"])
+            .is_err());
+
+        // Without the opt-in the spec is ignored
+        let root = parse_mapping_root(&mapping(false)).unwrap();
+        let (app, _) = build_cli_with_openapi(&root, "https://x", Some(&api));
+        assert!(app
+            .try_get_matches_from(["cli", "items", "get", "abc"])
+            .is_ok());
+    }
+
+    #[test]
+    fn test_multiple_arg_collects_all_values() {
+        let yaml = r#"
+commands:
+  - name: items
+    subcommands:
+      - name: list
+        method: GET
+        endpoint: /items
+        query:
+          tag: "{tag}"
+        args:
+          - name: tag
+            long: tag
+            multiple: true
+"#;
+        let root = parse_mapping_root(yaml).unwrap();
+        let (app, path_map) = build_cli(&root, "https://api.example.com");
+        let matches = app
+            .try_get_matches_from(["cli", "items", "list", "--tag", "a", "--tag", "b,c"])
+            .unwrap();
+        let (path, leaf) = collect_subcommand_path(&matches);
+        let (vars, selected, _) = collect_vars_from_matches(&path_map[&path], leaf);
+        assert_eq!(vars.get("tag"), Some(&"a\0b,c".to_string()));
+
+        // Each value is its own query parameter, commas included; templates see them joined
+        let RequestSpec::Simple(raw) =
+            crate::build_request_from_command(None, &path_map[&path], &vars, &selected)
+        else {
+            panic!("Expected RequestSpec::Simple");
+        };
+        assert_eq!(raw.endpoint, "/items?tag=a&tag=b%2Cc");
+    }
+
     // ==================== merge_arg_specs coverage ====================
 
     #[test]
@@ -1757,6 +2883,891 @@ commands:
         }
     }
 
+    // ==================== mapping subcommand tests ====================
+
+    #[test]
+    fn test_mapping_lint_command_exists() {
+        let yaml = r#"
+commands:
+  - name: test
+    subcommands:
+      - name: cmd
+        method: GET
+        endpoint: /test
+"#;
+        let root = parse_mapping_root(yaml).unwrap();
+        let (app, _) = build_cli(&root, "https://api.example.com");
+        let matches = app
+            .try_get_matches_from(["cli", "mapping", "lint"])
+            .unwrap();
+
+        // collect_subcommand_path stops at the built-in "mapping" command
+        let (path, _) = collect_subcommand_path(&matches);
+        assert_eq!(path, Vec::<String>::new());
+        assert!(matches!(matches.subcommand(), Some(("mapping", _))));
+    }
+
+    #[test]
+    fn test_run_mapping_lint_exit_codes() {
+        let ok = parse_mapping_root(
+            r#"
+commands:
+  - name: test
+    subcommands:
+      - name: cmd
+        method: GET
+        endpoint: /test
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            report_diagnostics(&validate_mapping(&ok), false).unwrap(),
+            0
+        );
+
+        let broken = parse_mapping_root(
+            r#"
+commands:
+  - name: test
+    subcommands:
+      - name: cmd
+        method: GET
+        endpoint: /test/{missing}
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            report_diagnostics(&validate_mapping(&broken), true).unwrap(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_mapping_check_openapi_exit_codes() {
+        let api = crate::parse_openapi(
+            r#"
+openapi: 3.0.3
+info:
+  title: Test API
+  version: 1.0.0
+paths:
+  /test:
+    get:
+      responses:
+        '200':
+          description: ok
+"#,
+        )
+        .unwrap();
+        let run = |yaml: &str| {
+            let root = parse_mapping_root(yaml).unwrap();
+            let (app, _) = build_cli(&root, "https://api.example.com");
+            let matches = app
+                .try_get_matches_from(["cli", "mapping", "check-openapi"])
+                .unwrap();
+            drive_command_with_openapi(
+                &root,
+                "https://api.example.com",
+                &matches,
+                &HandlerRegistry::new(),
+                "test",
+                Some(&api),
+            )
+            .unwrap()
+        };
+        let mapping = |method: &str| {
+            format!(
+                "commands:\n  - name: test\n    subcommands:\n      - name: cmd\n        method: {}\n        endpoint: /test\n",
+                method
+            )
+        };
+        assert_eq!(run(&mapping("GET")), 0);
+        assert_eq!(run(&mapping("POST")), 1);
+    }
+
+    #[test]
+    fn test_strict_templates_error_names_command_and_field() {
+        let yaml = r#"
+strict_templates: true
+commands:
+  - name: users
+    subcommands:
+      - name: delete
+        method: DELETE
+        endpoint: /users/{id}
+        args:
+          - name: id
+            long: id
+"#;
+        let root = parse_mapping_root(yaml).unwrap();
+        let (app, _) = build_cli(&root, "http://127.0.0.1:9");
+        let matches = app
+            .try_get_matches_from(["cli", "users", "delete"])
+            .unwrap();
+        // Fails before any request is sent
+        let err = drive_command(
+            &root,
+            "http://127.0.0.1:9",
+            &matches,
+            &HandlerRegistry::new(),
+            "test",
+        )
+        .unwrap_err();
+        assert_eq!(
+            format!("{:#}", err),
+            "Cannot build request for 'users delete': endpoint: unresolved placeholders: {id}"
+        );
+    }
+
+    // ==================== pagination tests ====================
+
+    const PAGED_MAPPING: &str = r#"
+commands:
+  - name: items
+    subcommands:
+      - name: list
+        method: GET
+        endpoint: /items
+        query:
+          skip: "{skip}"
+          limit: "{limit}"
+        list_path: "$.items"
+        pagination:
+          type: offset
+          offset_var: skip
+          total: "$.total"
+        args:
+          - name: skip
+            long: skip
+          - name: limit
+            long: limit
+      - name: feed
+        method: GET
+        endpoint: /feed
+        pagination:
+          type: link
+      - name: get
+        method: GET
+        endpoint: /items/1
+"#;
+
+    fn run_paged(server: &crate::test_server::TestServer, argv: &[&str]) -> anyhow::Result<i32> {
+        let root = parse_mapping_root(PAGED_MAPPING).unwrap();
+        let (app, _) = build_cli(&root, &server.url);
+        let mut full = vec!["cli"];
+        full.extend_from_slice(argv);
+        let matches = app.try_get_matches_from(full).unwrap();
+        drive_command(
+            &root,
+            &server.url,
+            &matches,
+            &HandlerRegistry::new(),
+            "test",
+        )
+    }
+
+    #[test]
+    fn test_all_follows_offset_pages() {
+        use crate::test_server::{Reply, TestServer};
+        // Five items, two per page
+        let server = TestServer::start(|req| {
+            let skip: usize = req
+                .target
+                .split(['?', '&'])
+                .find_map(|kv| kv.strip_prefix("skip="))
+                .and_then(|n| n.parse().ok())
+                .unwrap_or(0);
+            let items: Vec<String> = (skip..(skip + 2).min(5))
+                .map(|i| format!("{{\"id\":{}}}", i))
+                .collect();
+            Reply::json(
+                200,
+                &format!(
+                    "{{\"items\":[{}],\"total\":5,\"skip\":{}}}",
+                    items.join(","),
+                    skip
+                ),
+            )
+        });
+
+        assert_eq!(
+            run_paged(
+                &server,
+                &["-o", "ndjson", "--all", "items", "list", "--limit", "2"]
+            )
+            .unwrap(),
+            0
+        );
+        let targets: Vec<String> = server.requests().iter().map(|r| r.target.clone()).collect();
+        assert_eq!(
+            targets,
+            [
+                "/items?limit=2",
+                "/items?skip=2&limit=2",
+                "/items?skip=4&limit=2"
+            ]
+        );
+
+        // --max-pages stops early
+        let server2 =
+            TestServer::start(|_| Reply::json(200, r#"{"items":[{"id":1},{"id":2}],"total":100}"#));
+        assert_eq!(
+            run_paged(&server2, &["--all", "--max-pages", "2", "items", "list"]).unwrap(),
+            0
+        );
+        assert_eq!(server2.requests().len(), 2);
+    }
+
+    #[test]
+    fn test_all_follows_link_headers() {
+        use crate::test_server::{Reply, TestServer};
+        let server = TestServer::start(|req| match req.target.as_str() {
+            "/feed" => Reply::json(200, r#"[{"id":1}]"#).with_header(
+                "Link",
+                r#"</feed?page=2>; rel="next", </feed?page=3>; rel="last""#,
+            ),
+            "/feed?page=2" => Reply::json(200, r#"[{"id":2}]"#),
+            _ => Reply::json(404, "{}"),
+        });
+        assert_eq!(
+            run_paged(&server, &["-o", "json", "--all", "items", "feed"]).unwrap(),
+            0
+        );
+        let targets: Vec<String> = server.requests().iter().map(|r| r.target.clone()).collect();
+        assert_eq!(targets, ["/feed", "/feed?page=2"]);
+
+        // A command without pagination: says so
+        let err = run_paged(&server, &["--all", "items", "get"]).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("'items get' has no pagination: block"));
+    }
+
+    // ==================== stream tests ====================
+
+    const STREAM_MAPPING: &str = r#"
+commands:
+  - name: events
+    subcommands:
+      - name: tail
+        method: GET
+        endpoint: /events
+        stream: sse
+"#;
+
+    fn run_stream(server: &crate::test_server::TestServer, argv: &[&str]) -> anyhow::Result<i32> {
+        let root = parse_mapping_root(STREAM_MAPPING).unwrap();
+        let (app, _) = build_cli(&root, &server.url);
+        let mut full = vec!["cli"];
+        full.extend_from_slice(argv);
+        let matches = app.try_get_matches_from(full).unwrap();
+        drive_command(
+            &root,
+            &server.url,
+            &matches,
+            &HandlerRegistry::new(),
+            "test",
+        )
+    }
+
+    #[test]
+    fn test_sse_reconnects_with_last_event_id() {
+        use crate::test_server::{Reply, TestServer};
+        // Each connection sends the events after Last-Event-ID, two at a time, and closes
+        let server = TestServer::start(|req| {
+            let after: u32 = req
+                .header("last-event-id")
+                .and_then(|id| id.parse().ok())
+                .unwrap_or(0);
+            let body: String = (after + 1..=after + 2)
+                .map(|n| format!("id: {}\ndata: {{\"n\":{}}}\n\n", n, n))
+                .collect();
+            Reply::sse(200, &format!("retry: 10\n{}", body))
+        });
+        assert_eq!(
+            run_stream(
+                &server,
+                &["-o", "ndjson", "--max-events", "5", "events", "tail"]
+            )
+            .unwrap(),
+            0
+        );
+        // The third connection brings the fifth event
+        let requests = server.requests();
+        assert!(requests.len() >= 3);
+        assert_eq!(requests[0].header("accept"), Some("text/event-stream"));
+        assert_eq!(requests[0].header("last-event-id"), None);
+        assert_eq!(requests[1].header("last-event-id"), Some("2"));
+        assert_eq!(requests[2].header("last-event-id"), Some("4"));
+    }
+
+    #[test]
+    fn test_sse_stream_ends() {
+        use crate::test_server::{Reply, TestServer};
+        // Without ids the stream cannot be resumed, so it ends with the connection
+        let server = TestServer::start(|_| Reply::sse(200, "data: one\n\ndata: [DONE]\n\n"));
+        assert_eq!(
+            run_stream(&server, &["--query", "$.n", "events", "tail"]).unwrap(),
+            0
+        );
+        assert_eq!(server.requests().len(), 1);
+
+        // 204 tells the client not to reconnect
+        let server = TestServer::start(|req| match req.header("last-event-id") {
+            None => Reply::sse(200, "retry: 10\nid: 1\ndata: x\n\n"),
+            Some(_) => Reply::sse(204, ""),
+        });
+        assert_eq!(
+            run_stream(&server, &["-o", "raw", "events", "tail"]).unwrap(),
+            0
+        );
+        assert_eq!(server.requests().len(), 2);
+
+        // Error statuses and other content types fail
+        let server = TestServer::start(|_| Reply::json(503, r#"{"error":"busy"}"#));
+        assert_eq!(run_stream(&server, &["events", "tail"]).unwrap(), 1);
+        let server = TestServer::start(|_| Reply::json(200, "{}"));
+        let err = run_stream(&server, &["events", "tail"]).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("Expected a text/event-stream response, got 'application/json'"));
+    }
+
+    // ==================== auth tests ====================
+
+    const AUTH_SPEC: &str = r#"
+openapi: 3.0.3
+info:
+  title: Test API
+  version: 1.0.0
+security:
+  - bearerAuth: []
+paths:
+  /me:
+    get:
+      responses:
+        '200':
+          description: ok
+  /search:
+    get:
+      security:
+        - keyQuery: []
+      responses:
+        '200':
+          description: ok
+components:
+  securitySchemes:
+    bearerAuth:
+      type: http
+      scheme: bearer
+    keyQuery:
+      type: apiKey
+      in: query
+      name: api_key
+"#;
+
+    const AUTH_MAPPING: &str = r#"
+commands:
+  - name: me
+    subcommands:
+      - name: get
+        method: GET
+        endpoint: /me
+      - name: anonymous
+        method: GET
+        endpoint: /me
+        auth: none
+      - name: search
+        method: GET
+        endpoint: /search
+        query:
+          q: "{q}"
+        args:
+          - name: q
+            long: q
+"#;
+
+    fn run_against(server: &crate::test_server::TestServer, argv: &[&str]) -> anyhow::Result<i32> {
+        let api = crate::parse_openapi(AUTH_SPEC).unwrap();
+        let root = parse_mapping_root(AUTH_MAPPING).unwrap();
+        let (app, _) = build_cli_with_openapi(&root, &server.url, Some(&api));
+        let mut full = vec!["cli"];
+        full.extend_from_slice(argv);
+        let matches = app.try_get_matches_from(full).unwrap();
+        drive_command_with_openapi(
+            &root,
+            &server.url,
+            &matches,
+            &HandlerRegistry::new(),
+            "test",
+            Some(&api),
+        )
+    }
+
+    #[test]
+    fn test_auth_applied_from_security_schemes() {
+        use crate::test_server::{Reply, TestServer};
+        let server = TestServer::start(|_| Reply::json(200, "{}"));
+
+        assert_eq!(
+            run_against(&server, &["-j", "--token", "s3cret", "me", "get"]).unwrap(),
+            0
+        );
+        assert_eq!(
+            run_against(&server, &["-j", "--token", "s3cret", "me", "anonymous"]).unwrap(),
+            0
+        );
+        assert_eq!(
+            run_against(
+                &server,
+                &["-j", "--api-key", "k 1", "me", "search", "--q", "x"]
+            )
+            .unwrap(),
+            0
+        );
+        assert_eq!(
+            run_against(
+                &server,
+                &[
+                    "-j",
+                    "raw",
+                    "--method",
+                    "POST",
+                    "--endpoint",
+                    "/me",
+                    "--body",
+                    "{}",
+                    "--auth",
+                    "none"
+                ]
+            )
+            .unwrap(),
+            0
+        );
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 4);
+        assert_eq!(requests[0].header("authorization"), Some("Bearer s3cret"));
+        assert_eq!(requests[1].header("authorization"), None);
+        assert_eq!(requests[2].target, "/search?q=x&api_key=k+1");
+        assert_eq!(requests[3].method, "POST");
+        assert_eq!(requests[3].body, "{}");
+        assert_eq!(requests[3].header("authorization"), None);
+    }
+
+    #[test]
+    fn test_auth_only_sent_below_the_base_url() {
+        let base = "https://api.example.com/v1";
+        assert_eq!(
+            path_under_base("https://api.example.com/v1/me?x=1", base).as_deref(),
+            Some("/me?x=1")
+        );
+        assert_eq!(
+            path_under_base("https://API.example.com:443/v1", base).as_deref(),
+            Some("")
+        );
+        for other in [
+            "https://api.example.com.evil.io/v1/me",
+            "https://api.example.com@evil.io/v1/me",
+            "https://api.example.com:8443/v1/me",
+            "http://api.example.com/v1/me",
+            "https://api.example.com/v10/me",
+            "https://api.example.com/me",
+        ] {
+            assert_eq!(path_under_base(other, base), None, "{}", other);
+        }
+
+        // No credentials for look-alike hosts, even with --token given
+        let api = crate::parse_openapi(AUTH_SPEC).unwrap();
+        let root = parse_mapping_root(AUTH_MAPPING).unwrap();
+        let flags = Credentials {
+            token: Some("s3cret".to_string()),
+            ..Credentials::default()
+        };
+        let sources = CredentialSources::load(flags, &root.auth_settings(), None).unwrap();
+        let auth_for = |endpoint: &str| {
+            request_auth(Some(&api), &root, &sources, None, "GET", endpoint, base).unwrap()
+        };
+        assert_eq!(
+            auth_for("https://api.example.com/v1/me"),
+            vec![RequestAuth::Bearer("s3cret".to_string())]
+        );
+        assert!(auth_for("https://api.example.com.evil.io/v1/me").is_empty());
+        assert!(auth_for("https://api.example.com@evil.io/v1/me").is_empty());
+    }
+
+    #[test]
+    fn test_auth_missing_credentials_is_an_error() {
+        use crate::test_server::{Reply, TestServer};
+        let server = TestServer::start(|_| Reply::json(200, "{}"));
+        let err = run_against(&server, &["me", "get"]).unwrap_err();
+        assert_eq!(
+            format!("{:#}", err),
+            "Cannot authenticate 'me get': missing credentials for bearerAuth \
+             (use --token or the credentials file)"
+        );
+        assert!(server.requests().is_empty());
+    }
+
+    #[test]
+    fn test_oauth2_login_whoami_logout() {
+        use crate::test_server::{Reply, TestServer};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        let issued = AtomicUsize::new(0);
+        let server = TestServer::start(move |req| match req.target.as_str() {
+            "/oauth/token" => {
+                let n = issued.fetch_add(1, Ordering::SeqCst) + 1;
+                Reply::json(
+                    200,
+                    &format!(r#"{{"access_token":"tok-{}","expires_in":3600}}"#, n),
+                )
+            }
+            // The first token is revoked server-side
+            _ if req.header("authorization") == Some("Bearer tok-2") => Reply::json(200, "[]"),
+            _ => Reply::json(401, r#"{"error":"invalid_token"}"#),
+        });
+        let dir = std::env::temp_dir().join(format!("rclib-cli-oauth-{}", uuid::Uuid::new_v4()));
+        let cache = dir.join("tokens.json");
+        let spec = format!(
+            r#"
+openapi: 3.0.3
+info:
+  title: Test API
+  version: 1.0.0
+security:
+  - oauth: []
+paths:
+  /orders:
+    get:
+      responses:
+        '200':
+          description: ok
+components:
+  securitySchemes:
+    oauth:
+      type: oauth2
+      flows:
+        clientCredentials:
+          tokenUrl: {}/oauth/token
+          scopes: {{}}
+"#,
+            server.url
+        );
+        let mapping = format!(
+            r#"
+auth:
+  token_cache: {}
+commands:
+  - name: orders
+    subcommands:
+      - name: list
+        method: GET
+        endpoint: /orders
+"#,
+            cache.display()
+        );
+        let api = crate::parse_openapi(&spec).unwrap();
+        let root = parse_mapping_root(&mapping).unwrap();
+        let run = |argv: &[&str]| {
+            let (app, _) = build_cli_with_openapi(&root, &server.url, Some(&api));
+            let mut full = vec!["cli", "--client-id", "app", "--client-secret", "s3"];
+            full.extend_from_slice(argv);
+            let matches = app.try_get_matches_from(full).unwrap();
+            drive_command_with_openapi(
+                &root,
+                &server.url,
+                &matches,
+                &HandlerRegistry::new(),
+                "test",
+                Some(&api),
+            )
+        };
+
+        assert_eq!(run(&["login"]).unwrap(), 0);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&cache).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        // tok-1 is rejected, so a new token is fetched and the request retried
+        assert_eq!(run(&["-j", "orders", "list"]).unwrap(), 0);
+        assert_eq!(run(&["whoami"]).unwrap(), 0);
+        assert_eq!(run(&["logout"]).unwrap(), 0);
+        assert_eq!(run(&["whoami"]).unwrap(), 1);
+
+        let requests: Vec<(String, Option<String>)> = server
+            .requests()
+            .into_iter()
+            .map(|r| {
+                let auth = r.header("authorization").map(str::to_string);
+                (r.target, auth)
+            })
+            .collect();
+        let basic = Some("Basic YXBwOnMz".to_string());
+        assert_eq!(
+            requests,
+            vec![
+                ("/oauth/token".to_string(), basic.clone()),
+                ("/orders".to_string(), Some("Bearer tok-1".to_string())),
+                ("/oauth/token".to_string(), basic),
+                ("/orders".to_string(), Some("Bearer tok-2".to_string())),
+            ]
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_session_commands_yield_to_mapping_names() {
+        let yaml = r#"
+commands:
+  - name: login
+    method: POST
+    endpoint: /login
+"#;
+        let root = parse_mapping_root(yaml).unwrap();
+        let (app, _) = build_cli(&root, "https://api.example.com");
+        let login = app
+            .get_subcommands()
+            .find(|c| c.get_name() == "login")
+            .unwrap();
+        assert_eq!(login.get_about(), None);
+        assert!(app.get_subcommands().any(|c| c.get_name() == "whoami"));
+    }
+
+    // ==================== log file tests ====================
+
+    #[test]
+    fn test_table_settings_revert_after_the_command() {
+        use crate::test_server::{Reply, TestServer};
+        let server = TestServer::start(|_| Reply::json(200, r#"[{"id":1}]"#));
+        // An unusual depth, so other tests running at the same time cannot produce it
+        assert_eq!(
+            run_against(
+                &server,
+                &["--column-depth", "17", "--token", "s3cret", "me", "get"]
+            )
+            .unwrap(),
+            0
+        );
+        assert_ne!(table::settings().column_depth, 17);
+    }
+
+    #[test]
+    fn test_log_file_journals_every_exchange() {
+        use crate::test_server::{Reply, TestServer};
+        let server = TestServer::start(|req| match req.target.as_str() {
+            "/me" => Reply::json(200, r#"{"id":1,"token":"secret-1"}"#),
+            _ => Reply::json(404, r#"{"message":"nope"}"#),
+        });
+        let path = std::env::temp_dir().join(format!("rclib-log-{}.jsonl", uuid::Uuid::new_v4()));
+        let log = path.to_str().unwrap();
+
+        assert_eq!(
+            run_against(
+                &server,
+                &["-j", "-l", log, "--token", "s3cret", "me", "get"]
+            )
+            .unwrap(),
+            0
+        );
+        assert_eq!(
+            run_against(
+                &server,
+                &[
+                    "-j",
+                    "-l",
+                    log,
+                    "-n",
+                    "2",
+                    "raw",
+                    "--method",
+                    "GET",
+                    "--endpoint",
+                    "/missing",
+                    "--auth",
+                    "none"
+                ]
+            )
+            .unwrap(),
+            1
+        );
+        // The journal is gone once the command returns
+        assert!(crate::journal::current().is_none_or(|j| j.path != log));
+
+        // Other tests may send requests while the journal is installed; keep only this
+        // server's lines
+        let lines: Vec<serde_json::Value> = std::fs::read_to_string(&path)
+            .unwrap()
+            .lines()
+            .map(|l| serde_json::from_str::<serde_json::Value>(l).unwrap())
+            .filter(|l| {
+                l["request"]["url"]
+                    .as_str()
+                    .is_some_and(|u| u.starts_with(&server.url))
+            })
+            .collect();
+        assert_eq!(lines.len(), 3);
+        let me = &lines[0];
+        assert_eq!(me["command"], "me get");
+        assert_eq!(me["request"]["method"], "GET");
+        assert_eq!(me["request"]["url"], format!("{}/me", server.url));
+        assert_eq!(me["request"]["headers"]["authorization"], "Bearer ****");
+        assert_eq!(me["response"]["status"], 200);
+        assert_eq!(
+            me["response"]["headers"]["content-type"],
+            "application/json"
+        );
+        assert_eq!(me["response"]["body"], r#"{"id":1,"token":"****"}"#);
+        assert!(me["latency_ms"].is_u64());
+        assert!(me["timestamp"].is_string());
+        // Every request of a perf run is logged
+        for line in &lines[1..] {
+            assert_eq!(line["command"], "raw");
+            assert_eq!(line["response"]["status"], 404);
+        }
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_dry_run_sends_nothing() {
+        use crate::test_server::{Reply, TestServer};
+        let server = TestServer::start(|_| Reply::json(200, "{}"));
+
+        assert_eq!(
+            run_against(&server, &["--dry-run", "--token", "s3cret", "me", "get"]).unwrap(),
+            0
+        );
+        assert_eq!(
+            run_against(
+                &server,
+                &[
+                    "--as-curl",
+                    "--token",
+                    "s3cret",
+                    "raw",
+                    "--method",
+                    "GET",
+                    "--endpoint",
+                    "/me"
+                ]
+            )
+            .unwrap(),
+            0
+        );
+        assert!(server.requests().is_empty());
+
+        let api = crate::parse_openapi(AUTH_SPEC).unwrap();
+        let root = parse_mapping_root(AUTH_MAPPING).unwrap();
+        let (app, _) = build_cli_with_openapi(&root, &server.url, Some(&api));
+        assert!(app
+            .try_get_matches_from(["cli", "--dry-run", "--as-curl", "me", "get"])
+            .is_err());
+    }
+
+    #[test]
+    fn test_raw_from_curl() {
+        use crate::test_server::{Reply, TestServer};
+        let server = TestServer::start(|_| Reply::json(200, "{}"));
+        let pasted = format!(
+            "curl '{}/me?x=1' -H 'Authorization: Bearer pasted' -H 'X-A: 1' --data-raw '{{\"a\":1}}'",
+            server.url
+        );
+        assert_eq!(
+            run_against(
+                &server,
+                &[
+                    "-j",
+                    "--token",
+                    "s3cret",
+                    "raw",
+                    "--from-curl",
+                    &pasted,
+                    "--method",
+                    "PUT",
+                    "-H",
+                    "X-B: 2"
+                ]
+            )
+            .unwrap(),
+            0
+        );
+        let requests = server.requests();
+        assert_eq!(requests.len(), 1);
+        assert_eq!(requests[0].method, "PUT");
+        assert_eq!(requests[0].target, "/me?x=1");
+        // The pasted credentials win over the spec's scheme
+        assert_eq!(requests[0].header("authorization"), Some("Bearer pasted"));
+        assert_eq!(requests[0].header("x-a"), Some("1"));
+        assert_eq!(requests[0].header("x-b"), Some("2"));
+        assert_eq!(requests[0].body, r#"{"a":1}"#);
+    }
+
+    #[test]
+    fn test_raw_query_json_form_and_body_file() {
+        use crate::test_server::{Reply, TestServer};
+        let server = TestServer::start(|_| Reply::json(200, "{}"));
+        let raw = |extra: &[&str]| {
+            let mut argv = vec!["-j", "raw", "--auth", "none", "--method", "POST"];
+            argv.extend_from_slice(extra);
+            run_against(&server, &argv).unwrap()
+        };
+
+        assert_eq!(
+            raw(&[
+                "--endpoint",
+                "/items?a=1",
+                "-p",
+                "q=red shoes&more",
+                "--json",
+                "name=Ada",
+                "--json",
+                "meta.n:=2"
+            ]),
+            0
+        );
+        let upload = std::env::temp_dir().join(format!("rclib-form-{}.txt", uuid::Uuid::new_v4()));
+        std::fs::write(&upload, "file-contents").unwrap();
+        let file_field = format!("doc=@{}", upload.display());
+        assert_eq!(
+            raw(&["--endpoint", "/up", "-F", "note=hi", "-F", &file_field]),
+            0
+        );
+        let body_file = upload.to_str().unwrap();
+        assert_eq!(raw(&["--endpoint", "/b", "--body-file", body_file]), 0);
+        std::fs::remove_file(&upload).unwrap();
+
+        let requests = server.requests();
+        assert_eq!(requests[0].target, "/items?a=1&q=red%20shoes%26more");
+        assert_eq!(requests[0].header("content-type"), Some("application/json"));
+        assert_eq!(requests[0].body, r#"{"name":"Ada","meta":{"n":2}}"#);
+        assert!(requests[1]
+            .header("content-type")
+            .is_some_and(|ct| ct.starts_with("multipart/form-data")));
+        assert!(requests[1].body.contains("name=\"note\"\r\n\r\nhi"));
+        assert!(requests[1].body.contains("file-contents"));
+        assert_eq!(requests[2].body, "file-contents");
+
+        let (app, _) = build_cli(
+            &parse_mapping_root(AUTH_MAPPING).unwrap(),
+            "https://api.example.com",
+        );
+        assert!(app
+            .try_get_matches_from([
+                "cli",
+                "raw",
+                "--method",
+                "POST",
+                "--endpoint",
+                "/x",
+                "--body",
+                "{}",
+                "--json",
+                "a=1"
+            ])
+            .is_err());
+    }
+
     // ==================== global args tests ====================
 
     #[test]
@@ -1798,6 +3809,148 @@ commands:
         assert!(matches.get_flag("json-output"));
     }
 
+    #[test]
+    fn test_global_args_output_format() {
+        let yaml = r#"
+commands:
+  - name: test
+    subcommands:
+      - name: cmd
+        method: GET
+        endpoint: /test
+"#;
+        let root = parse_mapping_root(yaml).unwrap();
+        let (app, _) = build_cli(&root, "https://api.example.com");
+        let format = |argv: &[&str]| {
+            let mut full = vec!["cli"];
+            full.extend_from_slice(argv);
+            full.extend_from_slice(&["test", "cmd"]);
+            app.clone()
+                .try_get_matches_from(full)
+                .map(|m| output_format(&m))
+        };
+
+        assert_eq!(format(&[]).unwrap(), OutputFormat::Human);
+        assert_eq!(format(&["-j"]).unwrap(), OutputFormat::Json);
+        assert_eq!(format(&["-o", "csv"]).unwrap(), OutputFormat::Csv);
+        assert_eq!(format(&["--output", "table"]).unwrap(), OutputFormat::Human);
+        assert_eq!(
+            format(&["--output", "json-compact"]).unwrap(),
+            OutputFormat::JsonCompact
+        );
+        assert!(format(&["-o", "xml"]).is_err());
+        assert!(format(&["-j", "-o", "yaml"]).is_err());
+    }
+
+    #[test]
+    fn test_global_args_query_after_subcommand() {
+        let root = parse_mapping_root(AUTH_MAPPING).unwrap();
+        let (app, _) = build_cli(&root, "https://api.example.com");
+        let matches = app
+            .clone()
+            .try_get_matches_from(["cli", "me", "get", "--query", "$.id", "--raw-value"])
+            .unwrap();
+        assert_eq!(
+            matches.get_one::<String>(QUERY_ID).map(String::as_str),
+            Some("$.id")
+        );
+        assert!(matches.get_flag(RAW_VALUE_ID));
+
+        // raw takes query parameters as -p/--param, leaving --query to the filter
+        let matches = app
+            .try_get_matches_from([
+                "cli",
+                "raw",
+                "--method",
+                "GET",
+                "--endpoint",
+                "/users",
+                "--param",
+                "limit=5",
+                "--query",
+                "$.users",
+            ])
+            .unwrap();
+        assert_eq!(
+            matches.get_one::<String>(QUERY_ID).map(String::as_str),
+            Some("$.users")
+        );
+        let Some(("raw", raw_m)) = matches.subcommand() else {
+            panic!("Expected raw subcommand");
+        };
+        assert_eq!(
+            raw_m.get_one::<String>("param").map(String::as_str),
+            Some("limit=5")
+        );
+    }
+
+    #[test]
+    fn test_mapping_arg_named_like_a_global_does_not_set_it() {
+        let mapping = r#"
+commands:
+  - name: things
+    subcommands:
+      - name: list
+        method: GET
+        endpoint: "/things?f={filter}&q={query}"
+        args:
+          - name: filter
+            long: filter
+          - name: query
+            positional: true
+"#;
+        let root = parse_mapping_root(mapping).unwrap();
+        let (app, _) = build_cli(&root, "https://api.example.com");
+        let matches = app
+            .try_get_matches_from(["cli", "things", "list", "--filter", "red", "shoes"])
+            .unwrap();
+        assert_eq!(matches.get_one::<String>(QUERY_ID), None);
+        let (_, list_m) = matches.subcommand().unwrap().1.subcommand().unwrap();
+        assert_eq!(
+            list_m.get_one::<String>("filter").map(String::as_str),
+            Some("red")
+        );
+        assert_eq!(
+            list_m.get_one::<String>("query").map(String::as_str),
+            Some("shoes")
+        );
+    }
+
+    #[test]
+    fn test_row_shaping_args() {
+        use crate::test_server::{Reply, TestServer};
+        let server = TestServer::start(|_| Reply::json(200, r#"[{"id":1},{"id":2}]"#));
+        let argv = [
+            "--columns",
+            "id",
+            "--where",
+            "id>1",
+            "--sort-by",
+            "id",
+            "--desc",
+            "--limit-rows",
+            "5",
+            "--token",
+            "s3cret",
+            "me",
+            "get",
+        ];
+        assert_eq!(run_against(&server, &argv).unwrap(), 0);
+
+        let err = run_against(
+            &server,
+            &["--where", "id", "--token", "s3cret", "me", "get"],
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("--where 'id'"), "{}", err);
+
+        let root = parse_mapping_root(AUTH_MAPPING).unwrap();
+        let (app, _) = build_cli(&root, &server.url);
+        assert!(app
+            .try_get_matches_from(["cli", "--desc", "me", "get"])
+            .is_err());
+    }
+
     #[test]
     fn test_global_args_verbose() {
         let yaml = r#"
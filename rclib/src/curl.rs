@@ -0,0 +1,1015 @@
+//! Importing `curl` command lines, as copied from browser devtools or API docs.
+//!
+//! [`CurlCommand::parse`] understands the options such snippets use; `raw --from-curl`
+//! sends the result and `mapping add-from-curl` turns it into a mapping entry.
+
+use std::collections::{HashMap, HashSet};
+
+use anyhow::{bail, Context, Result};
+use base64::Engine;
+use indexmap::IndexMap;
+use serde_json::Value;
+
+use crate::journal::is_sensitive;
+use crate::mapping::{
+    is_reserved_long_flag, to_kebab_case, to_snake_case, ArgSpec, BodyField, CommandSpec,
+};
+use crate::{append_query, percent_encode_component, RawRequestSpec};
+
+/// A request described by a `curl` command line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CurlCommand {
+    pub method: String,
+    /// As given: absolute, or a path relative to the base URL
+    pub url: String,
+    /// In command-line order; `-u`, `-A`, `-b` and `-e` show up here as headers
+    pub headers: Vec<(String, String)>,
+    pub body: Option<String>,
+    /// `-F name=value` text fields
+    pub form: Vec<(String, String)>,
+    /// `-F name=@path` file fields
+    pub files: Vec<(String, String)>,
+}
+
+/// Options that take no value and do not change the request.
+const IGNORED_FLAGS: &[&str] = &[
+    "--compressed",
+    "--silent",
+    "--show-error",
+    "--location",
+    "--insecure",
+    "--verbose",
+    "--include",
+    "--fail",
+    "--globoff",
+    "--no-buffer",
+    "--http1.1",
+    "--http2",
+    "--progress-bar",
+];
+
+/// Options that take a value and do not change the request.
+const IGNORED_OPTIONS: &[&str] = &[
+    "--output",
+    "--max-time",
+    "--connect-timeout",
+    "--retry",
+    "--write-out",
+];
+
+/// Short options that take a value.
+const SHORT_WITH_VALUE: &str = "XHdFuAbeomw";
+
+/// Short options that take no value and do not change the request.
+const SHORT_IGNORED: &str = "sSLkvifg#46";
+
+impl CurlCommand {
+    /// Parse a `curl` command line. The leading `curl` is optional, and shell quoting
+    /// (`'...'`, `"..."`, `$'...'`, backslash line continuations) is understood.
+    ///
+    /// `--compressed` and `Accept-Encoding` headers are dropped: responses are read
+    /// uncompressed.
+    pub fn parse(command: &str) -> Result<Self> {
+        let mut words = split_shell_words(command)?.into_iter();
+        let mut pending: Vec<String> = Vec::new();
+        if let Some(first) = words.next() {
+            if first != "curl" {
+                pending.push(first);
+            }
+        }
+        let mut words = pending.into_iter().chain(words);
+
+        let mut explicit_method: Option<String> = None;
+        let mut url: Option<String> = None;
+        let mut headers: Vec<(String, String)> = Vec::new();
+        let mut data: Vec<String> = Vec::new();
+        let mut form: Vec<(String, String)> = Vec::new();
+        let mut files: Vec<(String, String)> = Vec::new();
+        let mut get = false;
+        let mut head = false;
+
+        while let Some(word) = words.next() {
+            let (option, inline) = if word.starts_with("--") && word.len() > 2 {
+                (word.clone(), None)
+            } else if word.starts_with('-') && word.len() > 1 {
+                // Short options may be clustered (-sSL) or carry their value (-XPOST)
+                let mut chars = word[1..].char_indices();
+                let mut found = None;
+                for (i, c) in chars.by_ref() {
+                    if SHORT_WITH_VALUE.contains(c) {
+                        let rest = &word[1 + i + c.len_utf8()..];
+                        found = Some((
+                            format!("-{}", c),
+                            (!rest.is_empty()).then(|| rest.to_string()),
+                        ));
+                        break;
+                    }
+                    match c {
+                        'I' => head = true,
+                        'G' => get = true,
+                        c if SHORT_IGNORED.contains(c) => {}
+                        _ => bail!("unsupported curl option '-{}'", c),
+                    }
+                }
+                match found {
+                    Some(found) => found,
+                    None => continue,
+                }
+            } else {
+                if url.replace(word.clone()).is_some() {
+                    bail!("the curl command has more than one URL");
+                }
+                continue;
+            };
+
+            let option = long_option(&option);
+            if option == "--head" {
+                head = true;
+                continue;
+            }
+            if option == "--get" {
+                get = true;
+                continue;
+            }
+            if IGNORED_FLAGS.contains(&option) {
+                continue;
+            }
+            let known = IGNORED_OPTIONS.contains(&option)
+                || matches!(
+                    option,
+                    "--request"
+                        | "--header"
+                        | "--data"
+                        | "--data-ascii"
+                        | "--data-raw"
+                        | "--data-binary"
+                        | "--data-urlencode"
+                        | "--form"
+                        | "--form-string"
+                        | "--user"
+                        | "--user-agent"
+                        | "--cookie"
+                        | "--referer"
+                        | "--url"
+                );
+            if !known {
+                bail!("unsupported curl option '{}'", word);
+            }
+            let value = match inline {
+                Some(v) => v,
+                None => words
+                    .next()
+                    .with_context(|| format!("curl option '{}' needs a value", word))?,
+            };
+            match option {
+                "--request" => explicit_method = Some(value.to_uppercase()),
+                "--header" => {
+                    let (name, val) = value
+                        .split_once(':')
+                        .with_context(|| format!("invalid curl header '{}'", value))?;
+                    let name = name.trim();
+                    if !name.eq_ignore_ascii_case("accept-encoding") {
+                        headers.push((name.to_string(), val.trim().to_string()));
+                    }
+                }
+                "--data" | "--data-ascii" => data.push(match value.strip_prefix('@') {
+                    Some(path) => read_file(path)?.replace(['\r', '\n'], ""),
+                    None => value,
+                }),
+                "--data-binary" => data.push(match value.strip_prefix('@') {
+                    Some(path) => read_file(path)?,
+                    None => value,
+                }),
+                "--data-raw" => data.push(value),
+                "--data-urlencode" => data.push(urlencode_data(&value)?),
+                "--form" => {
+                    let (name, val) = value
+                        .split_once('=')
+                        .with_context(|| format!("invalid curl form field '{}'", value))?;
+                    if let Some(path) = val.strip_prefix('@') {
+                        // Drop ;type=... and ;filename=... attributes
+                        let path = path.split(';').next().unwrap_or(path);
+                        files.push((name.to_string(), path.to_string()));
+                    } else if let Some(path) = val.strip_prefix('<') {
+                        form.push((name.to_string(), read_file(path)?));
+                    } else {
+                        form.push((name.to_string(), val.to_string()));
+                    }
+                }
+                "--form-string" => {
+                    let (name, val) = value
+                        .split_once('=')
+                        .with_context(|| format!("invalid curl form field '{}'", value))?;
+                    form.push((name.to_string(), val.to_string()));
+                }
+                "--user" => {
+                    let credentials = if value.contains(':') {
+                        value
+                    } else {
+                        format!("{}:", value)
+                    };
+                    headers.push((
+                        "Authorization".to_string(),
+                        format!(
+                            "Basic {}",
+                            base64::engine::general_purpose::STANDARD.encode(credentials)
+                        ),
+                    ));
+                }
+                "--user-agent" => headers.push(("User-Agent".to_string(), value)),
+                "--cookie" => {
+                    if !value.contains('=') {
+                        bail!("curl cookie files are not supported; pass the cookies inline");
+                    }
+                    headers.push(("Cookie".to_string(), value));
+                }
+                "--referer" => headers.push(("Referer".to_string(), value)),
+                "--url" if url.replace(value).is_some() => {
+                    bail!("the curl command has more than one URL");
+                }
+                _ => {}
+            }
+        }
+
+        let mut url = url.context("the curl command has no URL")?;
+        if !url.contains("://") && !url.starts_with('/') {
+            // curl assumes http:// for bare hosts
+            url = format!("http://{}", url);
+        }
+        let multipart = !form.is_empty() || !files.is_empty();
+        if multipart && !data.is_empty() {
+            bail!("curl -d and -F cannot be combined");
+        }
+        let mut body = None;
+        if !data.is_empty() {
+            let joined = data.join("&");
+            if get {
+                url = append_query(&url, &joined);
+            } else {
+                if !headers
+                    .iter()
+                    .any(|(k, _)| k.eq_ignore_ascii_case("content-type"))
+                {
+                    headers.push((
+                        "Content-Type".to_string(),
+                        "application/x-www-form-urlencoded".to_string(),
+                    ));
+                }
+                body = Some(joined);
+            }
+        }
+        let method = explicit_method.unwrap_or_else(|| {
+            if head {
+                "HEAD"
+            } else if body.is_some() || multipart {
+                "POST"
+            } else {
+                "GET"
+            }
+            .to_string()
+        });
+
+        Ok(Self {
+            method,
+            url,
+            headers,
+            body,
+            form,
+            files,
+        })
+    }
+
+    /// Whether the command carries its own credentials (`-u` or an `Authorization` header).
+    pub fn has_credentials(&self) -> bool {
+        self.headers
+            .iter()
+            .any(|(k, _)| k.eq_ignore_ascii_case("authorization"))
+    }
+
+    /// The request as `raw` would send it.
+    pub fn to_raw_spec(&self, base_url: &str) -> RawRequestSpec {
+        RawRequestSpec {
+            base_url: Some(base_url.to_string()),
+            method: self.method.clone(),
+            endpoint: self.url.clone(),
+            headers: self
+                .headers
+                .iter()
+                .map(|(k, v)| format!("{}: {}", k, v))
+                .collect(),
+            body: self.body.clone(),
+            multipart: !self.form.is_empty() || !self.files.is_empty(),
+            file_fields: self.files.iter().cloned().collect(),
+            form_fields: self.form.clone(),
+            table_view: None,
+            list_path: None,
+            table_views: IndexMap::new(),
+            detail_view: None,
+            output_template: None,
+            auth: Vec::new(),
+        }
+    }
+
+    /// A mapping entry for this request. The endpoint is made relative to `base_url`
+    /// (or to its host), id-like path segments become positional args, query values
+    /// become flags, and the fields of a JSON object body become `body_fields`. Captured
+    /// values are kept as defaults unless they look like secrets.
+    ///
+    /// Credentials and browser-only headers are left out; auth belongs to the security
+    /// schemes.
+    pub fn to_command_spec(&self, name: Option<&str>, base_url: &str) -> Result<CommandSpec> {
+        if !self.form.is_empty() {
+            bail!("multipart text fields (-F name=value) cannot be expressed in a mapping");
+        }
+        let (path, query_string) = split_url(&self.url, base_url);
+        let mut names = Names::default();
+        let mut args: Vec<ArgSpec> = Vec::new();
+
+        // Path segments
+        let segments: Vec<&str> = path.split('/').collect();
+        let mut endpoint_parts: Vec<String> = Vec::new();
+        let mut previous_literal: Option<&str> = None;
+        for (i, segment) in segments.iter().enumerate() {
+            if !looks_like_id(segment) {
+                if !segment.is_empty() {
+                    previous_literal = Some(segment);
+                }
+                endpoint_parts.push(segment.to_string());
+                continue;
+            }
+            let wanted = match previous_literal {
+                Some(prev) if i + 1 < segments.len() => {
+                    format!("{}_id", singular(&to_snake_case(prev)))
+                }
+                _ => "id".to_string(),
+            };
+            let var = names.claim(&wanted);
+            endpoint_parts.push(format!("{{{}}}", var));
+            args.push(ArgSpec {
+                name: Some(var),
+                help: Some(format!("e.g. {}", segment)),
+                positional: Some(true),
+                required: Some(true),
+                arg_type: segment
+                    .bytes()
+                    .all(|b| b.is_ascii_digit())
+                    .then(|| "int".to_string()),
+                ..Default::default()
+            });
+        }
+        let endpoint = endpoint_parts.join("/");
+        let literal_name = segments
+            .iter()
+            .rev()
+            .find(|s| !s.is_empty() && !looks_like_id(s))
+            .map(|s| to_kebab_case(s));
+
+        // Query parameters: repeated keys become one `multiple` arg
+        let mut query_values: IndexMap<String, Vec<String>> = IndexMap::new();
+        for pair in query_string.split('&').filter(|p| !p.is_empty()) {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            query_values
+                .entry(decode_query(key))
+                .or_default()
+                .push(decode_query(value));
+        }
+        let mut query: IndexMap<String, String> = IndexMap::new();
+        for (key, values) in &query_values {
+            let var = names.claim(&var_name(key));
+            query.insert(key.clone(), format!("{{{}}}", var));
+            let multiple = values.len() > 1;
+            // A `multiple` default lists its values separated by commas, so values that
+            // contain one cannot be a default
+            let joined = values.join(",");
+            let expressible = !multiple || !values.iter().any(|v| v.contains(','));
+            args.push(ArgSpec {
+                name: Some(var.clone()),
+                long: Some(var.replace('_', "-")),
+                default: (expressible && !is_sensitive(key) && !joined.is_empty())
+                    .then_some(joined),
+                arg_type: (!multiple).then(|| scalar_type(&values[0])).flatten(),
+                multiple,
+                ..Default::default()
+            });
+        }
+
+        // Body
+        let json_body = self
+            .body
+            .as_deref()
+            .and_then(|b| serde_json::from_str::<Value>(b).ok());
+        let mut body = None;
+        let mut body_fields: IndexMap<String, BodyField> = IndexMap::new();
+        match json_body {
+            Some(Value::Object(fields)) if !fields.is_empty() => {
+                for (key, value) in &fields {
+                    let var = names.claim(&var_name(key));
+                    let field_type = match value {
+                        Value::String(_) => None,
+                        Value::Number(n) if n.is_i64() || n.is_u64() => Some("int"),
+                        Value::Number(_) => Some("float"),
+                        Value::Bool(_) => Some("bool"),
+                        _ => Some("json"),
+                    };
+                    body_fields.insert(
+                        key.clone(),
+                        BodyField::Short(match field_type {
+                            Some(t) => format!("{}:{}", var, t),
+                            None => var.clone(),
+                        }),
+                    );
+                    let default = match value {
+                        Value::String(s) => s.clone(),
+                        other => other.to_string(),
+                    };
+                    args.push(ArgSpec {
+                        name: Some(var.clone()),
+                        long: Some(var.replace('_', "-")),
+                        default: (!is_sensitive(key)).then_some(default),
+                        ..Default::default()
+                    });
+                }
+            }
+            _ => body = self.body.clone(),
+        }
+
+        // Uploaded files
+        for (field, path) in &self.files {
+            let var = names.claim(field);
+            args.push(ArgSpec {
+                name: Some(var.clone()),
+                help: Some(format!("File to upload, e.g. {}", path)),
+                long: Some(var.replace('_', "-")),
+                required: Some(true),
+                file_upload: true,
+                ..Default::default()
+            });
+        }
+
+        let headers: HashMap<String, String> = self
+            .headers
+            .iter()
+            .filter(|(k, v)| {
+                let lower = k.to_ascii_lowercase();
+                let implied_json = !body_fields.is_empty()
+                    && lower == "content-type"
+                    && v.starts_with("application/json");
+                !implied_json
+                    && !is_sensitive(&lower)
+                    && !lower.starts_with("sec-")
+                    && !BROWSER_HEADERS.contains(&lower.as_str())
+            })
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+
+        let name = match name {
+            Some(n) => n.to_string(),
+            None => match literal_name {
+                Some(literal) => format!("{}-{}", self.method.to_lowercase(), literal),
+                None => self.method.to_lowercase(),
+            },
+        };
+        Ok(CommandSpec {
+            name: Some(name),
+            method: Some(self.method.clone()),
+            endpoint: Some(if endpoint.is_empty() {
+                "/".to_string()
+            } else {
+                endpoint
+            }),
+            body,
+            headers,
+            query,
+            body_fields,
+            multipart: !self.files.is_empty(),
+            args,
+            ..Default::default()
+        })
+    }
+}
+
+/// Headers a browser adds on its own; not worth keeping in a mapping.
+const BROWSER_HEADERS: &[&str] = &[
+    "user-agent",
+    "referer",
+    "origin",
+    "accept-language",
+    "connection",
+    "content-length",
+    "host",
+    "priority",
+    "pragma",
+    "cache-control",
+    "dnt",
+    "upgrade-insecure-requests",
+];
+
+/// Arg names already used by one mapping entry.
+#[derive(Default)]
+struct Names(HashSet<String>);
+
+impl Names {
+    /// `wanted`, or `wanted_2`, `wanted_3`, ... if taken or if its flag is reserved.
+    fn claim(&mut self, wanted: &str) -> String {
+        let mut name = wanted.to_string();
+        let mut n = 2;
+        while self.0.contains(&name) || is_reserved_long_flag(&name.replace('_', "-")) {
+            name = format!("{}_{}", wanted, n);
+            n += 1;
+        }
+        self.0.insert(name.clone());
+        name
+    }
+}
+
+/// Map `-d`/`--data-raw` style option aliases onto their long names.
+fn long_option(option: &str) -> &str {
+    match option {
+        "-X" => "--request",
+        "-H" => "--header",
+        "-d" => "--data",
+        "-F" => "--form",
+        "-u" => "--user",
+        "-A" => "--user-agent",
+        "-b" => "--cookie",
+        "-e" => "--referer",
+        "-o" => "--output",
+        "-m" => "--max-time",
+        "-w" => "--write-out",
+        other => other,
+    }
+}
+
+fn read_file(path: &str) -> Result<String> {
+    std::fs::read_to_string(path).with_context(|| format!("Failed to read file: {}", path))
+}
+
+/// `--data-urlencode` forms: `content`, `=content`, `name=content`, `@file`, `name@file`.
+fn urlencode_data(value: &str) -> Result<String> {
+    if let Some((name, content)) = value.split_once('=') {
+        let encoded = percent_encode_component(content);
+        return Ok(if name.is_empty() {
+            encoded
+        } else {
+            format!("{}={}", name, encoded)
+        });
+    }
+    if let Some((name, path)) = value.split_once('@') {
+        let encoded = percent_encode_component(&read_file(path)?);
+        return Ok(if name.is_empty() {
+            encoded
+        } else {
+            format!("{}={}", name, encoded)
+        });
+    }
+    Ok(percent_encode_component(value))
+}
+
+/// Path and query string of a URL, with the path made relative to `base_url` when the
+/// URL is under it, and to its host otherwise.
+fn split_url<'a>(url: &'a str, base_url: &str) -> (&'a str, &'a str) {
+    let url = url.split('#').next().unwrap_or(url);
+    let relative = match url.strip_prefix(base_url.trim_end_matches('/')) {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') || rest.starts_with('?') => rest,
+        _ => match url.find("://") {
+            Some(i) => {
+                let after_scheme = &url[i + 3..];
+                let host_end = after_scheme.find(['/', '?']).unwrap_or(after_scheme.len());
+                &after_scheme[host_end..]
+            }
+            None => url,
+        },
+    };
+    relative.split_once('?').unwrap_or((relative, ""))
+}
+
+/// Numbers, UUIDs and long hex strings.
+fn looks_like_id(segment: &str) -> bool {
+    if segment.is_empty() {
+        return false;
+    }
+    if segment.bytes().all(|b| b.is_ascii_digit()) {
+        return true;
+    }
+    let is_uuid = segment.len() == 36
+        && segment.char_indices().all(|(i, c)| match i {
+            8 | 13 | 18 | 23 => c == '-',
+            _ => c.is_ascii_hexdigit(),
+        });
+    let is_hex = segment.len() >= 12
+        && segment.bytes().all(|b| b.is_ascii_hexdigit())
+        && segment.bytes().any(|b| b.is_ascii_digit());
+    is_uuid || is_hex
+}
+
+/// "categories" -> "category", "users" -> "user"
+fn singular(word: &str) -> String {
+    if let Some(stem) = word.strip_suffix("ies") {
+        format!("{}y", stem)
+    } else if word.ends_with("ss") {
+        word.to_string()
+    } else {
+        word.strip_suffix('s').unwrap_or(word).to_string()
+    }
+}
+
+fn var_name(key: &str) -> String {
+    let var = to_snake_case(key).trim_matches('_').to_string();
+    if var.is_empty() {
+        "param".to_string()
+    } else {
+        var
+    }
+}
+
+fn scalar_type(value: &str) -> Option<String> {
+    if value.parse::<i64>().is_ok() {
+        Some("int".to_string())
+    } else if value.parse::<f64>().is_ok() && value.contains('.') {
+        Some("float".to_string())
+    } else {
+        None
+    }
+}
+
+fn decode_query(s: &str) -> String {
+    percent_encoding::percent_decode_str(&s.replace('+', " "))
+        .decode_utf8_lossy()
+        .into_owned()
+}
+
+/// Split a command line into words the way a POSIX shell would, without expansions.
+pub fn split_shell_words(line: &str) -> Result<Vec<String>> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut in_word = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            '\\' => match chars.next() {
+                // Line continuation
+                Some('\n') => {}
+                Some('\r') if chars.peek() == Some(&'\n') => {
+                    chars.next();
+                }
+                Some(next) => {
+                    word.push(next);
+                    in_word = true;
+                }
+                None => {}
+            },
+            '\'' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(ch) => word.push(ch),
+                        None => bail!("unterminated ' quote in the curl command"),
+                    }
+                }
+            }
+            '"' => {
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(ch @ ('"' | '\\' | '$' | '`')) => word.push(ch),
+                            Some('\n') => {}
+                            Some(ch) => {
+                                word.push('\\');
+                                word.push(ch);
+                            }
+                            None => bail!("unterminated \" quote in the curl command"),
+                        },
+                        Some(ch) => word.push(ch),
+                        None => bail!("unterminated \" quote in the curl command"),
+                    }
+                }
+            }
+            '$' if chars.peek() == Some(&'\'') => {
+                // ANSI-C quoting, as used by "Copy as cURL (bash)"
+                chars.next();
+                in_word = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some('\\') => match chars.next() {
+                            Some('n') => word.push('\n'),
+                            Some('t') => word.push('\t'),
+                            Some('r') => word.push('\r'),
+                            Some('x') => {
+                                let hex: String =
+                                    std::iter::from_fn(|| chars.next_if(|c| c.is_ascii_hexdigit()))
+                                        .take(2)
+                                        .collect();
+                                let code = u32::from_str_radix(&hex, 16)
+                                    .context("invalid \\x escape in the curl command")?;
+                                word.extend(char::from_u32(code));
+                            }
+                            Some('u') => {
+                                let hex: String =
+                                    std::iter::from_fn(|| chars.next_if(|c| c.is_ascii_hexdigit()))
+                                        .take(4)
+                                        .collect();
+                                let code = u32::from_str_radix(&hex, 16)
+                                    .context("invalid \\u escape in the curl command")?;
+                                word.extend(char::from_u32(code));
+                            }
+                            Some(ch) => word.push(ch),
+                            None => bail!("unterminated $' quote in the curl command"),
+                        },
+                        Some(ch) => word.push(ch),
+                        None => bail!("unterminated $' quote in the curl command"),
+                    }
+                }
+            }
+            c => {
+                word.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if in_word {
+        words.push(word);
+    }
+    Ok(words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // ==================== split_shell_words tests ====================
+
+    #[test]
+    fn test_split_shell_words_quoting() {
+        let words = split_shell_words(
+            "curl 'https://x.test/a b' -H \"X-A: \\\"q\\\"\" \\\n  --data-raw $'{\"n\":\"a\\nb\\'c\"}' ''",
+        )
+        .unwrap();
+        assert_eq!(
+            words,
+            vec![
+                "curl",
+                "https://x.test/a b",
+                "-H",
+                "X-A: \"q\"",
+                "--data-raw",
+                "{\"n\":\"a\nb'c\"}",
+                ""
+            ]
+        );
+        assert!(split_shell_words("curl 'oops").is_err());
+    }
+
+    // ==================== CurlCommand::parse tests ====================
+
+    #[test]
+    fn test_parse_devtools_command() {
+        let cmd = CurlCommand::parse(
+            r#"curl 'https://api.example.com/users/42/posts?limit=10&tag=a&tag=b' \
+  -H 'accept: application/json' \
+  -H 'accept-encoding: gzip, deflate, br' \
+  -H 'content-type: application/json' \
+  --data-raw '{"title":"Hi","views":3}' \
+  --compressed"#,
+        )
+        .unwrap();
+        assert_eq!(cmd.method, "POST");
+        assert_eq!(
+            cmd.url,
+            "https://api.example.com/users/42/posts?limit=10&tag=a&tag=b"
+        );
+        assert_eq!(
+            cmd.headers,
+            vec![
+                ("accept".to_string(), "application/json".to_string()),
+                ("content-type".to_string(), "application/json".to_string()),
+            ]
+        );
+        assert_eq!(cmd.body.as_deref(), Some(r#"{"title":"Hi","views":3}"#));
+        assert!(!cmd.has_credentials());
+    }
+
+    #[test]
+    fn test_parse_short_options_and_defaults() {
+        let cmd =
+            CurlCommand::parse("curl -sSL -XPUT -u ada:pw -d a=1 -d b=2 localhost:8080/x").unwrap();
+        assert_eq!(cmd.method, "PUT");
+        assert_eq!(cmd.url, "http://localhost:8080/x");
+        assert_eq!(cmd.body.as_deref(), Some("a=1&b=2"));
+        assert_eq!(
+            cmd.headers,
+            vec![
+                ("Authorization".to_string(), "Basic YWRhOnB3".to_string()),
+                (
+                    "Content-Type".to_string(),
+                    "application/x-www-form-urlencoded".to_string()
+                ),
+            ]
+        );
+        assert!(cmd.has_credentials());
+
+        let cmd =
+            CurlCommand::parse("curl -G https://x.test/s?a=1 --data-urlencode 'q=hello world'")
+                .unwrap();
+        assert_eq!(cmd.method, "GET");
+        assert_eq!(cmd.url, "https://x.test/s?a=1&q=hello%20world");
+        assert_eq!(cmd.body, None);
+
+        assert_eq!(
+            CurlCommand::parse("curl -I https://x.test").unwrap().method,
+            "HEAD"
+        );
+    }
+
+    #[test]
+    fn test_parse_form_fields() {
+        let cmd = CurlCommand::parse(
+            "curl https://x.test/upload -F 'file=@./a.png;type=image/png' -F note=hello",
+        )
+        .unwrap();
+        assert_eq!(cmd.method, "POST");
+        assert_eq!(cmd.files, vec![("file".to_string(), "./a.png".to_string())]);
+        assert_eq!(cmd.form, vec![("note".to_string(), "hello".to_string())]);
+
+        let raw = cmd.to_raw_spec("https://x.test");
+        assert!(raw.multipart);
+        assert_eq!(
+            raw.file_fields.get("file").map(|s| s.as_str()),
+            Some("./a.png")
+        );
+        assert_eq!(raw.form_fields, cmd.form);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let err = CurlCommand::parse("curl --proxy http://p https://x.test").unwrap_err();
+        assert_eq!(err.to_string(), "unsupported curl option '--proxy'");
+        let err = CurlCommand::parse("curl -H").unwrap_err();
+        assert_eq!(err.to_string(), "curl option '-H' needs a value");
+        let err = CurlCommand::parse("curl -s").unwrap_err();
+        assert_eq!(err.to_string(), "the curl command has no URL");
+        assert!(CurlCommand::parse("curl https://a.test https://b.test").is_err());
+    }
+
+    // ==================== to_command_spec tests ====================
+
+    #[test]
+    fn test_command_spec_placeholders() {
+        let cmd = CurlCommand::parse(
+            r#"curl 'https://api.example.com/v1/users/42/posts/7?limit=10&tag=a&tag=b&api_key=k' \
+  -H 'Authorization: Bearer t' -H 'X-Trace: on' -H 'sec-fetch-mode: cors' \
+  -H 'content-type: application/json' --data-raw '{"title":"Hi","views":3,"tags":["x"]}'"#,
+        )
+        .unwrap();
+        let spec = cmd
+            .to_command_spec(None, "https://api.example.com/v1")
+            .unwrap();
+        assert_eq!(spec.name.as_deref(), Some("post-posts"));
+        assert_eq!(spec.method.as_deref(), Some("POST"));
+        assert_eq!(
+            spec.endpoint.as_deref(),
+            Some("/users/{user_id}/posts/{id}")
+        );
+        assert_eq!(spec.query.get("limit").map(|s| s.as_str()), Some("{limit}"));
+        assert_eq!(
+            spec.query.get("api_key").map(|s| s.as_str()),
+            Some("{api_key}")
+        );
+        assert_eq!(spec.headers.len(), 1);
+        assert_eq!(spec.headers.get("X-Trace").map(|s| s.as_str()), Some("on"));
+        assert_eq!(spec.body, None);
+        assert_eq!(
+            spec.body_fields.get("views").map(|f| f.arg()),
+            Some("views")
+        );
+
+        let arg = |name: &str| {
+            spec.args
+                .iter()
+                .find(|a| a.name.as_deref() == Some(name))
+                .unwrap()
+        };
+        assert_eq!(arg("user_id").positional, Some(true));
+        assert_eq!(arg("user_id").arg_type.as_deref(), Some("int"));
+        assert_eq!(arg("limit").default.as_deref(), Some("10"));
+        assert!(arg("tag").multiple);
+        assert_eq!(arg("tag").default.as_deref(), Some("a,b"));
+        assert_eq!(arg("api_key").default, None);
+        assert_eq!(arg("tags").default.as_deref(), Some(r#"["x"]"#));
+
+        // The generated entry is a valid mapping command
+        let yaml = format!(
+            "commands:\n  - name: posts\n    subcommands:\n{}",
+            serde_yaml::to_string(&vec![spec])
+                .unwrap()
+                .lines()
+                .map(|l| format!("      {}\n", l))
+                .collect::<String>()
+        );
+        let root = crate::mapping::parse_mapping_root(&yaml).unwrap();
+        assert!(crate::mapping::validate_mapping(&root).is_empty());
+    }
+
+    #[test]
+    fn test_command_spec_repeated_query_key_round_trips() {
+        let build = |url: &str| {
+            let cmd = CurlCommand::parse(&format!("curl '{}'", url)).unwrap();
+            let spec = cmd
+                .to_command_spec(Some("list"), "https://api.example.com")
+                .unwrap();
+            let yaml = format!(
+                "commands:\n  - name: items\n    subcommands:\n{}",
+                serde_yaml::to_string(&vec![spec])
+                    .unwrap()
+                    .lines()
+                    .map(|l| format!("      {}\n", l))
+                    .collect::<String>()
+            );
+            let root = crate::mapping::parse_mapping_root(&yaml).unwrap();
+            let (app, path_map) = crate::cli::build_cli(&root, "https://api.example.com");
+            let matches = app.try_get_matches_from(["cli", "items", "list"]).unwrap();
+            let (path, leaf) = crate::cli::collect_subcommand_path(&matches);
+            let cmd = &path_map[&path];
+            let (vars, selected, _) = crate::cli::collect_vars_from_matches(cmd, leaf);
+            let crate::RequestSpec::Simple(raw) =
+                crate::build_request_from_command(None, cmd, &vars, &selected)
+            else {
+                panic!("Expected RequestSpec::Simple");
+            };
+            raw.endpoint
+        };
+        // The imported command sends the same repeated keys by default
+        assert_eq!(
+            build("https://api.example.com/items?tag=a&tag=b"),
+            "/items?tag=a&tag=b"
+        );
+        // Values with commas cannot be listed in a default, so none is set
+        assert_eq!(
+            build("https://api.example.com/items?tag=a,b&tag=c"),
+            "/items"
+        );
+    }
+
+    #[test]
+    fn test_command_spec_renames_reserved_flags() {
+        let cmd = CurlCommand::parse(
+            "curl 'https://api.example.com/search?query=red&help=1&raw-value=y&query_2=z'",
+        )
+        .unwrap();
+        let spec = cmd
+            .to_command_spec(Some("search"), "https://api.example.com")
+            .unwrap();
+        let query: Vec<(&str, &str)> = spec
+            .query
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect();
+        assert_eq!(
+            query,
+            vec![
+                ("query", "{query_2}"),
+                ("help", "{help_2}"),
+                ("raw-value", "{raw_value_2}"),
+                ("query_2", "{query_2_2}"),
+            ]
+        );
+        assert_eq!(spec.args[0].long.as_deref(), Some("query-2"));
+
+        let yaml = format!(
+            "commands:\n  - name: items\n    subcommands:\n{}",
+            serde_yaml::to_string(&vec![spec])
+                .unwrap()
+                .lines()
+                .map(|l| format!("      {}\n", l))
+                .collect::<String>()
+        );
+        let root = crate::mapping::parse_mapping_root(&yaml).unwrap();
+        assert!(crate::mapping::validate_mapping(&root).is_empty());
+    }
+
+    #[test]
+    fn test_command_spec_other_host_and_name() {
+        let cmd = CurlCommand::parse(
+            "curl https://staging.example.com/files/3f2a9c1e-8b7d-4e6f-a5c4-1b2d3e4f5a6b -X DELETE",
+        )
+        .unwrap();
+        let spec = cmd
+            .to_command_spec(Some("remove"), "https://api.example.com")
+            .unwrap();
+        assert_eq!(spec.name.as_deref(), Some("remove"));
+        assert_eq!(spec.endpoint.as_deref(), Some("/files/{id}"));
+        assert_eq!(spec.args[0].arg_type, None);
+
+        let cmd = CurlCommand::parse("curl https://x.test/up -F a=b").unwrap();
+        assert!(cmd.to_command_spec(None, "https://x.test").is_err());
+    }
+}
@@ -0,0 +1,603 @@
+//! `--dry-run`, `--as-curl` and `--as-httpie`: show the requests a command would send.
+
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::{bail, Context, Result};
+use reqwest::blocking::Client;
+use serde_json::{json, Map, Value};
+
+use crate::journal::{is_sensitive, redact_header, redact_url};
+use crate::mapping::PollingConfig;
+use crate::{
+    build_http_request, build_raw_spec_from_step, RawRequestSpec, RequestSpec, ScenarioSpec,
+};
+
+/// How a dry run is printed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DryRunFormat {
+    /// HTTP-message style text, credentials masked
+    Plain,
+    /// JSON object, credentials masked
+    Json,
+    /// `curl` command line, credentials included
+    Curl,
+    /// HTTPie (`http`) command line, credentials included
+    Httpie,
+}
+
+impl DryRunFormat {
+    /// Whether the output is meant to be run, and so carries real credentials.
+    fn reveals_credentials(self) -> bool {
+        matches!(self, DryRunFormat::Curl | DryRunFormat::Httpie)
+    }
+}
+
+/// Scenario variables extracted from an earlier step render as this marker until the
+/// output is finished, then as `<name>`.
+const RUNTIME_MARKER: &str = "__rclib_runtime_";
+
+fn runtime_marker(name: &str) -> String {
+    format!("{}{}__", RUNTIME_MARKER, name)
+}
+
+fn replace_runtime_markers(text: &str, names: &[String]) -> String {
+    names.iter().fold(text.to_string(), |acc, name| {
+        acc.replace(&runtime_marker(name), &format!("<{}>", name))
+    })
+}
+
+// =====================
+// Prepared requests
+// =====================
+
+/// A request exactly as rclib would send it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PreparedRequest {
+    pub method: String,
+    pub url: String,
+    /// Names lowercased, in sending order
+    pub headers: Vec<(String, String)>,
+    pub body: Option<String>,
+    /// Multipart text fields as (field, value)
+    pub fields: Vec<(String, String)>,
+    /// Multipart file fields as (field, path); the files are not read
+    pub files: Vec<(String, String)>,
+}
+
+impl PreparedRequest {
+    /// Resolve a spec the way the executor does, including its credentials. Unless
+    /// `reveal` is set, credentials in headers and query parameters are masked.
+    pub fn from_spec(spec: &RawRequestSpec, reveal: bool) -> Result<Self> {
+        let mut files: Vec<(String, String)> = spec
+            .file_fields
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+        files.sort();
+        let multipart = spec.multipart && !(files.is_empty() && spec.form_fields.is_empty());
+        let fields = if multipart {
+            spec.form_fields
+                .iter()
+                .map(|(k, v)| {
+                    let shown = if reveal || !is_sensitive(k) {
+                        v
+                    } else {
+                        "****"
+                    };
+                    (k.clone(), shown.to_string())
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+        let mut probe = spec.clone();
+        if multipart {
+            probe.file_fields.clear();
+            probe.form_fields.clear();
+            probe.body = None;
+        }
+        let req = build_http_request(&Client::new(), &probe, &spec.auth)?
+            .build()
+            .context("Failed to build HTTP request")?;
+        let secrets = crate::auth::secret_names(&spec.auth);
+        let headers = req
+            .headers()
+            .iter()
+            .map(|(name, value)| {
+                let value = value.to_str().unwrap_or("<binary>");
+                let value = if reveal {
+                    value.to_string()
+                } else {
+                    redact_header(name.as_str(), value, &secrets)
+                };
+                (name.as_str().to_string(), value)
+            })
+            .collect();
+        Ok(Self {
+            method: req.method().as_str().to_string(),
+            url: if reveal {
+                req.url().to_string()
+            } else {
+                redact_url(req.url(), &secrets)
+            },
+            headers,
+            body: req
+                .body()
+                .and_then(|b| b.as_bytes())
+                .map(|b| String::from_utf8_lossy(b).into_owned()),
+            fields,
+            files,
+        })
+    }
+
+    fn map_text(self, f: impl Fn(&str) -> String) -> Self {
+        Self {
+            method: self.method,
+            url: f(&self.url),
+            headers: self.headers.into_iter().map(|(k, v)| (k, f(&v))).collect(),
+            body: self.body.as_deref().map(&f),
+            fields: self.fields.into_iter().map(|(k, v)| (k, f(&v))).collect(),
+            files: self.files,
+        }
+    }
+
+    /// Request line, headers, then the body, like an HTTP message.
+    pub fn to_plain(&self) -> String {
+        let mut out = format!("{} {}\n", self.method, self.url);
+        for (k, v) in &self.headers {
+            out.push_str(&format!("{}: {}\n", k, v));
+        }
+        if !self.fields.is_empty() || !self.files.is_empty() {
+            out.push_str("\nmultipart form:\n");
+            for (field, value) in &self.fields {
+                out.push_str(&format!("  {}: {}\n", field, value));
+            }
+            for (field, path) in &self.files {
+                out.push_str(&format!("  {}: @{}\n", field, path));
+            }
+        }
+        if let Some(body) = &self.body {
+            out.push('\n');
+            out.push_str(body);
+            out.push('\n');
+        }
+        out
+    }
+
+    pub fn to_json(&self) -> Value {
+        let headers: Map<String, Value> = self
+            .headers
+            .iter()
+            .map(|(k, v)| (k.clone(), Value::String(v.clone())))
+            .collect();
+        let mut out = json!({
+            "method": self.method,
+            "url": self.url,
+            "headers": headers,
+            "body": self.body,
+        });
+        if !self.fields.is_empty() {
+            let fields: Map<String, Value> = self
+                .fields
+                .iter()
+                .map(|(k, v)| (k.clone(), Value::String(v.clone())))
+                .collect();
+            out["fields"] = Value::Object(fields);
+        }
+        if !self.files.is_empty() {
+            let files: Map<String, Value> = self
+                .files
+                .iter()
+                .map(|(k, v)| (k.clone(), Value::String(v.clone())))
+                .collect();
+            out["files"] = Value::Object(files);
+        }
+        out
+    }
+
+    /// A `curl` command line.
+    pub fn to_curl(&self) -> String {
+        let mut parts = vec![if self.method == "GET" {
+            format!("curl {}", shell_quote(&self.url))
+        } else {
+            format!("curl -X {} {}", self.method, shell_quote(&self.url))
+        }];
+        for (k, v) in &self.headers {
+            parts.push(format!("-H {}", shell_quote(&format!("{}: {}", k, v))));
+        }
+        for (field, value) in &self.fields {
+            parts.push(format!(
+                "--form-string {}",
+                shell_quote(&format!("{}={}", field, value))
+            ));
+        }
+        for (field, path) in &self.files {
+            parts.push(format!(
+                "-F {}",
+                shell_quote(&format!("{}=@{}", field, path))
+            ));
+        }
+        if let Some(body) = &self.body {
+            parts.push(format!("--data-raw {}", shell_quote(body)));
+        }
+        parts.join(" \\\n  ")
+    }
+
+    /// An HTTPie command line.
+    pub fn to_httpie(&self) -> String {
+        let mut parts = vec![format!("http {} {}", self.method, shell_quote(&self.url))];
+        if !self.fields.is_empty() || !self.files.is_empty() {
+            parts.push("--multipart".to_string());
+        }
+        for (k, v) in &self.headers {
+            parts.push(shell_quote(&format!("{}:{}", k, v)));
+        }
+        for (field, value) in &self.fields {
+            parts.push(shell_quote(&format!("{}={}", field, value)));
+        }
+        for (field, path) in &self.files {
+            parts.push(shell_quote(&format!("{}@{}", field, path)));
+        }
+        if let Some(body) = &self.body {
+            parts.push(format!("--raw {}", shell_quote(body)));
+        }
+        parts.join(" \\\n  ")
+    }
+
+    fn render(&self, format: DryRunFormat) -> String {
+        match format {
+            DryRunFormat::Plain => self.to_plain(),
+            DryRunFormat::Json => pretty(&self.to_json()),
+            DryRunFormat::Curl => format!("{}\n", self.to_curl()),
+            DryRunFormat::Httpie => format!("{}\n", self.to_httpie()),
+        }
+    }
+}
+
+fn pretty(value: &Value) -> String {
+    format!(
+        "{}\n",
+        serde_json::to_string_pretty(value).unwrap_or_default()
+    )
+}
+
+/// Quote a word for POSIX shells; plain words are left alone.
+pub fn shell_quote(word: &str) -> String {
+    let plain = !word.is_empty()
+        && word
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./:=@,+%".contains(c));
+    if plain {
+        word.to_string()
+    } else {
+        format!("'{}'", word.replace('\'', r"'\''"))
+    }
+}
+
+// =====================
+// Scenarios
+// =====================
+
+/// One scenario step as it would run.
+#[derive(Debug, Clone)]
+pub struct StepPreview {
+    pub name: String,
+    /// Variables known when the step runs; extracted ones show as `<from step>`
+    pub variables: BTreeMap<String, String>,
+    pub request: PreparedRequest,
+    pub polling: Option<PollingConfig>,
+}
+
+/// Resolve every step of a scenario. Values a step extracts from its response are not
+/// known yet, so later steps show `<name>` where they are used.
+pub fn preview_scenario(spec: &ScenarioSpec, reveal: bool) -> Result<Vec<StepPreview>> {
+    let mut vars = spec.vars.clone();
+    let mut extracted: HashMap<String, String> = HashMap::new();
+    let mut steps = Vec::new();
+    for step in &spec.scenario.steps {
+        let mut raw = build_raw_spec_from_step(&spec.base_url, step, &vars, spec.strict_templates)?;
+        raw.auth = spec.auth.get(&step.name).cloned().unwrap_or_default();
+        let names: Vec<String> = extracted.keys().cloned().collect();
+        let request = PreparedRequest::from_spec(&raw, reveal)?
+            .map_text(|text| replace_runtime_markers(text, &names));
+        let variables = vars
+            .iter()
+            .map(|(k, v)| {
+                let shown = match extracted.get(k) {
+                    Some(from) => format!("<from {}>", from),
+                    None if !reveal && is_sensitive(k) => "****".to_string(),
+                    None => v.clone(),
+                };
+                (k.clone(), shown)
+            })
+            .collect();
+        steps.push(StepPreview {
+            name: step.name.clone(),
+            variables,
+            request,
+            polling: step.polling.clone(),
+        });
+        for key in step.extract_response.keys() {
+            vars.insert(key.clone(), runtime_marker(key));
+            extracted.insert(key.clone(), step.name.clone());
+        }
+    }
+    Ok(steps)
+}
+
+fn describe_polling(polling: &PollingConfig) -> String {
+    let statuses: Vec<&str> = polling
+        .completion_conditions
+        .iter()
+        .map(|c| c.status.as_str())
+        .collect();
+    format!(
+        "repeated every {}s for up to {}s until status is {}",
+        polling.interval_seconds,
+        polling.timeout_seconds,
+        statuses.join(" or ")
+    )
+}
+
+fn describe_variables(variables: &BTreeMap<String, String>) -> String {
+    variables
+        .iter()
+        .map(|(k, v)| format!("{}={}", k, v))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Render what a request spec would send, without sending anything.
+pub fn render(spec: &RequestSpec, format: DryRunFormat) -> Result<String> {
+    let reveal = format.reveals_credentials();
+    match spec {
+        RequestSpec::Simple(raw) => Ok(PreparedRequest::from_spec(raw, reveal)?.render(format)),
+        RequestSpec::Scenario(scenario) => {
+            let steps = preview_scenario(scenario, reveal)?;
+            if format == DryRunFormat::Json {
+                let steps: Vec<Value> = steps
+                    .iter()
+                    .map(|step| {
+                        let mut out = json!({
+                            "name": step.name,
+                            "variables": step.variables,
+                            "request": step.request.to_json(),
+                        });
+                        if let Some(polling) = &step.polling {
+                            out["polling"] = serde_json::to_value(polling).unwrap_or(Value::Null);
+                        }
+                        out
+                    })
+                    .collect();
+                let out = json!({
+                    "scenario": scenario.scenario.scenario_type,
+                    "steps": steps,
+                });
+                return Ok(pretty(&out));
+            }
+            let mut out = String::new();
+            for (i, step) in steps.iter().enumerate() {
+                if i > 0 {
+                    out.push('\n');
+                }
+                out.push_str(&format!("# Step {}: {}\n", i + 1, step.name));
+                if let Some(polling) = &step.polling {
+                    out.push_str(&format!("# {}\n", describe_polling(polling)));
+                }
+                if !step.variables.is_empty() {
+                    out.push_str(&format!(
+                        "# Variables: {}\n",
+                        describe_variables(&step.variables)
+                    ));
+                }
+                out.push_str(&step.request.render(format));
+            }
+            Ok(out)
+        }
+        RequestSpec::CustomHandler { handler_name, vars } => {
+            let variables: BTreeMap<String, String> = vars
+                .iter()
+                .map(|(k, v)| {
+                    let shown = if is_sensitive(k) { "****" } else { v };
+                    (k.clone(), shown.to_string())
+                })
+                .collect();
+            match format {
+                DryRunFormat::Plain => Ok(format!(
+                    "# Custom handler '{}'; its requests are only known when it runs\n# Variables: {}\n",
+                    handler_name,
+                    describe_variables(&variables)
+                )),
+                DryRunFormat::Json => Ok(pretty(
+                    &json!({ "custom_handler": handler_name, "variables": variables }),
+                )),
+                DryRunFormat::Curl | DryRunFormat::Httpie => bail!(
+                    "custom handler '{}' cannot be exported as a shell command",
+                    handler_name
+                ),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::auth::RequestAuth;
+
+    fn spec(method: &str, endpoint: &str, headers: &[&str], body: Option<&str>) -> RawRequestSpec {
+        RawRequestSpec {
+            base_url: Some("https://api.example.com".to_string()),
+            method: method.to_string(),
+            endpoint: endpoint.to_string(),
+            headers: headers.iter().map(|h| h.to_string()).collect(),
+            body: body.map(str::to_string),
+            multipart: false,
+            file_fields: HashMap::new(),
+            form_fields: Vec::new(),
+            table_view: None,
+            list_path: None,
+            table_views: Default::default(),
+            detail_view: None,
+            output_template: None,
+            auth: vec![RequestAuth::Bearer("s3cret".to_string())],
+        }
+    }
+
+    // ==================== prepared request tests ====================
+
+    #[test]
+    fn test_plain_masks_credentials() {
+        let raw = spec(
+            "POST",
+            "/posts?tag=a b",
+            &["Content-Type: application/json"],
+            Some(r#"{"title":"x"}"#),
+        );
+        assert_eq!(
+            render(&RequestSpec::Simple(raw), DryRunFormat::Plain).unwrap(),
+            "POST https://api.example.com/posts?tag=a%20b\n\
+             content-type: application/json\n\
+             authorization: Bearer ****\n\
+             \n\
+             {\"title\":\"x\"}\n"
+        );
+    }
+
+    #[test]
+    fn test_curl_and_httpie_export() {
+        let raw = spec(
+            "POST",
+            "/posts",
+            &["Content-Type: application/json"],
+            Some(r#"{"title":"it's"}"#),
+        );
+        let prepared = PreparedRequest::from_spec(&raw, true).unwrap();
+        assert_eq!(
+            prepared.to_curl(),
+            "curl -X POST https://api.example.com/posts \\\n  \
+             -H 'content-type: application/json' \\\n  \
+             -H 'authorization: Bearer s3cret' \\\n  \
+             --data-raw '{\"title\":\"it'\\''s\"}'"
+        );
+        assert_eq!(
+            prepared.to_httpie(),
+            "http POST https://api.example.com/posts \\\n  \
+             content-type:application/json \\\n  \
+             'authorization:Bearer s3cret' \\\n  \
+             --raw '{\"title\":\"it'\\''s\"}'"
+        );
+
+        let get = PreparedRequest::from_spec(&spec("GET", "/posts/1", &[], None), true).unwrap();
+        assert_eq!(
+            get.to_curl(),
+            "curl https://api.example.com/posts/1 \\\n  -H 'authorization: Bearer s3cret'"
+        );
+    }
+
+    #[test]
+    fn test_multipart_files_are_listed_not_read() {
+        let mut raw = spec(
+            "POST",
+            "/upload",
+            &["Content-Type: multipart/form-data"],
+            None,
+        );
+        raw.multipart = true;
+        raw.file_fields
+            .insert("file".to_string(), "/no/such/report.pdf".to_string());
+        let prepared = PreparedRequest::from_spec(&raw, true).unwrap();
+        assert_eq!(
+            prepared.files,
+            vec![("file".to_string(), "/no/such/report.pdf".to_string())]
+        );
+        assert!(prepared.to_curl().contains("-F file=@/no/such/report.pdf"));
+        assert!(prepared
+            .to_httpie()
+            .starts_with("http POST https://api.example.com/upload \\\n  --multipart"));
+    }
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(
+            shell_quote("https://a.example/x?y=1"),
+            "'https://a.example/x?y=1'"
+        );
+        assert_eq!(shell_quote("plain-word_1"), "plain-word_1");
+        assert_eq!(shell_quote(""), "''");
+        assert_eq!(shell_quote("it's"), r"'it'\''s'");
+    }
+
+    // ==================== scenario tests ====================
+
+    #[test]
+    fn test_scenario_preview_marks_runtime_values() {
+        let scenario: crate::mapping::Scenario = serde_yaml::from_str(
+            r#"
+type: job_with_polling
+steps:
+  - name: schedule_job
+    method: POST
+    endpoint: /jobs
+    headers:
+      Content-Type: application/json
+    body: '{"name": "{name}"}'
+    extract_response:
+      job_id: "$.id"
+  - name: poll_job
+    method: GET
+    endpoint: /jobs/{job_id}
+    polling:
+      interval_seconds: 2
+      timeout_seconds: 60
+      completion_conditions:
+        - status: done
+          action: succeed
+        - status: failed
+          action: fail
+"#,
+        )
+        .unwrap();
+        let spec = ScenarioSpec {
+            base_url: Some("https://api.example.com".to_string()),
+            scenario,
+            vars: HashMap::from([
+                ("name".to_string(), "nightly".to_string()),
+                ("api_token".to_string(), "t".to_string()),
+            ]),
+            strict_templates: true,
+            auth: HashMap::new(),
+        };
+        let out = render(&RequestSpec::Scenario(spec), DryRunFormat::Plain).unwrap();
+        assert_eq!(
+            out,
+            "# Step 1: schedule_job\n\
+             # Variables: api_token=****, name=nightly\n\
+             POST https://api.example.com/jobs\n\
+             content-type: application/json\n\
+             \n\
+             {\"name\": \"nightly\"}\n\
+             \n\
+             # Step 2: poll_job\n\
+             # repeated every 2s for up to 60s until status is done or failed\n\
+             # Variables: api_token=****, job_id=<from schedule_job>, name=nightly\n\
+             GET https://api.example.com/jobs/<job_id>\n"
+        );
+    }
+
+    #[test]
+    fn test_custom_handler_cannot_be_exported() {
+        let spec = RequestSpec::CustomHandler {
+            handler_name: "export_users".to_string(),
+            vars: HashMap::from([("format".to_string(), "csv".to_string())]),
+        };
+        assert!(render(&spec, DryRunFormat::Plain)
+            .unwrap()
+            .contains("Variables: format=csv"));
+        let err = render(&spec, DryRunFormat::Curl).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "custom handler 'export_users' cannot be exported as a shell command"
+        );
+    }
+}
@@ -0,0 +1,298 @@
+//! Named cell formatters for table columns, used as `path:name` or `path:name:arg` in a
+//! `table_view` or `--columns` entry (`price:round:2`, `created:ago`).
+//!
+//! Applications can add their own formatters by name, or replace a built-in one:
+//!
+//! ```
+//! rclib::formatters::register("upper", |value, _arg| {
+//!     value.as_str().map(str::to_uppercase).unwrap_or_else(|| value.to_string())
+//! });
+//! ```
+
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use once_cell::sync::Lazy;
+use serde_json::Value;
+
+use crate::output::as_number;
+use crate::scalar_to_string;
+
+/// A formatter: the cell value and the text after its second colon, if any.
+pub type Formatter = dyn Fn(&Value, Option<&str>) -> String + Send + Sync;
+
+/// Formatters available without registration.
+pub const BUILTIN: &[&str] = &[
+    "bytes", "datetime", "ago", "duration", "percent", "round", "int", "bool", "len", "join",
+    "truncate",
+];
+
+static CUSTOM: Lazy<RwLock<HashMap<String, Arc<Formatter>>>> =
+    Lazy::new(|| RwLock::new(HashMap::new()));
+
+/// Make `name` usable in column specs. A custom formatter replaces a built-in one of the
+/// same name. Names are case-insensitive.
+pub fn register<F>(name: &str, formatter: F)
+where
+    F: Fn(&Value, Option<&str>) -> String + Send + Sync + 'static,
+{
+    CUSTOM
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(name.to_lowercase(), Arc::new(formatter));
+}
+
+/// Format `value` with the named formatter; `None` when there is no such formatter.
+/// Values a built-in formatter does not apply to (text given to `round`) are printed
+/// as they are.
+pub fn apply(name: &str, arg: Option<&str>, value: &Value) -> Option<String> {
+    let name = name.to_lowercase();
+    let custom = CUSTOM
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(&name)
+        .cloned();
+    if let Some(formatter) = custom {
+        return Some(formatter(value, arg));
+    }
+    let formatted = match name.as_str() {
+        "bytes" => as_number(value).map(bytes),
+        "datetime" => as_datetime(value).map(|t| datetime(t, arg.unwrap_or("%Y-%m-%d %H:%M:%S"))),
+        "ago" => as_datetime(value).map(|t| ago(t, Utc::now())),
+        "duration" => as_number(value).map(|n| {
+            let secs = if arg == Some("ms") { n / 1000.0 } else { n };
+            duration(secs)
+        }),
+        "percent" => as_number(value).map(|n| {
+            let n = if arg == Some("ratio") { n * 100.0 } else { n };
+            format!("{}%", trim_decimals(&format!("{:.1}", n)))
+        }),
+        "round" => as_number(value).map(|n| {
+            let places = arg.and_then(|a| a.parse::<usize>().ok()).unwrap_or(0);
+            format!("{:.*}", places, n)
+        }),
+        "int" => as_number(value).map(|n| format!("{:.0}", n)),
+        "bool" => Some(if truthy(value) { "✓" } else { "✗" }.to_string()),
+        "len" => match value {
+            Value::Array(items) => Some(items.len().to_string()),
+            Value::Object(map) => Some(map.len().to_string()),
+            Value::String(s) => Some(s.chars().count().to_string()),
+            _ => None,
+        },
+        "join" => value.as_array().map(|items| {
+            items
+                .iter()
+                .map(scalar_to_string)
+                .collect::<Vec<_>>()
+                .join(arg.unwrap_or(", "))
+        }),
+        "truncate" => {
+            let width = arg.and_then(|a| a.parse().ok()).unwrap_or(40);
+            Some(crate::table::truncate(&scalar_to_string(value), width))
+        }
+        _ => return None,
+    };
+    Some(formatted.unwrap_or_else(|| scalar_to_string(value)))
+}
+
+/// `t` in the strftime `format`; a format with an invalid specifier (`%Q`), which
+/// chrono only reports while writing, gives the RFC 3339 text instead.
+fn datetime(t: DateTime<Utc>, format: &str) -> String {
+    use std::fmt::Write;
+
+    let mut out = String::new();
+    match write!(out, "{}", t.format(format)) {
+        Ok(()) => out,
+        Err(_) => t.to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true),
+    }
+}
+
+/// RFC 3339 text, `YYYY-MM-DD HH:MM:SS` (taken as UTC), or Unix seconds (milliseconds
+/// when the number is too large to be seconds).
+fn as_datetime(value: &Value) -> Option<DateTime<Utc>> {
+    if let Value::String(s) = value {
+        if let Ok(t) = DateTime::parse_from_rfc3339(s) {
+            return Some(t.with_timezone(&Utc));
+        }
+        for fmt in ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S%.f"] {
+            if let Ok(t) = NaiveDateTime::parse_from_str(s, fmt) {
+                return Some(t.and_utc());
+            }
+        }
+    }
+    let n = as_number(value)?;
+    let millis = if n.abs() >= 1e11 { n } else { n * 1000.0 };
+    Utc.timestamp_millis_opt(millis as i64).single()
+}
+
+fn trim_decimals(s: &str) -> &str {
+    if s.contains('.') {
+        s.trim_end_matches('0').trim_end_matches('.')
+    } else {
+        s
+    }
+}
+
+/// `512 B`, `1.5 KB`, `2.3 GB` (1024-based, like the `kb`/`mb`/`gb` modifiers).
+fn bytes(n: f64) -> String {
+    const UNITS: [&str; 6] = ["B", "KB", "MB", "GB", "TB", "PB"];
+    let mut size = n;
+    let mut unit = 0;
+    while size.abs() >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", size, UNITS[0])
+    } else {
+        format!("{} {}", trim_decimals(&format!("{:.1}", size)), UNITS[unit])
+    }
+}
+
+/// Seconds as `1d2h`, `1h2m5s`, `45s` or `250ms`.
+fn duration(secs: f64) -> String {
+    let sign = if secs < 0.0 { "-" } else { "" };
+    let secs = secs.abs();
+    if secs < 1.0 && secs > 0.0 {
+        return format!("{}{}ms", sign, (secs * 1000.0).round());
+    }
+    let mut rest = secs.round() as u64;
+    let mut out = String::from(sign);
+    for (unit, size) in [("d", 86_400), ("h", 3_600), ("m", 60)] {
+        if rest >= size {
+            out.push_str(&format!("{}{}", rest / size, unit));
+            rest %= size;
+        }
+    }
+    if rest > 0 || out.len() == sign.len() {
+        out.push_str(&format!("{}s", rest));
+    }
+    out
+}
+
+/// `5m ago`, `3h ago`, `2mo ago`, or `in 2d` for times after `now`.
+fn ago(t: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let secs = (now - t).num_seconds();
+    let abs = secs.unsigned_abs();
+    if abs < 10 {
+        return "just now".to_string();
+    }
+    let span = match abs {
+        0..=59 => format!("{}s", abs),
+        60..=3_599 => format!("{}m", abs / 60),
+        3_600..=86_399 => format!("{}h", abs / 3_600),
+        86_400..=2_591_999 => format!("{}d", abs / 86_400),
+        2_592_000..=31_535_999 => format!("{}mo", abs / 2_592_000),
+        _ => format!("{}y", abs / 31_536_000),
+    };
+    if secs < 0 {
+        format!("in {}", span)
+    } else {
+        format!("{} ago", span)
+    }
+}
+
+fn truthy(value: &Value) -> bool {
+    match value {
+        Value::Bool(b) => *b,
+        Value::Number(n) => n.as_f64().is_some_and(|n| n != 0.0),
+        Value::String(s) => matches!(s.to_lowercase().as_str(), "true" | "yes" | "y" | "1" | "on"),
+        Value::Array(items) => !items.is_empty(),
+        Value::Object(map) => !map.is_empty(),
+        Value::Null => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn fmt(name: &str, arg: Option<&str>, value: Value) -> String {
+        apply(name, arg, &value).unwrap()
+    }
+
+    // ==================== built-in formatter tests ====================
+
+    #[test]
+    fn test_numeric_formatters() {
+        assert_eq!(fmt("bytes", None, json!(512)), "512 B");
+        assert_eq!(fmt("bytes", None, json!(1536)), "1.5 KB");
+        assert_eq!(fmt("bytes", None, json!(3 * 1024 * 1024)), "3 MB");
+        assert_eq!(fmt("bytes", None, json!("2147483648")), "2 GB");
+        assert_eq!(fmt("round", Some("2"), json!(19.987)), "19.99");
+        assert_eq!(fmt("round", None, json!(2.6)), "3");
+        assert_eq!(fmt("int", None, json!(99.4)), "99");
+        assert_eq!(fmt("percent", None, json!(12.48)), "12.5%");
+        assert_eq!(fmt("percent", Some("ratio"), json!(0.25)), "25%");
+        // Not a number: printed as is
+        assert_eq!(fmt("round", Some("2"), json!("n/a")), "n/a");
+    }
+
+    #[test]
+    fn test_time_formatters() {
+        assert_eq!(fmt("duration", None, json!(3720)), "1h2m");
+        assert_eq!(fmt("duration", None, json!(93_784)), "1d2h3m4s");
+        assert_eq!(fmt("duration", None, json!(45)), "45s");
+        assert_eq!(fmt("duration", None, json!(0)), "0s");
+        assert_eq!(fmt("duration", None, json!(0.25)), "250ms");
+        assert_eq!(fmt("duration", Some("ms"), json!(90_000)), "1m30s");
+
+        assert_eq!(
+            fmt("datetime", None, json!("2024-05-23T08:56:21.618Z")),
+            "2024-05-23 08:56:21"
+        );
+        assert_eq!(
+            fmt("datetime", Some("%Y-%m-%d"), json!(1_700_000_000)),
+            "2023-11-14"
+        );
+        assert_eq!(
+            fmt("datetime", Some("%H:%M"), json!(1_700_000_000_000_i64)),
+            "22:13"
+        );
+        // Column specs come from users: a bad specifier must not panic
+        assert_eq!(
+            fmt("datetime", Some("%Q"), json!(1_700_000_000)),
+            "2023-11-14T22:13:20Z"
+        );
+
+        let now = Utc.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap();
+        let before = |secs: i64| ago(now - chrono::Duration::seconds(secs), now);
+        assert_eq!(before(3), "just now");
+        assert_eq!(before(300), "5m ago");
+        assert_eq!(before(3 * 3600), "3h ago");
+        assert_eq!(before(2 * 86_400), "2d ago");
+        assert_eq!(before(65 * 86_400), "2mo ago");
+        assert_eq!(before(-7200), "in 2h");
+    }
+
+    #[test]
+    fn test_collection_and_text_formatters() {
+        assert_eq!(fmt("bool", None, json!(true)), "✓");
+        assert_eq!(fmt("bool", None, json!(0)), "✗");
+        assert_eq!(fmt("bool", None, json!("yes")), "✓");
+        assert_eq!(fmt("len", None, json!([1, 2, 3])), "3");
+        assert_eq!(fmt("len", None, json!("héllo")), "5");
+        assert_eq!(fmt("join", None, json!(["a", "b", 3])), "a, b, 3");
+        assert_eq!(fmt("join", Some("|"), json!(["a", "b"])), "a|b");
+        assert_eq!(fmt("truncate", Some("6"), json!("hello world")), "hello…");
+        assert!(apply("nope", None, &json!(1)).is_none());
+    }
+
+    #[test]
+    fn test_register_custom_formatter() {
+        register("Shout", |value, arg| {
+            format!(
+                "{}{}",
+                scalar_to_string(value).to_uppercase(),
+                arg.unwrap_or("!")
+            )
+        });
+        assert_eq!(fmt("shout", None, json!("hi")), "HI!");
+        assert_eq!(fmt("shout", Some("?"), json!("hi")), "HI?");
+        // Names are case-insensitive when used too, built-in ones included
+        assert_eq!(fmt("SHOUT", None, json!("hi")), "HI!");
+        assert_eq!(fmt("Round", Some("1"), json!(2.25)), "2.2");
+    }
+}
@@ -0,0 +1,499 @@
+//! JSON-lines journal of HTTP exchanges, written when `--log-file` is given.
+//!
+//! The journal is process-wide so that scenario steps, perf-test workers and custom
+//! handlers all append to the same file without threading it through every call.
+//! `drive_command` installs it for the length of one command.
+
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Instant;
+
+use anyhow::{Context, Result};
+use once_cell::sync::Lazy;
+use reqwest::blocking::{Client, Request, RequestBuilder, Response};
+use reqwest::header::HeaderMap;
+use reqwest::StatusCode;
+use serde_json::{json, Map, Value};
+
+/// Bodies longer than this many bytes are cut in the journal.
+pub const MAX_LOGGED_BODY: usize = 4096;
+
+const REDACTED: &str = "****";
+
+static JOURNAL: Lazy<RwLock<Option<Arc<Journal>>>> = Lazy::new(|| RwLock::new(None));
+
+/// An open journal file and the command whose exchanges it records.
+pub struct Journal {
+    pub path: String,
+    /// Command path, e.g. "users get" or "raw"
+    pub command: String,
+    file: Mutex<File>,
+}
+
+impl Journal {
+    /// Open `path` for appending.
+    pub fn open(path: &str, command: &str) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Failed to open log file: {}", path))?;
+        Ok(Self {
+            path: path.to_string(),
+            command: command.to_string(),
+            file: Mutex::new(file),
+        })
+    }
+
+    /// Append one entry; `timestamp` and `command` are added in front.
+    pub fn write(&self, entry: Map<String, Value>) -> Result<()> {
+        let mut line = Map::new();
+        line.insert(
+            "timestamp".to_string(),
+            Value::String(chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Millis, true)),
+        );
+        line.insert("command".to_string(), Value::String(self.command.clone()));
+        line.extend(entry);
+        let mut text = serde_json::to_string(&Value::Object(line))?;
+        text.push('\n');
+        // One write per line keeps lines whole when several threads log at once
+        let mut file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        file.write_all(text.as_bytes())
+            .with_context(|| format!("Failed to write log file: {}", self.path))
+    }
+}
+
+/// Install `journal` until the returned guard is dropped, which puts back the journal
+/// that was installed before (if this one is still the current one).
+pub fn install_scoped(journal: Journal) -> Installed {
+    let journal = Arc::new(journal);
+    let previous = JOURNAL
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .replace(Arc::clone(&journal));
+    Installed { journal, previous }
+}
+
+/// Keeps a journal installed; see [`install_scoped`].
+#[must_use = "the journal is uninstalled when the guard is dropped"]
+pub struct Installed {
+    journal: Arc<Journal>,
+    previous: Option<Arc<Journal>>,
+}
+
+impl Drop for Installed {
+    fn drop(&mut self) {
+        let mut current = JOURNAL.write().unwrap_or_else(|e| e.into_inner());
+        if current
+            .as_ref()
+            .is_some_and(|j| Arc::ptr_eq(j, &self.journal))
+        {
+            *current = self.previous.take();
+        }
+    }
+}
+
+/// The installed journal, if any.
+pub fn current() -> Option<Arc<Journal>> {
+    JOURNAL.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// Append a custom entry, e.g. from a custom handler: `{"event": event, "data": data}`.
+/// Does nothing without a journal; write errors are reported on stderr.
+pub fn note(event: &str, data: Value) {
+    if let Some(journal) = current() {
+        let mut entry = Map::new();
+        entry.insert("event".to_string(), Value::String(event.to_string()));
+        entry.insert("data".to_string(), data);
+        warn_on_error(journal.write(entry));
+    }
+}
+
+fn warn_on_error(result: Result<()>) {
+    if let Err(e) = result {
+        eprintln!("Warning: {:#}", e);
+    }
+}
+
+// =====================
+// Exchanges
+// =====================
+
+/// A response read in full.
+#[derive(Debug, Clone)]
+pub struct Fetched {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    /// The body as text, with invalid UTF-8 replaced
+    pub body: String,
+    /// The body exactly as received
+    pub bytes: Vec<u8>,
+}
+
+/// Send a request, read the whole response and journal the exchange. `step` names the
+/// scenario step, if any; `secrets` names headers and query parameters to mask besides
+/// the ones that look sensitive. Failed sends are journaled with their error.
+pub fn fetch(
+    http: &Client,
+    req: RequestBuilder,
+    step: Option<&str>,
+    secrets: &[&str],
+) -> Result<Fetched> {
+    let req = req.build().context("Failed to build HTTP request")?;
+    let journal = current();
+    let request = journal.as_ref().map(|_| request_entry(&req, secrets));
+    let started = Instant::now();
+    let result = http
+        .execute(req)
+        .context("HTTP request failed")
+        .and_then(|resp| {
+            let status = resp.status();
+            let headers = resp.headers().clone();
+            let bytes = resp
+                .bytes()
+                .context("Failed to read response body")?
+                .to_vec();
+            Ok(Fetched {
+                status,
+                headers,
+                body: String::from_utf8_lossy(&bytes).into_owned(),
+                bytes,
+            })
+        });
+    if let (Some(journal), Some(request)) = (journal, request) {
+        let response = result.as_ref().map(|f| response_entry(f, secrets));
+        write_exchange(&journal, step, request, response, started);
+    }
+    result
+}
+
+/// Send a request whose body the caller reads as it arrives, such as an event stream.
+/// The exchange is journaled when the response headers are in, with the body noted as
+/// streamed. `secrets` is as for [`fetch`].
+pub fn fetch_streaming(
+    http: &Client,
+    req: RequestBuilder,
+    step: Option<&str>,
+    secrets: &[&str],
+) -> Result<Response> {
+    let req = req.build().context("Failed to build HTTP request")?;
+    let journal = current();
+    let request = journal.as_ref().map(|_| request_entry(&req, secrets));
+    let started = Instant::now();
+    let result = http.execute(req).context("HTTP request failed");
+    if let (Some(journal), Some(request)) = (journal, request) {
+        let response = result.as_ref().map(|resp| {
+            json!({
+                "status": resp.status().as_u16(),
+                "headers": headers_entry(resp.headers(), secrets),
+                "body": "<streamed body>",
+            })
+        });
+        write_exchange(&journal, step, request, response, started);
+    }
+    result
+}
+
+fn write_exchange(
+    journal: &Journal,
+    step: Option<&str>,
+    request: Value,
+    response: std::result::Result<Value, &anyhow::Error>,
+    started: Instant,
+) {
+    let mut entry = Map::new();
+    if let Some(step) = step {
+        entry.insert("step".to_string(), Value::String(step.to_string()));
+    }
+    entry.insert("request".to_string(), request);
+    match response {
+        Ok(response) => {
+            entry.insert("response".to_string(), response);
+        }
+        Err(e) => {
+            entry.insert("error".to_string(), Value::String(format!("{:#}", e)));
+        }
+    }
+    entry.insert(
+        "latency_ms".to_string(),
+        json!(started.elapsed().as_millis() as u64),
+    );
+    warn_on_error(journal.write(entry));
+}
+
+fn request_entry(req: &Request, secrets: &[&str]) -> Value {
+    let body = match req.body() {
+        Some(body) => match body.as_bytes() {
+            Some(bytes) => body_value(bytes, req.headers()),
+            None => Value::String("<streamed body>".to_string()),
+        },
+        None => Value::Null,
+    };
+    json!({
+        "method": req.method().as_str(),
+        "url": redact_url(req.url(), secrets),
+        "headers": headers_entry(req.headers(), secrets),
+        "body": body,
+    })
+}
+
+fn response_entry(fetched: &Fetched, secrets: &[&str]) -> Value {
+    let mut entry = json!({
+        "status": fetched.status.as_u16(),
+        "headers": headers_entry(&fetched.headers, secrets),
+        "body": body_value(&fetched.bytes, &fetched.headers),
+    });
+    if fetched.bytes.len() > MAX_LOGGED_BODY {
+        entry["body_bytes"] = json!(fetched.bytes.len());
+    }
+    entry
+}
+
+fn headers_entry(headers: &HeaderMap, secrets: &[&str]) -> Value {
+    let mut map = Map::new();
+    for (name, value) in headers {
+        let value = redact_header(name.as_str(), value.to_str().unwrap_or("<binary>"), secrets);
+        // Repeated headers are joined the way HTTP allows
+        match map.get_mut(name.as_str()) {
+            Some(Value::String(existing)) => {
+                existing.push_str(", ");
+                existing.push_str(&value);
+            }
+            _ => {
+                map.insert(name.as_str().to_string(), Value::String(value));
+            }
+        }
+    }
+    Value::Object(map)
+}
+
+/// A URL with sensitive query parameters, and those named in `secrets`, masked.
+pub(crate) fn redact_url(url: &reqwest::Url, secrets: &[&str]) -> String {
+    let mut url = url.clone();
+    if url.query_pairs().any(|(k, _)| is_secret(&k, secrets)) {
+        let pairs: Vec<(String, String)> = url
+            .query_pairs()
+            .map(|(k, v)| {
+                let v = if is_secret(&k, secrets) {
+                    REDACTED.to_string()
+                } else {
+                    v.into_owned()
+                };
+                (k.into_owned(), v)
+            })
+            .collect();
+        url.query_pairs_mut().clear().extend_pairs(pairs);
+    }
+    url.to_string()
+}
+
+/// A header value, masked when the header carries a secret or is named in `secrets`.
+pub(crate) fn redact_header(name: &str, value: &str, secrets: &[&str]) -> String {
+    if name.eq_ignore_ascii_case("authorization")
+        || name.eq_ignore_ascii_case("proxy-authorization")
+    {
+        // Keep the scheme, e.g. "Bearer ****"
+        match value.split_once(' ') {
+            Some((scheme, _)) => format!("{} {}", scheme, REDACTED),
+            None => REDACTED.to_string(),
+        }
+    } else if is_secret(name, secrets) {
+        REDACTED.to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+/// Whether a header or query parameter is named in `secrets` or looks sensitive.
+fn is_secret(name: &str, secrets: &[&str]) -> bool {
+    secrets.iter().any(|s| s.eq_ignore_ascii_case(name)) || is_sensitive(name)
+}
+
+/// Redacted body text, cut to [`MAX_LOGGED_BODY`].
+fn body_value(bytes: &[u8], headers: &HeaderMap) -> Value {
+    Value::String(truncate(&redact_body(bytes, headers), MAX_LOGGED_BODY))
+}
+
+/// Body text with secrets in JSON and form bodies masked.
+fn redact_body(bytes: &[u8], headers: &HeaderMap) -> String {
+    let text = String::from_utf8_lossy(bytes);
+    let content_type = headers
+        .get("content-type")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("");
+    if let Ok(mut json) = serde_json::from_str::<Value>(&text) {
+        redact_json(&mut json);
+        json.to_string()
+    } else if content_type.starts_with("application/x-www-form-urlencoded") {
+        text.split('&')
+            .map(|pair| match pair.split_once('=') {
+                Some((k, _)) if is_sensitive(k) => format!("{}={}", k, REDACTED),
+                _ => pair.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join("&")
+    } else {
+        text.into_owned()
+    }
+}
+
+fn redact_json(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (k, v) in map.iter_mut() {
+                if is_sensitive(k) && !v.is_object() && !v.is_array() {
+                    *v = Value::String(REDACTED.to_string());
+                } else {
+                    redact_json(v);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(redact_json),
+        _ => {}
+    }
+}
+
+/// Whether a header, query parameter or JSON key likely holds a secret.
+pub fn is_sensitive(name: &str) -> bool {
+    let name: String = name
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+    name == "cookie"
+        || name == "setcookie"
+        || name == "key"
+        || [
+            "password",
+            "passwd",
+            "secret",
+            "token",
+            "apikey",
+            "authorization",
+            "credential",
+        ]
+        .iter()
+        .any(|s| name.contains(s))
+}
+
+fn truncate(text: &str, max: usize) -> String {
+    if text.len() <= max {
+        return text.to_string();
+    }
+    let mut end = max;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}...", &text[..end])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // ==================== redaction tests ====================
+
+    #[test]
+    fn test_is_sensitive() {
+        for name in [
+            "Authorization",
+            "X-API-Key",
+            "api_key",
+            "password",
+            "accessToken",
+            "refresh_token",
+            "client_secret",
+            "Cookie",
+            "key",
+        ] {
+            assert!(is_sensitive(name), "{}", name);
+        }
+        for name in ["content-type", "username", "id", "keyword", "monkey"] {
+            assert!(!is_sensitive(name), "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_request_entry_redacts_secrets() {
+        let client = Client::new();
+        let req = client
+            .post("http://localhost/login?q=a+b&api_key=k1")
+            .bearer_auth("t0p")
+            .header("X-Api-Key", "k2")
+            .header("Content-Type", "application/json")
+            .body(r#"{"username":"ann","password":"pw","nested":{"token":"t"},"tokens":[1]}"#)
+            .build()
+            .unwrap();
+        let entry = request_entry(&req, &[]);
+        assert_eq!(entry["method"], "POST");
+        assert_eq!(entry["url"], "http://localhost/login?q=a+b&api_key=****");
+        assert_eq!(entry["headers"]["authorization"], "Bearer ****");
+        assert_eq!(entry["headers"]["x-api-key"], "****");
+        assert_eq!(entry["headers"]["content-type"], "application/json");
+        assert_eq!(
+            entry["body"],
+            r#"{"username":"ann","password":"****","nested":{"token":"****"},"tokens":[1]}"#
+        );
+
+        let form = client
+            .post("http://localhost/token")
+            .form(&[("grant_type", "refresh_token"), ("refresh_token", "r1")])
+            .build()
+            .unwrap();
+        assert_eq!(
+            request_entry(&form, &[])["body"],
+            "grant_type=refresh_token&refresh_token=****"
+        );
+    }
+
+    #[test]
+    fn test_request_entry_redacts_applied_credentials() {
+        // API keys whose names do not look sensitive are masked when named as secrets
+        let req = Client::new()
+            .get("http://localhost/items?sig=s1&page=2")
+            .header("X-Tenant", "t1")
+            .build()
+            .unwrap();
+        let entry = request_entry(&req, &["sig", "x-tenant"]);
+        assert_eq!(entry["url"], "http://localhost/items?sig=****&page=2");
+        assert_eq!(entry["headers"]["x-tenant"], "****");
+        let entry = request_entry(&req, &[]);
+        assert_eq!(entry["url"], "http://localhost/items?sig=s1&page=2");
+    }
+
+    #[test]
+    fn test_body_value_truncates_on_char_boundary() {
+        let text = "é".repeat(MAX_LOGGED_BODY);
+        let Value::String(logged) = body_value(text.as_bytes(), &HeaderMap::new()) else {
+            panic!("body is not a string");
+        };
+        assert!(logged.ends_with("..."));
+        assert!(logged.len() <= MAX_LOGGED_BODY + 3);
+    }
+
+    // ==================== journal tests ====================
+
+    #[test]
+    fn test_journal_writes_json_lines() {
+        let path =
+            std::env::temp_dir().join(format!("rclib-journal-{}.jsonl", uuid::Uuid::new_v4()));
+        let journal = Journal::open(path.to_str().unwrap(), "users get").unwrap();
+        let mut entry = Map::new();
+        entry.insert("event".to_string(), json!("one"));
+        journal.write(entry.clone()).unwrap();
+        entry.insert("event".to_string(), json!("two"));
+        journal.write(entry).unwrap();
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<Value> = text
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["command"], "users get");
+        assert_eq!(lines[1]["event"], "two");
+        assert!(lines[0]["timestamp"].as_str().unwrap().ends_with('Z'));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,3 +1,4 @@
+use std::borrow::Cow;
 use std::collections::{HashMap, HashSet};
 use std::sync::mpsc;
 use std::sync::{
@@ -8,17 +9,32 @@ use std::thread;
 use std::time::{Duration, Instant};
 
 use anyhow::{bail, Context, Result};
+use indexmap::IndexMap;
 use jsonpath_lib as jsonpath;
 use openapiv3::OpenAPI;
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
 use regex::Regex;
-use reqwest::blocking::{Client, ClientBuilder, Response};
+use reqwest::blocking::{Client, ClientBuilder};
 use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
 use reqwest::Method;
 use serde_json::Value;
 use uuid::Uuid;
 
+pub mod auth;
 pub mod cli;
+pub mod curl;
+pub mod dry_run;
+pub mod formatters;
+pub mod journal;
 pub mod mapping;
+pub mod openapi;
+pub mod output;
+pub mod output_template;
+pub mod pagination;
+pub mod sse;
+pub mod table;
+#[cfg(test)]
+mod test_server;
 
 // =====================
 // Public API
@@ -29,6 +45,50 @@ pub enum OutputFormat {
     Json,
     Human,
     Quiet,
+    /// JSON on a single line
+    JsonCompact,
+    Yaml,
+    Csv,
+    Tsv,
+    /// One JSON object per table row
+    Ndjson,
+    /// The response body exactly as received
+    Raw,
+}
+
+impl OutputFormat {
+    /// Names accepted by `--output`, in help order.
+    pub const NAMES: &'static [&'static str] = &[
+        "json",
+        "json-compact",
+        "yaml",
+        "csv",
+        "tsv",
+        "ndjson",
+        "table",
+        "raw",
+    ];
+
+    /// Parse an `--output` name; `table` is [`OutputFormat::Human`].
+    pub fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "json" => Self::Json,
+            "json-compact" => Self::JsonCompact,
+            "yaml" => Self::Yaml,
+            "csv" => Self::Csv,
+            "tsv" => Self::Tsv,
+            "ndjson" => Self::Ndjson,
+            "table" => Self::Human,
+            "raw" => Self::Raw,
+            _ => return None,
+        })
+    }
+
+    /// Whether the output is meant for other programs, so progress messages and
+    /// summaries are left out.
+    pub fn is_machine_readable(self) -> bool {
+        !matches!(self, Self::Human | Self::Quiet)
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -40,7 +100,13 @@ pub struct RawRequestSpec {
     pub body: Option<String>,
     pub multipart: bool,
     pub file_fields: HashMap<String, String>, // field_name -> file_path
+    pub form_fields: Vec<(String, String)>,   // multipart text fields (name, value)
     pub table_view: Option<Vec<String>>,      // optional column hints for array responses
+    pub list_path: Option<String>,            // JSONPath of the array table_view applies to
+    pub table_views: IndexMap<String, Vec<String>>, // columns for other arrays, by path
+    pub detail_view: Option<Vec<mapping::DetailSection>>, // sections for single objects
+    pub output_template: Option<String>,      // text replacing the human output on success
+    pub auth: Vec<auth::RequestAuth>,         // credentials applied when the request is sent
 }
 
 #[derive(Debug, Clone)]
@@ -58,6 +124,10 @@ pub struct ScenarioSpec {
     pub base_url: Option<String>,
     pub scenario: mapping::Scenario,
     pub vars: HashMap<String, String>,
+    /// Fail on unresolved step placeholders instead of substituting empty strings
+    pub strict_templates: bool,
+    /// Credentials per step name
+    pub auth: HashMap<String, Vec<auth::RequestAuth>>,
 }
 
 /// Configuration for request execution including timeouts, output format, and load testing options.
@@ -71,6 +141,8 @@ pub struct ExecutionConfig<'a> {
     pub count: Option<u32>,
     pub duration_secs: u32,
     pub concurrency: u32,
+    /// Client-side filtering of the response before it is printed
+    pub filter: output::ResponseFilter,
 }
 
 impl<'a> ExecutionConfig<'a> {
@@ -85,6 +157,7 @@ impl<'a> ExecutionConfig<'a> {
             count: None,
             duration_secs: 0,
             concurrency: 1,
+            filter: output::ResponseFilter::default(),
         }
     }
 }
@@ -126,39 +199,75 @@ fn apply_file_overrides(args: &[mapping::ArgSpec], vars: &mut HashMap<String, St
 }
 
 /// Build a RequestSpec from a command entry and variable map, handling simple, scenario, and custom handler commands.
+///
+/// Unresolved placeholders become empty strings, `body_fields` values that do not
+/// convert to their type are sent as JSON strings, and a field nesting inside a
+/// non-object value replaces it; see [`try_build_request_from_command`].
 pub fn build_request_from_command(
     base_url: Option<String>,
     cmd: &mapping::CommandSpec,
     vars: &HashMap<String, String>,
     selected_args: &HashSet<String>,
 ) -> RequestSpec {
+    build_request(base_url, cmd, vars, selected_args, false, true)
+        .expect("lenient builds render without checks and overwrite nesting conflicts")
+}
+
+/// Same as [`build_request_from_command`], but a `body_fields` value that does not convert
+/// to its type is an error. With `strict_templates`, any endpoint, body or header
+/// placeholder (including scenario steps) left without a value is an error naming the
+/// template field. `query:` entries are exempt, since leaving them out is their point.
+pub fn try_build_request_from_command(
+    base_url: Option<String>,
+    cmd: &mapping::CommandSpec,
+    vars: &HashMap<String, String>,
+    selected_args: &HashSet<String>,
+    strict_templates: bool,
+) -> Result<RequestSpec> {
+    build_request(base_url, cmd, vars, selected_args, strict_templates, false)
+}
+
+fn build_request(
+    base_url: Option<String>,
+    cmd: &mapping::CommandSpec,
+    vars: &HashMap<String, String>,
+    selected_args: &HashSet<String>,
+    strict_templates: bool,
+    lenient_body_fields: bool,
+) -> Result<RequestSpec> {
     // Check if this is a custom handler command
     if let Some(handler_name) = &cmd.custom_handler {
         // Add built-in variables
-        let mut vars_with_builtins = vars.clone();
+        let mut vars_with_builtins = joined_multi_values(vars);
         vars_with_builtins.insert("uuid".to_string(), Uuid::new_v4().to_string());
 
         apply_file_overrides(&cmd.args, &mut vars_with_builtins);
 
-        return RequestSpec::CustomHandler {
+        return Ok(RequestSpec::CustomHandler {
             handler_name: handler_name.clone(),
             vars: vars_with_builtins,
-        };
+        });
     }
 
     // Check if this is a scenario command
     if let Some(scenario) = &cmd.scenario {
         // Add built-in variables
-        let mut vars_with_builtins = vars.clone();
+        let mut vars_with_builtins = joined_multi_values(vars);
         vars_with_builtins.insert("uuid".to_string(), Uuid::new_v4().to_string());
 
         apply_file_overrides(&cmd.args, &mut vars_with_builtins);
 
-        return RequestSpec::Scenario(ScenarioSpec {
+        if strict_templates {
+            check_scenario_templates(scenario, &vars_with_builtins)?;
+        }
+
+        return Ok(RequestSpec::Scenario(ScenarioSpec {
             base_url,
             scenario: scenario.clone(),
             vars: vars_with_builtins,
-        });
+            strict_templates,
+            auth: HashMap::new(),
+        }));
     }
 
     // Handle regular command
@@ -178,17 +287,19 @@ pub fn build_request_from_command(
 
     apply_file_overrides(&cmd.args, &mut vars_with_builtins);
 
-    // Start with command-level values
+    // Start with command-level values; templates are rendered once overrides are settled,
+    // since escaping depends on where each value lands
     let mut method = method;
-    let mut endpoint = substitute_template(endpoint_template, &vars_with_builtins);
-    let mut body = cmd
-        .body
-        .as_ref()
-        .map(|t| substitute_template(t, &vars_with_builtins));
-    let mut headers_map: HashMap<String, String> = cmd
+    let mut endpoint_template = endpoint_template.as_str();
+    let mut query = cmd.query.clone();
+    let mut endpoint_field = "endpoint".to_string();
+    let mut body_template = cmd.body.as_deref().map(|b| (b, "body".to_string()));
+    let mut body_overridden = false;
+    let mut body_fields = cmd.body_fields.clone();
+    let mut header_templates: HashMap<String, (&str, String)> = cmd
         .headers
         .iter()
-        .map(|(k, v)| (k.clone(), substitute_template(v, &vars_with_builtins)))
+        .map(|(k, v)| (k.clone(), (v.as_str(), format!("header '{}'", k))))
         .collect();
 
     // Apply per-arg overrides, if present
@@ -197,23 +308,88 @@ pub fn build_request_from_command(
         if let Some(arg_name) = a.name.as_ref() {
             if selected_args.contains(arg_name) {
                 if let Some(ep) = &a.endpoint {
-                    endpoint = substitute_template(ep, &vars_with_builtins);
+                    endpoint_template = ep;
+                    endpoint_field = format!("endpoint override of '{}'", arg_name);
                 }
                 if let Some(m) = &a.method {
                     method = m.clone();
                 }
                 if let Some(hdrs) = &a.headers {
                     for (k, v) in hdrs {
-                        headers_map.insert(k.clone(), substitute_template(v, &vars_with_builtins));
+                        header_templates.insert(
+                            k.clone(),
+                            (v, format!("header '{}' override of '{}'", k, arg_name)),
+                        );
                     }
                 }
                 if let Some(b) = &a.body {
-                    body = Some(substitute_template(b, &vars_with_builtins));
+                    body_template = Some((b, format!("body override of '{}'", arg_name)));
+                    body_overridden = true;
+                }
+                if let Some(fields) = &a.body_fields {
+                    body_fields.extend(fields.iter().map(|(k, v)| (k.clone(), v.clone())));
+                }
+                if let Some(q) = &a.query {
+                    query.extend(q.iter().map(|(k, v)| (k.clone(), v.clone())));
                 }
             }
         }
     }
 
+    let render = |field: &str, template: &str, context: TemplateContext| {
+        render_field(
+            field,
+            template,
+            &vars_with_builtins,
+            context,
+            strict_templates,
+        )
+    };
+    let mut headers_map: HashMap<String, String> = HashMap::new();
+    for (k, (template, field)) in &header_templates {
+        headers_map.insert(k.clone(), render(field, template, TemplateContext::Raw)?);
+    }
+    let mut endpoint = render(&endpoint_field, endpoint_template, TemplateContext::Url)?;
+    let body_context = if is_json_content_type(&headers_map) {
+        TemplateContext::Json
+    } else {
+        TemplateContext::Raw
+    };
+    // An arg's `body` override beats `body_fields`, which beat the command's `body`
+    let body = match body_template {
+        Some((template, field)) if body_overridden || body_fields.is_empty() => {
+            Some(render(&field, template, body_context)?)
+        }
+        _ if !body_fields.is_empty() => {
+            if !headers_map
+                .keys()
+                .any(|k| k.eq_ignore_ascii_case("content-type"))
+            {
+                headers_map.insert("Content-Type".to_string(), "application/json".to_string());
+            }
+            let value = build_json_body(
+                &body_fields,
+                &cmd.args,
+                &vars_with_builtins,
+                selected_args,
+                lenient_body_fields,
+            )?;
+            Some(value.to_string())
+        }
+        _ => None,
+    };
+
+    let multi_vars: HashSet<String> = cmd
+        .args
+        .iter()
+        .filter(|a| a.multiple)
+        .filter_map(|a| a.name.clone())
+        .collect();
+    endpoint = append_query(
+        &endpoint,
+        &build_query_string(&query, &vars_with_builtins, &multi_vars),
+    );
+
     let headers: Vec<String> = headers_map
         .into_iter()
         .map(|(k, v)| format!("{}: {}", k, v))
@@ -233,7 +409,7 @@ pub fn build_request_from_command(
         }
     }
 
-    RequestSpec::Simple(RawRequestSpec {
+    Ok(RequestSpec::Simple(RawRequestSpec {
         base_url,
         method,
         endpoint,
@@ -241,8 +417,216 @@ pub fn build_request_from_command(
         body,
         multipart: cmd.multipart,
         file_fields,
+        form_fields: Vec::new(),
         table_view: cmd.table_view.clone(),
-    })
+        list_path: cmd.list_path.clone(),
+        table_views: cmd.table_views.clone(),
+        detail_view: cmd.detail_view.clone(),
+        output_template: cmd.output_template.clone(),
+        auth: Vec::new(),
+    }))
+}
+
+/// Render one template field, strictly or not; strict errors name the field.
+fn render_field(
+    field: &str,
+    template: &str,
+    vars: &HashMap<String, String>,
+    context: TemplateContext,
+    strict: bool,
+) -> Result<String> {
+    if strict {
+        render_template_strict(template, vars, context).with_context(|| field.to_string())
+    } else {
+        Ok(render_template(template, vars, context))
+    }
+}
+
+/// Assemble a JSON body from `body_fields`, leaving out fields whose arg was not provided.
+///
+/// Bool flags count as provided only when set; other args when they have a value (given
+/// or defaulted). With `lenient`, values that do not convert are sent as strings, and a
+/// field nesting inside a non-object value replaces it, so the later key wins.
+fn build_json_body(
+    fields: &IndexMap<String, mapping::BodyField>,
+    args: &[mapping::ArgSpec],
+    vars: &HashMap<String, String>,
+    selected_args: &HashSet<String>,
+    lenient: bool,
+) -> Result<serde_json::Value> {
+    let mut body = serde_json::Value::Object(serde_json::Map::new());
+    for (key, field) in fields {
+        let arg = args.iter().find(|a| a.name.as_deref() == Some(field.arg()));
+        let is_flag = arg.is_some_and(|a| a.arg_type.as_deref() == Some("bool"));
+        let Some(raw) = vars.get(field.arg()) else {
+            continue;
+        };
+        if is_flag && !selected_args.contains(field.arg()) {
+            continue;
+        }
+        let field_type = field.field_type().unwrap_or(match arg {
+            Some(a) if a.multiple => "array",
+            Some(a) => match a.arg_type.as_deref() {
+                Some(t @ ("int" | "float" | "bool")) => t,
+                _ => "string",
+            },
+            None => "string",
+        });
+        let value = if field_type == "array" && arg.is_some_and(|a| a.multiple) {
+            serde_json::Value::Array(
+                raw.split(MULTI_VALUE_SEPARATOR)
+                    .filter(|v| !v.is_empty())
+                    .map(|v| serde_json::Value::String(v.to_string()))
+                    .collect(),
+            )
+        } else {
+            let raw = raw.replace(MULTI_VALUE_SEPARATOR, ",");
+            match coerce_body_value(&raw, field_type) {
+                Ok(value) => value,
+                Err(_) if lenient => serde_json::Value::String(raw),
+                Err(reason) => bail!("body field '{}' ({}): {}", key, field.arg(), reason),
+            }
+        };
+        insert_body_value(&mut body, key, value, lenient)?;
+    }
+    Ok(body)
+}
+
+/// Build a JSON object from `key=value` (string) and `key:=json` (raw JSON) items, as
+/// given to `raw --json`. Keys nest like `body_fields` keys.
+pub fn json_body_from_pairs(pairs: &[String]) -> Result<serde_json::Value> {
+    let mut body = serde_json::Value::Object(serde_json::Map::new());
+    for pair in pairs {
+        let (key, value) = match pair.split_once('=') {
+            Some((key, raw)) => match key.strip_suffix(':') {
+                Some(key) => (
+                    key,
+                    serde_json::from_str(raw)
+                        .with_context(|| format!("--json '{}': invalid JSON value", pair))?,
+                ),
+                None => (key, serde_json::Value::String(raw.to_string())),
+            },
+            None => bail!("--json '{}': expected key=value or key:=json", pair),
+        };
+        if key.is_empty() {
+            bail!("--json '{}': missing key", pair);
+        }
+        insert_body_value(&mut body, key, value, false)?;
+    }
+    Ok(body)
+}
+
+/// Convert an arg value to one of [`mapping::BODY_FIELD_TYPES`].
+pub fn coerce_body_value(raw: &str, field_type: &str) -> Result<serde_json::Value, String> {
+    use serde_json::Value;
+    match field_type {
+        "string" => Ok(Value::String(raw.to_string())),
+        "int" => raw
+            .trim()
+            .parse::<i64>()
+            .map(Value::from)
+            .map_err(|_| format!("expected an integer, got '{}'", raw)),
+        "float" => raw
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .and_then(serde_json::Number::from_f64)
+            .map(Value::Number)
+            .ok_or_else(|| format!("expected a number, got '{}'", raw)),
+        "bool" => match raw.trim().to_ascii_lowercase().as_str() {
+            "true" | "1" | "yes" => Ok(Value::Bool(true)),
+            "false" | "0" | "no" => Ok(Value::Bool(false)),
+            _ => Err(format!("expected true or false, got '{}'", raw)),
+        },
+        "json" => serde_json::from_str(raw).map_err(|e| format!("invalid JSON: {}", e)),
+        "array" => Ok(Value::Array(
+            raw.split(',')
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .map(|v| Value::String(v.to_string()))
+                .collect(),
+        )),
+        other => Err(format!("unknown type '{}'", other)),
+    }
+}
+
+/// Split a `body_fields` key into object keys: JSON pointer (`/a/b`, with `~1`/`~0`
+/// escapes) or dotted path (`a.b`).
+pub fn body_field_path(key: &str) -> Vec<String> {
+    match key.strip_prefix('/') {
+        Some(pointer) => pointer
+            .split('/')
+            .map(|s| s.replace("~1", "/").replace("~0", "~"))
+            .collect(),
+        None => key.split('.').map(|s| s.to_string()).collect(),
+    }
+}
+
+/// Set `value` at the path named by `key`, creating objects along the way. A non-object
+/// value in the way is an error, or is replaced by an object with `overwrite`.
+fn insert_body_value(
+    body: &mut serde_json::Value,
+    key: &str,
+    value: serde_json::Value,
+    overwrite: bool,
+) -> Result<()> {
+    let path = body_field_path(key);
+    let (last, parents) = path.split_last().expect("split always yields a segment");
+    let mut current = body;
+    for segment in parents {
+        current = as_object(current, key, overwrite)?
+            .entry(segment.clone())
+            .or_insert_with(|| serde_json::Value::Object(serde_json::Map::new()));
+    }
+    as_object(current, key, overwrite)?.insert(last.clone(), value);
+    Ok(())
+}
+
+fn as_object<'a>(
+    value: &'a mut serde_json::Value,
+    key: &str,
+    overwrite: bool,
+) -> Result<&'a mut serde_json::Map<String, serde_json::Value>> {
+    if overwrite && !value.is_object() {
+        *value = serde_json::Value::Object(serde_json::Map::new());
+    }
+    match value {
+        serde_json::Value::Object(map) => Ok(map),
+        _ => bail!(
+            "body field '{}' nests inside a value that is not an object",
+            key
+        ),
+    }
+}
+
+/// Check every step template up front, counting values extracted by earlier steps as set,
+/// so a strict scenario fails before its first request.
+fn check_scenario_templates(
+    scenario: &mapping::Scenario,
+    vars: &HashMap<String, String>,
+) -> Result<()> {
+    let mut known = vars.clone();
+    for step in &scenario.steps {
+        let mut templates = vec![(format!("step '{}' endpoint", step.name), &step.endpoint)];
+        if let Some(body) = &step.body {
+            templates.push((format!("step '{}' body", step.name), body));
+        }
+        let mut header_names: Vec<&String> = step.headers.keys().collect();
+        header_names.sort();
+        for k in header_names {
+            templates.push((
+                format!("step '{}' header '{}'", step.name, k),
+                &step.headers[k],
+            ));
+        }
+        for (field, template) in templates {
+            render_template_strict(template, &known, TemplateContext::Raw).context(field)?;
+        }
+        for key in step.extract_response.keys() {
+            known.entry(key.clone()).or_default();
+        }
+    }
+    Ok(())
 }
 
 /// Execute a request and print output according to format.
@@ -251,13 +635,23 @@ pub fn execute_request(
     output: OutputFormat,
     user_agent: &str,
 ) -> Result<i32> {
-    execute_request_with_timeout(spec, output, None, None, user_agent, &HashSet::new(), false)
+    execute_request_with_timeout(
+        spec,
+        output,
+        &output::ResponseFilter::default(),
+        None,
+        None,
+        user_agent,
+        &HashSet::new(),
+        false,
+    )
 }
 
 /// Execute either a simple request or a scenario.
 pub fn execute_request_spec(
     spec: &RequestSpec,
     output: OutputFormat,
+    filter: &output::ResponseFilter,
     conn_timeout_secs: Option<f64>,
     request_timeout_secs: Option<f64>,
     user_agent: &str,
@@ -267,6 +661,7 @@ pub fn execute_request_spec(
         RequestSpec::Simple(raw_spec) => execute_request_with_timeout(
             raw_spec,
             output,
+            filter,
             conn_timeout_secs,
             request_timeout_secs,
             user_agent,
@@ -276,6 +671,7 @@ pub fn execute_request_spec(
         RequestSpec::Scenario(scenario_spec) => execute_scenario(
             scenario_spec,
             output,
+            filter,
             conn_timeout_secs,
             request_timeout_secs,
             user_agent,
@@ -309,6 +705,7 @@ fn execute_worker_request(
     let result = execute_request_spec(
         spec,
         output,
+        &output::ResponseFilter::default(),
         conn_timeout_secs,
         request_timeout_secs,
         user_agent,
@@ -342,6 +739,7 @@ pub fn execute_requests_loop(spec: &RequestSpec, config: &ExecutionConfig<'_>) -
         count,
         duration_secs,
         concurrency,
+        ref filter,
     } = *config;
 
     // Determine execution mode: duration-based or count-based
@@ -355,6 +753,7 @@ pub fn execute_requests_loop(spec: &RequestSpec, config: &ExecutionConfig<'_>) -
                 return execute_request_spec(
                     spec,
                     output,
+                    filter,
                     conn_timeout_secs,
                     request_timeout_secs,
                     user_agent,
@@ -379,6 +778,7 @@ pub fn execute_requests_loop(spec: &RequestSpec, config: &ExecutionConfig<'_>) -
         return execute_request_spec(
             spec,
             output,
+            filter,
             conn_timeout_secs,
             request_timeout_secs,
             user_agent,
@@ -516,7 +916,7 @@ pub fn execute_requests_loop(spec: &RequestSpec, config: &ExecutionConfig<'_>) -
     let overall_duration = overall_start.elapsed();
 
     // Print summary
-    if final_executed_count > 1 && !matches!(output, OutputFormat::Json) {
+    if final_executed_count > 1 && !output.is_machine_readable() {
         println!("======= Execution Summary =======");
         println!("Concurrency:            {}", concurrency);
         println!(
@@ -566,9 +966,11 @@ pub fn execute_requests_loop(spec: &RequestSpec, config: &ExecutionConfig<'_>) -
 }
 
 /// Execute a request with optional connection and request timeout seconds.
+#[allow(clippy::too_many_arguments)]
 pub fn execute_request_with_timeout(
     spec: &RawRequestSpec,
     output: OutputFormat,
+    filter: &output::ResponseFilter,
     conn_timeout_secs: Option<f64>,
     request_timeout_secs: Option<f64>,
     user_agent: &str,
@@ -584,6 +986,46 @@ pub fn execute_request_with_timeout(
     }
     let client = builder.build().context("Failed to build HTTP client")?;
 
+    // Built per attempt: a rejected OAuth2 token is renewed and the request sent again
+    let build = |auth: &[auth::RequestAuth]| build_http_request(&client, spec, auth);
+
+    let full_url = build_url(&spec.base_url, &spec.endpoint)?;
+    if verbose {
+        eprintln!("-> {} {}", spec.method, full_url);
+        if !spec.headers.is_empty() {
+            eprintln!("-> Headers:");
+            for h in &spec.headers {
+                eprintln!("   {}", h);
+            }
+        }
+        for a in &spec.auth {
+            eprintln!("-> Auth: {}", a.describe());
+        }
+        if let Some(b) = &spec.body {
+            eprintln!("-> Body: {}", b);
+        }
+    }
+
+    let started = std::time::Instant::now();
+    let resp = auth::send_with_auth(&client, &spec.auth, verbose, None, build)?;
+    let elapsed_ms = started.elapsed().as_millis();
+    if verbose {
+        eprintln!(
+            "<- {} {} ({} ms)",
+            resp.status.as_u16(),
+            full_url,
+            elapsed_ms
+        );
+    }
+    output_response(resp, output, filter, TableLayout::of(spec))
+}
+
+/// Turn a spec into a request on `client` with the given credentials.
+fn build_http_request(
+    client: &Client,
+    spec: &RawRequestSpec,
+    auth: &[auth::RequestAuth],
+) -> Result<reqwest::blocking::RequestBuilder> {
     let url = build_url(&spec.base_url, &spec.endpoint)?;
     let method = parse_method(&spec.method)?;
     let mut req = client.request(method, url);
@@ -597,10 +1039,14 @@ pub fn execute_request_with_timeout(
     if !extra_headers.is_empty() {
         req = req.headers(extra_headers);
     }
+    req = auth::apply_auth(req, auth);
 
-    if spec.multipart && !spec.file_fields.is_empty() {
+    if spec.multipart && (!spec.file_fields.is_empty() || !spec.form_fields.is_empty()) {
         // Build multipart form
         let mut form = reqwest::blocking::multipart::Form::new();
+        for (field_name, value) in &spec.form_fields {
+            form = form.text(field_name.clone(), value.clone());
+        }
 
         for (field_name, file_path) in &spec.file_fields {
             let file_contents = std::fs::read(file_path)
@@ -619,39 +1065,14 @@ pub fn execute_request_with_timeout(
     } else if let Some(body) = &spec.body {
         req = req.body(body.clone());
     }
-
-    let full_url = build_url(&spec.base_url, &spec.endpoint)?;
-    if verbose {
-        eprintln!("-> {} {}", spec.method, full_url);
-        if !spec.headers.is_empty() {
-            eprintln!("-> Headers:");
-            for h in &spec.headers {
-                eprintln!("   {}", h);
-            }
-        }
-        if let Some(b) = &spec.body {
-            eprintln!("-> Body: {}", b);
-        }
-    }
-
-    let started = std::time::Instant::now();
-    let resp = req.send().context("HTTP request failed")?;
-    let elapsed_ms = started.elapsed().as_millis();
-    if verbose {
-        eprintln!(
-            "<- {} {} ({} ms)",
-            resp.status().as_u16(),
-            full_url,
-            elapsed_ms
-        );
-    }
-    output_response(resp, output, spec.table_view.as_ref())
+    Ok(req)
 }
 
 /// Execute a scenario with multiple steps.
 pub fn execute_scenario(
     scenario_spec: &ScenarioSpec,
     output: OutputFormat,
+    filter: &output::ResponseFilter,
     conn_timeout_secs: Option<f64>,
     request_timeout_secs: Option<f64>,
     user_agent: &str,
@@ -664,6 +1085,7 @@ pub fn execute_scenario(
             scenario_spec,
             &mut variables,
             output,
+            filter,
             conn_timeout_secs,
             request_timeout_secs,
             user_agent,
@@ -679,10 +1101,12 @@ pub fn execute_scenario(
 }
 
 /// Execute a job_with_polling scenario.
+#[allow(clippy::too_many_arguments)]
 fn execute_job_with_polling_scenario(
     scenario_spec: &ScenarioSpec,
     variables: &mut HashMap<String, String>,
     output: OutputFormat,
+    filter: &output::ResponseFilter,
     conn_timeout_secs: Option<f64>,
     request_timeout_secs: Option<f64>,
     user_agent: &str,
@@ -698,8 +1122,17 @@ fn execute_job_with_polling_scenario(
         bail!("First step must be named 'schedule_job'");
     }
 
-    let schedule_spec =
-        build_raw_spec_from_step(&scenario_spec.base_url, schedule_step, variables)?;
+    let mut schedule_spec = build_raw_spec_from_step(
+        &scenario_spec.base_url,
+        schedule_step,
+        variables,
+        scenario_spec.strict_templates,
+    )?;
+    schedule_spec.auth = scenario_spec
+        .auth
+        .get(&schedule_step.name)
+        .cloned()
+        .unwrap_or_default();
     if verbose {
         eprintln!(
             "-> {} {}",
@@ -709,6 +1142,7 @@ fn execute_job_with_polling_scenario(
     }
     let schedule_response = execute_single_request(
         &schedule_spec,
+        &schedule_step.name,
         conn_timeout_secs,
         request_timeout_secs,
         user_agent,
@@ -721,7 +1155,7 @@ fn execute_job_with_polling_scenario(
         variables,
     )?;
 
-    if output == OutputFormat::Json {
+    if output.is_machine_readable() {
         println!("Step 1 (schedule_job) completed");
     } else {
         let job_id = variables
@@ -754,7 +1188,17 @@ fn execute_job_with_polling_scenario(
             );
         }
 
-        let poll_spec = build_raw_spec_from_step(&scenario_spec.base_url, poll_step, variables)?;
+        let mut poll_spec = build_raw_spec_from_step(
+            &scenario_spec.base_url,
+            poll_step,
+            variables,
+            scenario_spec.strict_templates,
+        )?;
+        poll_spec.auth = scenario_spec
+            .auth
+            .get(&poll_step.name)
+            .cloned()
+            .unwrap_or_default();
         if verbose {
             eprintln!(
                 "-> {} {}",
@@ -764,6 +1208,7 @@ fn execute_job_with_polling_scenario(
         }
         let poll_response = execute_single_request(
             &poll_spec,
+            &poll_step.name,
             conn_timeout_secs,
             request_timeout_secs,
             user_agent,
@@ -780,8 +1225,24 @@ fn execute_job_with_polling_scenario(
                     if status_str == condition.status {
                         match condition.action.as_str() {
                             "success" => {
-                                if output == OutputFormat::Json {
-                                    println!("{}", poll_response);
+                                let template = scenario_spec.scenario.output_template.as_deref();
+                                if output.is_machine_readable() || !filter.is_empty() {
+                                    print_response_text(
+                                        &poll_response,
+                                        200,
+                                        output,
+                                        filter,
+                                        TableLayout::default(),
+                                    )?;
+                                } else if let Some(template) = template {
+                                    print!(
+                                        "{}",
+                                        render_output_template(
+                                            template,
+                                            &response_json,
+                                            variables
+                                        )?
+                                    );
                                 } else {
                                     println!("Operation completed successfully");
                                 }
@@ -797,8 +1258,14 @@ fn execute_job_with_polling_scenario(
                                     "Operation failed".to_string()
                                 };
 
-                                if output == OutputFormat::Json {
-                                    println!("{}", poll_response);
+                                if output.is_machine_readable() || !filter.is_empty() {
+                                    print_response_text(
+                                        &poll_response,
+                                        200,
+                                        output,
+                                        filter,
+                                        TableLayout::default(),
+                                    )?;
                                 } else {
                                     eprintln!("Error: {}", error_msg);
                                 }
@@ -814,7 +1281,7 @@ fn execute_job_with_polling_scenario(
         }
 
         // Show progress if available and not in JSON mode
-        if output != OutputFormat::Json {
+        if !output.is_machine_readable() {
             if let Some(progress_value) = response_json.get("progress") {
                 if let Some(progress) = progress_value.as_f64() {
                     print!("\rProgress: {:.1}%", progress);
@@ -833,17 +1300,38 @@ fn build_raw_spec_from_step(
     base_url: &Option<String>,
     step: &mapping::ScenarioStep,
     variables: &HashMap<String, String>,
+    strict: bool,
 ) -> Result<RawRequestSpec> {
-    let endpoint = substitute_template(&step.endpoint, variables);
-    let body = step
-        .body
-        .as_ref()
-        .map(|b| substitute_template(b, variables));
-    let headers: Vec<String> = step
-        .headers
-        .iter()
-        .map(|(k, v)| format!("{}: {}", k, substitute_template(v, variables)))
-        .collect();
+    let render = |field: String, template: &str, context: TemplateContext| {
+        render_field(&field, template, variables, context, strict)
+    };
+    let endpoint = render(
+        format!("step '{}' endpoint", step.name),
+        &step.endpoint,
+        TemplateContext::Url,
+    )?;
+    let body_context = if is_json_content_type(&step.headers) {
+        TemplateContext::Json
+    } else {
+        TemplateContext::Raw
+    };
+    let body = match &step.body {
+        Some(b) => Some(render(
+            format!("step '{}' body", step.name),
+            b,
+            body_context,
+        )?),
+        None => None,
+    };
+    let mut headers: Vec<String> = Vec::new();
+    for (k, v) in &step.headers {
+        let field = format!("step '{}' header '{}'", step.name, k);
+        headers.push(format!(
+            "{}: {}",
+            k,
+            render(field, v, TemplateContext::Raw)?
+        ));
+    }
 
     Ok(RawRequestSpec {
         base_url: base_url.clone(),
@@ -853,13 +1341,20 @@ fn build_raw_spec_from_step(
         body,
         multipart: false, // Scenarios don't currently support multipart
         file_fields: HashMap::new(),
+        form_fields: Vec::new(),
         table_view: None,
+        list_path: None,
+        table_views: IndexMap::new(),
+        detail_view: None,
+        output_template: None,
+        auth: Vec::new(), // Filled in per step by the scenario runner
     })
 }
 
 /// Execute a single HTTP request and return the response body as a string.
 fn execute_single_request(
     spec: &RawRequestSpec,
+    step: &str,
     conn_timeout_secs: Option<f64>,
     request_timeout_secs: Option<f64>,
     user_agent: &str,
@@ -873,23 +1368,11 @@ fn execute_single_request(
     }
     let client = builder.build().context("Failed to build HTTP client")?;
 
-    let url = build_url(&spec.base_url, &spec.endpoint)?;
-    let method = parse_method(&spec.method)?;
-    let mut req = client.request(method, url);
-
-    // Headers
-    let extra_headers = parse_headers(&spec.headers)?;
-    if !extra_headers.is_empty() {
-        req = req.headers(extra_headers);
-    }
-
-    if let Some(body) = &spec.body {
-        req = req.body(body.clone());
-    }
+    let build = |auth: &[auth::RequestAuth]| build_http_request(&client, spec, auth);
 
-    let resp = req.send().context("HTTP request failed")?;
-    let status = resp.status();
-    let body = resp.text().context("Failed to read response body")?;
+    let resp = auth::send_with_auth(&client, &spec.auth, false, Some(step), build)?;
+    let status = resp.status;
+    let body = resp.body;
 
     if !status.is_success() {
         bail!("HTTP request failed with status {}: {}", status, body);
@@ -995,34 +1478,67 @@ fn parse_headers(raw_headers: &[String]) -> Result<HeaderMap> {
     Ok(map)
 }
 
+/// How a response is laid out in human output: which array is the table, the columns
+/// of each array, and the record or template view of the whole response.
+#[derive(Debug, Clone, Copy, Default)]
+struct TableLayout<'a> {
+    table_view: Option<&'a Vec<String>>,
+    list_path: Option<&'a str>,
+    table_views: Option<&'a IndexMap<String, Vec<String>>>,
+    detail_view: Option<&'a [mapping::DetailSection]>,
+    output_template: Option<&'a str>,
+}
+
+impl<'a> TableLayout<'a> {
+    fn of(spec: &'a RawRequestSpec) -> Self {
+        Self {
+            table_view: spec.table_view.as_ref(),
+            list_path: spec.list_path.as_deref(),
+            table_views: Some(&spec.table_views),
+            detail_view: spec.detail_view.as_deref(),
+            output_template: spec.output_template.as_deref(),
+        }
+    }
+
+    /// The array named by `list_path`, if it is set and matches one.
+    fn list<'v>(&self, v: &'v serde_json::Value) -> Option<&'v Vec<serde_json::Value>> {
+        let path = output::rooted_jsonpath(self.list_path?);
+        jsonpath::select(v, &path)
+            .ok()?
+            .into_iter()
+            .find_map(|m| m.as_array())
+    }
+
+    /// The `table_views` entry for the array in field `key` (`reviews` or `$.reviews`).
+    fn view_of(&self, key: &str) -> Option<&'a Vec<String>> {
+        self.table_views?
+            .iter()
+            .find(|(path, _)| path.trim_start_matches('$').trim_start_matches('.') == key)
+            .map(|(_, view)| view)
+    }
+
+    /// What CSV, TSV and NDJSON rows come from: the list, or the whole value.
+    fn rows_value<'v>(&self, v: &'v serde_json::Value) -> Cow<'v, serde_json::Value> {
+        match self.list(v) {
+            Some(list) => Cow::Owned(serde_json::Value::Array(list.clone())),
+            None => Cow::Borrowed(v),
+        }
+    }
+}
+
 fn output_response(
-    resp: Response,
+    resp: journal::Fetched,
     output: OutputFormat,
-    table_view: Option<&Vec<String>>,
+    filter: &output::ResponseFilter,
+    layout: TableLayout,
 ) -> Result<i32> {
-    let status = resp.status();
-    let text = resp.text().unwrap_or_default();
-
-    match output {
-        OutputFormat::Json => {
-            let json_val: serde_json::Value = serde_json::from_str(&text).unwrap_or_else(|_| {
-                serde_json::json!({
-                    "status": status.as_u16(),
-                    "body": text,
-                })
-            });
-            println!("{}", serde_json::to_string_pretty(&json_val)?);
-        }
-        OutputFormat::Human => {
-            if let Ok(json_val) = serde_json::from_str::<serde_json::Value>(&text) {
-                print_human_readable(&json_val, table_view);
-            } else {
-                println!("{}", text);
-            }
-        }
-        OutputFormat::Quiet => {
-            // Do nothing
-        }
+    let status = resp.status;
+    if output == OutputFormat::Raw && filter.is_empty() {
+        let mut stdout = std::io::stdout();
+        std::io::Write::write_all(&mut stdout, &resp.bytes)?;
+        std::io::Write::flush(&mut stdout)?;
+    } else {
+        print_response_text(&resp.body, status.as_u16(), output, filter, layout)?;
     }
 
     if status.is_success() {
@@ -1032,159 +1548,436 @@ fn output_response(
     }
 }
 
-fn print_human_readable(v: &serde_json::Value, table_view: Option<&Vec<String>>) {
-    match v {
-        serde_json::Value::Object(map) => {
-            // First pass: print scalar fields aligned
-            let mut scalar_entries: Vec<(&String, &serde_json::Value)> = Vec::new();
-            let mut array_entries: Vec<(&String, &serde_json::Value)> = Vec::new();
-            for (k, val) in map.iter() {
-                match val {
-                    serde_json::Value::Array(_) => array_entries.push((k, val)),
-                    _ => scalar_entries.push((k, val)),
-                }
-            }
-            scalar_entries.sort_by_key(|(k, _)| *k);
-            let width = scalar_entries
-                .iter()
-                .map(|(k, _)| k.len())
-                .max()
-                .unwrap_or(0);
-            for (k, val) in scalar_entries {
-                let s = scalar_to_string(val);
-                println!("{key:width$}: {val}", key = k, width = width, val = s);
-            }
-            // Then print arrays as tables
-            for (k, val) in array_entries {
-                println!();
-                println!("{}:", k);
-                if let serde_json::Value::Array(arr) = val {
-                    print_array_table(arr, table_view);
-                }
-            }
-        }
-        serde_json::Value::Array(arr) => {
-            print_array_table(arr, table_view);
-        }
-        _ => {
-            println!("{}", scalar_to_string(v));
+/// Print a response body in the given format, after the client-side filters.
+fn print_response_text(
+    text: &str,
+    status: u16,
+    output: OutputFormat,
+    filter: &output::ResponseFilter,
+    layout: TableLayout,
+) -> Result<()> {
+    if output == OutputFormat::Quiet {
+        return Ok(());
+    }
+    let layout = TableLayout {
+        table_view: filter.table_view(layout.table_view),
+        // A query picks some other part of the response than the record the view describes
+        detail_view: layout.detail_view.filter(|_| filter.query.is_none()),
+        ..layout
+    };
+    let tabular = matches!(
+        output,
+        OutputFormat::Csv | OutputFormat::Tsv | OutputFormat::Ndjson
+    );
+    let parsed = serde_json::from_str::<serde_json::Value>(text);
+    if filter.is_empty() || (filter.query.is_none() && parsed.is_err()) {
+        match (output, parsed) {
+            (OutputFormat::Human, Ok(json_val)) => match layout.output_template {
+                // Error responses keep the default view: the template describes success
+                Some(template) if (200..300).contains(&status) => print!(
+                    "{}",
+                    render_output_template(template, &json_val, &HashMap::new())?
+                ),
+                _ => print!("{}", render_human(&json_val, &layout, &table::settings())),
+            },
+            (OutputFormat::Human, Err(_)) => println!("{}", text),
+            (_, Ok(json_val)) if tabular => print!(
+                "{}",
+                output::render_value(&layout.rows_value(&json_val), output, layout.table_view)?
+            ),
+            _ => print!(
+                "{}",
+                output::render_text(text, status, output, layout.table_view)?
+            ),
         }
+        return Ok(());
+    }
+    let value = parsed.context("--query needs a JSON response")?;
+    let value = filter.apply(value)?;
+    if filter.raw_value {
+        print!("{}", output::raw_value_text(&value));
+    } else if output == OutputFormat::Human {
+        print!("{}", render_human(&value, &layout, &table::settings()));
+    } else if tabular {
+        let rows = layout.rows_value(&value);
+        print!(
+            "{}",
+            output::render_value(&rows, output, layout.table_view)?
+        );
+    } else {
+        print!(
+            "{}",
+            output::render_value(&value, output, layout.table_view)?
+        );
     }
+    Ok(())
 }
 
-fn print_array_table(arr: &Vec<serde_json::Value>, table_view: Option<&Vec<String>>) {
-    if arr.is_empty() {
-        println!("(empty)");
-        return;
+/// Fill in an `output_template` from a response, ending the text with a newline.
+fn render_output_template(
+    template: &str,
+    value: &serde_json::Value,
+    vars: &HashMap<String, String>,
+) -> Result<String> {
+    let template =
+        output_template::OutputTemplate::parse(template).context("Invalid output_template")?;
+    let mut text = template.render(value, vars);
+    if !text.ends_with('\n') {
+        text.push('\n');
     }
+    Ok(text)
+}
 
-    // Parse column specifications (path and optional modifier)
-    let col_specs: Vec<ColumnSpec> = if let Some(cols) = table_view {
-        cols.iter().map(|c| parse_column_spec(c)).collect()
-    } else {
-        let mut derived: Vec<String> = Vec::new();
-        for item in arr {
-            if let serde_json::Value::Object(map) = item {
-                for (k, v) in map.iter() {
-                    match v {
-                        serde_json::Value::Object(inner) => {
-                            for inner_k in inner.keys() {
-                                let path = format!("{}.{}", k, inner_k);
-                                if !derived.contains(&path) {
-                                    derived.push(path);
-                                }
-                            }
-                        }
-                        _ => {
-                            if !derived.contains(k) {
-                                derived.push(k.clone());
-                            }
-                        }
-                    }
-                }
-            }
+/// Print a JSON value the way commands print responses in the default table output,
+/// e.g. from a custom handler.
+pub fn print_human_readable(v: &serde_json::Value, table_view: Option<&Vec<String>>) {
+    match v {
+        serde_json::Value::Array(arr) => print_array_table(arr, table_view),
+        _ => {
+            let layout = TableLayout {
+                table_view,
+                ..TableLayout::default()
+            };
+            print!("{}", render_human(v, &layout, &table::settings()));
         }
-        derived.iter().map(|c| parse_column_spec(c)).collect()
+    }
+}
+
+/// The human-readable view of a response. An object's scalar fields come first, one
+/// per line, then each array as a table. With a `list_path` that matches, that array
+/// is the table and the envelope's total/skip/limit are summarized in a footer. An
+/// object with a `detail_view` is shown as its sections instead.
+fn render_human(
+    v: &serde_json::Value,
+    layout: &TableLayout,
+    settings: &table::TableSettings,
+) -> String {
+    let map = match v {
+        serde_json::Value::Object(map) => match layout.detail_view {
+            Some(sections) => return render_detail(v, sections, layout, settings),
+            None => map,
+        },
+        serde_json::Value::Array(arr) => return table::render(arr, layout.table_view, settings),
+        _ => return format!("{}\n", scalar_to_string(v)),
+    };
+    let list = layout.list(v);
+    // The object holding the list carries the envelope fields
+    let envelope = list.and_then(|list| parent_object(v, list));
+    let footer = match (list, envelope) {
+        (Some(list), Some(envelope)) => table::envelope_footer(envelope, list.len()),
+        _ => None,
     };
-    if col_specs.is_empty() {
-        for (i, item) in arr.iter().enumerate() {
-            println!("{:<6} {}", i, scalar_to_string(item));
+    let hide_envelope = footer.is_some() && envelope.is_some_and(|e| std::ptr::eq(e, map));
+
+    let mut scalar_entries: Vec<(&String, &serde_json::Value)> = Vec::new();
+    let mut array_entries: Vec<(&String, &Vec<serde_json::Value>)> = Vec::new();
+    for (k, val) in map.iter() {
+        if list.is_some_and(|list| contains_value(val, list))
+            || (hide_envelope && table::is_envelope_key(k))
+        {
+            continue;
+        }
+        match val {
+            serde_json::Value::Array(arr) => array_entries.push((k, arr)),
+            _ => scalar_entries.push((k, val)),
         }
-        return;
     }
 
-    // Build header labels (humanized, multi-line by whitespace)
-    let header_labels: Vec<String> = col_specs
-        .iter()
-        .map(|c| humanize_column_label_with_modifier(&c.path, &c.modifier))
-        .collect();
-    let header_lines: Vec<Vec<String>> = header_labels
-        .iter()
-        .map(|lbl| lbl.split_whitespace().map(|s| s.to_string()).collect())
-        .collect();
-    let header_max_lines = header_lines.iter().map(|v| v.len()).max().unwrap_or(1);
-
-    // Compute column widths from header parts and cell data
-    let mut widths: Vec<usize> = header_lines
+    let mut out = String::new();
+    // Scalar fields, keys aligned
+    scalar_entries.sort_by_key(|(k, _)| *k);
+    let width = scalar_entries
         .iter()
-        .map(|parts| parts.iter().map(|s| s.len()).max().unwrap_or(0))
-        .collect();
-    for item in arr {
-        if let serde_json::Value::Object(_) = item {
-            for (idx, col_spec) in col_specs.iter().enumerate() {
-                let cell_val = get_value_by_path(item, &col_spec.path);
-                let cell = scalar_to_string_with_modifier(cell_val, &col_spec.modifier);
-                if cell.len() > widths[idx] {
-                    widths[idx] = cell.len();
-                }
-            }
+        .map(|(k, _)| table::display_width(k))
+        .max()
+        .unwrap_or(0);
+    for (k, val) in &scalar_entries {
+        let s = scalar_to_string(val);
+        let pad = " ".repeat(width - table::display_width(k));
+        out.push_str(&format!(
+            "{}{}: {}\n",
+            k,
+            pad,
+            table::paint_value(val, &s, settings.color)
+        ));
+    }
+    if let Some(list) = list {
+        if !scalar_entries.is_empty() {
+            out.push('\n');
+        }
+        out.push_str(&table::render(list, layout.table_view, settings));
+        if let Some(footer) = footer {
+            out.push_str(&format!("{}\n", footer));
         }
     }
-
-    // Separator line like +-----+-----+
-    let mut sep_line = String::from("+");
-    for w in &widths {
-        sep_line.push_str(&"-".repeat(w + 2));
-        sep_line.push('+');
+    // Then arrays as tables
+    for (k, arr) in array_entries {
+        // With a list, `table_view` belongs to it; other arrays only get their own view
+        let view = match list {
+            Some(_) => layout.view_of(k),
+            None => layout.view_of(k).or(layout.table_view),
+        };
+        out.push_str(&format!("\n{}:\n", k));
+        out.push_str(&table::render(arr, view, settings));
     }
-    println!("{}", sep_line);
+    out
+}
 
-    // Print header (multi-line)
-    for line_idx in 0..header_max_lines {
-        let mut parts: Vec<String> = Vec::new();
-        for (i, col_parts) in header_lines.iter().enumerate() {
-            let s = if line_idx < col_parts.len() {
-                &col_parts[line_idx]
-            } else {
-                ""
-            };
-            parts.push(format!(" {:<width$} ", s, width = widths[i]));
+/// A single object laid out by `detail_view` sections. Each section's title is followed
+/// by its fields, indented, with labels aligned. A nested object is shown as an indented
+/// block of its keys and an array of objects as a table (with its `table_views` columns).
+fn render_detail(
+    v: &serde_json::Value,
+    sections: &[mapping::DetailSection],
+    layout: &TableLayout,
+    settings: &table::TableSettings,
+) -> String {
+    let mut out = String::new();
+    for (i, section) in sections.iter().enumerate() {
+        if i > 0 {
+            out.push('\n');
         }
-        println!("|{}|", parts.join("|"));
+        let indent = match &section.title {
+            Some(title) => {
+                out.push_str(&format!(
+                    "{}\n",
+                    table::paint_heading(title, settings.color)
+                ));
+                2
+            }
+            None => 0,
+        };
+        let fields: Vec<DetailField> = section
+            .fields
+            .iter()
+            .map(|spec| {
+                let column = parse_column_spec(spec);
+                DetailField {
+                    label: column.detail_label(),
+                    path: column.path.clone(),
+                    value: column.value(v),
+                    column: Some(column),
+                }
+            })
+            .collect();
+        render_detail_fields(&fields, indent, layout, settings, &mut out);
     }
+    out
+}
 
-    println!("{}", sep_line);
+/// A line (or block) of a detail view.
+struct DetailField<'v> {
+    label: String,
+    /// Path from the response root, for looking up `table_views`
+    path: String,
+    value: Cow<'v, serde_json::Value>,
+    /// The `detail_view` entry; `None` for the keys of a nested object
+    column: Option<ColumnSpec>,
+}
 
-    // Rows
-    for item in arr {
-        if let serde_json::Value::Object(_) = item {
-            let mut row_parts: Vec<String> = Vec::new();
-            for (i, col_spec) in col_specs.iter().enumerate() {
-                let cell_val = get_value_by_path(item, &col_spec.path);
-                let cell = scalar_to_string_with_modifier(cell_val, &col_spec.modifier);
-                row_parts.push(format!(" {:<width$} ", cell, width = widths[i]));
+fn render_detail_fields(
+    fields: &[DetailField],
+    indent: usize,
+    layout: &TableLayout,
+    settings: &table::TableSettings,
+    out: &mut String,
+) {
+    let margin = " ".repeat(indent);
+    let width = fields
+        .iter()
+        .map(|f| table::display_width(&f.label))
+        .max()
+        .unwrap_or(0);
+    for field in fields {
+        // A formatter or size modifier asks for the value on one line, whatever its shape
+        let formatted = field
+            .column
+            .as_ref()
+            .filter(|c| c.formatter.is_some() || c.modifier.is_some());
+        match (&*field.value, formatted) {
+            (serde_json::Value::Object(map), None) if !map.is_empty() => {
+                out.push_str(&format!("{}{}:\n", margin, field.label));
+                let nested: Vec<DetailField> = map
+                    .iter()
+                    .map(|(k, val)| DetailField {
+                        label: k.clone(),
+                        path: format!("{}.{}", field.path, k),
+                        value: Cow::Borrowed(val),
+                        column: None,
+                    })
+                    .collect();
+                render_detail_fields(&nested, indent + 2, layout, settings, out);
+            }
+            (serde_json::Value::Array(items), None) if items.iter().any(|i| i.is_object()) => {
+                out.push_str(&format!("{}{}:\n", margin, field.label));
+                let table_settings = table::TableSettings {
+                    max_width: settings.max_width.map(|w| w.saturating_sub(indent + 2)),
+                    ..settings.clone()
+                };
+                let view = layout.view_of(&field.path);
+                for line in table::render(items, view, &table_settings).lines() {
+                    out.push_str(&format!("{}  {}\n", margin, line));
+                }
+            }
+            (value, formatted) => {
+                let text = match (formatted, value) {
+                    (Some(column), _) => column.format(value),
+                    (None, serde_json::Value::Array(items)) if !items.is_empty() => items
+                        .iter()
+                        .map(scalar_to_string)
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                    _ => scalar_to_string(value),
+                };
+                let pad = " ".repeat(width - table::display_width(&field.label));
+                out.push_str(&format!(
+                    "{}{}{}: {}\n",
+                    margin,
+                    field.label,
+                    pad,
+                    table::paint_value(value, &text, settings.color)
+                ));
             }
-            println!("|{}|", row_parts.join("|"));
         }
     }
+}
 
-    println!("{}", sep_line);
+/// Whether `target` is `v` or nested somewhere inside it.
+fn contains_value(v: &serde_json::Value, target: &Vec<serde_json::Value>) -> bool {
+    match v {
+        serde_json::Value::Array(items) => {
+            std::ptr::eq(items, target) || items.iter().any(|item| contains_value(item, target))
+        }
+        serde_json::Value::Object(map) => map.values().any(|val| contains_value(val, target)),
+        _ => false,
+    }
+}
+
+/// The object that has `target` as one of its fields.
+fn parent_object<'v>(
+    v: &'v serde_json::Value,
+    target: &Vec<serde_json::Value>,
+) -> Option<&'v serde_json::Map<String, serde_json::Value>> {
+    match v {
+        serde_json::Value::Object(map) => {
+            if map
+                .values()
+                .any(|val| val.as_array().is_some_and(|arr| std::ptr::eq(arr, target)))
+            {
+                Some(map)
+            } else {
+                map.values().find_map(|val| parent_object(val, target))
+            }
+        }
+        serde_json::Value::Array(items) => {
+            items.iter().find_map(|item| parent_object(item, target))
+        }
+        _ => None,
+    }
+}
+
+fn print_array_table(arr: &[serde_json::Value], table_view: Option<&Vec<String>>) {
+    print!("{}", table::render(arr, table_view, &table::settings()));
+}
+
+/// Table columns for a list: the `table_view` specs, or else the columns derived from
+/// the items down to `depth` levels of nesting.
+fn table_columns(
+    arr: &[serde_json::Value],
+    table_view: Option<&Vec<String>>,
+    depth: usize,
+) -> Vec<ColumnSpec> {
+    match table_view {
+        Some(cols) => cols.iter().map(|c| parse_column_spec(c)).collect(),
+        None => derived_columns(arr, depth)
+            .iter()
+            .map(|c| parse_column_spec(c))
+            .collect(),
+    }
+}
+
+/// Every key of the items, descending into nested objects `depth` levels deep: with 2,
+/// `dimensions` becomes `dimensions.width` and `dimensions.height`, while anything
+/// nested deeper is shown as JSON.
+fn derived_columns(arr: &[serde_json::Value], depth: usize) -> Vec<String> {
+    fn derive(
+        map: &serde_json::Map<String, serde_json::Value>,
+        prefix: &str,
+        depth: usize,
+        out: &mut Vec<String>,
+    ) {
+        for (k, v) in map.iter() {
+            let path = if prefix.is_empty() {
+                k.clone()
+            } else {
+                format!("{}.{}", prefix, k)
+            };
+            match v {
+                serde_json::Value::Object(inner) if depth > 1 => {
+                    derive(inner, &path, depth - 1, out)
+                }
+                _ => {
+                    if !out.contains(&path) {
+                        out.push(path);
+                    }
+                }
+            }
+        }
+    }
+    let mut derived: Vec<String> = Vec::new();
+    for item in arr {
+        if let serde_json::Value::Object(map) = item {
+            derive(map, "", depth.max(1), &mut derived);
+        }
+    }
+    derived
 }
 
 #[derive(Debug, Clone)]
 struct ColumnSpec {
     path: String,
     modifier: Option<SizeModifier>,
+    /// Named formatter and its argument, e.g. `round` and `2` from `price:round:2`
+    formatter: Option<(String, Option<String>)>,
+    /// Header from `path as Label`, used instead of the humanized path
+    label: Option<String>,
+}
+
+impl ColumnSpec {
+    fn header(&self) -> String {
+        match &self.label {
+            Some(label) => label.clone(),
+            None => humanize_column_label_with_modifier(&self.path, &self.modifier),
+        }
+    }
+
+    /// The value this column shows for `item`.
+    fn value<'a>(&self, item: &'a serde_json::Value) -> Cow<'a, serde_json::Value> {
+        lookup_path(item, &self.path)
+    }
+
+    /// Label for the field in a detail view: the header on one line, `Size (GB)`.
+    fn detail_label(&self) -> String {
+        match (&self.label, &self.modifier) {
+            (Some(label), _) => label.clone(),
+            (None, Some(_)) => format!("{})", self.header().replacen('\n', " (", 1)),
+            (None, None) => humanize_column_label(&self.path),
+        }
+    }
+
+    /// Cell text for `v`. Formatters leave nulls alone so missing values read the same
+    /// in every column; `[*]` columns are joined unless another formatter is given.
+    fn format(&self, v: &serde_json::Value) -> String {
+        match &self.formatter {
+            Some((name, arg)) if !v.is_null() => {
+                formatters::apply(name, arg.as_deref(), v).unwrap_or_else(|| scalar_to_string(v))
+            }
+            None if v.is_array() && self.path.contains("[*]") => {
+                formatters::apply("join", None, v).unwrap_or_default()
+            }
+            _ => scalar_to_string_with_modifier(v, &self.modifier),
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -1194,45 +1987,166 @@ enum SizeModifier {
     Kilobytes,
 }
 
+/// Parse `path`, `path:gb`, `path:formatter`, `path:formatter:arg`, each optionally
+/// followed by ` as Header Label`.
 fn parse_column_spec(spec: &str) -> ColumnSpec {
-    if let Some(colon_pos) = spec.find(':') {
-        let path = spec[..colon_pos].to_string();
-        let modifier_str = spec[colon_pos + 1..].to_lowercase();
-        let modifier = match modifier_str.as_str() {
-            "gb" => Some(SizeModifier::Gigabytes),
-            "mb" => Some(SizeModifier::Megabytes),
-            "kb" => Some(SizeModifier::Kilobytes),
-            _ => None,
+    let (spec, label) = match spec.split_once(" as ") {
+        Some((spec, label)) => (spec.trim(), Some(label.trim().to_string())),
+        None => (spec, None),
+    };
+    let mut parts = spec.splitn(3, ':');
+    let path = parts.next().unwrap_or_default().to_string();
+    let name = parts.next().map(|n| n.to_lowercase());
+    let arg = parts.next().map(|a| a.to_string());
+    let modifier = match (name.as_deref(), &arg) {
+        (Some("gb"), None) => Some(SizeModifier::Gigabytes),
+        (Some("mb"), None) => Some(SizeModifier::Megabytes),
+        (Some("kb"), None) => Some(SizeModifier::Kilobytes),
+        _ => None,
+    };
+    let formatter = match modifier {
+        Some(_) => None,
+        None => name.map(|name| (name, arg)),
+    };
+    ColumnSpec {
+        path,
+        modifier,
+        formatter,
+        label,
+    }
+}
+
+/// One step of a column path such as `reviews[0].rating`, `tags[*]` or `images.length()`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum PathSegment {
+    /// Object key; a number also indexes arrays (`reviews.0.rating`)
+    Key(String),
+    /// `[n]`; negative counts from the end
+    Index(i64),
+    /// `[*]`: every item
+    All,
+    /// `length()`: items of an array, keys of an object or characters of a string
+    Length,
+}
+
+fn parse_path(path: &str) -> Vec<PathSegment> {
+    let mut segments = Vec::new();
+    for part in path.split('.') {
+        if part == "length()" {
+            segments.push(PathSegment::Length);
+            continue;
+        }
+        let Some(open) = part.find('[').filter(|_| part.ends_with(']')) else {
+            segments.push(PathSegment::Key(part.to_string()));
+            continue;
         };
-        ColumnSpec { path, modifier }
-    } else {
-        ColumnSpec {
-            path: spec.to_string(),
-            modifier: None,
+        if open > 0 {
+            segments.push(PathSegment::Key(part[..open].to_string()));
+        }
+        for index in part[open + 1..part.len() - 1].split("][") {
+            segments.push(match index.trim() {
+                "*" => PathSegment::All,
+                i => match i.parse() {
+                    Ok(n) => PathSegment::Index(n),
+                    Err(_) => PathSegment::Key(i.trim_matches(['"', '\'']).to_string()),
+                },
+            });
         }
     }
+    segments
 }
 
+/// The child of `v` named by a key or index segment.
+fn path_child<'a>(v: &'a serde_json::Value, seg: &PathSegment) -> Option<&'a serde_json::Value> {
+    match (v, seg) {
+        (serde_json::Value::Object(map), PathSegment::Key(k)) => map.get(k),
+        (serde_json::Value::Array(items), PathSegment::Key(k)) => {
+            items.get(k.parse::<usize>().ok()?)
+        }
+        (serde_json::Value::Array(items), PathSegment::Index(i)) => {
+            let idx = match usize::try_from(*i) {
+                Ok(idx) => idx,
+                Err(_) => items
+                    .len()
+                    .checked_sub(usize::try_from(i.unsigned_abs()).ok()?)?,
+            };
+            items.get(idx)
+        }
+        _ => None,
+    }
+}
+
+/// The single value at `path`, or null. `[*]` and `length()` need [`lookup_path`].
 fn get_value_by_path<'a>(v: &'a serde_json::Value, path: &str) -> &'a serde_json::Value {
     let mut current = v;
-    for seg in path.split('.') {
-        match current {
-            serde_json::Value::Object(map) => {
-                if let Some(next) = map.get(seg) {
-                    current = next;
-                } else {
-                    return &serde_json::Value::Null;
-                }
-            }
-            _ => return &serde_json::Value::Null,
+    for seg in parse_path(path) {
+        match path_child(current, &seg) {
+            Some(next) => current = next,
+            None => return &serde_json::Value::Null,
         }
     }
     current
 }
 
+/// The value at `path`, where `[*]` collects the rest of the path from every item into
+/// an array and `length()` counts.
+fn lookup_path<'a>(v: &'a serde_json::Value, path: &str) -> Cow<'a, serde_json::Value> {
+    fn walk<'a>(v: &'a serde_json::Value, segs: &[PathSegment]) -> Cow<'a, serde_json::Value> {
+        let Some((seg, rest)) = segs.split_first() else {
+            return Cow::Borrowed(v);
+        };
+        match seg {
+            PathSegment::All => match v {
+                serde_json::Value::Array(items) => Cow::Owned(serde_json::Value::Array(
+                    items
+                        .iter()
+                        .map(|item| walk(item, rest).into_owned())
+                        .collect(),
+                )),
+                _ => Cow::Owned(serde_json::Value::Null),
+            },
+            PathSegment::Length => {
+                let len = match v {
+                    serde_json::Value::Array(items) => items.len(),
+                    serde_json::Value::Object(map) => map.len(),
+                    serde_json::Value::String(s) => s.chars().count(),
+                    _ => return Cow::Owned(serde_json::Value::Null),
+                };
+                Cow::Owned(serde_json::Value::from(len))
+            }
+            _ => match path_child(v, seg) {
+                Some(next) => walk(next, rest),
+                None => Cow::Owned(serde_json::Value::Null),
+            },
+        }
+    }
+    let segments = parse_path(path);
+    if segments
+        .iter()
+        .any(|seg| matches!(seg, PathSegment::All | PathSegment::Length))
+    {
+        walk(v, &segments)
+    } else {
+        Cow::Borrowed(get_value_by_path(v, path))
+    }
+}
+
+/// Header for a column path: its last key, humanized (`reviews[0].rating` is "Rating"),
+/// with "Count" added for `length()`.
 fn humanize_column_label(path: &str) -> String {
-    let last = path.split('.').next_back().unwrap_or(path);
-    let spaced = last.replace(['_', '-'], " ");
+    let segments = parse_path(path);
+    let last = segments
+        .iter()
+        .rev()
+        .find_map(|seg| match seg {
+            PathSegment::Key(k) if k.parse::<usize>().is_err() => Some(k.as_str()),
+            _ => None,
+        })
+        .unwrap_or(path);
+    let mut spaced = last.replace(['_', '-'], " ");
+    if segments.last() == Some(&PathSegment::Length) {
+        spaced.push_str(" count");
+    }
     let mut out_words: Vec<String> = Vec::new();
     for w in spaced.split_whitespace() {
         if w.is_empty() {
@@ -1313,94 +2227,1041 @@ fn scalar_to_string_with_modifier(
         }
         None => scalar_to_string(v),
     }
-}
+}
+
+static PLACEHOLDER_RE: once_cell::sync::Lazy<Regex> = once_cell::sync::Lazy::new(|| {
+    Regex::new(r"\{([a-zA-Z_][a-zA-Z0-9_]*)((?:\|[a-zA-Z0-9_]+(?::[^|{}]*)?)*)\}")
+        .expect("valid regex")
+});
+
+/// Filters understood in `{name|filter}` placeholders.
+pub const TEMPLATE_FILTERS: &[&str] = &[
+    "json",
+    "urlencode",
+    "path",
+    "raw",
+    "base64",
+    "lower",
+    "upper",
+    "default",
+];
+
+/// Filters that escape the value themselves and so turn off context escaping.
+const ESCAPING_FILTERS: &[&str] = &["json", "urlencode", "path", "raw"];
+
+/// Where a template is rendered; decides how placeholder values are escaped by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TemplateContext {
+    /// Values are inserted as-is (headers, non-JSON bodies)
+    Raw,
+    /// Values are percent-encoded as a URL component (endpoints)
+    Url,
+    /// Values inside JSON string literals are JSON-escaped; elsewhere they are
+    /// inserted as-is, so `{"n": {count}, "tags": {tags}}` still takes JSON fragments
+    Json,
+}
+
+/// A `{name|filter:arg|...}` placeholder found in a template.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Placeholder {
+    pub name: String,
+    /// Filters in application order, with their optional `:argument`
+    pub filters: Vec<(String, Option<String>)>,
+}
+
+impl Placeholder {
+    fn from_captures(caps: &regex::Captures) -> Self {
+        let filters = caps[2]
+            .split('|')
+            .skip(1)
+            .map(|f| match f.split_once(':') {
+                Some((name, arg)) => (name.to_string(), Some(arg.to_string())),
+                None => (f.to_string(), None),
+            })
+            .collect();
+        Self {
+            name: caps[1].to_string(),
+            filters,
+        }
+    }
+
+    /// Fallback from a `default:` filter, used when the variable is unset or empty.
+    pub fn default_value(&self) -> Option<&str> {
+        self.filters
+            .iter()
+            .find(|(f, _)| f == "default")
+            .map(|(_, arg)| arg.as_deref().unwrap_or(""))
+    }
+
+    /// Whether a filter on this placeholder already escapes the value.
+    fn escapes_itself(&self) -> bool {
+        self.filters
+            .iter()
+            .any(|(f, _)| ESCAPING_FILTERS.contains(&f.as_str()))
+    }
+
+    /// Apply the filters to the variable's value; `None` if it is unset and has no default.
+    fn filtered_value(&self, vars: &HashMap<String, String>) -> Option<String> {
+        let mut value = vars
+            .get(&self.name)
+            .map(|v| v.replace(MULTI_VALUE_SEPARATOR, ","));
+        for (filter, arg) in &self.filters {
+            if filter == "default" {
+                if value.as_deref().is_none_or(str::is_empty) {
+                    value = Some(arg.clone().unwrap_or_default());
+                }
+                continue;
+            }
+            let Some(v) = value.as_deref() else {
+                continue;
+            };
+            value = Some(match filter.as_str() {
+                "json" => serde_json::Value::String(v.to_string()).to_string(),
+                "urlencode" => percent_encode_component(v),
+                "path" => v
+                    .split('/')
+                    .map(percent_encode_component)
+                    .collect::<Vec<_>>()
+                    .join("/"),
+                "base64" => {
+                    use base64::Engine;
+                    base64::engine::general_purpose::STANDARD.encode(v)
+                }
+                "lower" => v.to_lowercase(),
+                "upper" => v.to_uppercase(),
+                _ => v.to_string(),
+            });
+        }
+        value
+    }
+}
+
+/// Parse every placeholder of a template, in order of appearance (duplicates included).
+pub fn parse_placeholders(template: &str) -> Vec<Placeholder> {
+    PLACEHOLDER_RE
+        .captures_iter(template)
+        .map(|caps| Placeholder::from_captures(&caps))
+        .collect()
+}
+
+/// Substitute `{name}` placeholders (with optional filters) without any escaping;
+/// unset variables become empty strings.
+pub fn substitute_template(template: &str, vars: &HashMap<String, String>) -> String {
+    render_template(template, vars, TemplateContext::Raw)
+}
+
+/// Substitute placeholders, escaping values for `context` unless a `json`, `urlencode`,
+/// `path` or `raw` filter already took care of it. Unset variables become empty strings.
+pub fn render_template(
+    template: &str,
+    vars: &HashMap<String, String>,
+    context: TemplateContext,
+) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut last = 0;
+    let mut in_json_string = false;
+    for caps in PLACEHOLDER_RE.captures_iter(template) {
+        let m = caps.get(0).expect("whole match");
+        let literal = &template[last..m.start()];
+        if context == TemplateContext::Json {
+            in_json_string = json_string_state_after(literal, in_json_string);
+        }
+        out.push_str(literal);
+        let placeholder = Placeholder::from_captures(&caps);
+        let value = placeholder.filtered_value(vars).unwrap_or_default();
+        if placeholder.escapes_itself() {
+            out.push_str(&value);
+        } else {
+            match context {
+                TemplateContext::Url => out.push_str(&percent_encode_component(&value)),
+                TemplateContext::Json if in_json_string => {
+                    let quoted = serde_json::Value::String(value).to_string();
+                    out.push_str(&quoted[1..quoted.len() - 1]);
+                }
+                _ => out.push_str(&value),
+            }
+        }
+        last = m.end();
+    }
+    out.push_str(&template[last..]);
+    out
+}
+
+/// Like [`render_template`], but fails with every unresolved placeholder listed instead
+/// of substituting empty strings.
+pub fn render_template_strict(
+    template: &str,
+    vars: &HashMap<String, String>,
+    context: TemplateContext,
+) -> Result<String> {
+    let missing = unresolved_placeholders(template, vars);
+    if !missing.is_empty() {
+        let names: Vec<String> = missing.iter().map(|n| format!("{{{}}}", n)).collect();
+        bail!("unresolved placeholders: {}", names.join(", "));
+    }
+    Ok(render_template(template, vars, context))
+}
+
+/// Like [`substitute_template`], but fails with every unresolved placeholder listed.
+pub fn substitute_template_strict(
+    template: &str,
+    vars: &HashMap<String, String>,
+) -> Result<String> {
+    render_template_strict(template, vars, TemplateContext::Raw)
+}
+
+/// Whether we are inside a JSON string literal after scanning `text`.
+fn json_string_state_after(text: &str, mut in_string: bool) -> bool {
+    let mut escaped = false;
+    for c in text.chars() {
+        if in_string && escaped {
+            escaped = false;
+        } else if in_string && c == '\\' {
+            escaped = true;
+        } else if c == '"' {
+            in_string = !in_string;
+        }
+    }
+    in_string
+}
+
+/// Whether a `Content-Type` among `headers` (case-insensitive name) is JSON.
+pub fn is_json_content_type<'a, I>(headers: I) -> bool
+where
+    I: IntoIterator<Item = (&'a String, &'a String)>,
+{
+    headers.into_iter().any(|(k, v)| {
+        k.eq_ignore_ascii_case("content-type") && {
+            let ct = v.split(';').next().unwrap_or("").trim();
+            ct == "application/json" || ct.ends_with("+json")
+        }
+    })
+}
+
+/// Names of placeholders that are unset in `vars` and have no `default:` filter, deduped.
+pub fn unresolved_placeholders(template: &str, vars: &HashMap<String, String>) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for p in parse_placeholders(template) {
+        if !vars.contains_key(&p.name) && p.default_value().is_none() && !names.contains(&p.name) {
+            names.push(p.name);
+        }
+    }
+    names
+}
+
+/// Characters left as-is in a URL component: RFC 3986 "unreserved".
+const URL_COMPONENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~');
+
+/// Percent-encode a value for use as a URL path segment or query key/value.
+pub fn percent_encode_component(value: &str) -> String {
+    utf8_percent_encode(value, URL_COMPONENT).to_string()
+}
+
+/// Joins the values of a `multiple: true` arg in the variable map. Command-line arguments
+/// cannot contain it, so every value stays whole; templates see the values joined with `,`.
+pub const MULTI_VALUE_SEPARATOR: char = '\0';
+
+/// Copy of `vars` with multi-valued args joined with `,`, as handed to custom handlers.
+fn joined_multi_values(vars: &HashMap<String, String>) -> HashMap<String, String> {
+    vars.iter()
+        .map(|(k, v)| (k.clone(), v.replace(MULTI_VALUE_SEPARATOR, ",")))
+        .collect()
+}
+
+/// Render `query:` entries into a percent-encoded query string (without the leading `?`).
+///
+/// An entry is left out when its template references a variable missing from `vars`
+/// (and has no `default:` filter), or renders empty. A value that is exactly `{var}`
+/// for a var in `multi_vars` becomes one repeated key per value.
+pub fn build_query_string(
+    query: &IndexMap<String, String>,
+    vars: &HashMap<String, String>,
+    multi_vars: &HashSet<String>,
+) -> String {
+    let mut pairs: Vec<String> = Vec::new();
+    for (key, template) in query {
+        if !unresolved_placeholders(template, vars).is_empty() {
+            continue;
+        }
+        let placeholders = template_placeholders(template);
+        let whole_var = placeholders
+            .first()
+            .filter(|p| *template == format!("{{{}}}", p) && multi_vars.contains(*p));
+        let values: Vec<String> = match whole_var {
+            Some(var) => vars[var]
+                .split(MULTI_VALUE_SEPARATOR)
+                .map(|v| v.to_string())
+                .collect(),
+            None => vec![substitute_template(template, vars)],
+        };
+        for value in values {
+            if value.is_empty() && !placeholders.is_empty() {
+                continue;
+            }
+            pairs.push(format!(
+                "{}={}",
+                percent_encode_component(key),
+                percent_encode_component(&value)
+            ));
+        }
+    }
+    pairs.join("&")
+}
+
+/// Append a query string to an endpoint that may already carry one.
+pub fn append_query(endpoint: &str, query: &str) -> String {
+    if query.is_empty() {
+        return endpoint.to_string();
+    }
+    let sep = match endpoint.find('?') {
+        None => "?",
+        Some(_) if endpoint.ends_with('?') || endpoint.ends_with('&') => "",
+        Some(_) => "&",
+    };
+    format!("{}{}{}", endpoint, sep, query)
+}
+
+/// List the variable names referenced by `{name}` placeholders in a template, in order of appearance.
+pub fn template_placeholders(template: &str) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+    for caps in PLACEHOLDER_RE.captures_iter(template) {
+        let name = caps[1].to_string();
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    names
+}
+
+// Re-export useful types for consumers
+pub use openapiv3;
+pub use reqwest;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // ==================== substitute_template tests ====================
+
+    #[test]
+    fn test_substitute_template_single_var() {
+        let mut vars = HashMap::new();
+        vars.insert("name".to_string(), "John".to_string());
+        let result = substitute_template("Hello {name}!", &vars);
+        assert_eq!(result, "Hello John!");
+    }
+
+    #[test]
+    fn test_substitute_template_multiple_vars() {
+        let mut vars = HashMap::new();
+        vars.insert("first".to_string(), "John".to_string());
+        vars.insert("last".to_string(), "Doe".to_string());
+        let result = substitute_template("{first} {last}", &vars);
+        assert_eq!(result, "John Doe");
+    }
+
+    #[test]
+    fn test_substitute_template_missing_var() {
+        let vars = HashMap::new();
+        let result = substitute_template("Hello {name}!", &vars);
+        assert_eq!(result, "Hello !"); // Missing vars become empty
+    }
+
+    #[test]
+    fn test_substitute_template_no_placeholders() {
+        let vars = HashMap::new();
+        let result = substitute_template("Hello World!", &vars);
+        assert_eq!(result, "Hello World!");
+    }
+
+    #[test]
+    fn test_substitute_template_empty_template() {
+        let vars = HashMap::new();
+        let result = substitute_template("", &vars);
+        assert_eq!(result, "");
+    }
+
+    #[test]
+    fn test_substitute_template_repeated_var() {
+        let mut vars = HashMap::new();
+        vars.insert("x".to_string(), "A".to_string());
+        let result = substitute_template("{x}{x}{x}", &vars);
+        assert_eq!(result, "AAA");
+    }
+
+    #[test]
+    fn test_substitute_template_url_path() {
+        let mut vars = HashMap::new();
+        vars.insert("org".to_string(), "acme".to_string());
+        vars.insert("id".to_string(), "123".to_string());
+        let result = substitute_template("/orgs/{org}/users/{id}", &vars);
+        assert_eq!(result, "/orgs/acme/users/123");
+    }
+
+    #[test]
+    fn test_substitute_template_json_body() {
+        let mut vars = HashMap::new();
+        vars.insert("name".to_string(), "Test".to_string());
+        vars.insert("value".to_string(), "42".to_string());
+        let template = r#"{"name": "{name}", "value": {value}}"#;
+        let result = substitute_template(template, &vars);
+        assert_eq!(result, r#"{"name": "Test", "value": 42}"#);
+    }
+
+    #[test]
+    fn test_template_placeholders() {
+        let names = template_placeholders(r#"/users/{id}?q={q}&again={id} {"json": 1}"#);
+        assert_eq!(names, vec!["id".to_string(), "q".to_string()]);
+    }
+
+    // ==================== query string tests ====================
+
+    #[test]
+    fn test_build_query_string_skips_unset_and_encodes() {
+        let mut query = IndexMap::new();
+        query.insert("q".to_string(), "{q}".to_string());
+        query.insert("select".to_string(), "{select}".to_string());
+        query.insert("empty".to_string(), "{empty}".to_string());
+        query.insert("fixed".to_string(), "10".to_string());
+        query.insert("sort by".to_string(), "{field}:asc".to_string());
+        let mut vars = HashMap::new();
+        vars.insert("q".to_string(), "a&b c/ü".to_string());
+        vars.insert("empty".to_string(), String::new());
+        vars.insert("field".to_string(), "price".to_string());
+
+        let qs = build_query_string(&query, &vars, &HashSet::new());
+        assert_eq!(qs, "q=a%26b%20c%2F%C3%BC&fixed=10&sort%20by=price%3Aasc");
+    }
+
+    #[test]
+    fn test_build_query_string_repeated_keys() {
+        let mut query = IndexMap::new();
+        query.insert("tag".to_string(), "{tags}".to_string());
+        query.insert("note".to_string(), "[{tags}]".to_string());
+        let mut vars = HashMap::new();
+        vars.insert("tags".to_string(), "red\0blue,green".to_string());
+        let multi: HashSet<String> = ["tags".to_string()].into_iter().collect();
+
+        // Only a value that is exactly the multi-valued var is split, and commas stay
+        let qs = build_query_string(&query, &vars, &multi);
+        assert_eq!(qs, "tag=red&tag=blue%2Cgreen&note=%5Bred%2Cblue%2Cgreen%5D");
+    }
+
+    #[test]
+    fn test_append_query() {
+        assert_eq!(append_query("/items", ""), "/items");
+        assert_eq!(append_query("/items", "a=1"), "/items?a=1");
+        assert_eq!(append_query("/items?x=2", "a=1"), "/items?x=2&a=1");
+        assert_eq!(append_query("/items?", "a=1"), "/items?a=1");
+    }
+
+    #[test]
+    fn test_build_request_with_query_map_and_arg_override() {
+        let mut query = IndexMap::new();
+        query.insert("limit".to_string(), "{limit}".to_string());
+        query.insert("select".to_string(), "{select}".to_string());
+        let mut override_query = IndexMap::new();
+        override_query.insert("order".to_string(), "desc".to_string());
+        let cmd = mapping::CommandSpec {
+            name: Some("list".to_string()),
+            method: Some("GET".to_string()),
+            endpoint: Some("/products?delay={delay}".to_string()),
+            query,
+            args: vec![
+                mapping::ArgSpec {
+                    name: Some("newest".to_string()),
+                    arg_type: Some("bool".to_string()),
+                    query: Some(override_query),
+                    ..Default::default()
+                },
+                mapping::ArgSpec {
+                    name: Some("tags".to_string()),
+                    multiple: true,
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        let mut vars = HashMap::new();
+        vars.insert("limit".to_string(), "30".to_string());
+        vars.insert("delay".to_string(), "0".to_string());
+        vars.insert("newest".to_string(), "true".to_string());
+        let selected: HashSet<String> = ["newest".to_string()].into_iter().collect();
+
+        let RequestSpec::Simple(raw) = build_request_from_command(None, &cmd, &vars, &selected)
+        else {
+            panic!("Expected RequestSpec::Simple");
+        };
+        assert_eq!(raw.endpoint, "/products?delay=0&limit=30&order=desc");
+    }
+
+    // ==================== template filter tests ====================
+
+    fn vars_of(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_placeholders_with_filters() {
+        let parsed = parse_placeholders("/{id|path}?n={limit|default:30|lower}");
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed[0].name, "id");
+        assert_eq!(parsed[0].filters, vec![("path".to_string(), None)]);
+        assert_eq!(parsed[1].default_value(), Some("30"));
+        assert_eq!(
+            template_placeholders("{a|json} {b} {a}"),
+            vec!["a".to_string(), "b".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_template_filters() {
+        let vars = vars_of(&[("s", "Hello \"W\"/x y"), ("empty", "")]);
+        assert_eq!(
+            substitute_template("{s|json}", &vars),
+            r#""Hello \"W\"/x y""#
+        );
+        assert_eq!(
+            substitute_template("{s|urlencode}", &vars),
+            "Hello%20%22W%22%2Fx%20y"
+        );
+        assert_eq!(
+            substitute_template("{s|path}", &vars),
+            "Hello%20%22W%22/x%20y"
+        );
+        assert_eq!(substitute_template("{s|lower}", &vars), "hello \"w\"/x y");
+        assert_eq!(
+            substitute_template("{s|base64}", &vars),
+            "SGVsbG8gIlciL3ggeQ=="
+        );
+        assert_eq!(substitute_template("{empty|default:30}", &vars), "30");
+        assert_eq!(substitute_template("{missing|default:a b}", &vars), "a b");
+        assert_eq!(substitute_template("{missing|default:}", &vars), "");
+        // Filters chain left to right
+        assert_eq!(
+            substitute_template("{missing|default:A B|lower|urlencode}", &vars),
+            "a%20b"
+        );
+    }
+
+    #[test]
+    fn test_render_template_url_context() {
+        let vars = vars_of(&[("id", "a/b c"), ("path", "docs/read me")]);
+        assert_eq!(
+            render_template("/items/{id}", &vars, TemplateContext::Url),
+            "/items/a%2Fb%20c"
+        );
+        assert_eq!(
+            render_template("/files/{path|path}", &vars, TemplateContext::Url),
+            "/files/docs/read%20me"
+        );
+        assert_eq!(
+            render_template("/files/{path|raw}", &vars, TemplateContext::Url),
+            "/files/docs/read me"
+        );
+    }
+
+    #[test]
+    fn test_render_template_json_context() {
+        let vars = vars_of(&[("title", "say \"hi\"\n"), ("n", "3"), ("tags", "[\"a\"]")]);
+        let template = r#"{"title": "{title}", "n": {n}, "tags": {tags}, "raw": {title|json}}"#;
+        let rendered = render_template(template, &vars, TemplateContext::Json);
+        assert_eq!(
+            rendered,
+            r#"{"title": "say \"hi\"\n", "n": 3, "tags": ["a"], "raw": "say \"hi\"\n"}"#
+        );
+        let parsed: serde_json::Value = serde_json::from_str(&rendered).unwrap();
+        assert_eq!(parsed["title"], "say \"hi\"\n");
+        // Escaped quotes in the template do not confuse string tracking
+        let rendered =
+            render_template(r#"{"a": "x\"{n}", "b": {n}}"#, &vars, TemplateContext::Json);
+        assert_eq!(rendered, r#"{"a": "x\"3", "b": 3}"#);
+    }
 
-pub fn substitute_template(template: &str, vars: &HashMap<String, String>) -> String {
-    // Replace {name} occurrences. Use a regex to find placeholders.
-    static PLACEHOLDER_RE: once_cell::sync::Lazy<Regex> = once_cell::sync::Lazy::new(|| {
-        Regex::new(r"\{([a-zA-Z_][a-zA-Z0-9_]*)\}").expect("valid regex")
-    });
-    PLACEHOLDER_RE
-        .replace_all(template, |caps: &regex::Captures| {
-            let key = &caps[1];
-            vars.get(key).cloned().unwrap_or_default()
-        })
-        .to_string()
-}
+    #[test]
+    fn test_unresolved_placeholders() {
+        let vars = vars_of(&[("a", "1")]);
+        assert_eq!(
+            unresolved_placeholders("{a}{b}{c|default:1}{b}", &vars),
+            vec!["b".to_string()]
+        );
+    }
 
-// Re-export useful types for consumers
-pub use openapiv3;
-pub use reqwest;
+    #[test]
+    fn test_build_query_string_uses_default_filter() {
+        let mut query = IndexMap::new();
+        query.insert("limit".to_string(), "{limit|default:30}".to_string());
+        query.insert("skip".to_string(), "{skip}".to_string());
+        let qs = build_query_string(&query, &HashMap::new(), &HashSet::new());
+        assert_eq!(qs, "limit=30");
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn test_build_request_escapes_by_context() {
+        let mut headers = HashMap::new();
+        headers.insert("Content-Type".to_string(), "application/json".to_string());
+        let cmd = mapping::CommandSpec {
+            name: Some("update".to_string()),
+            method: Some("PUT".to_string()),
+            endpoint: Some("/products/{id}".to_string()),
+            body: Some(r#"{"title": "{title}"}"#.to_string()),
+            headers,
+            args: vec![mapping::ArgSpec {
+                name: Some("by_slug".to_string()),
+                arg_type: Some("bool".to_string()),
+                endpoint: Some("/products/slug/{id|path}".to_string()),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let vars = vars_of(&[
+            ("id", "a b/c"),
+            ("title", "5\" screen"),
+            ("by_slug", "false"),
+        ]);
 
-    // ==================== substitute_template tests ====================
+        let RequestSpec::Simple(raw) =
+            build_request_from_command(None, &cmd, &vars, &HashSet::new())
+        else {
+            panic!("Expected RequestSpec::Simple");
+        };
+        assert_eq!(raw.endpoint, "/products/a%20b%2Fc");
+        assert_eq!(raw.body.as_deref(), Some(r#"{"title": "5\" screen"}"#));
+
+        // The override endpoint is rendered with the same escaping rules
+        let selected: HashSet<String> = ["by_slug".to_string()].into_iter().collect();
+        let RequestSpec::Simple(raw) = build_request_from_command(None, &cmd, &vars, &selected)
+        else {
+            panic!("Expected RequestSpec::Simple");
+        };
+        assert_eq!(raw.endpoint, "/products/slug/a%20b/c");
+    }
 
     #[test]
-    fn test_substitute_template_single_var() {
-        let mut vars = HashMap::new();
-        vars.insert("name".to_string(), "John".to_string());
-        let result = substitute_template("Hello {name}!", &vars);
-        assert_eq!(result, "Hello John!");
+    fn test_build_request_plain_body_is_not_json_escaped() {
+        let cmd = mapping::CommandSpec {
+            name: Some("note".to_string()),
+            method: Some("POST".to_string()),
+            endpoint: Some("/notes".to_string()),
+            body: Some("text={text}".to_string()),
+            ..Default::default()
+        };
+        let vars = vars_of(&[("text", "a \"b\"")]);
+        let RequestSpec::Simple(raw) =
+            build_request_from_command(None, &cmd, &vars, &HashSet::new())
+        else {
+            panic!("Expected RequestSpec::Simple");
+        };
+        assert_eq!(raw.body.as_deref(), Some("text=a \"b\""));
     }
 
     #[test]
-    fn test_substitute_template_multiple_vars() {
-        let mut vars = HashMap::new();
-        vars.insert("first".to_string(), "John".to_string());
-        vars.insert("last".to_string(), "Doe".to_string());
-        let result = substitute_template("{first} {last}", &vars);
-        assert_eq!(result, "John Doe");
+    fn test_build_raw_spec_from_step_escapes_by_context() {
+        let mut headers = HashMap::new();
+        headers.insert(
+            "content-type".to_string(),
+            "application/json; charset=utf-8".to_string(),
+        );
+        let step = mapping::ScenarioStep {
+            name: "create".to_string(),
+            method: "POST".to_string(),
+            endpoint: "/carts/{user}".to_string(),
+            headers,
+            body: Some(r#"{"note": "{note}"}"#.to_string()),
+            extract_response: HashMap::new(),
+            polling: None,
+        };
+        let vars = vars_of(&[("user", "x/y"), ("note", "line\nbreak")]);
+        let raw = build_raw_spec_from_step(&None, &step, &vars, false).unwrap();
+        assert_eq!(raw.endpoint, "/carts/x%2Fy");
+        assert_eq!(raw.body.as_deref(), Some(r#"{"note": "line\nbreak"}"#));
     }
 
+    // ==================== strict template tests ====================
+
     #[test]
-    fn test_substitute_template_missing_var() {
-        let vars = HashMap::new();
-        let result = substitute_template("Hello {name}!", &vars);
-        assert_eq!(result, "Hello !"); // Missing vars become empty
+    fn test_render_template_strict_lists_all_unresolved() {
+        let vars = vars_of(&[("a", "1")]);
+        assert_eq!(
+            substitute_template_strict("{a}-{b|default:2}", &vars).unwrap(),
+            "1-2"
+        );
+        let err = substitute_template_strict("/{org}/{a}/{id}/{org}", &vars).unwrap_err();
+        assert_eq!(err.to_string(), "unresolved placeholders: {org}, {id}");
+        // An empty value counts as set
+        let vars = vars_of(&[("id", "")]);
+        assert_eq!(
+            render_template_strict("/users/{id}", &vars, TemplateContext::Url).unwrap(),
+            "/users/"
+        );
     }
 
     #[test]
-    fn test_substitute_template_no_placeholders() {
-        let vars = HashMap::new();
-        let result = substitute_template("Hello World!", &vars);
-        assert_eq!(result, "Hello World!");
+    fn test_try_build_request_strict_names_field() {
+        let mut headers = HashMap::new();
+        headers.insert("X-Org".to_string(), "{org}".to_string());
+        let mut query = IndexMap::new();
+        query.insert("q".to_string(), "{q}".to_string());
+        let cmd = mapping::CommandSpec {
+            name: Some("delete".to_string()),
+            method: Some("DELETE".to_string()),
+            endpoint: Some("/users/{id}".to_string()),
+            headers,
+            query,
+            args: vec![mapping::ArgSpec {
+                name: Some("soft".to_string()),
+                arg_type: Some("bool".to_string()),
+                endpoint: Some("/users/{id}/{mode}".to_string()),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let vars = vars_of(&[("org", "acme"), ("soft", "true")]);
+        let err =
+            try_build_request_from_command(None, &cmd, &vars, &HashSet::new(), true).unwrap_err();
+        assert_eq!(
+            format!("{:#}", err),
+            "endpoint: unresolved placeholders: {id}"
+        );
+
+        let selected: HashSet<String> = ["soft".to_string()].into_iter().collect();
+        let err = try_build_request_from_command(None, &cmd, &vars, &selected, true).unwrap_err();
+        assert_eq!(
+            format!("{:#}", err),
+            "endpoint override of 'soft': unresolved placeholders: {id}, {mode}"
+        );
+
+        let vars = vars_of(&[("id", "7")]);
+        let err =
+            try_build_request_from_command(None, &cmd, &vars, &HashSet::new(), true).unwrap_err();
+        assert_eq!(
+            format!("{:#}", err),
+            "header 'X-Org': unresolved placeholders: {org}"
+        );
+
+        // Lenient mode keeps substituting empty strings; query entries are never an error
+        let RequestSpec::Simple(raw) =
+            try_build_request_from_command(None, &cmd, &vars, &HashSet::new(), false).unwrap()
+        else {
+            panic!("Expected RequestSpec::Simple");
+        };
+        assert_eq!(raw.headers, vec!["X-Org: ".to_string()]);
+        let vars = vars_of(&[("id", "7"), ("org", "acme")]);
+        let RequestSpec::Simple(raw) =
+            try_build_request_from_command(None, &cmd, &vars, &HashSet::new(), true).unwrap()
+        else {
+            panic!("Expected RequestSpec::Simple");
+        };
+        assert_eq!(raw.endpoint, "/users/7");
     }
 
     #[test]
-    fn test_substitute_template_empty_template() {
-        let vars = HashMap::new();
-        let result = substitute_template("", &vars);
-        assert_eq!(result, "");
+    fn test_try_build_request_strict_scenario() {
+        let step = |name: &str, endpoint: &str, extract: &[&str]| mapping::ScenarioStep {
+            name: name.to_string(),
+            method: "GET".to_string(),
+            endpoint: endpoint.to_string(),
+            headers: HashMap::new(),
+            body: None,
+            extract_response: extract
+                .iter()
+                .map(|k| (k.to_string(), format!("$.{}", k)))
+                .collect(),
+            polling: None,
+        };
+        let cmd = |steps| mapping::CommandSpec {
+            name: Some("run".to_string()),
+            scenario: Some(mapping::Scenario {
+                scenario_type: "job_with_polling".to_string(),
+                steps,
+                output_template: None,
+            }),
+            ..Default::default()
+        };
+        let vars = vars_of(&[("model", "m1")]);
+
+        // Values extracted by an earlier step count as set
+        let ok = cmd(vec![
+            step("schedule_job", "/jobs/{model}", &["job_id"]),
+            step("poll_job", "/jobs/{job_id}", &[]),
+        ]);
+        let spec = try_build_request_from_command(None, &ok, &vars, &HashSet::new(), true);
+        assert!(matches!(
+            spec,
+            Ok(RequestSpec::Scenario(ScenarioSpec {
+                strict_templates: true,
+                ..
+            }))
+        ));
+
+        let bad = cmd(vec![
+            step("schedule_job", "/jobs/{model}", &[]),
+            step("poll_job", "/jobs/{job}", &[]),
+        ]);
+        let err =
+            try_build_request_from_command(None, &bad, &vars, &HashSet::new(), true).unwrap_err();
+        assert_eq!(
+            format!("{:#}", err),
+            "step 'poll_job' endpoint: unresolved placeholders: {job}"
+        );
+
+        // Steps rendered at run time are checked too
+        let err = build_raw_spec_from_step(&None, &bad.scenario.unwrap().steps[1], &vars, true)
+            .unwrap_err();
+        assert_eq!(
+            format!("{:#}", err),
+            "step 'poll_job' endpoint: unresolved placeholders: {job}"
+        );
     }
 
+    // ==================== body_fields tests ====================
+
     #[test]
-    fn test_substitute_template_repeated_var() {
-        let mut vars = HashMap::new();
-        vars.insert("x".to_string(), "A".to_string());
-        let result = substitute_template("{x}{x}{x}", &vars);
-        assert_eq!(result, "AAA");
+    fn test_coerce_body_value() {
+        use serde_json::json;
+        assert_eq!(coerce_body_value("7", "string").unwrap(), json!("7"));
+        assert_eq!(coerce_body_value(" 42 ", "int").unwrap(), json!(42));
+        assert_eq!(coerce_body_value("1.5", "float").unwrap(), json!(1.5));
+        assert_eq!(coerce_body_value("Yes", "bool").unwrap(), json!(true));
+        assert_eq!(coerce_body_value("0", "bool").unwrap(), json!(false));
+        assert_eq!(
+            coerce_body_value(r#"{"a": [1, null]}"#, "json").unwrap(),
+            json!({"a": [1, null]})
+        );
+        assert_eq!(
+            coerce_body_value("red, blue,", "array").unwrap(),
+            json!(["red", "blue"])
+        );
+        assert_eq!(coerce_body_value("", "array").unwrap(), json!([]));
+        assert_eq!(
+            coerce_body_value("4.5", "int").unwrap_err(),
+            "expected an integer, got '4.5'"
+        );
+        assert!(coerce_body_value("nan", "float").is_err());
+        assert!(coerce_body_value("maybe", "bool").is_err());
+        assert!(coerce_body_value("{", "json").is_err());
     }
 
     #[test]
-    fn test_substitute_template_url_path() {
-        let mut vars = HashMap::new();
-        vars.insert("org".to_string(), "acme".to_string());
-        vars.insert("id".to_string(), "123".to_string());
-        let result = substitute_template("/orgs/{org}/users/{id}", &vars);
-        assert_eq!(result, "/orgs/acme/users/123");
+    fn test_body_field_path() {
+        assert_eq!(body_field_path("title"), vec!["title"]);
+        assert_eq!(body_field_path("dims.width"), vec!["dims", "width"]);
+        assert_eq!(body_field_path("/meta/a~1b~0c"), vec!["meta", "a/b~c"]);
+        // Dots are literal in JSON pointers
+        assert_eq!(body_field_path("/v1.2"), vec!["v1.2"]);
     }
 
     #[test]
-    fn test_substitute_template_json_body() {
-        let mut vars = HashMap::new();
-        vars.insert("name".to_string(), "Test".to_string());
-        vars.insert("value".to_string(), "42".to_string());
-        let template = r#"{"name": "{name}", "value": {value}}"#;
-        let result = substitute_template(template, &vars);
-        assert_eq!(result, r#"{"name": "Test", "value": 42}"#);
+    fn test_json_body_from_pairs() {
+        use serde_json::json;
+        let pairs: Vec<String> = [
+            "name=Ada",
+            "age:=36",
+            "tags:=[\"a\"]",
+            "dims.w:=1.5",
+            "note=a=b",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+        assert_eq!(
+            json_body_from_pairs(&pairs).unwrap(),
+            json!({"name": "Ada", "age": 36, "tags": ["a"], "dims": {"w": 1.5}, "note": "a=b"})
+        );
+        assert!(json_body_from_pairs(&["name".to_string()]).is_err());
+        assert!(json_body_from_pairs(&["age:=x".to_string()]).is_err());
+        assert!(json_body_from_pairs(&["=1".to_string()]).is_err());
+    }
+
+    fn body_fields_cmd() -> mapping::CommandSpec {
+        let field = |arg: &str, t: Option<&str>| mapping::BodyField::Full {
+            arg: arg.to_string(),
+            field_type: t.map(|t| t.to_string()),
+        };
+        let mut body_fields = IndexMap::new();
+        body_fields.insert(
+            "title".to_string(),
+            mapping::BodyField::Short("title".to_string()),
+        );
+        body_fields.insert("price".to_string(), field("price", None));
+        body_fields.insert("dims.width".to_string(), field("width", Some("float")));
+        body_fields.insert("/meta/tags".to_string(), field("tags", None));
+        body_fields.insert("inStock".to_string(), field("in_stock", None));
+        body_fields.insert("extra".to_string(), field("extra", Some("json")));
+        let mut override_fields = IndexMap::new();
+        override_fields.insert(
+            "status".to_string(),
+            mapping::BodyField::Short("draft:string".to_string()),
+        );
+        mapping::CommandSpec {
+            name: Some("add".to_string()),
+            method: Some("POST".to_string()),
+            endpoint: Some("/products/add".to_string()),
+            body: Some("ignored".to_string()),
+            body_fields,
+            args: vec![
+                mapping::ArgSpec {
+                    name: Some("price".to_string()),
+                    arg_type: Some("int".to_string()),
+                    ..Default::default()
+                },
+                mapping::ArgSpec {
+                    name: Some("tags".to_string()),
+                    multiple: true,
+                    ..Default::default()
+                },
+                mapping::ArgSpec {
+                    name: Some("in_stock".to_string()),
+                    arg_type: Some("bool".to_string()),
+                    ..Default::default()
+                },
+                mapping::ArgSpec {
+                    name: Some("draft".to_string()),
+                    arg_type: Some("bool".to_string()),
+                    body_fields: Some(override_fields),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        }
+    }
+
+    fn simple_body(spec: Result<RequestSpec>) -> serde_json::Value {
+        let Ok(RequestSpec::Simple(raw)) = spec else {
+            panic!("Expected RequestSpec::Simple");
+        };
+        assert!(raw
+            .headers
+            .contains(&"Content-Type: application/json".to_string()));
+        serde_json::from_str(raw.body.as_deref().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_build_request_body_fields() {
+        let cmd = body_fields_cmd();
+        let vars = vars_of(&[
+            ("title", "Desk \"XL\""),
+            ("price", "120"),
+            ("width", "1.5"),
+            ("tags", "wood\0office"),
+            ("in_stock", "false"),
+            ("draft", "false"),
+        ]);
+        let selected: HashSet<String> = ["title", "price", "width", "tags"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let body = simple_body(try_build_request_from_command(
+            None, &cmd, &vars, &selected, false,
+        ));
+        // Unset flags and missing args are left out; types follow the field or the arg
+        assert_eq!(
+            body,
+            serde_json::json!({
+                "title": "Desk \"XL\"",
+                "price": 120,
+                "dims": {"width": 1.5},
+                "meta": {"tags": ["wood", "office"]}
+            })
+        );
+
+        // A selected arg adds its own fields
+        let mut vars = vars;
+        vars.insert("in_stock".to_string(), "true".to_string());
+        vars.insert("draft".to_string(), "true".to_string());
+        let mut selected = selected;
+        selected.insert("in_stock".to_string());
+        selected.insert("draft".to_string());
+        let body = simple_body(try_build_request_from_command(
+            None, &cmd, &vars, &selected, false,
+        ));
+        assert_eq!(body["inStock"], serde_json::json!(true));
+        assert_eq!(body["status"], serde_json::json!("true"));
+    }
+
+    #[test]
+    fn test_build_request_body_fields_type_errors() {
+        let cmd = body_fields_cmd();
+        let vars = vars_of(&[("price", "cheap"), ("extra", "{")]);
+        let err =
+            try_build_request_from_command(None, &cmd, &vars, &HashSet::new(), false).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "body field 'price' (price): expected an integer, got 'cheap'"
+        );
+
+        // The infallible builder sends such values as strings instead
+        let RequestSpec::Simple(raw) =
+            build_request_from_command(None, &cmd, &vars, &HashSet::new())
+        else {
+            panic!("Expected RequestSpec::Simple");
+        };
+        let body: serde_json::Value = serde_json::from_str(raw.body.as_deref().unwrap()).unwrap();
+        assert_eq!(body, serde_json::json!({"price": "cheap", "extra": "{"}));
+    }
+
+    #[test]
+    fn test_build_request_body_fields_nesting_conflicts() {
+        let mut body_fields = IndexMap::new();
+        body_fields.insert(
+            "meta".to_string(),
+            mapping::BodyField::Short("meta:json".to_string()),
+        );
+        body_fields.insert(
+            "meta.source".to_string(),
+            mapping::BodyField::Short("source".to_string()),
+        );
+        body_fields.insert("a".to_string(), mapping::BodyField::Short("a".to_string()));
+        body_fields.insert(
+            "a.b".to_string(),
+            mapping::BodyField::Short("b".to_string()),
+        );
+        let cmd = mapping::CommandSpec {
+            name: Some("add".to_string()),
+            method: Some("POST".to_string()),
+            endpoint: Some("/products/add".to_string()),
+            body_fields,
+            ..Default::default()
+        };
+        let vars = vars_of(&[("meta", "[1]"), ("source", "cli"), ("a", "x"), ("b", "y")]);
+        let err =
+            try_build_request_from_command(None, &cmd, &vars, &HashSet::new(), false).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "body field 'meta.source' nests inside a value that is not an object"
+        );
+
+        // The infallible builder lets the later field win
+        let RequestSpec::Simple(raw) =
+            build_request_from_command(None, &cmd, &vars, &HashSet::new())
+        else {
+            panic!("Expected RequestSpec::Simple");
+        };
+        let body: serde_json::Value = serde_json::from_str(raw.body.as_deref().unwrap()).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!({"meta": {"source": "cli"}, "a": {"b": "y"}})
+        );
+    }
+
+    #[test]
+    fn test_build_request_body_override_beats_body_fields() {
+        let mut cmd = body_fields_cmd();
+        cmd.args[0].body = Some(r#"{"price": {price}}"#.to_string());
+        let vars = vars_of(&[("price", "5"), ("title", "x")]);
+        let selected: HashSet<String> = ["price".to_string()].into_iter().collect();
+        let RequestSpec::Simple(raw) = build_request_from_command(None, &cmd, &vars, &selected)
+        else {
+            panic!("Expected RequestSpec::Simple");
+        };
+        assert_eq!(raw.body.as_deref(), Some(r#"{"price": 5}"#));
     }
 
     // ==================== ExecutionConfig tests ====================
@@ -1446,6 +3307,7 @@ mod tests {
             custom_handler: None,
             args: vec![],
             use_common_args: vec![],
+            ..Default::default()
         };
         let vars = HashMap::new();
         let selected = HashSet::new();
@@ -1481,6 +3343,7 @@ mod tests {
             custom_handler: None,
             args: vec![],
             use_common_args: vec![],
+            ..Default::default()
         };
         let mut vars = HashMap::new();
         vars.insert("id".to_string(), "123".to_string());
@@ -1515,6 +3378,7 @@ mod tests {
             custom_handler: None,
             args: vec![],
             use_common_args: vec![],
+            ..Default::default()
         };
         let mut vars = HashMap::new();
         vars.insert("name".to_string(), "John".to_string());
@@ -1551,6 +3415,7 @@ mod tests {
             custom_handler: None,
             args: vec![],
             use_common_args: vec![],
+            ..Default::default()
         };
         let mut vars = HashMap::new();
         vars.insert("token".to_string(), "secret123".to_string());
@@ -1580,6 +3445,7 @@ mod tests {
             custom_handler: Some("export_users".to_string()),
             args: vec![],
             use_common_args: vec![],
+            ..Default::default()
         };
         let mut vars = HashMap::new();
         vars.insert("format".to_string(), "csv".to_string());
@@ -1612,6 +3478,7 @@ mod tests {
                 extract_response: HashMap::new(),
                 polling: None,
             }],
+            output_template: None,
         };
 
         let cmd = mapping::CommandSpec {
@@ -1628,6 +3495,7 @@ mod tests {
             custom_handler: None,
             args: vec![],
             use_common_args: vec![],
+            ..Default::default()
         };
         let vars = HashMap::new();
         let selected = HashSet::new();
@@ -1700,7 +3568,13 @@ paths: {}
             body: None,
             multipart: false,
             file_fields: HashMap::new(),
+            form_fields: Vec::new(),
             table_view: None,
+            list_path: None,
+            table_views: IndexMap::new(),
+            detail_view: None,
+            output_template: None,
+            auth: Vec::new(),
         };
         assert!(spec.base_url.is_none());
         assert!(spec.headers.is_empty());
@@ -1925,6 +3799,37 @@ paths: {}
         assert!(spec.modifier.is_none());
     }
 
+    #[test]
+    fn test_parse_column_spec_formatter_and_label() {
+        let spec = parse_column_spec("stock:int as In Stock");
+        assert_eq!(spec.path, "stock");
+        assert_eq!(spec.formatter, Some(("int".to_string(), None)));
+        assert_eq!(spec.header(), "In Stock");
+        assert_eq!(spec.format(&serde_json::json!(7.6)), "8");
+
+        let spec = parse_column_spec("created:datetime:%H:%M");
+        assert_eq!(
+            spec.formatter,
+            Some(("datetime".to_string(), Some("%H:%M".to_string())))
+        );
+        assert_eq!(spec.header(), "Created");
+
+        let spec = parse_column_spec("size:MB as Memory");
+        assert!(matches!(spec.modifier, Some(SizeModifier::Megabytes)));
+        assert_eq!(spec.header(), "Memory");
+        assert_eq!(spec.format(&serde_json::json!(1048576)), "1.00");
+
+        // Formatters leave missing values alone; unknown ones print values as they are
+        assert_eq!(
+            parse_column_spec("x:round:2").format(&serde_json::Value::Null),
+            "null"
+        );
+        assert_eq!(
+            parse_column_spec("x:nope").format(&serde_json::json!(1.5)),
+            "1.5"
+        );
+    }
+
     // ==================== get_value_by_path tests ====================
 
     #[test]
@@ -2152,6 +4057,7 @@ paths: {}
                 ..Default::default()
             }],
             use_common_args: vec![],
+            ..Default::default()
         };
         let vars = HashMap::new();
         let mut selected = HashSet::new();
@@ -2191,6 +4097,7 @@ paths: {}
                 ..Default::default()
             }],
             use_common_args: vec![],
+            ..Default::default()
         };
         let mut vars = HashMap::new();
         vars.insert("file".to_string(), "/path/to/file.txt".to_string());
@@ -2260,8 +4167,12 @@ paths: {}
         vars.insert("id".to_string(), "123".to_string());
         vars.insert("name".to_string(), "Test".to_string());
 
-        let result =
-            build_raw_spec_from_step(&Some("https://api.example.com".to_string()), &step, &vars);
+        let result = build_raw_spec_from_step(
+            &Some("https://api.example.com".to_string()),
+            &step,
+            &vars,
+            false,
+        );
         assert!(result.is_ok());
         let spec = result.unwrap();
         assert_eq!(spec.method, "POST");
@@ -2285,7 +4196,7 @@ paths: {}
         let mut vars = HashMap::new();
         vars.insert("token".to_string(), "secret".to_string());
 
-        let result = build_raw_spec_from_step(&None, &step, &vars);
+        let result = build_raw_spec_from_step(&None, &step, &vars, false);
         assert!(result.is_ok());
         let spec = result.unwrap();
         assert!(spec.headers.iter().any(|h| h.contains("Bearer secret")));
@@ -2348,6 +4259,186 @@ paths: {}
         print_human_readable(&json, Some(&table_view));
     }
 
+    // ==================== render_human tests ====================
+
+    fn strings(items: &[&str]) -> Vec<String> {
+        items.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_render_human_list_path_with_footer() {
+        let json = serde_json::json!({
+            "products": [
+                {"id": 1, "title": "Phone", "price": 9},
+                {"id": 2, "title": "Case", "price": 3}
+            ],
+            "total": 194,
+            "skip": 0,
+            "limit": 2,
+            "source": "catalog"
+        });
+        let view = strings(&["id", "title"]);
+        let layout = TableLayout {
+            table_view: Some(&view),
+            list_path: Some("products"),
+            table_views: None,
+            ..TableLayout::default()
+        };
+        let out = render_human(&json, &layout, &table::TableSettings::default());
+        assert_eq!(
+            out,
+            "source: catalog\n\
+             \n\
+             +----+-------+\n\
+             | Id | Title |\n\
+             +----+-------+\n\
+             | 1  | Phone |\n\
+             | 2  | Case  |\n\
+             +----+-------+\n\
+             Showing 1–2 of 194\n"
+        );
+    }
+
+    #[test]
+    fn test_render_human_table_views_for_other_arrays() {
+        let json = serde_json::json!({
+            "data": {
+                "items": [{"id": 1, "name": "a"}],
+                "offset": 10,
+                "total": 11
+            },
+            "tags": [{"tag": "x", "count": 2}],
+            "links": [{"rel": "next", "href": "/p/2"}]
+        });
+        let view = strings(&["name"]);
+        let views: IndexMap<String, Vec<String>> = [("$.tags".to_string(), strings(&["tag"]))]
+            .into_iter()
+            .collect();
+        let layout = TableLayout {
+            table_view: Some(&view),
+            list_path: Some("$.data.items"),
+            table_views: Some(&views),
+            ..TableLayout::default()
+        };
+        let out = render_human(&json, &layout, &table::TableSettings::default());
+        // The list gets table_view and a footer from its own envelope
+        assert!(out.starts_with("+------+\n| Name |\n"), "{}", out);
+        assert!(out.contains("Showing 11–11 of 11\n"), "{}", out);
+        assert!(!out.contains("data:"), "{}", out);
+        // Other arrays get their own view, or derived columns, never the list's
+        assert!(out.contains("tags:\n+-----+\n| Tag |"), "{}", out);
+        assert!(
+            out.contains("links:\n+------+------+\n| Rel  | Href |"),
+            "{}",
+            out
+        );
+
+        // Without a list_path, table_views still apply and table_view covers the rest
+        let layout = TableLayout {
+            list_path: None,
+            ..layout
+        };
+        let out = render_human(&json, &layout, &table::TableSettings::default());
+        assert!(out.contains("tags:\n+-----+\n| Tag |"), "{}", out);
+        assert!(out.contains("links:\n+------+\n| Name |"), "{}", out);
+        assert!(out.contains("data: {"), "{}", out);
+    }
+
+    #[test]
+    fn test_render_detail_sections() {
+        let json = serde_json::json!({
+            "id": 7,
+            "title": "Phone",
+            "price": 19.987,
+            "size": 3221225472_u64,
+            "tags": ["a", "b"],
+            "dimensions": {"h": 2, "w": 10},
+            "reviews": [{"rating": 5, "comment": "ok", "reviewerName": "Ann"}]
+        });
+        let sections = vec![
+            mapping::DetailSection {
+                title: Some("Product".to_string()),
+                fields: strings(&[
+                    "id",
+                    "title",
+                    "price:round:2 as Price",
+                    "size:gb",
+                    "missing",
+                ]),
+            },
+            mapping::DetailSection {
+                title: None,
+                fields: strings(&["tags", "dimensions", "reviews"]),
+            },
+        ];
+        let views: IndexMap<String, Vec<String>> =
+            [("reviews".to_string(), strings(&["rating", "comment"]))]
+                .into_iter()
+                .collect();
+        let layout = TableLayout {
+            table_views: Some(&views),
+            detail_view: Some(&sections),
+            ..TableLayout::default()
+        };
+        let out = render_human(&json, &layout, &table::TableSettings::default());
+        assert_eq!(
+            out,
+            "Product\n\
+             \x20 Id       : 7\n\
+             \x20 Title    : Phone\n\
+             \x20 Price    : 19.99\n\
+             \x20 Size (GB): 3.00\n\
+             \x20 Missing  : null\n\
+             \n\
+             Tags      : a, b\n\
+             Dimensions:\n\
+             \x20 h: 2\n\
+             \x20 w: 10\n\
+             Reviews:\n\
+             \x20 +--------+---------+\n\
+             \x20 | Rating | Comment |\n\
+             \x20 +--------+---------+\n\
+             \x20 | 5      | ok      |\n\
+             \x20 +--------+---------+\n"
+        );
+
+        // Arrays are tables, not a detail view
+        let list = serde_json::json!([{"id": 1}]);
+        assert!(render_human(&list, &layout, &table::TableSettings::default()).contains("| Id |"));
+    }
+
+    #[test]
+    fn test_render_output_template() {
+        let json = serde_json::json!({"id": 42, "title": "iPhone"});
+        let vars = HashMap::from([("job_id".to_string(), "7".to_string())]);
+        assert_eq!(
+            render_output_template("Created {id} ({title}) by job {@job_id}", &json, &vars)
+                .unwrap(),
+            "Created 42 (iPhone) by job 7\n"
+        );
+        let err = render_output_template("{#if id}", &json, &vars).unwrap_err();
+        assert!(format!("{:#}", err).contains("Invalid output_template: {#if id} is not closed"));
+    }
+
+    #[test]
+    fn test_list_path_rows_for_csv() {
+        let json = serde_json::json!({"meta": [{"x": 1}], "products": [{"id": 7}], "total": 1});
+        let layout = TableLayout {
+            list_path: Some("$.products"),
+            ..TableLayout::default()
+        };
+        assert_eq!(
+            output::render_value(&layout.rows_value(&json), OutputFormat::Csv, None).unwrap(),
+            "Id\n7\n"
+        );
+        // No match: the whole value, as before
+        let layout = TableLayout {
+            list_path: Some("$.missing"),
+            ..TableLayout::default()
+        };
+        assert_eq!(*layout.rows_value(&json), json);
+    }
+
     // ==================== print_array_table tests ====================
 
     #[test]
@@ -2406,7 +4497,13 @@ paths: {}
             body: None,
             multipart: false,
             file_fields: HashMap::new(),
+            form_fields: Vec::new(),
             table_view: None,
+            list_path: None,
+            table_views: IndexMap::new(),
+            detail_view: None,
+            output_template: None,
+            auth: Vec::new(),
         });
         let cloned = spec.clone();
         if let RequestSpec::Simple(raw) = cloned {
@@ -2419,11 +4516,14 @@ paths: {}
         let scenario = mapping::Scenario {
             scenario_type: "sequential".to_string(),
             steps: vec![],
+            output_template: None,
         };
         let spec = RequestSpec::Scenario(ScenarioSpec {
             base_url: Some("https://api.example.com".to_string()),
             scenario,
             vars: HashMap::new(),
+            strict_templates: false,
+            auth: HashMap::new(),
         });
         let cloned = spec.clone();
         assert!(matches!(cloned, RequestSpec::Scenario(_)));
@@ -2451,11 +4551,14 @@ paths: {}
         let scenario = mapping::Scenario {
             scenario_type: "job_with_polling".to_string(),
             steps: vec![],
+            output_template: None,
         };
         let spec = ScenarioSpec {
             base_url: Some("https://api.example.com".to_string()),
             scenario,
             vars: HashMap::new(),
+            strict_templates: false,
+            auth: HashMap::new(),
         };
         let debug_str = format!("{:?}", spec);
         assert!(debug_str.contains("ScenarioSpec"));
@@ -2503,6 +4606,7 @@ paths: {}
                 ..Default::default()
             }],
             use_common_args: vec![],
+            ..Default::default()
         };
         let vars = HashMap::new();
         let mut selected = HashSet::new();
@@ -2591,7 +4695,13 @@ paths: {}
             body: Some(r#"{"name": "test"}"#.to_string()),
             multipart: false,
             file_fields: HashMap::new(),
+            form_fields: Vec::new(),
             table_view: Some(vec!["id".to_string(), "name".to_string()]),
+            list_path: None,
+            table_views: IndexMap::new(),
+            detail_view: None,
+            output_template: None,
+            auth: Vec::new(),
         };
         let cloned = spec.clone();
         assert_eq!(cloned.method, "POST");
@@ -2609,7 +4719,13 @@ paths: {}
             body: None,
             multipart: false,
             file_fields: HashMap::new(),
+            form_fields: Vec::new(),
             table_view: None,
+            list_path: None,
+            table_views: IndexMap::new(),
+            detail_view: None,
+            output_template: None,
+            auth: Vec::new(),
         };
         let debug_str = format!("{:?}", spec);
         assert!(debug_str.contains("RawRequestSpec"));
@@ -2638,6 +4754,7 @@ paths: {}
             custom_handler: None,
             args: vec![],
             use_common_args: vec![],
+            ..Default::default()
         };
         let vars = HashMap::new();
         let selected = HashSet::new();
@@ -2674,6 +4791,7 @@ paths: {}
                 ..Default::default()
             }],
             use_common_args: vec![],
+            ..Default::default()
         };
         let mut vars = HashMap::new();
         vars.insert("description".to_string(), "test description".to_string());
@@ -2718,6 +4836,7 @@ paths: {}
                     }),
                 },
             ],
+            output_template: None,
         };
 
         let cmd = mapping::CommandSpec {
@@ -2734,6 +4853,7 @@ paths: {}
             custom_handler: None,
             args: vec![],
             use_common_args: vec![],
+            ..Default::default()
         };
         let mut vars = HashMap::new();
         vars.insert("job_name".to_string(), "test_job".to_string());
@@ -2787,6 +4907,7 @@ paths: {}
                 ..Default::default()
             }],
             use_common_args: vec![],
+            ..Default::default()
         };
         let mut vars = HashMap::new();
         vars.insert(
@@ -2846,6 +4967,8 @@ paths: {}
         let spec = ColumnSpec {
             path: "user.name".to_string(),
             modifier: Some(SizeModifier::Megabytes),
+            formatter: None,
+            label: None,
         };
         let debug_str = format!("{:?}", spec);
         assert!(debug_str.contains("ColumnSpec"));
@@ -2857,6 +4980,8 @@ paths: {}
         let spec = ColumnSpec {
             path: "size".to_string(),
             modifier: Some(SizeModifier::Gigabytes),
+            formatter: None,
+            label: None,
         };
         let cloned = spec.clone();
         assert_eq!(cloned.path, "size");
@@ -2915,6 +5040,7 @@ paths: {}
                 ..Default::default()
             }],
             use_common_args: vec![],
+            ..Default::default()
         };
         let mut vars = HashMap::new();
         vars.insert(
@@ -2979,6 +5105,7 @@ paths: {}
                     }),
                 },
             ],
+            output_template: None,
         };
 
         let cmd = mapping::CommandSpec {
@@ -3000,6 +5127,7 @@ paths: {}
                 ..Default::default()
             }],
             use_common_args: vec![],
+            ..Default::default()
         };
         let mut vars = HashMap::new();
         vars.insert(
@@ -3049,6 +5177,85 @@ paths: {}
         assert_eq!(result, "User Id");
     }
 
+    // ==================== column path tests ====================
+
+    #[test]
+    fn test_parse_path() {
+        use PathSegment::{All, Index, Key, Length};
+        let key = |k: &str| Key(k.to_string());
+        assert_eq!(parse_path("a.b"), [key("a"), key("b")]);
+        assert_eq!(parse_path("images[0]"), [key("images"), Index(0)]);
+        assert_eq!(
+            parse_path("reviews[*].rating"),
+            [key("reviews"), All, key("rating")]
+        );
+        assert_eq!(parse_path("m[1][-1]"), [key("m"), Index(1), Index(-1)]);
+        assert_eq!(parse_path("tags.length()"), [key("tags"), Length]);
+        assert_eq!(parse_path("meta['x-id']"), [key("meta"), key("x-id")]);
+    }
+
+    #[test]
+    fn test_lookup_path_indices_wildcards_and_length() {
+        let item = serde_json::json!({
+            "images": ["a.png", "b.png"],
+            "tags": ["x", "y", "z"],
+            "reviews": [{"rating": 5}, {"rating": 3}],
+            "meta": {"0": "zero"}
+        });
+        let at = |path: &str| lookup_path(&item, path).into_owned();
+        assert_eq!(at("images[0]"), "a.png");
+        assert_eq!(at("images[-1]"), "b.png");
+        assert_eq!(at("images[5]"), serde_json::Value::Null);
+        assert_eq!(at("reviews.0.rating"), 5);
+        assert_eq!(at("reviews[1].rating"), 3);
+        assert_eq!(at("meta.0"), "zero");
+        assert_eq!(at("reviews[*].rating"), serde_json::json!([5, 3]));
+        assert_eq!(at("tags.length()"), 3);
+        assert_eq!(at("reviews.length()"), 2);
+        assert_eq!(at("missing.length()"), serde_json::Value::Null);
+        // Single values are borrowed, not copied
+        assert!(matches!(lookup_path(&item, "images[1]"), Cow::Borrowed(_)));
+        assert_eq!(get_value_by_path(&item, "images[1]"), "b.png");
+    }
+
+    #[test]
+    fn test_column_paths_in_table_cells_and_headers() {
+        let item = serde_json::json!({"tags": ["a", "b"], "reviews": [{"rating": 4}]});
+        let cell = |spec: &str| {
+            let c = parse_column_spec(spec);
+            c.format(&c.value(&item))
+        };
+        assert_eq!(cell("tags[*]"), "a, b");
+        assert_eq!(cell("tags[*]:join:/"), "a/b");
+        assert_eq!(cell("tags"), r#"["a","b"]"#);
+        assert_eq!(cell("reviews[0].rating"), "4");
+        assert_eq!(cell("tags.length()"), "2");
+
+        assert_eq!(humanize_column_label("reviews[0].rating"), "Rating");
+        assert_eq!(humanize_column_label("images[0]"), "Images");
+        assert_eq!(humanize_column_label("reviews.0.rating"), "Rating");
+        assert_eq!(humanize_column_label("tags.length()"), "Tags Count");
+        assert_eq!(humanize_column_label("tags[*]"), "Tags");
+    }
+
+    #[test]
+    fn test_derived_columns_depth() {
+        let arr = vec![serde_json::json!({
+            "id": 1,
+            "meta": {"barcode": "123", "dims": {"w": 1, "h": 2}}
+        })];
+        assert_eq!(derived_columns(&arr, 1), ["id", "meta"]);
+        assert_eq!(
+            derived_columns(&arr, 2),
+            ["id", "meta.barcode", "meta.dims"]
+        );
+        assert_eq!(
+            derived_columns(&arr, 3),
+            ["id", "meta.barcode", "meta.dims.w", "meta.dims.h"]
+        );
+        assert_eq!(derived_columns(&arr, 0), derived_columns(&arr, 1));
+    }
+
     // ==================== get_value_by_path edge cases ====================
 
     #[test]
@@ -1,106 +1,201 @@
 use anyhow::{Context, Result};
+use indexmap::IndexMap;
+use openapiv3::OpenAPI;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+
+fn is_false(b: &bool) -> bool {
+    !*b
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FlatSpec {
+    /// Derive arg types and constraints from the OpenAPI parameter schemas
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub openapi_arg_types: bool,
+    /// Fail instead of substituting empty strings for unresolved template placeholders
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub strict_templates: bool,
+    /// Levels of nested objects turned into table columns for commands without a
+    /// `table_view` (default 2: `dimensions.width`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub column_depth: Option<usize>,
+    /// Where credentials for the OpenAPI security schemes come from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth: Option<AuthSettings>,
     pub commands: Vec<CommandSpec>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct CommandSpec {
     /// Optional name for hierarchical mapping. Required when used inside groups.
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub name: Option<String>,
     /// Optional about/description for help.
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub about: Option<String>,
     /// Pattern like: "sysinfo get {id}" (optional for hierarchical commands)
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub pattern: String,
     /// HTTP method like GET/POST (optional for scenario commands)
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub method: Option<String>,
     /// Endpoint template like "/sysinfo/?id={id}" (optional for scenario commands)
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub endpoint: Option<String>,
     /// Optional body template
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub body: Option<String>,
     /// Optional headers with template values
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
     pub headers: HashMap<String, String>,
+    /// Optional query parameters with template values, appended to the endpoint's query.
+    /// Entries referencing an unset variable are left out.
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub query: IndexMap<String, String>,
+    /// Optional structured JSON body: keys are dotted paths (`dimensions.width`) or JSON
+    /// pointers (`/meta/tags`), values name the arg and its JSON type. Used instead of
+    /// `body`; fields whose arg was not provided are left out
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub body_fields: IndexMap<String, BodyField>,
     /// Optional hint for rendering list responses as a table with specific columns
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub table_view: Option<Vec<String>>,
+    /// JSONPath of the array that `table_view` applies to when the list comes in an
+    /// envelope (`$.products`); the envelope's total/skip/limit become a footer
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub list_path: Option<String>,
+    /// Columns for the response's other arrays, keyed by field name or JSONPath
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub table_views: IndexMap<String, Vec<String>>,
+    /// Sections of fields for single-object responses, shown instead of the key dump
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail_view: Option<Vec<DetailSection>>,
+    /// Text printed instead of the human output for a successful response, with
+    /// `{path}` fields, `{#each}`/`{#if}` blocks and column formatters
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_template: Option<String>,
+    /// How `--all` fetches the following pages of a list
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pagination: Option<Pagination>,
+    /// `sse` to read a `text/event-stream` response and print each event as it arrives
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stream: Option<StreamType>,
     /// Optional scenario for multi-step operations
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub scenario: Option<Scenario>,
     /// Whether this command uses multipart/form-data uploads
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_false")]
     pub multipart: bool,
     /// Optional custom handler name for imperative logic
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub custom_handler: Option<String>,
+    /// Auth for this command: `none` to send no credentials, or a security scheme name to
+    /// use instead of what the OpenAPI spec declares for the operation
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth: Option<String>,
 
     /// Optional argument specifications to aid CLI generation
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub args: Vec<ArgSpec>,
     /// Optional list of common arg names to inherit from the parent group
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub use_common_args: Vec<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct ArgSpec {
     /// Name of the variable used in endpoint/body/headers templates
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub name: Option<String>,
     /// Inherit from parent's common_args[inherit]; merges & allows overrides
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub inherit: Option<String>,
     /// Help text shown in CLI
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub help: Option<String>,
     /// If true, expose as positional argument instead of --flag
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub positional: Option<bool>,
     /// Long flag name (defaults to placeholder/name)
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub long: Option<String>,
     /// Short flag (single character)
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub short: Option<String>,
     /// Whether argument is required
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub required: Option<bool>,
-    /// Default value
-    #[serde(default)]
+    /// Default value; for a `multiple` arg, its values separated by commas
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub default: Option<String>,
-    /// Argument type (e.g., "bool")
-    #[serde(default, rename = "type")]
+    /// Argument type: "bool", "file", "int", "float", "enum" or "string" (the default)
+    #[serde(default, rename = "type", skip_serializing_if = "Option::is_none")]
     pub arg_type: Option<String>,
+    /// Allowed values for type="enum"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub choices: Option<Vec<String>>,
+    /// Regular expression the value must match (unanchored, like OpenAPI `pattern`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub regex: Option<String>,
+    /// Inclusive lower bound for type="int"/"float"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<f64>,
+    /// Inclusive upper bound for type="int"/"float"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<f64>,
+    /// Make `min` exclusive: the value must be greater than it
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub exclusive_min: bool,
+    /// Make `max` exclusive: the value must be less than it
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub exclusive_max: bool,
+    /// Accept the arg several times, each value kept whole; templates see the values joined
+    /// with ',', and `query:` entries bound to it become repeated keys
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub multiple: bool,
     /// Conditional values for boolean flags
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub value: Option<ConditionalValue>,
     /// Whether this argument represents a file to upload
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_false")]
     pub file_upload: bool,
     /// For type="file", specifies which variable this file should override
-    #[serde(default, rename = "file-overrides-value-of")]
+    #[serde(
+        default,
+        rename = "file-overrides-value-of",
+        skip_serializing_if = "Option::is_none"
+    )]
     pub file_overrides_value_of: Option<String>,
 
     // Optional per-arg overrides of the overall command
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub endpoint: Option<String>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub method: Option<String>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub headers: Option<HashMap<String, String>>,
-    #[serde(default)]
+    /// Query parameters added (or replaced by key) when this arg is given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub query: Option<IndexMap<String, String>>,
+    /// `body_fields` entries added (or replaced by key) when this arg is given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body_fields: Option<IndexMap<String, BodyField>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub body: Option<String>,
 }
 
+impl ArgSpec {
+    /// Values of `default`: several for a `multiple` arg, one otherwise.
+    pub fn default_values(&self) -> Vec<&str> {
+        match &self.default {
+            Some(default) if self.multiple => default.split(',').collect(),
+            Some(default) => vec![default.as_str()],
+            None => Vec::new(),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum ConditionalValue {
@@ -117,6 +212,152 @@ pub struct ConditionalValueEntry {
     pub if_not_set: Option<String>,
 }
 
+/// Top-level `auth:` block telling rclib where to find credentials.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AuthSettings {
+    /// Prefix of the credential env vars, e.g. `MYAPI` for `MYAPI_TOKEN`, `MYAPI_USERNAME`,
+    /// `MYAPI_PASSWORD` and `MYAPI_API_KEY`; env vars are not read without it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub env_prefix: Option<String>,
+    /// Credentials file keyed by scheme name (or `default`), read if it exists;
+    /// a leading `~/` expands to the home directory
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub credentials_file: Option<String>,
+    /// Scheme for requests whose operation is not in the OpenAPI spec
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_scheme: Option<String>,
+    /// OAuth2 client id (public clients can ship it in the mapping)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+    /// OAuth2 scopes requested by `login` and the client-credentials flow
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub scopes: Vec<String>,
+    /// Device authorization endpoint (RFC 8628) for `login --flow device`; OpenAPI
+    /// cannot declare it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device_authorization_url: Option<String>,
+    /// OAuth2 token cache file; defaults to `~/.cache/<app>/tokens.json`, where `<app>`
+    /// is the lowercased env prefix
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_cache: Option<String>,
+}
+
+/// JSON types a `body_fields` value can be converted to.
+pub const BODY_FIELD_TYPES: &[&str] = &["string", "int", "float", "bool", "json", "array"];
+
+/// Source of one `body_fields` entry: `"arg"`, `"arg:type"` or `{arg: ..., type: ...}`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum BodyField {
+    Short(String),
+    Full {
+        arg: String,
+        #[serde(default, rename = "type", skip_serializing_if = "Option::is_none")]
+        field_type: Option<String>,
+    },
+}
+
+impl BodyField {
+    /// Name of the arg providing the value.
+    pub fn arg(&self) -> &str {
+        match self {
+            BodyField::Short(s) => s.split_once(':').map_or(s.as_str(), |(arg, _)| arg),
+            BodyField::Full { arg, .. } => arg,
+        }
+    }
+
+    /// Explicit JSON type, if any; otherwise the arg's own type decides.
+    pub fn field_type(&self) -> Option<&str> {
+        match self {
+            BodyField::Short(s) => s.split_once(':').map(|(_, t)| t),
+            BodyField::Full { field_type, .. } => field_type.as_deref(),
+        }
+    }
+}
+
+/// One section of a `detail_view`: an optional heading and the fields under it, each
+/// written like a `table_view` column (`path`, `path:formatter:arg`, `path as Label`).
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DetailSection {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    pub fields: Vec<String>,
+}
+
+/// Where the next page of a list comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PaginationType {
+    /// An item offset that grows by the rows received (`skip`/`limit`)
+    Offset,
+    /// A page number counting up from the first request's (`page`/`per_page`)
+    Page,
+    /// A token taken from each response (`next_cursor`) and sent with the next request
+    Cursor,
+    /// The `Link: <...>; rel="next"` response header (RFC 5988)
+    Link,
+}
+
+/// How a streaming command's response is read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StreamType {
+    /// Server-Sent Events: `text/event-stream`, one event per blank-line-separated block
+    Sse,
+}
+
+/// A command's `pagination:` block. The `*_var` fields name the template variables
+/// (usually args used in `query:`) that `--all` sets for each following request.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Pagination {
+    #[serde(rename = "type")]
+    pub pagination_type: PaginationType,
+    /// Offset variable (default `offset`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub offset_var: Option<String>,
+    /// Page size variable for `offset` (default `limit`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit_var: Option<String>,
+    /// Page number variable (default `page`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page_var: Option<String>,
+    /// Page size variable for `page` (default `per_page`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub per_page_var: Option<String>,
+    /// Cursor variable (default `cursor`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cursor_var: Option<String>,
+    /// JSONPath of the next cursor in a response; required for `cursor`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+    /// JSONPath of the total item count, so `offset` and `page` stop without asking
+    /// for an empty page
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total: Option<String>,
+}
+
+impl Pagination {
+    pub fn offset_var(&self) -> &str {
+        self.offset_var.as_deref().unwrap_or("offset")
+    }
+
+    pub fn limit_var(&self) -> &str {
+        self.limit_var.as_deref().unwrap_or("limit")
+    }
+
+    pub fn page_var(&self) -> &str {
+        self.page_var.as_deref().unwrap_or("page")
+    }
+
+    pub fn per_page_var(&self) -> &str {
+        self.per_page_var.as_deref().unwrap_or("per_page")
+    }
+
+    pub fn cursor_var(&self) -> &str {
+        self.cursor_var.as_deref().unwrap_or("cursor")
+    }
+}
+
 // =====================
 // Scenario support for multi-step operations
 // =====================
@@ -126,6 +367,10 @@ pub struct Scenario {
     #[serde(rename = "type")]
     pub scenario_type: String,
     pub steps: Vec<ScenarioStep>,
+    /// Text printed for the final response on success, like a command's
+    /// `output_template`; scenario variables are available as `{@name}`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_template: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -133,13 +378,13 @@ pub struct ScenarioStep {
     pub name: String,
     pub method: String,
     pub endpoint: String,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub body: Option<String>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
     pub headers: HashMap<String, String>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
     pub extract_response: HashMap<String, String>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub polling: Option<PollingConfig>,
 }
 
@@ -154,9 +399,9 @@ pub struct PollingConfig {
 pub struct CompletionCondition {
     pub status: String,
     pub action: String,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub error_field: Option<String>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub error_message: Option<String>,
 }
 
@@ -166,7 +411,20 @@ pub struct CompletionCondition {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HierSpec {
-    #[serde(default)]
+    /// Derive arg types and constraints from the OpenAPI parameter schemas
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub openapi_arg_types: bool,
+    /// Fail instead of substituting empty strings for unresolved template placeholders
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub strict_templates: bool,
+    /// Levels of nested objects turned into table columns for commands without a
+    /// `table_view` (default 2: `dimensions.width`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub column_depth: Option<usize>,
+    /// Where credentials for the OpenAPI security schemes come from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auth: Option<AuthSettings>,
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
     pub common_args: HashMap<String, ArgSpec>,
     pub commands: Vec<CommandGroup>,
 }
@@ -174,11 +432,11 @@ pub struct HierSpec {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CommandGroup {
     pub name: String,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub about: Option<String>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
     pub common_args: HashMap<String, ArgSpec>,
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub subcommands: Vec<CommandNode>,
 }
 
@@ -243,6 +501,41 @@ pub enum MappingRoot {
     Flat(FlatSpec),
 }
 
+impl MappingRoot {
+    /// Whether `openapi_arg_types: true` is set at the top of the mapping.
+    pub fn openapi_arg_types(&self) -> bool {
+        match self {
+            MappingRoot::Hier(h) => h.openapi_arg_types,
+            MappingRoot::Flat(f) => f.openapi_arg_types,
+        }
+    }
+
+    /// Top-level `column_depth:`, if set.
+    pub fn column_depth(&self) -> Option<usize> {
+        match self {
+            MappingRoot::Hier(h) => h.column_depth,
+            MappingRoot::Flat(f) => f.column_depth,
+        }
+    }
+
+    /// Top-level `auth:` settings, or the defaults.
+    pub fn auth_settings(&self) -> AuthSettings {
+        match self {
+            MappingRoot::Hier(h) => h.auth.clone(),
+            MappingRoot::Flat(f) => f.auth.clone(),
+        }
+        .unwrap_or_default()
+    }
+
+    /// Whether `strict_templates: true` is set at the top of the mapping.
+    pub fn strict_templates(&self) -> bool {
+        match self {
+            MappingRoot::Hier(h) => h.strict_templates,
+            MappingRoot::Flat(f) => f.strict_templates,
+        }
+    }
+}
+
 /// Load flat mapping spec from YAML string.
 pub fn parse_flat_spec(yaml: &str) -> Result<FlatSpec> {
     let spec: FlatSpec = serde_yaml::from_str(yaml).context("Failed to parse mapping YAML")?;
@@ -283,6 +576,12 @@ pub fn derive_args_from_pattern(pattern: &str) -> Vec<ArgSpec> {
                 required: Some(true),
                 default: None,
                 arg_type: None,
+                choices: None,
+                regex: None,
+                min: None,
+                max: None,
+                exclusive_min: false,
+                exclusive_max: false,
                 value: None,
                 file_upload: false,
                 file_overrides_value_of: None,
@@ -290,13 +589,1084 @@ pub fn derive_args_from_pattern(pattern: &str) -> Vec<ArgSpec> {
                 endpoint: None,
                 method: None,
                 headers: None,
+                query: None,
+                body_fields: None,
                 body: None,
+                multiple: false,
             });
         }
     }
     args
 }
 
+/// Merge an arg that uses `inherit:` over its base from `common_args`; set fields of the override win.
+pub fn merge_arg_specs(base: &ArgSpec, override_spec: &ArgSpec) -> ArgSpec {
+    ArgSpec {
+        name: override_spec.name.clone().or_else(|| base.name.clone()),
+        inherit: None,
+        help: override_spec.help.clone().or_else(|| base.help.clone()),
+        positional: override_spec.positional.or(base.positional),
+        long: override_spec.long.clone().or_else(|| base.long.clone()),
+        short: override_spec.short.clone().or_else(|| base.short.clone()),
+        required: override_spec.required.or(base.required),
+        default: override_spec
+            .default
+            .clone()
+            .or_else(|| base.default.clone()),
+        arg_type: override_spec
+            .arg_type
+            .clone()
+            .or_else(|| base.arg_type.clone()),
+        choices: override_spec
+            .choices
+            .clone()
+            .or_else(|| base.choices.clone()),
+        regex: override_spec.regex.clone().or_else(|| base.regex.clone()),
+        min: override_spec.min.or(base.min),
+        max: override_spec.max.or(base.max),
+        exclusive_min: if override_spec.min.is_some() {
+            override_spec.exclusive_min
+        } else {
+            base.exclusive_min
+        },
+        exclusive_max: if override_spec.max.is_some() {
+            override_spec.exclusive_max
+        } else {
+            base.exclusive_max
+        },
+        value: override_spec.value.clone().or_else(|| base.value.clone()),
+        file_upload: override_spec.file_upload || base.file_upload,
+        endpoint: override_spec
+            .endpoint
+            .clone()
+            .or_else(|| base.endpoint.clone()),
+        method: override_spec.method.clone().or_else(|| base.method.clone()),
+        headers: override_spec
+            .headers
+            .clone()
+            .or_else(|| base.headers.clone()),
+        body: override_spec.body.clone().or_else(|| base.body.clone()),
+        query: override_spec.query.clone().or_else(|| base.query.clone()),
+        body_fields: override_spec
+            .body_fields
+            .clone()
+            .or_else(|| base.body_fields.clone()),
+        multiple: override_spec.multiple || base.multiple,
+        file_overrides_value_of: override_spec
+            .file_overrides_value_of
+            .clone()
+            .or_else(|| base.file_overrides_value_of.clone()),
+    }
+}
+
+/// Name of a command inside a group: explicit `name`, or the last literal token of `pattern`.
+pub fn command_name(cmd: &CommandSpec) -> String {
+    cmd.name.clone().unwrap_or_else(|| {
+        cmd.pattern
+            .split_whitespace()
+            .rfind(|t| !is_placeholder(t))
+            .unwrap_or("")
+            .to_string()
+    })
+}
+
+/// Resolve the effective args of a hierarchical command.
+///
+/// `inherit:` keys are looked up in the parent group's `common_args` first, then in the
+/// top-level ones; `use_common_args` only consults the parent group. Returns the resolved
+/// args together with the keys that matched nothing (those args are kept as written).
+pub fn resolve_command_args(
+    cmd: &CommandSpec,
+    group_common_args: &HashMap<String, ArgSpec>,
+    top_level_common_args: &HashMap<String, ArgSpec>,
+) -> (Vec<ArgSpec>, Vec<String>) {
+    let mut unresolved: Vec<String> = Vec::new();
+    let mut resolved_args: Vec<ArgSpec> = if cmd.args.is_empty() {
+        derive_args_from_pattern(&cmd.pattern)
+    } else {
+        cmd.args
+            .iter()
+            .map(|a| match &a.inherit {
+                Some(key) => match group_common_args
+                    .get(key)
+                    .or_else(|| top_level_common_args.get(key))
+                {
+                    Some(base) => merge_arg_specs(base, a),
+                    None => {
+                        unresolved.push(key.clone());
+                        a.clone()
+                    }
+                },
+                None => a.clone(),
+            })
+            .collect()
+    };
+
+    // Add common args requested via legacy use_common_args
+    for common_arg_name in &cmd.use_common_args {
+        if let Some(common_arg) = group_common_args.get(common_arg_name) {
+            resolved_args.push(common_arg.clone());
+        } else {
+            unresolved.push(common_arg_name.clone());
+        }
+    }
+    (resolved_args, unresolved)
+}
+
+/// A mapping command together with its CLI path and resolved args.
+#[derive(Debug, Clone)]
+pub struct ResolvedCommand<'a> {
+    pub path: Vec<String>,
+    pub spec: &'a CommandSpec,
+    pub args: Vec<ArgSpec>,
+    /// `inherit:`/`use_common_args` keys that did not match any `common_args` entry
+    pub unresolved_inherits: Vec<String>,
+}
+
+/// Flatten a mapping into its commands, in declaration order (duplicates are kept).
+pub fn collect_commands(root: &MappingRoot) -> Vec<ResolvedCommand<'_>> {
+    let mut out: Vec<ResolvedCommand<'_>> = Vec::new();
+    match root {
+        MappingRoot::Flat(flat) => {
+            for cmd in &flat.commands {
+                let path: Vec<String> = cmd
+                    .pattern
+                    .split_whitespace()
+                    .filter(|t| !is_placeholder(t))
+                    .map(str::to_string)
+                    .collect();
+                let args = if cmd.args.is_empty() {
+                    derive_args_from_pattern(&cmd.pattern)
+                } else {
+                    cmd.args.clone()
+                };
+                // Flat mappings have no common_args, so every inherit key is dangling
+                let unresolved_inherits = cmd
+                    .args
+                    .iter()
+                    .filter_map(|a| a.inherit.clone())
+                    .chain(cmd.use_common_args.iter().cloned())
+                    .collect();
+                out.push(ResolvedCommand {
+                    path,
+                    spec: cmd,
+                    args,
+                    unresolved_inherits,
+                });
+            }
+        }
+        MappingRoot::Hier(hier) => {
+            fn walk<'a>(
+                group: &'a CommandGroup,
+                path: &mut Vec<String>,
+                top_level_common_args: &HashMap<String, ArgSpec>,
+                out: &mut Vec<ResolvedCommand<'a>>,
+            ) {
+                path.push(group.name.clone());
+                for node in &group.subcommands {
+                    match node {
+                        CommandNode::Group(g) => walk(g, path, top_level_common_args, out),
+                        CommandNode::Command(cmd) => {
+                            let mut cmd_path = path.clone();
+                            let name = command_name(cmd);
+                            if !name.is_empty() {
+                                cmd_path.push(name);
+                            }
+                            let (args, unresolved_inherits) = resolve_command_args(
+                                cmd,
+                                &group.common_args,
+                                top_level_common_args,
+                            );
+                            out.push(ResolvedCommand {
+                                path: cmd_path,
+                                spec: cmd,
+                                args,
+                                unresolved_inherits,
+                            });
+                        }
+                    }
+                }
+                path.pop();
+            }
+            for g in &hier.commands {
+                walk(g, &mut Vec::new(), &hier.common_args, &mut out);
+            }
+        }
+    }
+    out
+}
+
+// =====================
+// Argument value checks
+// =====================
+
+/// Arg types understood by [`check_arg_value`] and the CLI builder.
+pub const KNOWN_ARG_TYPES: &[&str] = &["string", "bool", "file", "int", "float", "enum"];
+
+/// Check a CLI value against the arg's `type`, `choices`, `regex` and `min`/`max`.
+///
+/// The error is a short reason suitable for clap's "invalid value" message.
+pub fn check_arg_value(spec: &ArgSpec, value: &str) -> std::result::Result<(), String> {
+    let number = match spec.arg_type.as_deref() {
+        Some("int") => Some(
+            value
+                .parse::<i64>()
+                .map_err(|_| "expected an integer".to_string())? as f64,
+        ),
+        Some("float") => Some(
+            value
+                .parse::<f64>()
+                .ok()
+                .filter(|n| n.is_finite())
+                .ok_or_else(|| "expected a number".to_string())?,
+        ),
+        _ => None,
+    };
+    if let Some(n) = number {
+        if let Some(min) = spec.min {
+            if spec.exclusive_min && n <= min {
+                return Err(format!("must be greater than {}", min));
+            }
+            if n < min {
+                return Err(format!("must be at least {}", min));
+            }
+        }
+        if let Some(max) = spec.max {
+            if spec.exclusive_max && n >= max {
+                return Err(format!("must be less than {}", max));
+            }
+            if n > max {
+                return Err(format!("must be at most {}", max));
+            }
+        }
+    }
+    if let Some(choices) = &spec.choices {
+        if !choices.iter().any(|c| c == value) {
+            return Err(format!("must be one of: {}", choices.join(", ")));
+        }
+    }
+    if let Some(pattern) = &spec.regex {
+        let re = regex::Regex::new(pattern)
+            .map_err(|e| format!("invalid regex '{}': {}", pattern, e))?;
+        if !re.is_match(value) {
+            return Err(format!("must match pattern '{}'", pattern));
+        }
+    }
+    Ok(())
+}
+
+// =====================
+// Mapping validation
+// =====================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// A single problem found by [`validate_mapping`] or [`crate::openapi::check_mapping_against_openapi`].
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    /// Command path the problem belongs to, e.g. "products list"
+    pub path: String,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn error(path: &[String], message: String) -> Self {
+        Self {
+            severity: Severity::Error,
+            path: path.join(" "),
+            message,
+        }
+    }
+
+    pub fn warning(path: &[String], message: String) -> Self {
+        Self {
+            severity: Severity::Warning,
+            path: path.join(" "),
+            message,
+        }
+    }
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let level = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(f, "{}: {}: {}", level, self.path, self.message)
+    }
+}
+
+/// Variables that are always available to templates without a matching arg.
+const BUILTIN_VARS: &[&str] = &["uuid"];
+
+/// Flags clap generates for every subcommand.
+pub(crate) const RESERVED_LONG_FLAGS: &[&str] = &["help"];
+const RESERVED_SHORT_FLAGS: &[char] = &['h'];
+/// Global options, which clap adds to every subcommand under the same id.
+pub(crate) const GLOBAL_FLAGS: &[&str] = &["query", "raw-value"];
+
+/// Whether a mapping arg can't use `--<flag>` because clap or a global option already does.
+pub(crate) fn is_reserved_long_flag(flag: &str) -> bool {
+    RESERVED_LONG_FLAGS.contains(&flag) || GLOBAL_FLAGS.contains(&flag)
+}
+
+/// Statically check a mapping for mistakes that would otherwise only show up at request time.
+///
+/// Reports template placeholders without a matching arg, dangling `inherit:` keys,
+/// duplicate command paths, clashing `short`/`long` flags and commands with nothing to execute.
+pub fn validate_mapping(root: &MappingRoot) -> Vec<Diagnostic> {
+    let mut diags: Vec<Diagnostic> = Vec::new();
+    let mut seen_paths: HashSet<Vec<String>> = HashSet::new();
+
+    for entry in collect_commands(root) {
+        if !seen_paths.insert(entry.path.clone()) {
+            diags.push(Diagnostic::error(
+                &entry.path,
+                "duplicate command path; the later definition silently replaces the earlier one"
+                    .to_string(),
+            ));
+        }
+        for key in &entry.unresolved_inherits {
+            diags.push(Diagnostic::error(
+                &entry.path,
+                format!("'{}' does not match any common_args entry", key),
+            ));
+        }
+        lint_command_kind(&entry, &mut diags);
+        lint_flags(&entry, &mut diags);
+        lint_arg_types(&entry, &mut diags);
+        lint_placeholders(&entry, &mut diags);
+        lint_body_fields(&entry, &mut diags);
+        lint_output_templates(&entry, &mut diags);
+        lint_pagination(&entry, &mut diags);
+        lint_stream(&entry, &mut diags);
+    }
+    diags
+}
+
+fn lint_command_kind(entry: &ResolvedCommand<'_>, diags: &mut Vec<Diagnostic>) {
+    let cmd = entry.spec;
+    let has_request = cmd.method.is_some() || cmd.endpoint.is_some();
+    let has_alternative = cmd.scenario.is_some() || cmd.custom_handler.is_some();
+    if !has_request && !has_alternative {
+        diags.push(Diagnostic::error(
+            &entry.path,
+            "command has neither method/endpoint nor scenario/custom_handler".to_string(),
+        ));
+    } else if !has_alternative {
+        if cmd.method.is_none() {
+            diags.push(Diagnostic::error(
+                &entry.path,
+                "endpoint is set but method is missing".to_string(),
+            ));
+        }
+        if cmd.endpoint.is_none() {
+            diags.push(Diagnostic::error(
+                &entry.path,
+                "method is set but endpoint is missing".to_string(),
+            ));
+        }
+    } else if has_request {
+        diags.push(Diagnostic::warning(
+            &entry.path,
+            "method/endpoint are ignored because scenario or custom_handler is set".to_string(),
+        ));
+    }
+}
+
+fn lint_output_templates(entry: &ResolvedCommand<'_>, diags: &mut Vec<Diagnostic>) {
+    let cmd = entry.spec;
+    let templates = [
+        ("output_template", cmd.output_template.as_deref()),
+        (
+            "scenario output_template",
+            cmd.scenario
+                .as_ref()
+                .and_then(|s| s.output_template.as_deref()),
+        ),
+    ];
+    for (field, template) in templates {
+        if let Some(Err(e)) = template.map(crate::output_template::OutputTemplate::parse) {
+            diags.push(Diagnostic::error(&entry.path, format!("{}: {}", field, e)));
+        }
+    }
+}
+
+fn lint_pagination(entry: &ResolvedCommand<'_>, diags: &mut Vec<Diagnostic>) {
+    let cmd = entry.spec;
+    let Some(pagination) = &cmd.pagination else {
+        return;
+    };
+    let (field, var) = match pagination.pagination_type {
+        PaginationType::Offset => ("offset_var", pagination.offset_var()),
+        PaginationType::Page => ("page_var", pagination.page_var()),
+        PaginationType::Cursor => ("cursor_var", pagination.cursor_var()),
+        PaginationType::Link => return,
+    };
+    if pagination.pagination_type == PaginationType::Cursor && pagination.next_cursor.is_none() {
+        diags.push(Diagnostic::error(
+            &entry.path,
+            "cursor pagination needs next_cursor, the JSONPath of the next cursor".to_string(),
+        ));
+    }
+    let templates = cmd
+        .endpoint
+        .iter()
+        .chain(cmd.body.iter())
+        .chain(cmd.query.values())
+        .chain(cmd.headers.values());
+    let used = templates
+        .flat_map(|t| crate::parse_placeholders(t))
+        .any(|p| p.name == var)
+        || cmd.body_fields.values().any(|f| f.arg() == var);
+    if !used {
+        diags.push(Diagnostic::error(
+            &entry.path,
+            format!(
+                "pagination {} '{}' is not used by the endpoint, query, headers or body, so --all would repeat the first page",
+                field, var
+            ),
+        ));
+    }
+}
+
+fn lint_stream(entry: &ResolvedCommand<'_>, diags: &mut Vec<Diagnostic>) {
+    let cmd = entry.spec;
+    if cmd.stream.is_none() {
+        return;
+    }
+    if cmd.pagination.is_some() {
+        diags.push(Diagnostic::error(
+            &entry.path,
+            "stream and pagination cannot be combined; a stream has no pages".to_string(),
+        ));
+    }
+    if cmd.scenario.is_some() || cmd.custom_handler.is_some() {
+        diags.push(Diagnostic::warning(
+            &entry.path,
+            "stream is ignored because scenario or custom_handler is set".to_string(),
+        ));
+    }
+}
+
+fn lint_flags(entry: &ResolvedCommand<'_>, diags: &mut Vec<Diagnostic>) {
+    let mut ids: HashSet<String> = HashSet::new();
+    let mut shorts: HashMap<char, String> = HashMap::new();
+    for arg in &entry.args {
+        let id = arg
+            .long
+            .clone()
+            .or_else(|| arg.name.clone())
+            .unwrap_or_default();
+        if id.is_empty() {
+            continue;
+        }
+        // The long flag doubles as the clap arg id, so a repeated id is a long flag clash
+        if !ids.insert(id.clone()) {
+            diags.push(Diagnostic::error(
+                &entry.path,
+                format!("--{} is defined by more than one argument", id),
+            ));
+            continue;
+        }
+        if arg.positional.unwrap_or(false) {
+            continue;
+        }
+        if GLOBAL_FLAGS.contains(&id.as_str()) {
+            diags.push(Diagnostic::error(
+                &entry.path,
+                format!("--{} clashes with the global option of the same name", id),
+            ));
+        }
+        if RESERVED_LONG_FLAGS.contains(&id.as_str()) {
+            diags.push(Diagnostic::error(
+                &entry.path,
+                format!("--{} clashes with the built-in help flag", id),
+            ));
+        }
+
+        if let Some(short) = arg.short.as_deref() {
+            let mut chars = short.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => {
+                    if RESERVED_SHORT_FLAGS.contains(&c) {
+                        diags.push(Diagnostic::error(
+                            &entry.path,
+                            format!("-{} on '{}' clashes with the built-in help flag", c, id),
+                        ));
+                    } else if let Some(other) = shorts.insert(c, id.clone()) {
+                        diags.push(Diagnostic::error(
+                            &entry.path,
+                            format!("-{} is used by both '{}' and '{}'", c, other, id),
+                        ));
+                    }
+                }
+                _ => diags.push(Diagnostic::error(
+                    &entry.path,
+                    format!(
+                        "short flag '{}' on '{}' must be a single character",
+                        short, id
+                    ),
+                )),
+            }
+        }
+    }
+}
+
+fn lint_arg_types(entry: &ResolvedCommand<'_>, diags: &mut Vec<Diagnostic>) {
+    for arg in &entry.args {
+        let name = arg.name.as_deref().or(arg.long.as_deref()).unwrap_or("");
+        match arg.arg_type.as_deref() {
+            Some(t) if !KNOWN_ARG_TYPES.contains(&t) => diags.push(Diagnostic::warning(
+                &entry.path,
+                format!("'{}' has unknown type '{}'", name, t),
+            )),
+            Some("enum") if arg.choices.as_ref().is_none_or(|c| c.is_empty()) => {
+                diags.push(Diagnostic::error(
+                    &entry.path,
+                    format!("'{}' has type enum but no choices", name),
+                ))
+            }
+            _ => {}
+        }
+        if let Some(Err(e)) = arg.regex.as_deref().map(regex::Regex::new) {
+            diags.push(Diagnostic::error(
+                &entry.path,
+                format!("'{}' has an invalid regex: {}", name, e),
+            ));
+        }
+        if let (Some(min), Some(max)) = (arg.min, arg.max) {
+            if min > max {
+                diags.push(Diagnostic::error(
+                    &entry.path,
+                    format!("'{}' has min {} greater than max {}", name, min, max),
+                ));
+            }
+        }
+        let numeric = matches!(arg.arg_type.as_deref(), Some("int" | "float"));
+        if !numeric && (arg.min.is_some() || arg.max.is_some()) {
+            diags.push(Diagnostic::warning(
+                &entry.path,
+                format!("'{}' sets min/max but is not of type int or float", name),
+            ));
+        }
+        for default in arg.default_values() {
+            if let Err(reason) = check_arg_value(arg, default) {
+                diags.push(Diagnostic::error(
+                    &entry.path,
+                    format!(
+                        "default '{}' of '{}' is rejected: {}",
+                        default, name, reason
+                    ),
+                ));
+            }
+        }
+    }
+}
+
+fn lint_placeholders(entry: &ResolvedCommand<'_>, diags: &mut Vec<Diagnostic>) {
+    let cmd = entry.spec;
+    let mut known: HashSet<String> = BUILTIN_VARS.iter().map(|s| s.to_string()).collect();
+    for arg in &entry.args {
+        known.extend(arg.name.clone());
+        known.extend(arg.file_overrides_value_of.clone());
+    }
+
+    let mut check = |field: &str, template: &str, known: &HashSet<String>| {
+        let mut reported: HashSet<String> = HashSet::new();
+        for placeholder in crate::parse_placeholders(template) {
+            for (filter, _) in &placeholder.filters {
+                if !crate::TEMPLATE_FILTERS.contains(&filter.as_str()) {
+                    diags.push(Diagnostic::error(
+                        &entry.path,
+                        format!(
+                            "{} uses unknown filter '{}' on {{{}}} (known: {})",
+                            field,
+                            filter,
+                            placeholder.name,
+                            crate::TEMPLATE_FILTERS.join(", ")
+                        ),
+                    ));
+                }
+            }
+            let name = &placeholder.name;
+            if !known.contains(name)
+                && placeholder.default_value().is_none()
+                && reported.insert(name.clone())
+            {
+                diags.push(Diagnostic::error(
+                    &entry.path,
+                    format!(
+                        "{} references {{{}}} but no argument provides it",
+                        field, name
+                    ),
+                ));
+            }
+        }
+    };
+
+    if let Some(ep) = &cmd.endpoint {
+        check("endpoint", ep, &known);
+    }
+    if let Some(body) = &cmd.body {
+        check("body", body, &known);
+    }
+    for (k, v) in sorted(&cmd.headers) {
+        check(&format!("header '{}'", k), v, &known);
+    }
+    for (k, v) in &cmd.query {
+        check(&format!("query '{}'", k), v, &known);
+    }
+    for arg in &entry.args {
+        let arg_name = arg.name.as_deref().unwrap_or("?");
+        if let Some(ep) = &arg.endpoint {
+            check(&format!("endpoint override of '{}'", arg_name), ep, &known);
+        }
+        if let Some(body) = &arg.body {
+            check(&format!("body override of '{}'", arg_name), body, &known);
+        }
+        if let Some(headers) = &arg.headers {
+            for (k, v) in sorted(headers) {
+                check(
+                    &format!("header '{}' override of '{}'", k, arg_name),
+                    v,
+                    &known,
+                );
+            }
+        }
+        for (k, v) in arg.query.iter().flatten() {
+            check(
+                &format!("query '{}' override of '{}'", k, arg_name),
+                v,
+                &known,
+            );
+        }
+    }
+
+    if let Some(scenario) = &cmd.scenario {
+        for step in &scenario.steps {
+            let prefix = format!("step '{}'", step.name);
+            check(&format!("{} endpoint", prefix), &step.endpoint, &known);
+            if let Some(body) = &step.body {
+                check(&format!("{} body", prefix), body, &known);
+            }
+            for (k, v) in sorted(&step.headers) {
+                check(&format!("{} header '{}'", prefix, k), v, &known);
+            }
+            // Values extracted here become available to the following steps
+            known.extend(step.extract_response.keys().cloned());
+        }
+    }
+}
+
+fn lint_body_fields(entry: &ResolvedCommand<'_>, diags: &mut Vec<Diagnostic>) {
+    let cmd = entry.spec;
+    if cmd.body.is_some() && !cmd.body_fields.is_empty() {
+        diags.push(Diagnostic::warning(
+            &entry.path,
+            "both body and body_fields are set; body is ignored".to_string(),
+        ));
+    }
+    let mut known: HashSet<String> = BUILTIN_VARS.iter().map(|s| s.to_string()).collect();
+    for arg in &entry.args {
+        known.extend(arg.name.clone());
+        known.extend(arg.file_overrides_value_of.clone());
+    }
+
+    let mut sets: Vec<(String, &IndexMap<String, BodyField>)> =
+        vec![("body_fields".to_string(), &cmd.body_fields)];
+    for arg in &entry.args {
+        if let Some(fields) = &arg.body_fields {
+            let arg_name = arg.name.as_deref().unwrap_or("?");
+            sets.push((format!("body_fields override of '{}'", arg_name), fields));
+        }
+    }
+    for (origin, fields) in sets {
+        for (key, field) in fields {
+            if !known.contains(field.arg()) {
+                diags.push(Diagnostic::error(
+                    &entry.path,
+                    format!(
+                        "{} '{}' references '{}' but no argument provides it",
+                        origin,
+                        key,
+                        field.arg()
+                    ),
+                ));
+            }
+            if let Some(t) = field.field_type() {
+                if !BODY_FIELD_TYPES.contains(&t) {
+                    diags.push(Diagnostic::error(
+                        &entry.path,
+                        format!(
+                            "{} '{}' has unknown type '{}' (known: {})",
+                            origin,
+                            key,
+                            t,
+                            BODY_FIELD_TYPES.join(", ")
+                        ),
+                    ));
+                }
+            }
+        }
+    }
+
+    // A field cannot also be the parent object of another one
+    let paths: Vec<(&String, Vec<String>)> = cmd
+        .body_fields
+        .keys()
+        .map(|k| (k, crate::body_field_path(k)))
+        .collect();
+    for (key, path) in &paths {
+        for (other, other_path) in &paths {
+            if other_path.len() > path.len() && other_path.starts_with(path) {
+                diags.push(Diagnostic::error(
+                    &entry.path,
+                    format!("body_fields '{}' conflicts with '{}'", other, key),
+                ));
+            }
+        }
+    }
+}
+
+/// Iterate a map in key order so diagnostics come out deterministically.
+fn sorted(map: &HashMap<String, String>) -> Vec<(&String, &String)> {
+    let mut entries: Vec<(&String, &String)> = map.iter().collect();
+    entries.sort();
+    entries
+}
+
+// =====================
+// Generating a mapping from OpenAPI
+// =====================
+
+/// Options for [`generate_from_openapi`].
+#[derive(Debug, Clone)]
+pub struct GenerateOptions {
+    /// Group for operations without tags; `None` groups them by their first path segment
+    pub untagged_group: Option<String>,
+    /// Maximum number of `table_view` columns picked from array response items
+    pub max_table_columns: usize,
+}
+
+impl Default for GenerateOptions {
+    fn default() -> Self {
+        Self {
+            untagged_group: None,
+            max_table_columns: 6,
+        }
+    }
+}
+
+/// Generate a starter hierarchical mapping from an OpenAPI document.
+///
+/// Operations are grouped by their first tag and named after their `operationId`
+/// (kebab-cased). Path parameters become positional args, query parameters become flags
+/// feeding a `query:` map, header parameters become flags feeding `headers`, JSON request
+/// bodies get a `body` template with one arg per top-level property, and list responses
+/// get `table_view` columns from the item schema.
+pub fn generate_from_openapi(api: &OpenAPI, options: &GenerateOptions) -> HierSpec {
+    let mut groups: Vec<CommandGroup> = Vec::new();
+    for (path, item) in api.paths.iter() {
+        let Some(item) = item.as_item() else {
+            continue;
+        };
+        for (method, operation) in item.iter() {
+            let op = crate::openapi::OperationRef {
+                path,
+                item,
+                operation,
+            };
+            let group_name = match operation.tags.first() {
+                Some(tag) => to_kebab_case(tag),
+                None => options.untagged_group.clone().unwrap_or_else(|| {
+                    path.split('/')
+                        .find(|s| !s.is_empty() && !s.contains('{'))
+                        .map(to_kebab_case)
+                        .unwrap_or_else(|| "default".to_string())
+                }),
+            };
+            let idx = match groups.iter().position(|g| g.name == group_name) {
+                Some(idx) => idx,
+                None => {
+                    let about = operation.tags.first().and_then(|tag| {
+                        api.tags
+                            .iter()
+                            .find(|t| &t.name == tag)
+                            .and_then(|t| t.description.clone())
+                    });
+                    groups.push(CommandGroup {
+                        name: group_name,
+                        about,
+                        common_args: HashMap::new(),
+                        subcommands: Vec::new(),
+                    });
+                    groups.len() - 1
+                }
+            };
+
+            let mut cmd = generate_command(api, &op, method, options);
+            let group = &mut groups[idx];
+            let taken: HashSet<String> = group
+                .subcommands
+                .iter()
+                .filter_map(|n| match n {
+                    CommandNode::Command(c) => c.name.clone(),
+                    CommandNode::Group(g) => Some(g.name.clone()),
+                })
+                .collect();
+            let base = cmd.name.clone().unwrap_or_default();
+            let mut name = base.clone();
+            let mut n = 2;
+            while taken.contains(&name) {
+                name = format!("{}-{}", base, n);
+                n += 1;
+            }
+            cmd.name = Some(name);
+            group.subcommands.push(CommandNode::Command(cmd));
+        }
+    }
+    HierSpec {
+        // Keep generated args in sync with the spec instead of copying constraints
+        openapi_arg_types: true,
+        strict_templates: false,
+        column_depth: None,
+        auth: None,
+        common_args: HashMap::new(),
+        commands: groups,
+    }
+}
+
+fn generate_command(
+    api: &OpenAPI,
+    op: &crate::openapi::OperationRef<'_>,
+    method: &str,
+    options: &GenerateOptions,
+) -> CommandSpec {
+    use crate::openapi::{parameter_schema, request_body_schema, schema_type_name};
+    use openapiv3::Parameter;
+
+    let operation = op.operation;
+    let name = match &operation.operation_id {
+        Some(id) => to_kebab_case(id),
+        None => {
+            // e.g. GET /products/{id} -> "get-products-id"
+            let mut parts = vec![method.to_string()];
+            parts.extend(
+                op.path
+                    .split('/')
+                    .filter(|s| !s.is_empty())
+                    .map(to_kebab_case),
+            );
+            parts.join("-")
+        }
+    };
+    let about = operation.summary.clone().or_else(|| {
+        operation
+            .description
+            .as_ref()
+            .and_then(|d| d.lines().next())
+            .map(|l| l.to_string())
+    });
+
+    let mut args: Vec<ArgSpec> = Vec::new();
+    let mut endpoint = op.path.to_string();
+    let mut query: IndexMap<String, String> = IndexMap::new();
+    let mut headers: HashMap<String, String> = HashMap::new();
+    for param in crate::openapi::operation_parameters(api, op) {
+        let data = param.parameter_data_ref();
+        let var = unreserved_var(to_snake_case(&data.name), &args);
+        let default = parameter_schema(api, param)
+            .and_then(|s| s.schema_data.default.as_ref())
+            .map(json_default_to_string);
+        let mut arg = ArgSpec {
+            name: Some(var.clone()),
+            help: data.description.clone(),
+            long: Some(var.replace('_', "-")),
+            required: Some(data.required),
+            default,
+            ..Default::default()
+        };
+        match param {
+            Parameter::Path { .. } => {
+                endpoint = endpoint.replace(&format!("{{{}}}", data.name), &format!("{{{}}}", var));
+                arg.positional = Some(true);
+                arg.required = Some(true);
+            }
+            Parameter::Query { .. } => {
+                query.insert(data.name.clone(), format!("{{{}}}", var));
+            }
+            Parameter::Header { .. } => {
+                headers.insert(data.name.clone(), format!("{{{}}}", var));
+            }
+            Parameter::Cookie { .. } => continue,
+        }
+        args.push(arg);
+    }
+
+    let mut body = None;
+    if let Some(schema) = request_body_schema(api, operation) {
+        let mut fields: Vec<String> = Vec::new();
+        if let openapiv3::SchemaKind::Type(openapiv3::Type::Object(obj)) = &schema.schema_kind {
+            for (prop, prop_schema) in &obj.properties {
+                let prop_schema = crate::openapi::resolve_boxed_schema(api, prop_schema);
+                let mut var = to_snake_case(prop);
+                if args.iter().any(|a| a.name.as_deref() == Some(var.as_str())) {
+                    var = format!("body_{}", var);
+                }
+                let var = unreserved_var(var, &args);
+                let required = obj.required.contains(prop);
+                let is_string = prop_schema.and_then(schema_type_name) == Some("string");
+                let default = prop_schema
+                    .and_then(|s| s.schema_data.default.as_ref())
+                    .map(json_default_to_string)
+                    // Keep the body valid JSON when an optional non-string field is omitted
+                    .or_else(|| (!required && !is_string).then(|| "null".to_string()));
+                fields.push(if is_string {
+                    format!("\"{}\": \"{{{}}}\"", prop, var)
+                } else {
+                    format!("\"{}\": {{{}}}", prop, var)
+                });
+                args.push(ArgSpec {
+                    name: Some(var.clone()),
+                    help: prop_schema.and_then(|s| s.schema_data.description.clone()),
+                    long: Some(var.replace('_', "-")),
+                    required: Some(required),
+                    default,
+                    ..Default::default()
+                });
+            }
+        }
+        if fields.is_empty() {
+            args.push(ArgSpec {
+                name: Some("body".to_string()),
+                help: Some("JSON request body".to_string()),
+                long: Some("body".to_string()),
+                required: Some(true),
+                ..Default::default()
+            });
+            body = Some("{body}".to_string());
+        } else {
+            body = Some(format!("{{{}}}", fields.join(", ")));
+        }
+        headers.insert("Content-Type".to_string(), "application/json".to_string());
+    }
+
+    CommandSpec {
+        name: Some(name),
+        about,
+        method: Some(method.to_uppercase()),
+        endpoint: Some(endpoint),
+        body,
+        headers,
+        query,
+        table_view: generate_table_view(api, operation, options.max_table_columns),
+        args,
+        ..Default::default()
+    }
+}
+
+/// Scalar properties of the items of an array response (bare or inside an envelope object).
+fn generate_table_view(
+    api: &OpenAPI,
+    operation: &openapiv3::Operation,
+    max_columns: usize,
+) -> Option<Vec<String>> {
+    use crate::openapi::{
+        array_items, resolve_boxed_schema, schema_type_name, success_response_schema,
+    };
+    use openapiv3::{SchemaKind, Type};
+
+    let schema = success_response_schema(api, operation)?;
+    let items = match &schema.schema_kind {
+        SchemaKind::Type(Type::Object(obj)) => obj
+            .properties
+            .values()
+            .filter_map(|p| resolve_boxed_schema(api, p))
+            .find_map(|p| array_items(api, p).filter(|i| schema_type_name(i) == Some("object"))),
+        _ => array_items(api, schema),
+    }?;
+    let SchemaKind::Type(Type::Object(obj)) = &items.schema_kind else {
+        return None;
+    };
+    let columns: Vec<String> = obj
+        .properties
+        .iter()
+        .filter(|(_, p)| {
+            matches!(
+                resolve_boxed_schema(api, p).and_then(schema_type_name),
+                Some("string" | "integer" | "number" | "boolean")
+            )
+        })
+        .map(|(name, _)| name.clone())
+        .take(max_columns)
+        .collect();
+    if columns.is_empty() {
+        None
+    } else {
+        Some(columns)
+    }
+}
+
+/// `var`, or `var_2`, `var_3`, ... if another arg has it or its flag is reserved.
+fn unreserved_var(var: String, args: &[ArgSpec]) -> String {
+    let taken = |name: &str| {
+        is_reserved_long_flag(&name.replace('_', "-"))
+            || args.iter().any(|a| a.name.as_deref() == Some(name))
+    };
+    if !taken(&var) {
+        return var;
+    }
+    (2..)
+        .map(|n| format!("{}_{}", var, n))
+        .find(|name| !taken(name))
+        .unwrap()
+}
+
+fn json_default_to_string(v: &serde_json::Value) -> String {
+    match v {
+        serde_json::Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// "getProductById" / "user-id" -> "get_product_by_id" / "user_id"
+pub(crate) fn to_snake_case(s: &str) -> String {
+    let mut out = String::new();
+    let mut prev_lower = false;
+    for c in s.chars() {
+        if c.is_ascii_alphanumeric() {
+            if c.is_ascii_uppercase() && prev_lower {
+                out.push('_');
+            }
+            out.push(c.to_ascii_lowercase());
+            prev_lower = c.is_ascii_lowercase() || c.is_ascii_digit();
+        } else {
+            if !out.is_empty() && !out.ends_with('_') {
+                out.push('_');
+            }
+            prev_lower = false;
+        }
+    }
+    let out = out.trim_end_matches('_').to_string();
+    if out.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("_{}", out)
+    } else {
+        out
+    }
+}
+
+pub(crate) fn to_kebab_case(s: &str) -> String {
+    to_snake_case(s).trim_start_matches('_').replace('_', "-")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -707,6 +2077,47 @@ commands:
         assert_eq!(table_view[0], "id");
     }
 
+    #[test]
+    fn test_parse_command_with_list_path_and_table_views() {
+        let yaml = r#"
+commands:
+  - pattern: "products get"
+    method: GET
+    endpoint: /products
+    list_path: "$.products"
+    table_view: [id, title]
+    table_views:
+      reviews: [rating, comment]
+      "$.images": [url]
+"#;
+        let spec = parse_flat_spec(yaml).unwrap();
+        let cmd = &spec.commands[0];
+        assert_eq!(cmd.list_path.as_deref(), Some("$.products"));
+        assert_eq!(cmd.table_views["reviews"], ["rating", "comment"]);
+        assert_eq!(cmd.table_views["$.images"], ["url"]);
+    }
+
+    #[test]
+    fn test_parse_command_with_detail_view() {
+        let yaml = r#"
+commands:
+  - pattern: "products get {id}"
+    method: GET
+    endpoint: /products/{id}
+    detail_view:
+      - title: Product
+        fields: [id, title, "price:round:2 as Price"]
+      - fields: [dimensions, reviews]
+"#;
+        let spec = parse_flat_spec(yaml).unwrap();
+        let sections = spec.commands[0].detail_view.as_ref().unwrap();
+        assert_eq!(sections.len(), 2);
+        assert_eq!(sections[0].title.as_deref(), Some("Product"));
+        assert_eq!(sections[0].fields[2], "price:round:2 as Price");
+        assert_eq!(sections[1].title, None);
+        assert_eq!(sections[1].fields, ["dimensions", "reviews"]);
+    }
+
     // ==================== File override parsing ====================
 
     #[test]
@@ -731,4 +2142,802 @@ commands:
             Some("config_content".to_string())
         );
     }
+
+    // ==================== validate_mapping tests ====================
+
+    fn lint(yaml: &str) -> Vec<Diagnostic> {
+        validate_mapping(&parse_mapping_root(yaml).unwrap())
+    }
+
+    #[test]
+    fn test_validate_mapping_clean() {
+        let diags = lint(
+            r#"
+common_args:
+  limit:
+    name: limit
+    long: limit
+commands:
+  - name: users
+    subcommands:
+      - name: get
+        method: GET
+        endpoint: /users/{id}?limit={limit}&key={uuid}
+        args:
+          - name: id
+            positional: true
+          - inherit: limit
+"#,
+        );
+        assert!(diags.is_empty(), "unexpected diagnostics: {:?}", diags);
+    }
+
+    #[test]
+    fn test_validate_mapping_unknown_placeholder() {
+        let diags = lint(
+            r#"
+commands:
+  - name: users
+    subcommands:
+      - name: list
+        method: GET
+        endpoint: /users?limit={limt}
+        headers:
+          X-Org: "{org}"
+        args:
+          - name: limit
+            long: limit
+"#,
+        );
+        assert_eq!(diags.len(), 2);
+        assert!(diags.iter().all(|d| d.severity == Severity::Error));
+        assert_eq!(diags[0].path, "users list");
+        assert!(diags[0].message.contains("{limt}"));
+        assert!(diags[1].message.contains("header 'X-Org'"));
+    }
+
+    #[test]
+    fn test_validate_mapping_template_filters() {
+        let diags = lint(
+            r#"
+commands:
+  - name: items
+    subcommands:
+      - name: get
+        method: GET
+        endpoint: /items/{id|path}?page={page|default:1}
+        body: '{"name": {name|jsn}}'
+        args:
+          - name: id
+            positional: true
+          - name: name
+            long: name
+"#,
+        );
+        // {page} is covered by its default; the misspelled filter is reported
+        assert_eq!(diags.len(), 1, "unexpected diagnostics: {:?}", diags);
+        assert_eq!(diags[0].severity, Severity::Error);
+        assert!(diags[0].message.contains("unknown filter 'jsn' on {name}"));
+    }
+
+    #[test]
+    fn test_validate_mapping_output_templates() {
+        let diags = lint(
+            r#"
+commands:
+  - name: items
+    subcommands:
+      - name: create
+        method: POST
+        endpoint: /items
+        output_template: "Created item {id} ({title})"
+      - name: list
+        method: GET
+        endpoint: /items
+        output_template: "{#each items}{name}"
+      - name: export
+        scenario:
+          type: job_with_polling
+          output_template: "{/if}"
+          steps: []
+"#,
+        );
+        assert_eq!(diags.len(), 2, "unexpected diagnostics: {:?}", diags);
+        assert!(diags[0]
+            .message
+            .contains("output_template: {#each items} is not closed"));
+        assert!(diags[1]
+            .message
+            .starts_with("scenario output_template: {/if} at offset 0"));
+    }
+
+    #[test]
+    fn test_validate_mapping_pagination() {
+        let diags = lint(
+            r#"
+commands:
+  - name: items
+    subcommands:
+      - name: list
+        method: GET
+        endpoint: /items
+        query:
+          skip: "{skip}"
+        pagination: { type: offset, offset_var: skip, total: "$.total" }
+        args:
+          - name: skip
+            long: skip
+      - name: pages
+        method: GET
+        endpoint: /pages
+        pagination: { type: page }
+      - name: feed
+        method: GET
+        endpoint: /feed?after={after}
+        pagination: { type: cursor, cursor_var: after }
+        args:
+          - name: after
+            long: after
+"#,
+        );
+        assert_eq!(diags.len(), 2, "unexpected diagnostics: {:?}", diags);
+        assert!(diags[0]
+            .message
+            .starts_with("pagination page_var 'page' is not used"));
+        assert!(diags[1]
+            .message
+            .starts_with("cursor pagination needs next_cursor"));
+
+        let cmd: CommandSpec = serde_yaml::from_str(
+            "{name: x, pagination: {type: page, page_var: p, per_page_var: n}}",
+        )
+        .unwrap();
+        let pagination = cmd.pagination.unwrap();
+        assert_eq!(pagination.pagination_type, PaginationType::Page);
+        assert_eq!(
+            (pagination.page_var(), pagination.per_page_var()),
+            ("p", "n")
+        );
+        assert_eq!(pagination.offset_var(), "offset");
+    }
+
+    #[test]
+    fn test_validate_mapping_stream() {
+        let diags = lint(
+            r#"
+commands:
+  - name: events
+    subcommands:
+      - name: tail
+        method: GET
+        endpoint: /events
+        stream: sse
+      - name: paged
+        method: GET
+        endpoint: /events?page={page}
+        stream: sse
+        pagination: { type: page }
+        args:
+          - name: page
+            long: page
+"#,
+        );
+        assert_eq!(diags.len(), 1, "unexpected diagnostics: {:?}", diags);
+        assert_eq!(diags[0].path, "events paged");
+        assert!(diags[0]
+            .message
+            .starts_with("stream and pagination cannot be combined"));
+
+        let cmd: CommandSpec = serde_yaml::from_str("{name: x, stream: sse}").unwrap();
+        assert_eq!(cmd.stream, Some(StreamType::Sse));
+        assert!(serde_yaml::from_str::<CommandSpec>("{name: x, stream: websocket}").is_err());
+    }
+
+    #[test]
+    fn test_parse_body_fields_forms() {
+        let cmd: CommandSpec = serde_yaml::from_str(
+            r#"
+name: add
+method: POST
+endpoint: /items
+body_fields:
+  title: title
+  price: price:float
+  /meta/tags: { arg: tags, type: array }
+"#,
+        )
+        .unwrap();
+        let fields: Vec<(&str, &str, Option<&str>)> = cmd
+            .body_fields
+            .iter()
+            .map(|(k, f)| (k.as_str(), f.arg(), f.field_type()))
+            .collect();
+        assert_eq!(
+            fields,
+            vec![
+                ("title", "title", None),
+                ("price", "price", Some("float")),
+                ("/meta/tags", "tags", Some("array")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_validate_mapping_body_fields() {
+        let diags = lint(
+            r#"
+commands:
+  - name: items
+    subcommands:
+      - name: add
+        method: POST
+        endpoint: /items
+        body: '{}'
+        body_fields:
+          dims: dims:json
+          dims.width: width:decimal
+          owner: ownr
+        args:
+          - name: dims
+            long: dims
+          - name: width
+            long: width
+          - name: draft
+            type: bool
+            body_fields:
+              status: stat
+"#,
+        );
+        let messages: Vec<&str> = diags.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(diags.len(), 5, "unexpected diagnostics: {:?}", messages);
+        assert_eq!(diags[0].severity, Severity::Warning);
+        assert!(messages[0].contains("both body and body_fields"));
+        assert!(messages[1].contains("'dims.width' has unknown type 'decimal'"));
+        assert!(messages[2].contains("'owner' references 'ownr'"));
+        assert!(messages[3].contains("body_fields override of 'draft' 'status' references 'stat'"));
+        assert!(messages[4].contains("'dims.width' conflicts with 'dims'"));
+    }
+
+    #[test]
+    fn test_validate_mapping_unresolved_inherit() {
+        let diags = lint(
+            r#"
+commands:
+  - name: api
+    subcommands:
+      - name: call
+        method: GET
+        endpoint: /api
+        args:
+          - inherit: nonexistent
+            name: fallback
+"#,
+        );
+        assert_eq!(diags.len(), 1);
+        assert!(diags[0].message.contains("'nonexistent'"));
+    }
+
+    #[test]
+    fn test_validate_mapping_duplicate_paths() {
+        let diags = lint(
+            r#"
+commands:
+  - name: users
+    subcommands:
+      - name: list
+        method: GET
+        endpoint: /users
+      - pattern: "users list"
+        method: GET
+        endpoint: /v2/users
+"#,
+        );
+        assert_eq!(diags.len(), 1);
+        assert!(diags[0].message.contains("duplicate command path"));
+    }
+
+    #[test]
+    fn test_validate_mapping_flag_clashes() {
+        let diags = lint(
+            r#"
+commands:
+  - name: users
+    subcommands:
+      - name: list
+        method: GET
+        endpoint: /users
+        args:
+          - name: limit
+            long: limit
+            short: l
+          - name: level
+            long: level
+            short: l
+          - name: max
+            long: limit
+          - name: host
+            long: host
+            short: h
+          - name: term
+            long: query
+          - name: raw-value
+            positional: true
+"#,
+        );
+        let messages: Vec<&str> = diags.iter().map(|d| d.message.as_str()).collect();
+        assert_eq!(messages.len(), 4, "{:?}", messages);
+        assert!(messages[0].contains("-l is used by both 'limit' and 'level'"));
+        assert!(messages[1].contains("--limit is defined by more than one argument"));
+        assert!(messages[2].contains("built-in help flag"));
+        assert!(messages[3].contains("--query clashes with the global option"));
+    }
+
+    #[test]
+    fn test_validate_mapping_command_without_action() {
+        let diags = lint(
+            r#"
+commands:
+  - name: users
+    subcommands:
+      - name: broken
+        about: "Does nothing"
+      - name: half
+        endpoint: /users
+"#,
+        );
+        assert_eq!(diags.len(), 2);
+        assert_eq!(diags[0].path, "users broken");
+        assert!(diags[0].message.contains("neither method/endpoint"));
+        assert!(diags[1].message.contains("method is missing"));
+    }
+
+    #[test]
+    fn test_validate_mapping_scenario_extracted_vars() {
+        let diags = lint(
+            r#"
+commands:
+  - name: jobs
+    subcommands:
+      - name: run
+        scenario:
+          type: job_with_polling
+          steps:
+            - name: schedule_job
+              method: POST
+              endpoint: /jobs/{job_id}
+              extract_response:
+                job_id: "$.id"
+            - name: poll_job
+              method: GET
+              endpoint: /jobs/{job_id}
+"#,
+        );
+        // job_id is only known after the first step extracts it
+        assert_eq!(diags.len(), 1);
+        assert!(diags[0].message.contains("step 'schedule_job' endpoint"));
+    }
+
+    #[test]
+    fn test_validate_mapping_flat_inherit_is_dangling() {
+        let flat = parse_flat_spec(
+            r#"
+commands:
+  - pattern: "users get {id}"
+    method: GET
+    endpoint: /users/{id}
+    args:
+      - inherit: id
+"#,
+        )
+        .unwrap();
+        let diags = validate_mapping(&MappingRoot::Flat(flat));
+        assert!(diags
+            .iter()
+            .any(|d| d.message.contains("'id' does not match")));
+    }
+
+    #[test]
+    fn test_diagnostic_display() {
+        let d = Diagnostic::error(
+            &["users".to_string(), "list".to_string()],
+            "boom".to_string(),
+        );
+        assert_eq!(d.to_string(), "error: users list: boom");
+    }
+
+    // ==================== check_arg_value tests ====================
+
+    #[test]
+    fn test_check_arg_value_numbers() {
+        let int = ArgSpec {
+            arg_type: Some("int".to_string()),
+            min: Some(0.0),
+            max: Some(10.0),
+            ..Default::default()
+        };
+        assert!(check_arg_value(&int, "5").is_ok());
+        assert_eq!(
+            check_arg_value(&int, "1.5").unwrap_err(),
+            "expected an integer"
+        );
+        assert_eq!(
+            check_arg_value(&int, "-1").unwrap_err(),
+            "must be at least 0"
+        );
+        assert_eq!(
+            check_arg_value(&int, "11").unwrap_err(),
+            "must be at most 10"
+        );
+
+        let float = ArgSpec {
+            arg_type: Some("float".to_string()),
+            max: Some(0.5),
+            ..Default::default()
+        };
+        assert!(check_arg_value(&float, "0.25").is_ok());
+        assert_eq!(
+            check_arg_value(&float, "NaN").unwrap_err(),
+            "expected a number"
+        );
+        assert!(check_arg_value(&float, "0.75").is_err());
+
+        let positive = ArgSpec {
+            arg_type: Some("float".to_string()),
+            min: Some(0.0),
+            exclusive_min: true,
+            max: Some(1.0),
+            exclusive_max: true,
+            ..Default::default()
+        };
+        assert!(check_arg_value(&positive, "0.5").is_ok());
+        assert_eq!(
+            check_arg_value(&positive, "0").unwrap_err(),
+            "must be greater than 0"
+        );
+        assert_eq!(
+            check_arg_value(&positive, "1").unwrap_err(),
+            "must be less than 1"
+        );
+    }
+
+    #[test]
+    fn test_check_arg_value_choices_and_regex() {
+        let spec = ArgSpec {
+            arg_type: Some("enum".to_string()),
+            choices: Some(vec!["asc".to_string(), "desc".to_string()]),
+            ..Default::default()
+        };
+        assert!(check_arg_value(&spec, "asc").is_ok());
+        assert_eq!(
+            check_arg_value(&spec, "up").unwrap_err(),
+            "must be one of: asc, desc"
+        );
+
+        let spec = ArgSpec {
+            regex: Some("^[a-z]+$".to_string()),
+            ..Default::default()
+        };
+        assert!(check_arg_value(&spec, "abc").is_ok());
+        assert_eq!(
+            check_arg_value(&spec, "ABC").unwrap_err(),
+            "must match pattern '^[a-z]+$'"
+        );
+        // Untyped args accept anything
+        assert!(check_arg_value(&ArgSpec::default(), "anything").is_ok());
+    }
+
+    #[test]
+    fn test_validate_mapping_arg_types() {
+        let yaml = r#"
+commands:
+  - name: items
+    subcommands:
+      - name: list
+        method: GET
+        endpoint: /items?a={a}&b={b}&c={c}&d={d}&e={e}
+        args:
+          - name: a
+            type: enum
+          - name: b
+            regex: "(unclosed"
+          - name: c
+            type: int
+            min: 10
+            max: 1
+          - name: d
+            type: int
+            default: "ten"
+          - name: e
+            type: integer
+            min: 1
+"#;
+        let messages: Vec<String> = validate_mapping(&parse_mapping_root(yaml).unwrap())
+            .into_iter()
+            .map(|d| d.to_string())
+            .collect();
+        assert_eq!(messages.len(), 6, "{:?}", messages);
+        assert!(messages[0].contains("'a' has type enum but no choices"));
+        assert!(messages[1].contains("'b' has an invalid regex"));
+        assert!(messages[2].contains("'c' has min 10 greater than max 1"));
+        assert!(messages[3].contains("default 'ten' of 'd' is rejected: expected an integer"));
+        assert!(messages[4].starts_with("warning: items list: 'e' has unknown type 'integer'"));
+        assert!(messages[5].contains("'e' sets min/max but is not of type int or float"));
+    }
+
+    #[test]
+    fn test_parse_openapi_arg_types_flag() {
+        let yaml = "openapi_arg_types: true\ncommands: []\n";
+        assert!(parse_mapping_root(yaml).unwrap().openapi_arg_types());
+        let yaml = "commands: []\n";
+        assert!(!parse_mapping_root(yaml).unwrap().openapi_arg_types());
+    }
+
+    #[test]
+    fn test_parse_strict_templates_flag() {
+        let yaml = "strict_templates: true\ncommands: []\n";
+        assert!(parse_mapping_root(yaml).unwrap().strict_templates());
+        let yaml = "commands: []\n";
+        assert!(!parse_mapping_root(yaml).unwrap().strict_templates());
+    }
+
+    // ==================== merge_arg_specs tests ====================
+
+    #[test]
+    fn test_merge_arg_specs_override_wins() {
+        let base = ArgSpec {
+            name: Some("limit".to_string()),
+            default: Some("10".to_string()),
+            help: Some("Base help".to_string()),
+            ..Default::default()
+        };
+        let over = ArgSpec {
+            inherit: Some("limit".to_string()),
+            default: Some("50".to_string()),
+            ..Default::default()
+        };
+        let merged = merge_arg_specs(&base, &over);
+        assert_eq!(merged.name, Some("limit".to_string()));
+        assert_eq!(merged.default, Some("50".to_string()));
+        assert_eq!(merged.help, Some("Base help".to_string()));
+        assert!(merged.inherit.is_none());
+    }
+
+    // ==================== generate_from_openapi tests ====================
+
+    const GEN_SPEC: &str = r#"
+openapi: 3.0.3
+info:
+  title: Shop
+  version: 1.0.0
+tags:
+  - name: Products
+    description: Product catalog
+paths:
+  /products:
+    get:
+      tags: [Products]
+      operationId: listProducts
+      summary: List products
+      parameters:
+        - in: query
+          name: pageSize
+          description: Items per page
+          schema:
+            type: integer
+            default: 30
+      responses:
+        '200':
+          description: ok
+          content:
+            application/json:
+              schema:
+                type: object
+                properties:
+                  total:
+                    type: integer
+                  products:
+                    type: array
+                    items:
+                      $ref: '#/components/schemas/Product'
+    post:
+      tags: [Products]
+      operationId: createProduct
+      requestBody:
+        content:
+          application/json:
+            schema:
+              type: object
+              required: [title]
+              properties:
+                title:
+                  type: string
+                price:
+                  type: number
+      responses:
+        '201':
+          description: created
+  /products/{productId}:
+    get:
+      tags: [Products]
+      operationId: getProduct
+      parameters:
+        - in: path
+          name: productId
+          required: true
+          schema:
+            type: integer
+      responses:
+        '200':
+          description: ok
+  /health:
+    get:
+      responses:
+        '200':
+          description: ok
+components:
+  schemas:
+    Product:
+      type: object
+      properties:
+        id:
+          type: integer
+        title:
+          type: string
+        tags:
+          type: array
+          items:
+            type: string
+        price:
+          type: number
+"#;
+
+    fn generated() -> HierSpec {
+        let api = crate::parse_openapi(GEN_SPEC).unwrap();
+        generate_from_openapi(&api, &GenerateOptions::default())
+    }
+
+    fn generated_command<'a>(spec: &'a HierSpec, group: &str, name: &str) -> &'a CommandSpec {
+        let group = spec.commands.iter().find(|g| g.name == group).unwrap();
+        group
+            .subcommands
+            .iter()
+            .find_map(|n| match n {
+                CommandNode::Command(c) if c.name.as_deref() == Some(name) => Some(c),
+                _ => None,
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn test_generate_groups_by_tag() {
+        let spec = generated();
+        let names: Vec<&str> = spec.commands.iter().map(|g| g.name.as_str()).collect();
+        assert_eq!(names, vec!["products", "health"]);
+        assert_eq!(spec.commands[0].about.as_deref(), Some("Product catalog"));
+        assert_eq!(spec.commands[0].subcommands.len(), 3);
+        // No operationId: named after method and path
+        generated_command(&spec, "health", "get-health");
+    }
+
+    #[test]
+    fn test_generate_list_command() {
+        let spec = generated();
+        let cmd = generated_command(&spec, "products", "list-products");
+        assert_eq!(cmd.about.as_deref(), Some("List products"));
+        assert_eq!(cmd.method.as_deref(), Some("GET"));
+        assert_eq!(cmd.endpoint.as_deref(), Some("/products"));
+        assert_eq!(
+            cmd.query.get("pageSize").map(String::as_str),
+            Some("{page_size}")
+        );
+        let arg = &cmd.args[0];
+        assert_eq!(arg.name.as_deref(), Some("page_size"));
+        assert_eq!(arg.long.as_deref(), Some("page-size"));
+        assert_eq!(arg.help.as_deref(), Some("Items per page"));
+        assert_eq!(arg.required, Some(false));
+        assert_eq!(arg.default.as_deref(), Some("30"));
+        // Array-valued properties are left out of the table
+        assert_eq!(
+            cmd.table_view,
+            Some(vec![
+                "id".to_string(),
+                "title".to_string(),
+                "price".to_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn test_generate_path_params_and_body() {
+        let spec = generated();
+        let get = generated_command(&spec, "products", "get-product");
+        assert_eq!(get.endpoint.as_deref(), Some("/products/{product_id}"));
+        assert_eq!(get.args[0].positional, Some(true));
+        assert_eq!(get.args[0].required, Some(true));
+
+        let create = generated_command(&spec, "products", "create-product");
+        assert_eq!(
+            create.body.as_deref(),
+            Some(r#"{"title": "{title}", "price": {price}}"#)
+        );
+        assert_eq!(
+            create.headers.get("Content-Type").map(String::as_str),
+            Some("application/json")
+        );
+        assert_eq!(create.args[0].required, Some(true));
+        assert_eq!(create.args[1].required, Some(false));
+        assert_eq!(create.args[1].default.as_deref(), Some("null"));
+    }
+
+    #[test]
+    fn test_generate_renames_reserved_flags() {
+        let api = crate::parse_openapi(
+            r#"
+openapi: 3.0.0
+info:
+  title: Search
+  version: "1"
+paths:
+  /search:
+    post:
+      operationId: search
+      parameters:
+        - in: query
+          name: query
+          schema:
+            type: string
+      requestBody:
+        content:
+          application/json:
+            schema:
+              type: object
+              properties:
+                help:
+                  type: string
+                raw_value:
+                  type: string
+      responses:
+        '200':
+          description: ok
+"#,
+        )
+        .unwrap();
+        let spec = generate_from_openapi(&api, &GenerateOptions::default());
+        let cmd = generated_command(&spec, "search", "search");
+        let longs: Vec<&str> = cmd.args.iter().filter_map(|a| a.long.as_deref()).collect();
+        assert_eq!(longs, vec!["query-2", "help-2", "raw-value-2"]);
+        assert_eq!(
+            cmd.query.get("query").map(String::as_str),
+            Some("{query_2}")
+        );
+        assert_eq!(
+            cmd.body.as_deref(),
+            Some(r#"{"help": "{help_2}", "raw_value": "{raw_value_2}"}"#)
+        );
+        let root = parse_mapping_root(&serde_yaml::to_string(&spec).unwrap()).unwrap();
+        assert!(validate_mapping(&root).is_empty());
+    }
+
+    #[test]
+    fn test_generated_mapping_round_trips() {
+        let yaml = serde_yaml::to_string(&generated()).unwrap();
+        assert!(
+            !yaml.contains(": null\n"),
+            "empty fields are serialized:\n{}",
+            yaml
+        );
+        let root = parse_mapping_root(&yaml).unwrap();
+        assert!(validate_mapping(&root).is_empty());
+        let api = crate::parse_openapi(GEN_SPEC).unwrap();
+        assert!(crate::openapi::check_mapping_against_openapi(&root, &api).is_empty());
+    }
+
+    #[test]
+    fn test_to_snake_and_kebab_case() {
+        assert_eq!(to_snake_case("getProductById"), "get_product_by_id");
+        assert_eq!(to_snake_case("user-id"), "user_id");
+        assert_eq!(to_snake_case("page[size]"), "page_size");
+        assert_eq!(to_snake_case("2fa"), "_2fa");
+        assert_eq!(to_kebab_case("Product Reviews"), "product-reviews");
+    }
 }
@@ -0,0 +1,1063 @@
+use std::collections::HashSet;
+
+use indexmap::IndexMap;
+use openapiv3::{
+    MediaType, OpenAPI, Operation, Parameter, ParameterSchemaOrContent, PathItem, ReferenceOr,
+    RequestBody, Response, Schema, SchemaKind, StatusCode, Type,
+};
+use regex::Regex;
+
+use crate::mapping::{ArgSpec, CommandSpec, Diagnostic, MappingRoot, ResolvedCommand};
+
+// =====================
+// Operation lookup
+// =====================
+
+/// An operation of the OpenAPI document matched by a mapping endpoint.
+#[derive(Debug, Clone, Copy)]
+pub struct OperationRef<'a> {
+    /// Path template as written in the spec, e.g. "/products/{id}"
+    pub path: &'a str,
+    pub item: &'a PathItem,
+    pub operation: &'a Operation,
+}
+
+/// Split an endpoint template into its path and optional query string.
+pub fn split_endpoint(endpoint: &str) -> (&str, Option<&str>) {
+    match endpoint.split_once('?') {
+        Some((path, query)) => (path, Some(query)),
+        None => (endpoint, None),
+    }
+}
+
+/// Find the spec path matching a mapping endpoint path (query string already removed).
+///
+/// Mapping placeholders and literals both match spec path parameters; when several
+/// spec paths match, the one sharing the most literal segments wins, so
+/// `/products/categories` prefers a literal spec path over `/products/{id}`.
+pub fn match_path<'a>(api: &'a OpenAPI, endpoint_path: &str) -> Option<(&'a str, &'a PathItem)> {
+    let segments: Vec<&str> = endpoint_path.trim_end_matches('/').split('/').collect();
+    let mut best: Option<(usize, &'a str, &'a PathItem)> = None;
+    for (spec_path, item) in api.paths.iter() {
+        let Some(item) = item.as_item() else {
+            continue;
+        };
+        let spec_segments: Vec<&str> = spec_path.trim_end_matches('/').split('/').collect();
+        if spec_segments.len() != segments.len() {
+            continue;
+        }
+        let mut score = 0;
+        let mut matched = true;
+        for (spec_seg, seg) in spec_segments.iter().zip(&segments) {
+            if spec_seg == seg {
+                if !spec_seg.contains('{') {
+                    score += 1;
+                }
+            } else if !segment_matches(spec_seg, seg) {
+                matched = false;
+                break;
+            }
+        }
+        if matched && best.is_none_or(|(s, _, _)| score > s) {
+            best = Some((score, spec_path.as_str(), item));
+        }
+    }
+    best.map(|(_, path, item)| (path, item))
+}
+
+/// Whether a mapping segment can fill a spec segment that contains path parameters.
+fn segment_matches(spec_seg: &str, seg: &str) -> bool {
+    static PARAM_RE: once_cell::sync::Lazy<Regex> =
+        once_cell::sync::Lazy::new(|| Regex::new(r"\{[^}]+\}").expect("valid regex"));
+    if !spec_seg.contains('{') {
+        return false;
+    }
+    let mut pattern = String::from("^");
+    let mut last = 0;
+    for m in PARAM_RE.find_iter(spec_seg) {
+        pattern.push_str(&regex::escape(&spec_seg[last..m.start()]));
+        pattern.push_str(".+");
+        last = m.end();
+    }
+    pattern.push_str(&regex::escape(&spec_seg[last..]));
+    pattern.push('$');
+    // Placeholders stand for any non-empty value
+    let sample = PARAM_RE.replace_all(seg, "x");
+    Regex::new(&pattern).is_ok_and(|re| re.is_match(&sample))
+}
+
+/// Query `(key, value template)` pairs of a mapping request: those written inline in the
+/// endpoint followed by the entries of its `query:` map.
+pub fn query_pairs(endpoint: &str, query: &IndexMap<String, String>) -> Vec<(String, String)> {
+    let (_, inline) = split_endpoint(endpoint);
+    let mut pairs: Vec<(String, String)> = inline
+        .unwrap_or("")
+        .split('&')
+        .filter(|p| !p.is_empty())
+        .map(|p| {
+            let (k, v) = p.split_once('=').unwrap_or((p, ""));
+            (k.to_string(), v.to_string())
+        })
+        .collect();
+    pairs.extend(query.iter().map(|(k, v)| (k.clone(), v.clone())));
+    pairs
+}
+
+/// Operation declared on a path item for an HTTP method (case-insensitive).
+pub fn operation_for<'a>(item: &'a PathItem, method: &str) -> Option<&'a Operation> {
+    let method = method.to_lowercase();
+    item.iter().find(|(m, _)| *m == method).map(|(_, op)| op)
+}
+
+/// Find the operation a mapping `method` + `endpoint` template refers to.
+pub fn find_operation<'a>(
+    api: &'a OpenAPI,
+    method: &str,
+    endpoint: &str,
+) -> Option<OperationRef<'a>> {
+    let (path, _) = split_endpoint(endpoint);
+    let (spec_path, item) = match_path(api, path)?;
+    let operation = operation_for(item, method)?;
+    Some(OperationRef {
+        path: spec_path,
+        item,
+        operation,
+    })
+}
+
+/// Resolve a parameter, following `#/components/parameters/...` references.
+pub fn resolve_parameter<'a>(
+    api: &'a OpenAPI,
+    param: &'a ReferenceOr<Parameter>,
+) -> Option<&'a Parameter> {
+    match param {
+        ReferenceOr::Item(p) => Some(p),
+        ReferenceOr::Reference { reference } => {
+            let name = reference.strip_prefix("#/components/parameters/")?;
+            let target = api.components.as_ref()?.parameters.get(name)?;
+            resolve_parameter(api, target)
+        }
+    }
+}
+
+/// Effective parameters of an operation: path-level ones overridden by operation-level ones.
+pub fn operation_parameters<'a>(api: &'a OpenAPI, op: &OperationRef<'a>) -> Vec<&'a Parameter> {
+    let own: Vec<&'a Parameter> = op
+        .operation
+        .parameters
+        .iter()
+        .filter_map(|p| resolve_parameter(api, p))
+        .collect();
+    let mut params: Vec<&'a Parameter> = op
+        .item
+        .parameters
+        .iter()
+        .filter_map(|p| resolve_parameter(api, p))
+        .filter(|p| {
+            !own.iter().any(|o| {
+                o.parameter_data_ref().name == p.parameter_data_ref().name
+                    && std::mem::discriminant(*o) == std::mem::discriminant(*p)
+            })
+        })
+        .collect();
+    params.extend(own);
+    params
+}
+
+// =====================
+// Schema helpers
+// =====================
+
+/// Resolve a schema, following `#/components/schemas/...` references.
+pub fn resolve_schema<'a>(api: &'a OpenAPI, schema: &'a ReferenceOr<Schema>) -> Option<&'a Schema> {
+    match schema {
+        ReferenceOr::Item(s) => Some(s),
+        ReferenceOr::Reference { reference } => resolve_schema_ref(api, reference),
+    }
+}
+
+/// Same as [`resolve_schema`] for the boxed schemas used by properties and array items.
+pub fn resolve_boxed_schema<'a>(
+    api: &'a OpenAPI,
+    schema: &'a ReferenceOr<Box<Schema>>,
+) -> Option<&'a Schema> {
+    match schema {
+        ReferenceOr::Item(s) => Some(s),
+        ReferenceOr::Reference { reference } => resolve_schema_ref(api, reference),
+    }
+}
+
+fn resolve_schema_ref<'a>(api: &'a OpenAPI, reference: &str) -> Option<&'a Schema> {
+    let name = reference.strip_prefix("#/components/schemas/")?;
+    let target = api.components.as_ref()?.schemas.get(name)?;
+    resolve_schema(api, target)
+}
+
+/// Schema of a parameter declared with `schema:` (parameters using `content:` have none).
+pub fn parameter_schema<'a>(api: &'a OpenAPI, param: &'a Parameter) -> Option<&'a Schema> {
+    match &param.parameter_data_ref().format {
+        ParameterSchemaOrContent::Schema(schema) => resolve_schema(api, schema),
+        ParameterSchemaOrContent::Content(_) => None,
+    }
+}
+
+/// JSON media type of a content map (`application/json` or any `+json` type).
+pub fn json_media_type<'a, I>(content: I) -> Option<&'a MediaType>
+where
+    I: IntoIterator<Item = (&'a String, &'a MediaType)>,
+{
+    content
+        .into_iter()
+        .find(|(ct, _)| {
+            let ct = ct.split(';').next().unwrap_or("").trim();
+            ct == "application/json" || ct.ends_with("+json")
+        })
+        .map(|(_, media)| media)
+}
+
+/// Schema of an operation's JSON request body, if it has one.
+pub fn request_body_schema<'a>(api: &'a OpenAPI, op: &'a Operation) -> Option<&'a Schema> {
+    let body: &RequestBody = match op.request_body.as_ref()? {
+        ReferenceOr::Item(b) => b,
+        ReferenceOr::Reference { reference } => {
+            let name = reference.strip_prefix("#/components/requestBodies/")?;
+            api.components
+                .as_ref()?
+                .request_bodies
+                .get(name)?
+                .as_item()?
+        }
+    };
+    resolve_schema(api, json_media_type(&body.content)?.schema.as_ref()?)
+}
+
+/// Schema of an operation's first JSON success (2xx) response, if it has one.
+pub fn success_response_schema<'a>(api: &'a OpenAPI, op: &'a Operation) -> Option<&'a Schema> {
+    let (_, response) = op.responses.responses.iter().find(|(code, _)| match code {
+        StatusCode::Code(n) => (200..300).contains(n),
+        StatusCode::Range(n) => *n == 2,
+    })?;
+    let response: &Response = match response {
+        ReferenceOr::Item(r) => r,
+        ReferenceOr::Reference { reference } => {
+            let name = reference.strip_prefix("#/components/responses/")?;
+            api.components.as_ref()?.responses.get(name)?.as_item()?
+        }
+    };
+    resolve_schema(api, json_media_type(&response.content)?.schema.as_ref()?)
+}
+
+/// Item schema of an array schema.
+pub fn array_items<'a>(api: &'a OpenAPI, schema: &'a Schema) -> Option<&'a Schema> {
+    match &schema.schema_kind {
+        SchemaKind::Type(Type::Array(arr)) => resolve_boxed_schema(api, arr.items.as_ref()?),
+        _ => None,
+    }
+}
+
+/// JSON type name of a schema ("string", "integer", "number", "boolean", "object", "array").
+pub fn schema_type_name(schema: &Schema) -> Option<&'static str> {
+    match &schema.schema_kind {
+        SchemaKind::Type(Type::String(_)) => Some("string"),
+        SchemaKind::Type(Type::Integer(_)) => Some("integer"),
+        SchemaKind::Type(Type::Number(_)) => Some("number"),
+        SchemaKind::Type(Type::Boolean {}) => Some("boolean"),
+        SchemaKind::Type(Type::Object(_)) => Some("object"),
+        SchemaKind::Type(Type::Array(_)) => Some("array"),
+        SchemaKind::Any(any) => match any.typ.as_deref()? {
+            "string" => Some("string"),
+            "integer" => Some("integer"),
+            "number" => Some("number"),
+            "boolean" => Some("boolean"),
+            "object" => Some("object"),
+            "array" => Some("array"),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+// =====================
+// Arg constraints from parameter schemas
+// =====================
+
+/// Arg type and constraints implied by a parameter schema (only those fields are set).
+pub fn constraints_from_schema(schema: &Schema) -> ArgSpec {
+    fn choices<T: ToString>(values: impl IntoIterator<Item = Option<T>>) -> Option<Vec<String>> {
+        let v: Vec<String> = values
+            .into_iter()
+            .flatten()
+            .map(|x| x.to_string())
+            .collect();
+        (!v.is_empty()).then_some(v)
+    }
+    let mut c = ArgSpec::default();
+    match &schema.schema_kind {
+        SchemaKind::Type(Type::Integer(t)) => {
+            c.choices = choices(t.enumeration.iter().copied());
+            // Exclusive integer bounds become the next inclusive value
+            c.min = t
+                .minimum
+                .map(|m| if t.exclusive_minimum { m + 1 } else { m } as f64);
+            c.max = t
+                .maximum
+                .map(|m| if t.exclusive_maximum { m - 1 } else { m } as f64);
+            c.arg_type = Some("int".to_string());
+        }
+        SchemaKind::Type(Type::Number(t)) => {
+            c.choices = choices(t.enumeration.iter().copied());
+            c.min = t.minimum;
+            c.max = t.maximum;
+            c.exclusive_min = t.exclusive_minimum && t.minimum.is_some();
+            c.exclusive_max = t.exclusive_maximum && t.maximum.is_some();
+            c.arg_type = Some("float".to_string());
+        }
+        SchemaKind::Type(Type::String(t)) => {
+            c.choices = choices(t.enumeration.iter().cloned());
+            c.regex = t.pattern.clone();
+        }
+        SchemaKind::Type(Type::Boolean {}) => {
+            c.choices = Some(vec!["true".to_string(), "false".to_string()]);
+        }
+        SchemaKind::Any(any) => {
+            c.choices = choices(any.enumeration.iter().map(|v| match v {
+                serde_json::Value::String(s) => Some(s.clone()),
+                serde_json::Value::Null => None,
+                other => Some(other.to_string()),
+            }));
+            c.regex = any.pattern.clone();
+            match any.typ.as_deref() {
+                Some("integer") => c.arg_type = Some("int".to_string()),
+                Some("number") => c.arg_type = Some("float".to_string()),
+                _ => {}
+            }
+            if c.arg_type.is_some() {
+                c.min = any.minimum;
+                c.max = any.maximum;
+                c.exclusive_min = any.exclusive_minimum == Some(true) && any.minimum.is_some();
+                c.exclusive_max = any.exclusive_maximum == Some(true) && any.maximum.is_some();
+            }
+        }
+        _ => {}
+    }
+    if c.choices.is_some() {
+        c.arg_type = Some("enum".to_string());
+        c.min = None;
+        c.max = None;
+        c.exclusive_min = false;
+        c.exclusive_max = false;
+    }
+    c
+}
+
+/// Fill in type and constraints of the args that feed the operation's path and query
+/// parameters, for `openapi_arg_types: true`.
+///
+/// Only args bound to a whole path segment (`/items/{id}`) or query value (`?limit={limit}`
+/// or `query: {limit: "{limit}"}`) are touched, and anything set explicitly in the mapping
+/// wins over the spec.
+pub fn apply_openapi_arg_types(api: &OpenAPI, cmd: &CommandSpec, args: &mut [ArgSpec]) {
+    let (Some(method), Some(endpoint)) = (&cmd.method, &cmd.endpoint) else {
+        return;
+    };
+    let Some(op) = find_operation(api, method, endpoint) else {
+        return;
+    };
+    let (path, _) = split_endpoint(endpoint);
+    let segments: Vec<&str> = path.trim_end_matches('/').split('/').collect();
+    let spec_segments: Vec<&str> = op.path.trim_end_matches('/').split('/').collect();
+    let query = query_pairs(endpoint, &cmd.query);
+    let whole_placeholder = |token: &str| {
+        crate::mapping::is_placeholder(token)
+            .then(|| token[1..token.len() - 1].to_string())
+            .filter(|v| crate::template_placeholders(token) == [v.clone()])
+    };
+
+    for param in operation_parameters(api, &op) {
+        let data = param.parameter_data_ref();
+        let var = match param {
+            Parameter::Path { .. } => {
+                let marker = format!("{{{}}}", data.name);
+                spec_segments
+                    .iter()
+                    .position(|s| *s == marker)
+                    .and_then(|idx| segments.get(idx))
+                    .and_then(|seg| whole_placeholder(seg))
+            }
+            Parameter::Query { .. } => query
+                .iter()
+                .find(|(k, _)| *k == data.name)
+                .and_then(|(_, v)| whole_placeholder(v)),
+            _ => None,
+        };
+        let (Some(var), Some(schema)) = (var, parameter_schema(api, param)) else {
+            continue;
+        };
+        let derived = constraints_from_schema(schema);
+        for arg in args.iter_mut() {
+            if arg.name.as_deref() != Some(var.as_str())
+                || matches!(arg.arg_type.as_deref(), Some("bool" | "file"))
+            {
+                continue;
+            }
+            if arg.arg_type.is_none() {
+                arg.arg_type = derived.arg_type.clone();
+            }
+            if arg.choices.is_none() {
+                arg.choices = derived.choices.clone();
+            }
+            if arg.regex.is_none() {
+                arg.regex = derived.regex.clone();
+            }
+            if arg.min.is_none() {
+                arg.min = derived.min;
+                arg.exclusive_min = derived.exclusive_min;
+            }
+            if arg.max.is_none() {
+                arg.max = derived.max;
+                arg.exclusive_max = derived.exclusive_max;
+            }
+        }
+    }
+}
+
+// =====================
+// Mapping vs. spec check
+// =====================
+
+/// Cross-check every command and scenario step of a mapping against an OpenAPI document.
+///
+/// Reports operations missing from the spec, methods the path does not allow, spec path
+/// parameters that may be left empty, and required query parameters the endpoint never sets.
+pub fn check_mapping_against_openapi(root: &MappingRoot, api: &OpenAPI) -> Vec<Diagnostic> {
+    let mut diags: Vec<Diagnostic> = Vec::new();
+    if let Some(scheme) = root.auth_settings().default_scheme {
+        check_auth_scheme(api, &[], "auth.default_scheme", &scheme, &mut diags);
+    }
+    for entry in crate::mapping::collect_commands(root) {
+        let cmd = entry.spec;
+        if cmd.custom_handler.is_some() {
+            continue;
+        }
+        if let Some(scheme) = cmd.auth.as_deref().filter(|s| *s != "none") {
+            check_auth_scheme(api, &entry.path, "auth", scheme, &mut diags);
+        }
+        let provided = guaranteed_vars(&entry.args);
+
+        if let Some(scenario) = &cmd.scenario {
+            let mut known = provided;
+            for step in &scenario.steps {
+                let target = Target {
+                    field: format!("step '{}'", step.name),
+                    method: &step.method,
+                    endpoint: &step.endpoint,
+                    query: query_pairs(&step.endpoint, &IndexMap::new()),
+                };
+                check_operation(api, &entry, &target, &known, &mut diags);
+                known.extend(step.extract_response.keys().cloned());
+            }
+            continue;
+        }
+
+        if let (Some(method), Some(endpoint)) = (&cmd.method, &cmd.endpoint) {
+            let target = Target {
+                field: "endpoint".to_string(),
+                method,
+                endpoint,
+                query: query_pairs(endpoint, &cmd.query),
+            };
+            check_operation(api, &entry, &target, &provided, &mut diags);
+        }
+        for arg in entry
+            .args
+            .iter()
+            .filter(|a| a.endpoint.is_some() || a.method.is_some() || a.query.is_some())
+        {
+            let arg_name = arg.name.clone().unwrap_or_default();
+            let (Some(method), Some(endpoint)) = (
+                arg.method.as_ref().or(cmd.method.as_ref()),
+                arg.endpoint.as_ref().or(cmd.endpoint.as_ref()),
+            ) else {
+                continue;
+            };
+            let mut query = cmd.query.clone();
+            query.extend(arg.query.clone().unwrap_or_default());
+            let target = Target {
+                field: format!("override of '{}'", arg_name),
+                method,
+                endpoint,
+                query: query_pairs(endpoint, &query),
+            };
+            // The overriding arg is known to be set whenever its override applies
+            let mut known = provided.clone();
+            known.insert(arg_name);
+            check_operation(api, &entry, &target, &known, &mut diags);
+        }
+    }
+    diags
+}
+
+/// Report an `auth:` scheme name the spec does not define or rclib cannot apply.
+fn check_auth_scheme(
+    api: &OpenAPI,
+    path: &[String],
+    field: &str,
+    scheme: &str,
+    diags: &mut Vec<Diagnostic>,
+) {
+    match crate::auth::security_scheme(api, scheme) {
+        None => diags.push(Diagnostic::error(
+            path,
+            format!(
+                "{}: security scheme '{}' is not defined in components.securitySchemes",
+                field, scheme
+            ),
+        )),
+        Some(s) if crate::auth::AuthScheme::from_openapi(s).is_none() => {
+            diags.push(Diagnostic::warning(
+                path,
+                format!(
+                    "{}: security scheme '{}' is of a kind rclib cannot apply",
+                    field, scheme
+                ),
+            ))
+        }
+        Some(_) => {}
+    }
+}
+
+/// Variables that always have a value when a command runs.
+fn guaranteed_vars(args: &[ArgSpec]) -> HashSet<String> {
+    let mut vars: HashSet<String> = HashSet::new();
+    vars.insert("uuid".to_string());
+    for arg in args {
+        let always_set = arg.required.unwrap_or(false)
+            || arg.default.is_some()
+            || arg.arg_type.as_deref() == Some("bool");
+        if always_set {
+            vars.extend(arg.name.clone());
+        }
+    }
+    vars
+}
+
+/// One request of a command to look up in the spec.
+struct Target<'a> {
+    /// Which part of the command the request comes from, e.g. "endpoint" or "step 'poll'"
+    field: String,
+    method: &'a str,
+    endpoint: &'a str,
+    /// Query `(key, value template)` pairs, see [`query_pairs`]
+    query: Vec<(String, String)>,
+}
+
+fn check_operation(
+    api: &OpenAPI,
+    entry: &ResolvedCommand<'_>,
+    target: &Target<'_>,
+    provided: &HashSet<String>,
+    diags: &mut Vec<Diagnostic>,
+) {
+    let (field, method, endpoint) = (target.field.as_str(), target.method, target.endpoint);
+    if endpoint.starts_with("http://") || endpoint.starts_with("https://") {
+        // Absolute URLs point outside the documented API
+        return;
+    }
+    let (path, _) = split_endpoint(endpoint);
+    let Some((spec_path, item)) = match_path(api, path) else {
+        diags.push(Diagnostic::error(
+            &entry.path,
+            format!(
+                "{}: {} {} is not defined in the OpenAPI document",
+                field,
+                method.to_uppercase(),
+                path
+            ),
+        ));
+        return;
+    };
+    let Some(operation) = operation_for(item, method) else {
+        let allowed: Vec<String> = item.iter().map(|(m, _)| m.to_uppercase()).collect();
+        diags.push(Diagnostic::error(
+            &entry.path,
+            format!(
+                "{}: {} is not allowed on {} (spec allows: {})",
+                field,
+                method.to_uppercase(),
+                spec_path,
+                allowed.join(", ")
+            ),
+        ));
+        return;
+    };
+    let op = OperationRef {
+        path: spec_path,
+        item,
+        operation,
+    };
+
+    let segments: Vec<&str> = path.trim_end_matches('/').split('/').collect();
+    let spec_segments: Vec<&str> = spec_path.trim_end_matches('/').split('/').collect();
+
+    for param in operation_parameters(api, &op) {
+        let data = param.parameter_data_ref();
+        match param {
+            Parameter::Path { .. } => {
+                let marker = format!("{{{}}}", data.name);
+                let Some(idx) = spec_segments.iter().position(|s| s.contains(&marker)) else {
+                    continue;
+                };
+                let missing = unguaranteed(segments.get(idx).copied().unwrap_or(""), provided);
+                if let Some(var) = missing {
+                    diags.push(Diagnostic::error(
+                        &entry.path,
+                        format!(
+                            "{}: path parameter '{}' of {} comes from {{{}}}, which is optional and has no default",
+                            field, data.name, spec_path, var
+                        ),
+                    ));
+                }
+            }
+            Parameter::Query { .. } if data.required => {
+                match target.query.iter().find(|(k, _)| *k == data.name) {
+                    None => diags.push(Diagnostic::error(
+                        &entry.path,
+                        format!(
+                            "{}: required query parameter '{}' of {} {} is never set",
+                            field,
+                            data.name,
+                            method.to_uppercase(),
+                            spec_path
+                        ),
+                    )),
+                    Some((_, value)) => {
+                        if let Some(var) = unguaranteed(value, provided) {
+                            diags.push(Diagnostic::error(
+                                &entry.path,
+                                format!(
+                                    "{}: required query parameter '{}' comes from {{{}}}, which is optional and has no default",
+                                    field, data.name, var
+                                ),
+                            ));
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    let declared: HashSet<&str> = operation_parameters(api, &op)
+        .into_iter()
+        .filter(|p| matches!(p, Parameter::Query { .. }))
+        .map(|p| p.parameter_data_ref().name.as_str())
+        .collect();
+    for (key, _) in &target.query {
+        if !declared.contains(key.as_str()) {
+            diags.push(Diagnostic::warning(
+                &entry.path,
+                format!(
+                    "{}: query parameter '{}' is not declared for {} {}",
+                    field,
+                    key,
+                    method.to_uppercase(),
+                    spec_path
+                ),
+            ));
+        }
+    }
+}
+
+/// First placeholder in `template` whose variable may be unset at request time.
+fn unguaranteed(template: &str, provided: &HashSet<String>) -> Option<String> {
+    crate::template_placeholders(template)
+        .into_iter()
+        .find(|v| !provided.contains(v))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mapping::{parse_mapping_root, Severity};
+
+    const SPEC: &str = r#"
+openapi: 3.0.3
+info:
+  title: Test API
+  version: 1.0.0
+paths:
+  /products:
+    get:
+      parameters:
+        - in: query
+          name: limit
+          schema:
+            type: integer
+        - in: query
+          name: category
+          required: true
+          schema:
+            type: string
+      responses:
+        '200':
+          description: ok
+  /products/{id}:
+    parameters:
+      - $ref: '#/components/parameters/ProductId'
+    get:
+      responses:
+        '200':
+          description: ok
+    delete:
+      responses:
+        '200':
+          description: ok
+  /products/categories:
+    get:
+      responses:
+        '200':
+          description: ok
+components:
+  parameters:
+    ProductId:
+      in: path
+      name: id
+      required: true
+      schema:
+        type: integer
+"#;
+
+    fn api() -> OpenAPI {
+        crate::parse_openapi(SPEC).unwrap()
+    }
+
+    fn check(yaml: &str) -> Vec<Diagnostic> {
+        check_mapping_against_openapi(&parse_mapping_root(yaml).unwrap(), &api())
+    }
+
+    // ==================== match_path tests ====================
+
+    #[test]
+    fn test_match_path_prefers_literal_segments() {
+        let api = api();
+        let (path, _) = match_path(&api, "/products/categories").unwrap();
+        assert_eq!(path, "/products/categories");
+        let (path, _) = match_path(&api, "/products/{product_id}").unwrap();
+        assert_eq!(path, "/products/{id}");
+        let (path, _) = match_path(&api, "/products/42").unwrap();
+        assert_eq!(path, "/products/{id}");
+    }
+
+    #[test]
+    fn test_match_path_unknown() {
+        assert!(match_path(&api(), "/orders").is_none());
+        assert!(match_path(&api(), "/products/1/reviews").is_none());
+    }
+
+    #[test]
+    fn test_segment_matches_partial_template() {
+        assert!(segment_matches("{name}.json", "{file}.json"));
+        assert!(segment_matches("{name}.json", "report.json"));
+        assert!(!segment_matches("{name}.json", "report.xml"));
+        assert!(!segment_matches("products", "{x}"));
+    }
+
+    #[test]
+    fn test_find_operation_resolves_parameter_refs() {
+        let api = api();
+        let op = find_operation(&api, "delete", "/products/{id}").unwrap();
+        let params = operation_parameters(&api, &op);
+        assert_eq!(params.len(), 1);
+        assert_eq!(params[0].parameter_data_ref().name, "id");
+        assert!(find_operation(&api, "PUT", "/products/{id}").is_none());
+    }
+
+    // ==================== apply_openapi_arg_types tests ====================
+
+    #[test]
+    fn test_constraints_from_schema() {
+        let schema = |yaml: &str| serde_yaml::from_str::<Schema>(yaml).unwrap();
+        let c = constraints_from_schema(&schema(
+            "type: integer\nminimum: 0\nmaximum: 10\nexclusiveMaximum: true",
+        ));
+        assert_eq!(c.arg_type.as_deref(), Some("int"));
+        assert_eq!((c.min, c.max), (Some(0.0), Some(9.0)));
+
+        // Exclusive number bounds stay as they are and reject the bound itself
+        let c = constraints_from_schema(&schema(
+            "type: number\nminimum: 0\nexclusiveMinimum: true\nmaximum: 1",
+        ));
+        assert_eq!(c.arg_type.as_deref(), Some("float"));
+        assert_eq!((c.min, c.max), (Some(0.0), Some(1.0)));
+        assert!(c.exclusive_min && !c.exclusive_max);
+        assert!(crate::mapping::check_arg_value(&c, "0").is_err());
+        assert!(crate::mapping::check_arg_value(&c, "0.1").is_ok());
+        assert!(crate::mapping::check_arg_value(&c, "1").is_ok());
+
+        let c = constraints_from_schema(&schema("type: string\nenum: [asc, desc]"));
+        assert_eq!(c.arg_type.as_deref(), Some("enum"));
+        assert_eq!(c.choices, Some(vec!["asc".to_string(), "desc".to_string()]));
+
+        let c = constraints_from_schema(&schema("type: string\npattern: '^[a-z]+$'"));
+        assert_eq!(c.arg_type, None);
+        assert_eq!(c.regex.as_deref(), Some("^[a-z]+$"));
+
+        let c = constraints_from_schema(&schema("type: boolean"));
+        assert_eq!(c.arg_type.as_deref(), Some("enum"));
+    }
+
+    #[test]
+    fn test_apply_openapi_arg_types() {
+        let root = parse_mapping_root(
+            r#"
+commands:
+  - name: products
+    subcommands:
+      - name: get
+        method: GET
+        endpoint: /products/{id}
+        args:
+          - name: id
+            positional: true
+            required: true
+      - name: list
+        method: GET
+        endpoint: /products?limit=x{limit}&category={category}
+        args:
+          - name: limit
+          - name: category
+            type: enum
+            choices: [a, b]
+"#,
+        )
+        .unwrap();
+        let api = api();
+        let mut commands = crate::mapping::collect_commands(&root);
+
+        let get = &mut commands[0];
+        apply_openapi_arg_types(&api, get.spec, &mut get.args);
+        assert_eq!(get.args[0].arg_type.as_deref(), Some("int"));
+
+        let list = &mut commands[1];
+        apply_openapi_arg_types(&api, list.spec, &mut list.args);
+        // Only whole placeholders are bound to a parameter
+        assert_eq!(list.args[0].arg_type, None);
+        // Explicit mapping settings win
+        assert_eq!(
+            list.args[1].choices,
+            Some(vec!["a".to_string(), "b".to_string()])
+        );
+    }
+
+    // ==================== check_mapping_against_openapi tests ====================
+
+    #[test]
+    fn test_check_openapi_clean() {
+        let diags = check(
+            r#"
+commands:
+  - name: products
+    subcommands:
+      - name: list
+        method: GET
+        endpoint: /products?category={category}&limit={limit}
+        args:
+          - name: category
+            required: true
+          - name: limit
+      - name: get
+        method: GET
+        endpoint: /products/{id}
+        args:
+          - name: id
+            positional: true
+            required: true
+"#,
+        );
+        assert!(diags.is_empty(), "unexpected diagnostics: {:?}", diags);
+    }
+
+    #[test]
+    fn test_check_openapi_unknown_operation_and_method() {
+        let diags = check(
+            r#"
+commands:
+  - name: orders
+    subcommands:
+      - name: list
+        method: GET
+        endpoint: /orders
+      - name: update
+        method: PUT
+        endpoint: /products/{id}
+        args:
+          - name: id
+            required: true
+"#,
+        );
+        assert_eq!(diags.len(), 2);
+        assert!(diags[0].message.contains("GET /orders is not defined"));
+        assert!(diags[1]
+            .message
+            .contains("PUT is not allowed on /products/{id} (spec allows: GET, DELETE)"));
+    }
+
+    #[test]
+    fn test_check_openapi_optional_path_param() {
+        let diags = check(
+            r#"
+commands:
+  - name: products
+    subcommands:
+      - name: get
+        method: GET
+        endpoint: /products/{id}
+        args:
+          - name: id
+            long: id
+"#,
+        );
+        assert_eq!(diags.len(), 1);
+        assert!(diags[0].message.contains("path parameter 'id'"));
+    }
+
+    #[test]
+    fn test_check_openapi_required_query_and_undeclared() {
+        let diags = check(
+            r#"
+commands:
+  - name: products
+    subcommands:
+      - name: list
+        method: GET
+        endpoint: /products?limit=10&sort={sort}
+        args:
+          - name: sort
+            default: "id"
+"#,
+        );
+        assert_eq!(diags.len(), 2);
+        assert_eq!(diags[0].severity, Severity::Error);
+        assert!(diags[0]
+            .message
+            .contains("required query parameter 'category'"));
+        assert_eq!(diags[1].severity, Severity::Warning);
+        assert!(diags[1].message.contains("'sort' is not declared"));
+    }
+
+    #[test]
+    fn test_check_openapi_query_map() {
+        let diags = check(
+            r#"
+commands:
+  - name: products
+    subcommands:
+      - name: list
+        method: GET
+        endpoint: /products
+        query:
+          category: "{category}"
+          limit: "{limit}"
+        args:
+          - name: category
+          - name: limit
+"#,
+        );
+        assert_eq!(diags.len(), 1);
+        assert!(diags[0]
+            .message
+            .contains("required query parameter 'category' comes from {category}"));
+    }
+
+    #[test]
+    fn test_check_openapi_scenario_steps_and_overrides() {
+        let diags = check(
+            r#"
+commands:
+  - name: products
+    subcommands:
+      - name: cleanup
+        scenario:
+          type: job_with_polling
+          steps:
+            - name: schedule_job
+              method: POST
+              endpoint: /products
+              extract_response:
+                id: "$.id"
+            - name: poll_job
+              method: GET
+              endpoint: /products/{id}
+      - name: list
+        method: GET
+        endpoint: /products?category=all
+        args:
+          - name: id
+            endpoint: /products/{id}
+"#,
+        );
+        assert_eq!(diags.len(), 1);
+        assert!(diags[0].message.starts_with("step 'schedule_job'"));
+    }
+
+    #[test]
+    fn test_check_openapi_auth_schemes() {
+        let api = crate::parse_openapi(
+            r#"
+openapi: 3.0.3
+info:
+  title: Test API
+  version: 1.0.0
+paths:
+  /me:
+    get:
+      responses:
+        '200':
+          description: ok
+components:
+  securitySchemes:
+    bearerAuth:
+      type: http
+      scheme: bearer
+    oidc:
+      type: openIdConnect
+      openIdConnectUrl: https://example.com/.well-known/openid-configuration
+"#,
+        )
+        .unwrap();
+        let root = parse_mapping_root(
+            r#"
+auth:
+  default_scheme: bearer
+commands:
+  - name: me
+    subcommands:
+      - name: get
+        method: GET
+        endpoint: /me
+        auth: bearerAuth
+      - name: public
+        method: GET
+        endpoint: /me
+        auth: none
+      - name: sso
+        method: GET
+        endpoint: /me
+        auth: oidc
+"#,
+        )
+        .unwrap();
+        let diags = check_mapping_against_openapi(&root, &api);
+        assert_eq!(diags.len(), 2, "unexpected diagnostics: {:?}", diags);
+        assert_eq!(diags[0].severity, Severity::Error);
+        assert_eq!(diags[0].path, "");
+        assert!(diags[0]
+            .message
+            .contains("auth.default_scheme: security scheme 'bearer' is not defined"));
+        assert_eq!(diags[1].severity, Severity::Warning);
+        assert_eq!(diags[1].path, "me sso");
+        assert!(diags[1]
+            .message
+            .contains("'oidc' is of a kind rclib cannot apply"));
+    }
+}
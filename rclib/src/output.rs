@@ -0,0 +1,562 @@
+//! Text renderings of a response for `--output` formats other than the table, and the
+//! client-side filters (`--query`, `--where`, `--sort-by`, ...) applied before any of them.
+//!
+//! CSV, TSV and NDJSON rows are the rows the table would show, with the same
+//! `table_view` columns, so exports line up with what users see in the terminal.
+
+use std::cmp::Ordering;
+
+use anyhow::{bail, Context, Result};
+use jsonpath_lib as jsonpath;
+use serde_json::{json, Map, Value};
+
+use crate::{lookup_path, scalar_to_string, table, table_columns, OutputFormat};
+
+/// Client-side shaping of a response, from `--query`, `--raw-value`, `--columns`,
+/// `--where`, `--sort-by`/`--desc` and `--limit-rows`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ResponseFilter {
+    /// JSONPath expression; the leading `$` may be left out (`products[0].id`)
+    pub query: Option<String>,
+    /// Print strings without quotes and array items one per line
+    pub raw_value: bool,
+    /// Table columns replacing the command's `table_view`
+    pub columns: Option<Vec<String>>,
+    /// Row conditions; a row is kept when all of them hold
+    pub conditions: Vec<RowCondition>,
+    /// Column path to sort rows by
+    pub sort_by: Option<String>,
+    pub descending: bool,
+    /// Keep at most this many rows
+    pub limit_rows: Option<usize>,
+}
+
+impl ResponseFilter {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// The columns to show: `--columns` if given, else the command's `table_view`.
+    pub fn table_view<'a>(
+        &'a self,
+        table_view: Option<&'a Vec<String>>,
+    ) -> Option<&'a Vec<String>> {
+        self.columns.as_ref().or(table_view)
+    }
+
+    /// Apply the query, then the row filters, sorting and limit.
+    pub fn apply(&self, value: Value) -> Result<Value> {
+        let mut value = self.select(value)?;
+        match &mut value {
+            Value::Array(items) => self.shape_rows(items),
+            // Every array field is printed as its own table
+            Value::Object(map) => {
+                for field in map.values_mut() {
+                    if let Value::Array(items) = field {
+                        self.shape_rows(items);
+                    }
+                }
+            }
+            _ => {}
+        }
+        Ok(value)
+    }
+
+    fn shape_rows(&self, items: &mut Vec<Value>) {
+        items.retain(|item| self.conditions.iter().all(|c| c.matches(item)));
+        if let Some(path) = &self.sort_by {
+            items.sort_by(|a, b| {
+                let (a, b) = (lookup_path(a, path), lookup_path(b, path));
+                // Rows without the value go last either way
+                match (a.is_null(), b.is_null()) {
+                    (true, true) => Ordering::Equal,
+                    (true, false) => Ordering::Greater,
+                    (false, true) => Ordering::Less,
+                    (false, false) if self.descending => compare_cells(&b, &a),
+                    (false, false) => compare_cells(&a, &b),
+                }
+            });
+        }
+        if let Some(limit) = self.limit_rows {
+            items.truncate(limit);
+        }
+    }
+
+    /// A path naming a single value (`$.items[0].id`) yields that value, or null when it
+    /// is missing; wildcards, slices, filters, unions and `..` yield an array of every
+    /// match.
+    fn select(&self, value: Value) -> Result<Value> {
+        let Some(query) = &self.query else {
+            return Ok(value);
+        };
+        let path = rooted_jsonpath(query);
+        let matches = jsonpath::select(&value, &path)
+            .map_err(|e| anyhow::anyhow!("{:?}", e))
+            .with_context(|| format!("invalid --query '{}'", query))?;
+        let definite = !path.contains(['*', '?', ',', ':']) && !path.contains("..");
+        Ok(if definite {
+            matches.first().map(|v| (*v).clone()).unwrap_or(Value::Null)
+        } else {
+            Value::Array(matches.into_iter().cloned().collect())
+        })
+    }
+}
+
+/// `expr` with the leading `$` added if it was left out (`products[0]`, `.total`).
+pub fn rooted_jsonpath(expr: &str) -> String {
+    if expr.starts_with('$') {
+        expr.to_string()
+    } else if expr.starts_with('.') || expr.starts_with('[') {
+        format!("${}", expr)
+    } else {
+        format!("$.{}", expr)
+    }
+}
+
+/// A `--where` condition such as `category=beauty` or `price>100`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RowCondition {
+    pub path: String,
+    pub op: CompareOp,
+    pub value: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareOp {
+    Eq,
+    Ne,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+    /// Case-insensitive substring match (`~`)
+    Contains,
+}
+
+impl RowCondition {
+    /// Parse `path<op>value` with one of `=`, `!=`, `>`, `>=`, `<`, `<=` or `~`.
+    pub fn parse(expr: &str) -> Result<Self> {
+        let Some(at) = expr.find(['=', '!', '<', '>', '~']) else {
+            bail!(
+                "--where '{}': expected path=value, path!=value, path>value, path<value or path~text",
+                expr
+            );
+        };
+        let rest = &expr[at..];
+        let (op, len) = [
+            ("!=", CompareOp::Ne),
+            (">=", CompareOp::Ge),
+            ("<=", CompareOp::Le),
+            ("=", CompareOp::Eq),
+            (">", CompareOp::Gt),
+            ("<", CompareOp::Lt),
+            ("~", CompareOp::Contains),
+        ]
+        .into_iter()
+        .find(|(token, _)| rest.starts_with(token))
+        .map(|(token, op)| (op, token.len()))
+        .with_context(|| format!("--where '{}': unknown operator", expr))?;
+        let path = expr[..at].trim();
+        if path.is_empty() {
+            bail!("--where '{}': missing column path", expr);
+        }
+        Ok(Self {
+            path: path.to_string(),
+            op,
+            value: rest[len..].trim().to_string(),
+        })
+    }
+
+    /// Numbers (and numeric strings) compare as numbers, everything else as text.
+    /// Ordering operators never match a missing value.
+    pub fn matches(&self, row: &Value) -> bool {
+        let cell = &*lookup_path(row, &self.path);
+        let numeric = match (as_number(cell), self.value.parse::<f64>()) {
+            (Some(a), Ok(b)) => a.partial_cmp(&b),
+            _ => None,
+        };
+        let text = scalar_to_string(cell);
+        let ordering = numeric.unwrap_or_else(|| text.as_str().cmp(self.value.as_str()));
+        match self.op {
+            CompareOp::Eq => ordering == Ordering::Equal,
+            CompareOp::Ne => ordering != Ordering::Equal,
+            CompareOp::Contains => text.to_lowercase().contains(&self.value.to_lowercase()),
+            _ if cell.is_null() => false,
+            CompareOp::Gt => ordering == Ordering::Greater,
+            CompareOp::Ge => ordering != Ordering::Less,
+            CompareOp::Lt => ordering == Ordering::Less,
+            CompareOp::Le => ordering != Ordering::Greater,
+        }
+    }
+}
+
+/// A number, or text that parses as one.
+pub(crate) fn as_number(v: &Value) -> Option<f64> {
+    match v {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+/// Sort order for table cells: numerically when both are numbers, else by text,
+/// ignoring case.
+fn compare_cells(a: &Value, b: &Value) -> Ordering {
+    match (as_number(a), as_number(b)) {
+        (Some(x), Some(y)) => x.partial_cmp(&y).unwrap_or(Ordering::Equal),
+        _ => {
+            let (x, y) = (scalar_to_string(a), scalar_to_string(b));
+            x.to_lowercase()
+                .cmp(&y.to_lowercase())
+                .then_with(|| x.cmp(&y))
+        }
+    }
+}
+
+/// `--raw-value` text: strings unquoted, other scalars and objects as JSON, array items
+/// one per line.
+pub fn raw_value_text(value: &Value) -> String {
+    match value {
+        Value::Array(items) => items
+            .iter()
+            .map(|item| format!("{}\n", scalar_to_string(item)))
+            .collect(),
+        other => format!("{}\n", scalar_to_string(other)),
+    }
+}
+
+/// Render a response body. Bodies that are not JSON are wrapped as
+/// `{"status": .., "body": ..}` for JSON and YAML, and passed through otherwise.
+pub fn render_text(
+    text: &str,
+    status: u16,
+    format: OutputFormat,
+    table_view: Option<&Vec<String>>,
+) -> Result<String> {
+    if format == OutputFormat::Raw {
+        return Ok(text.to_string());
+    }
+    let value = match serde_json::from_str::<Value>(text) {
+        Ok(value) => value,
+        Err(_) => match format {
+            OutputFormat::Json | OutputFormat::JsonCompact | OutputFormat::Yaml => {
+                json!({ "status": status, "body": text })
+            }
+            _ => return Ok(with_newline(text)),
+        },
+    };
+    render_value(&value, format, table_view)
+}
+
+/// Render a JSON value. The table itself is printed by the human-readable printer;
+/// asking for it here gives pretty JSON.
+pub fn render_value(
+    value: &Value,
+    format: OutputFormat,
+    table_view: Option<&Vec<String>>,
+) -> Result<String> {
+    Ok(match format {
+        OutputFormat::Json | OutputFormat::Human => {
+            format!("{}\n", serde_json::to_string_pretty(value)?)
+        }
+        OutputFormat::JsonCompact | OutputFormat::Raw => {
+            format!("{}\n", serde_json::to_string(value)?)
+        }
+        OutputFormat::Yaml => serde_yaml::to_string(value)?,
+        OutputFormat::Csv => delimited(value, table_view, ','),
+        OutputFormat::Tsv => delimited(value, table_view, '\t'),
+        OutputFormat::Ndjson => ndjson(value, table_view)?,
+        OutputFormat::Quiet => String::new(),
+    })
+}
+
+/// The rows of a response: the items of a top-level array, else those of the object's
+/// first array field (e.g. `{"products": [...], "total": 194}`), else the value itself.
+pub fn table_rows(value: &Value) -> &[Value] {
+    match value {
+        Value::Array(items) => items,
+        Value::Object(map) => map
+            .values()
+            .find_map(|v| v.as_array())
+            .map(|items| items.as_slice())
+            .unwrap_or(std::slice::from_ref(value)),
+        _ => std::slice::from_ref(value),
+    }
+}
+
+/// CSV (RFC 4180 quoting) or TSV (tabs and line breaks in cells become spaces), with a
+/// header row of table column labels.
+fn delimited(value: &Value, table_view: Option<&Vec<String>>, separator: char) -> String {
+    let rows = table_rows(value);
+    let columns = table_columns(rows, table_view, table::settings().column_depth);
+    let mut out = String::new();
+    if columns.is_empty() {
+        push_record(&mut out, &["Value".to_string()], separator);
+        for item in rows {
+            push_record(&mut out, &[scalar_to_string(item)], separator);
+        }
+        return out;
+    }
+    let header: Vec<String> = columns
+        .iter()
+        .map(|c| c.header().split_whitespace().collect::<Vec<_>>().join(" "))
+        .collect();
+    push_record(&mut out, &header, separator);
+    for item in rows.iter().filter(|item| item.is_object()) {
+        let cells: Vec<String> = columns.iter().map(|c| c.format(&c.value(item))).collect();
+        push_record(&mut out, &cells, separator);
+    }
+    out
+}
+
+fn push_record(out: &mut String, cells: &[String], separator: char) {
+    let cells: Vec<String> = cells
+        .iter()
+        .map(|cell| {
+            if separator == '\t' {
+                cell.replace(['\t', '\n', '\r'], " ")
+            } else if cell.contains([separator, '"', '\n', '\r']) {
+                format!("\"{}\"", cell.replace('"', "\"\""))
+            } else {
+                cell.clone()
+            }
+        })
+        .collect();
+    out.push_str(&cells.join(&separator.to_string()));
+    out.push('\n');
+}
+
+/// One compact JSON value per row. With a `table_view`, each row is cut down to its
+/// columns, keyed by column path, with the values left unformatted.
+fn ndjson(value: &Value, table_view: Option<&Vec<String>>) -> Result<String> {
+    let rows = table_rows(value);
+    let columns =
+        table_view.map(|_| table_columns(rows, table_view, table::settings().column_depth));
+    let mut out = String::new();
+    for item in rows {
+        let line = match &columns {
+            Some(columns) if item.is_object() => {
+                let projected: Map<String, Value> = columns
+                    .iter()
+                    .map(|c| (c.path.clone(), c.value(item).into_owned()))
+                    .collect();
+                serde_json::to_string(&projected)?
+            }
+            _ => serde_json::to_string(item)?,
+        };
+        out.push_str(&line);
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+fn with_newline(text: &str) -> String {
+    if text.is_empty() || text.ends_with('\n') {
+        text.to_string()
+    } else {
+        format!("{}\n", text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn products() -> Value {
+        json!({
+            "products": [
+                {"id": 1, "title": "Phone, \"Pro\"", "size": 2048, "dims": {"w": 1}},
+                {"id": 2, "title": "Case\tblue", "size": 1024, "dims": {"w": 2}}
+            ],
+            "total": 194
+        })
+    }
+
+    // ==================== ResponseFilter tests ====================
+
+    fn query(expr: &str) -> ResponseFilter {
+        ResponseFilter {
+            query: Some(expr.to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_query_single_values_and_lists() {
+        let apply = |expr: &str| query(expr).apply(products()).unwrap();
+        assert_eq!(apply("$.total"), json!(194));
+        assert_eq!(apply("products[1].id"), json!(2));
+        assert_eq!(apply(".products[0].dims"), json!({"w": 1}));
+        assert_eq!(apply("$.missing"), Value::Null);
+        assert_eq!(apply("$.products[*].id"), json!([1, 2]));
+        assert_eq!(apply("$..w"), json!([1, 2]));
+        assert_eq!(apply("$.products[?(@.id > 1)].id"), json!([2]));
+        assert_eq!(apply("$.products[0:1].id"), json!([1]));
+        assert!(query("$.products[").apply(products()).is_err());
+        assert_eq!(ResponseFilter::default().apply(json!(1)).unwrap(), json!(1));
+    }
+
+    fn catalog() -> Value {
+        json!({
+            "items": [
+                {"id": 1, "category": "beauty", "price": 120, "title": "Mascara"},
+                {"id": 2, "category": "tools", "price": "80", "title": "drill"},
+                {"id": 3, "category": "beauty", "price": 15.5, "title": "Lipstick"},
+                {"id": 4, "category": "beauty", "title": "Sample"}
+            ],
+            "total": 4
+        })
+    }
+
+    fn ids(value: &Value) -> Vec<i64> {
+        table_rows(value)
+            .iter()
+            .map(|row| row["id"].as_i64().unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_where_sort_and_limit() {
+        let filter = |conditions: &[&str], sort_by: Option<&str>, descending, limit_rows| {
+            ResponseFilter {
+                conditions: conditions
+                    .iter()
+                    .map(|c| RowCondition::parse(c).unwrap())
+                    .collect(),
+                sort_by: sort_by.map(|s| s.to_string()),
+                descending,
+                limit_rows,
+                ..Default::default()
+            }
+            .apply(catalog())
+            .unwrap()
+        };
+        assert_eq!(
+            ids(&filter(&["category=beauty"], None, false, None)),
+            [1, 3, 4]
+        );
+        assert_eq!(ids(&filter(&["price>100"], None, false, None)), [1]);
+        assert_eq!(ids(&filter(&["price<=80"], None, false, None)), [2, 3]);
+        assert_eq!(ids(&filter(&["category!=beauty"], None, false, None)), [2]);
+        assert_eq!(ids(&filter(&["title~LIP"], None, false, None)), [3]);
+        assert_eq!(
+            ids(&filter(&["category=beauty", "price>20"], None, false, None)),
+            [1]
+        );
+        // Numeric strings sort as numbers; missing values go last
+        assert_eq!(ids(&filter(&[], Some("price"), false, None)), [3, 2, 1, 4]);
+        assert_eq!(ids(&filter(&[], Some("price"), true, None)), [1, 2, 3, 4]);
+        assert_eq!(ids(&filter(&[], Some("title"), false, None)), [2, 3, 1, 4]);
+        assert_eq!(ids(&filter(&[], Some("price"), true, Some(2))), [1, 2]);
+        // The envelope is kept
+        assert_eq!(filter(&[], None, false, Some(1))["total"], 4);
+    }
+
+    #[test]
+    fn test_row_condition_parse() {
+        let c = RowCondition::parse(" price >= 10 ").unwrap();
+        assert_eq!(
+            (c.path.as_str(), c.op, c.value.as_str()),
+            ("price", CompareOp::Ge, "10")
+        );
+        assert_eq!(RowCondition::parse("meta.tag=a=b").unwrap().value, "a=b");
+        assert!(RowCondition::parse("price").is_err());
+        assert!(RowCondition::parse("=1").is_err());
+        assert!(RowCondition::parse("a!b").is_err());
+    }
+
+    #[test]
+    fn test_columns_override_table_view() {
+        let view = vec!["id".to_string()];
+        let filter = ResponseFilter {
+            columns: Some(vec!["title".to_string()]),
+            ..Default::default()
+        };
+        assert_eq!(filter.table_view(Some(&view)), filter.columns.as_ref());
+        assert_eq!(
+            ResponseFilter::default().table_view(Some(&view)),
+            Some(&view)
+        );
+    }
+
+    #[test]
+    fn test_raw_value_text() {
+        assert_eq!(raw_value_text(&json!("abc")), "abc\n");
+        assert_eq!(raw_value_text(&json!(42)), "42\n");
+        assert_eq!(raw_value_text(&Value::Null), "null\n");
+        assert_eq!(
+            raw_value_text(&json!(["a", 1, {"b": 2}])),
+            "a\n1\n{\"b\":2}\n"
+        );
+    }
+
+    // ==================== render_text tests ====================
+
+    #[test]
+    fn test_render_json_yaml_and_non_json() {
+        let text = r#"{"b":1,"a":[true]}"#;
+        assert_eq!(
+            render_text(text, 200, OutputFormat::JsonCompact, None).unwrap(),
+            "{\"b\":1,\"a\":[true]}\n"
+        );
+        assert_eq!(
+            render_text(text, 200, OutputFormat::Yaml, None).unwrap(),
+            "b: 1\na:\n- true\n"
+        );
+        assert_eq!(
+            render_text("oops", 502, OutputFormat::JsonCompact, None).unwrap(),
+            "{\"status\":502,\"body\":\"oops\"}\n"
+        );
+        assert_eq!(
+            render_text("oops", 502, OutputFormat::Csv, None).unwrap(),
+            "oops\n"
+        );
+        assert_eq!(
+            render_text(text, 200, OutputFormat::Raw, None).unwrap(),
+            text
+        );
+    }
+
+    // ==================== csv/tsv tests ====================
+
+    #[test]
+    fn test_csv_uses_table_columns() {
+        let view = vec!["id".to_string(), "title".to_string(), "size:kb".to_string()];
+        assert_eq!(
+            render_value(&products(), OutputFormat::Csv, Some(&view)).unwrap(),
+            "Id,Title,Size KB\n1,\"Phone, \"\"Pro\"\"\",2.00\n2,Case\tblue,1.00\n"
+        );
+        assert_eq!(
+            render_value(&products(), OutputFormat::Tsv, Some(&view)).unwrap(),
+            "Id\tTitle\tSize KB\n1\tPhone, \"Pro\"\t2.00\n2\tCase blue\t1.00\n"
+        );
+        // Derived columns match the table: nested keys one level deep
+        assert_eq!(
+            render_value(&products(), OutputFormat::Csv, None)
+                .unwrap()
+                .lines()
+                .next(),
+            Some("Id,Title,Size,W")
+        );
+        assert_eq!(
+            render_value(&json!(["a", "b,c"]), OutputFormat::Csv, None).unwrap(),
+            "Value\na\n\"b,c\"\n"
+        );
+    }
+
+    // ==================== ndjson tests ====================
+
+    #[test]
+    fn test_ndjson_rows() {
+        let view = vec!["id".to_string(), "dims.w".to_string()];
+        assert_eq!(
+            render_value(&products(), OutputFormat::Ndjson, Some(&view)).unwrap(),
+            "{\"id\":1,\"dims.w\":1}\n{\"id\":2,\"dims.w\":2}\n"
+        );
+        let single = json!({"id": 7, "name": "x"});
+        assert_eq!(
+            render_value(&single, OutputFormat::Ndjson, None).unwrap(),
+            "{\"id\":7,\"name\":\"x\"}\n"
+        );
+    }
+}
@@ -0,0 +1,405 @@
+//! `output_template:` text that replaces the human output of a command or scenario,
+//! e.g. `Created product {id} ({title}) in {category}`.
+//!
+//! - `{path}` is a response field, with the same paths and formatters as table columns:
+//!   `{meta.createdAt:ago}`, `{price:round:2}`, `{tags[*]}`, `{reviews.length()}`
+//! - `{#each path}...{/each}` repeats its body for every item of an array; inside it,
+//!   paths are relative to the item, `{.}` is the item itself, `{@index}` counts from 0
+//!   and `{@number}` from 1
+//! - `{#if path}...{else}...{/if}` shows its body when the field is set and not `false`,
+//!   empty or `null`
+//! - `{$.path}` starts from the whole response, even inside a loop
+//! - `{@name}` is a variable passed by the caller (scenario variables such as `job_id`)
+//! - `{{` and `}}` are literal braces
+//!
+//! Nulls and missing fields print as nothing. A block tag alone on its line takes the
+//! line with it, so loops can be written one item per line.
+
+use std::collections::HashMap;
+
+use anyhow::{bail, Result};
+use serde_json::Value;
+
+use crate::{lookup_path, parse_column_spec, scalar_to_string, ColumnSpec};
+
+/// A parsed `output_template`.
+#[derive(Debug, Clone)]
+pub struct OutputTemplate {
+    nodes: Vec<Node>,
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Text(String),
+    Field(ColumnSpec),
+    Special(String),
+    Each {
+        path: String,
+        body: Vec<Node>,
+    },
+    If {
+        path: String,
+        then: Vec<Node>,
+        otherwise: Vec<Node>,
+    },
+}
+
+/// A block being parsed: its opening tag and the nodes collected so far.
+struct Open {
+    tag: Tag,
+    nodes: Vec<Node>,
+    /// Nodes before `{else}`, once it has been seen
+    then: Option<Vec<Node>>,
+}
+
+enum Tag {
+    Root,
+    Each(String),
+    If(String),
+}
+
+impl Tag {
+    fn describe(&self) -> String {
+        match self {
+            Tag::Root => String::new(),
+            Tag::Each(path) => format!("{{#each {}}}", path),
+            Tag::If(path) => format!("{{#if {}}}", path),
+        }
+    }
+}
+
+impl OutputTemplate {
+    pub fn parse(src: &str) -> Result<Self> {
+        let mut stack = vec![Open {
+            tag: Tag::Root,
+            nodes: Vec::new(),
+            then: None,
+        }];
+        let mut text = String::new();
+        // Nothing but blanks so far on the current line
+        let mut line_clean = true;
+        let mut rest = src;
+        while !rest.is_empty() {
+            if let Some(after) = rest.strip_prefix("{{") {
+                text.push('{');
+                line_clean = false;
+                rest = after;
+                continue;
+            }
+            if let Some(after) = rest.strip_prefix("}}") {
+                text.push('}');
+                line_clean = false;
+                rest = after;
+                continue;
+            }
+            if !rest.starts_with('{') {
+                let c = rest.chars().next().unwrap_or_default();
+                text.push(c);
+                if c == '\n' {
+                    line_clean = true;
+                } else if c != ' ' && c != '\t' {
+                    line_clean = false;
+                }
+                rest = &rest[c.len_utf8()..];
+                continue;
+            }
+            let offset = src.len() - rest.len();
+            let Some(end) = rest.find('}') else {
+                bail!("'{{' at offset {} is not closed", offset);
+            };
+            let tag = rest[1..end].trim();
+            rest = &rest[end + 1..];
+
+            let block = tag.starts_with('#') || tag.starts_with('/') || tag == "else";
+            if block && line_clean && ends_line(rest) {
+                // Drop the tag's line: its indentation and the newline after it
+                text.truncate(text.trim_end_matches([' ', '\t']).len());
+                rest = rest
+                    .strip_prefix("\r\n")
+                    .or_else(|| rest.strip_prefix('\n'))
+                    .unwrap_or(rest);
+            }
+            let top = stack.last_mut().expect("root block");
+            if !text.is_empty() {
+                top.nodes.push(Node::Text(std::mem::take(&mut text)));
+            }
+
+            if let Some(path) = tag.strip_prefix("#each ") {
+                stack.push(Open {
+                    tag: Tag::Each(path.trim().to_string()),
+                    nodes: Vec::new(),
+                    then: None,
+                });
+            } else if let Some(path) = tag.strip_prefix("#if ") {
+                stack.push(Open {
+                    tag: Tag::If(path.trim().to_string()),
+                    nodes: Vec::new(),
+                    then: None,
+                });
+            } else if tag == "else" {
+                if !matches!(top.tag, Tag::If(_)) || top.then.is_some() {
+                    bail!("{{else}} at offset {} is not inside {{#if}}", offset);
+                }
+                top.then = Some(std::mem::take(&mut top.nodes));
+            } else if let Some(name) = tag.strip_prefix('/') {
+                let open = stack.pop().expect("root block");
+                let node = match (open.tag, name.trim()) {
+                    (Tag::Each(path), "each") => Node::Each {
+                        path,
+                        body: open.nodes,
+                    },
+                    (Tag::If(path), "if") => match open.then {
+                        Some(then) => Node::If {
+                            path,
+                            then,
+                            otherwise: open.nodes,
+                        },
+                        None => Node::If {
+                            path,
+                            then: open.nodes,
+                            otherwise: Vec::new(),
+                        },
+                    },
+                    (tag, _) => bail!(
+                        "{{/{}}} at offset {} does not close {}",
+                        name.trim(),
+                        offset,
+                        match tag {
+                            Tag::Root => "any block".to_string(),
+                            other => other.describe(),
+                        }
+                    ),
+                };
+                stack.last_mut().expect("root block").nodes.push(node);
+            } else if tag.starts_with('#') {
+                bail!(
+                    "unknown block {{{}}} at offset {} (use #each or #if)",
+                    tag,
+                    offset
+                );
+            } else if let Some(name) = tag.strip_prefix('@') {
+                top.nodes.push(Node::Special(name.to_string()));
+                line_clean = false;
+            } else if tag.is_empty() {
+                bail!("empty {{}} at offset {}", offset);
+            } else {
+                top.nodes.push(Node::Field(parse_column_spec(tag)));
+                line_clean = false;
+            }
+        }
+        let mut last = stack.pop().expect("root block");
+        if !stack.is_empty() {
+            bail!("{} is not closed", last.tag.describe());
+        }
+        if !text.is_empty() {
+            last.nodes.push(Node::Text(text));
+        }
+        Ok(Self { nodes: last.nodes })
+    }
+
+    /// The template filled in from `value`; `{@name}` comes from `vars`.
+    pub fn render(&self, value: &Value, vars: &HashMap<String, String>) -> String {
+        let mut out = String::new();
+        let scope = Scope {
+            root: value,
+            item: value,
+            index: None,
+            vars,
+        };
+        render_nodes(&self.nodes, &scope, &mut out);
+        out
+    }
+}
+
+/// Whether a tag is the last thing on its line.
+fn ends_line(after: &str) -> bool {
+    after.is_empty() || after.starts_with('\n') || after.starts_with("\r\n")
+}
+
+struct Scope<'a> {
+    root: &'a Value,
+    item: &'a Value,
+    index: Option<usize>,
+    vars: &'a HashMap<String, String>,
+}
+
+impl<'a> Scope<'a> {
+    /// The value at `path`: `.` is the current item, `$.x` starts from the root.
+    fn lookup(&self, path: &str) -> std::borrow::Cow<'a, Value> {
+        if path == "." {
+            return std::borrow::Cow::Borrowed(self.item);
+        }
+        match path.strip_prefix('$') {
+            Some("") => std::borrow::Cow::Borrowed(self.root),
+            Some(rest) => lookup_path(self.root, rest.trim_start_matches('.')),
+            None => lookup_path(self.item, path),
+        }
+    }
+}
+
+fn render_nodes(nodes: &[Node], scope: &Scope, out: &mut String) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => out.push_str(text),
+            Node::Field(column) => {
+                let value = scope.lookup(&column.path);
+                out.push_str(&field_text(column, &value));
+            }
+            Node::Special(name) => match (name.as_str(), scope.index) {
+                ("index", Some(i)) => out.push_str(&i.to_string()),
+                ("number", Some(i)) => out.push_str(&(i + 1).to_string()),
+                _ => out.push_str(scope.vars.get(name).map_or("", String::as_str)),
+            },
+            Node::Each { path, body } => {
+                let value = scope.lookup(path);
+                let items: &[Value] = match &*value {
+                    Value::Array(items) => items,
+                    Value::Null => &[],
+                    single => std::slice::from_ref(single),
+                };
+                for (i, item) in items.iter().enumerate() {
+                    let inner = Scope {
+                        root: scope.root,
+                        item,
+                        index: Some(i),
+                        vars: scope.vars,
+                    };
+                    render_nodes(body, &inner, out);
+                }
+            }
+            Node::If {
+                path,
+                then,
+                otherwise,
+            } => {
+                let branch = if is_set(&scope.lookup(path)) {
+                    then
+                } else {
+                    otherwise
+                };
+                render_nodes(branch, scope, out);
+            }
+        }
+    }
+}
+
+/// Text for a field: formatted like a table cell, but nulls are left out and arrays of
+/// plain values are joined.
+fn field_text(column: &ColumnSpec, value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::Array(items) if column.formatter.is_none() && column.modifier.is_none() => items
+            .iter()
+            .map(scalar_to_string)
+            .collect::<Vec<_>>()
+            .join(", "),
+        _ => column.format(value),
+    }
+}
+
+fn is_set(value: &Value) -> bool {
+    match value {
+        Value::Null | Value::Bool(false) => false,
+        Value::String(s) => !s.is_empty(),
+        Value::Array(items) => !items.is_empty(),
+        Value::Object(map) => !map.is_empty(),
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn render(src: &str, value: Value) -> String {
+        OutputTemplate::parse(src)
+            .unwrap()
+            .render(&value, &HashMap::new())
+    }
+
+    // ==================== field tests ====================
+
+    #[test]
+    fn test_fields_and_formatters() {
+        let product = json!({
+            "id": 42,
+            "title": "iPhone",
+            "category": "smartphones",
+            "price": 19.987,
+            "tags": ["a", "b"],
+            "meta": {"barcode": "123"},
+            "brand": null
+        });
+        assert_eq!(
+            render(
+                "Created product {id} ({title}) in {category}",
+                product.clone()
+            ),
+            "Created product 42 (iPhone) in smartphones"
+        );
+        assert_eq!(
+            render(
+                "{price:round:2} {tags} {tags.length()} {meta.barcode} [{brand}] [{nope}]",
+                product.clone()
+            ),
+            "19.99 a, b 2 123 [] []"
+        );
+        assert_eq!(render("{{literal}} {id}", product), "{literal} 42");
+    }
+
+    // ==================== block tests ====================
+
+    #[test]
+    fn test_each_loop() {
+        let cart = json!({
+            "id": 7,
+            "products": [
+                {"title": "Phone", "quantity": 2},
+                {"title": "Case", "quantity": 1}
+            ]
+        });
+        assert_eq!(
+            render(
+                "Cart {id}:\n{#each products}\n  {@number}. {title} x{quantity} (cart {$.id})\n{/each}\nDone",
+                cart
+            ),
+            "Cart 7:\n  1. Phone x2 (cart 7)\n  2. Case x1 (cart 7)\nDone"
+        );
+        assert_eq!(
+            render("{#each tags}[{.}]{/each}", json!({"tags": ["x", "y"]})),
+            "[x][y]"
+        );
+        assert_eq!(render("{#each missing}x{/each}-", json!({})), "-");
+    }
+
+    #[test]
+    fn test_if_else() {
+        let src = "{#if discount}{discount:percent} off{else}full price{/if}";
+        assert_eq!(render(src, json!({"discount": 12.5})), "12.5% off");
+        assert_eq!(render(src, json!({"discount": null})), "full price");
+        assert_eq!(render(src, json!({})), "full price");
+        assert_eq!(render("{#if tags}tagged{/if}", json!({"tags": []})), "");
+    }
+
+    #[test]
+    fn test_vars() {
+        let template = OutputTemplate::parse("Job {@job_id}: {status}").unwrap();
+        let vars = HashMap::from([("job_id".to_string(), "j-1".to_string())]);
+        assert_eq!(
+            template.render(&json!({"status": "done"}), &vars),
+            "Job j-1: done"
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let err = |src: &str| OutputTemplate::parse(src).unwrap_err().to_string();
+        assert!(err("{#each items}x").contains("{#each items} is not closed"));
+        assert!(err("{#if a}x{/each}").contains("does not close {#if a}"));
+        assert!(err("x{/if}").contains("does not close any block"));
+        assert!(err("{id").contains("'{' at offset 0 is not closed"));
+        assert!(err("{#for x}{/for}").contains("unknown block"));
+        assert!(err("{else}").contains("not inside {#if}"));
+    }
+}
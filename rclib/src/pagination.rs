@@ -0,0 +1,573 @@
+//! `--all`: follow a list command's pages as described by its `pagination:` block and
+//! print them as one response.
+//!
+//! Each page is a fresh request built from the command's templates with the paging
+//! variables (`skip`, `page`, `cursor`, ...) moved on, except for `link` pagination,
+//! which requests the URL of the `rel="next"` link. Rows come from the `list_path`
+//! array, or from a bare array response.
+
+use std::collections::{HashMap, HashSet};
+
+use anyhow::{bail, Context, Result};
+use reqwest::blocking::{Client, ClientBuilder};
+use reqwest::header::HeaderMap;
+use serde_json::Value;
+
+use crate::mapping::{Pagination, PaginationType};
+use crate::{
+    auth, build_http_request, build_url, extract_jsonpath_value, output, print_response_text,
+    ExecutionConfig, OutputFormat, RawRequestSpec, TableLayout,
+};
+
+/// Fetch every page of a list, starting from `first`, which was built from `vars`.
+/// `build_page` rebuilds the request for changed variables. Rows are printed as one
+/// list; with `-o ndjson` and no client-side filters they are streamed page by page.
+/// `max_pages` stops early with a note on stderr.
+pub fn execute_paginated<F>(
+    first: &RawRequestSpec,
+    pagination: &Pagination,
+    vars: &HashMap<String, String>,
+    build_page: F,
+    config: &ExecutionConfig<'_>,
+    max_pages: Option<u32>,
+) -> Result<i32>
+where
+    F: Fn(&HashMap<String, String>) -> Result<RawRequestSpec>,
+{
+    if pagination.pagination_type == PaginationType::Cursor && pagination.next_cursor.is_none() {
+        bail!("cursor pagination needs a next_cursor JSONPath");
+    }
+    let mut builder: ClientBuilder = Client::builder().user_agent(config.user_agent);
+    if let Some(secs) = config.conn_timeout_secs {
+        builder = builder.connect_timeout(std::time::Duration::from_secs_f64(secs));
+    }
+    if let Some(secs) = config.request_timeout_secs {
+        builder = builder.timeout(std::time::Duration::from_secs_f64(secs));
+    }
+    let client = builder.build().context("Failed to build HTTP client")?;
+
+    let first_url = build_url(&first.base_url, &first.endpoint)?;
+    let layout = TableLayout::of(first);
+    let stream = config.output == OutputFormat::Ndjson && config.filter.is_empty();
+    let mut pager = Pager::new(pagination, vars);
+    let mut spec = first.clone();
+    let mut first_page: Option<(u16, Value)> = None;
+    let mut rows: Vec<Value> = Vec::new();
+    let mut pages: u32 = 0;
+    let mut exit_code = 0;
+
+    loop {
+        let url = build_url(&spec.base_url, &spec.endpoint)?;
+        if config.verbose {
+            eprintln!("-> {} {}", spec.method, url);
+        }
+        let build = |auth: &[auth::RequestAuth]| build_http_request(&client, &spec, auth);
+        let resp = auth::send_with_auth(&client, &spec.auth, config.verbose, None, build)?;
+        pages += 1;
+        if !resp.status.is_success() {
+            if pages == 1 {
+                // Nothing to combine: show the error response as a single request would
+                print_response_text(
+                    &resp.body,
+                    resp.status.as_u16(),
+                    config.output,
+                    &config.filter,
+                    layout,
+                )?;
+                return Ok(1);
+            }
+            eprintln!(
+                "Page {} failed with HTTP {}; showing the first {} pages",
+                pages,
+                resp.status.as_u16(),
+                pages - 1
+            );
+            exit_code = 1;
+            break;
+        }
+        let value: Value = serde_json::from_str(&resp.body)
+            .with_context(|| format!("--all: page {} is not JSON", pages))?;
+        let page_rows = page_rows(&value, &layout)
+            .context("--all: no list in the response; set list_path on the command")?
+            .clone();
+
+        let next = pager.advance(&value, &resp.headers, page_rows.len(), &url)?;
+        if stream {
+            print!(
+                "{}",
+                output::render_value(
+                    &Value::Array(page_rows),
+                    OutputFormat::Ndjson,
+                    layout.table_view
+                )?
+            );
+        } else {
+            rows.extend(page_rows);
+        }
+        if first_page.is_none() {
+            first_page = Some((resp.status.as_u16(), value));
+        }
+
+        let Some(next) = next else {
+            break;
+        };
+        if max_pages.is_some_and(|max| pages >= max) {
+            eprintln!(
+                "Stopped after {} pages (--max-pages); more are available",
+                pages
+            );
+            break;
+        }
+        spec = match next {
+            Next::Vars(vars) => build_page(&vars)?,
+            Next::Url(next_url) => {
+                let mut next_spec = RawRequestSpec {
+                    endpoint: next_url,
+                    ..spec
+                };
+                // Credentials are for the API, not for wherever its links point
+                if !next_spec.auth.is_empty() && !same_origin(&first_url, &next_spec.endpoint) {
+                    eprintln!(
+                        "Next page {} is on another host; requesting it without credentials",
+                        next_spec.endpoint
+                    );
+                    next_spec.auth.clear();
+                }
+                next_spec
+            }
+        };
+    }
+
+    if !stream {
+        if let Some((status, mut value)) = first_page {
+            replace_rows(&mut value, &layout, rows);
+            let text = serde_json::to_string(&value)?;
+            print_response_text(&text, status, config.output, &config.filter, layout)?;
+        }
+    }
+    Ok(exit_code)
+}
+
+/// The rows of one page: the `list_path` array, or the response itself if it is an array.
+fn page_rows<'v>(value: &'v Value, layout: &TableLayout) -> Option<&'v Vec<Value>> {
+    layout.list(value).or_else(|| value.as_array())
+}
+
+/// Put all `rows` in place of the first page's list, keeping its envelope.
+fn replace_rows(value: &mut Value, layout: &TableLayout, rows: Vec<Value>) {
+    let pointer = layout
+        .list(value)
+        .and_then(|list| pointer_to(value, list, String::new()));
+    match pointer.and_then(|p| value.pointer_mut(&p)) {
+        Some(list) => *list = Value::Array(rows),
+        None => *value = Value::Array(rows),
+    }
+}
+
+/// JSON pointer of the array `target` inside `v`.
+fn pointer_to(v: &Value, target: &Vec<Value>, at: String) -> Option<String> {
+    match v {
+        Value::Array(items) if std::ptr::eq(items, target) => Some(at),
+        Value::Array(items) => items
+            .iter()
+            .enumerate()
+            .find_map(|(i, item)| pointer_to(item, target, format!("{}/{}", at, i))),
+        Value::Object(map) => map.iter().find_map(|(k, item)| {
+            let key = k.replace('~', "~0").replace('/', "~1");
+            pointer_to(item, target, format!("{}/{}", at, key))
+        }),
+        _ => None,
+    }
+}
+
+/// What to request after a page.
+#[derive(Debug, Clone, PartialEq)]
+enum Next {
+    /// Rebuild the command's request with these variables
+    Vars(HashMap<String, String>),
+    /// Request this URL (`link` pagination)
+    Url(String),
+}
+
+/// The paging position between requests.
+struct Pager<'a> {
+    pagination: &'a Pagination,
+    vars: HashMap<String, String>,
+    /// Rows per page: the size variable if set, else the first page's row count
+    page_size: Option<u64>,
+    /// Cursors or links already followed, so a server that cycles cannot page forever
+    seen: HashSet<String>,
+}
+
+impl<'a> Pager<'a> {
+    fn new(pagination: &'a Pagination, vars: &HashMap<String, String>) -> Self {
+        let size_var = match pagination.pagination_type {
+            PaginationType::Offset => Some(pagination.limit_var()),
+            PaginationType::Page => Some(pagination.per_page_var()),
+            _ => None,
+        };
+        Self {
+            pagination,
+            vars: vars.clone(),
+            page_size: size_var
+                .and_then(|var| number_var(vars, var))
+                .filter(|n| *n > 0),
+            seen: HashSet::new(),
+        }
+    }
+
+    /// Take in a page of `count` rows from `url` and say what comes next, if anything.
+    fn advance(
+        &mut self,
+        page: &Value,
+        headers: &HeaderMap,
+        count: usize,
+        url: &str,
+    ) -> Result<Option<Next>> {
+        let count = count as u64;
+        let p = self.pagination;
+        let total = p
+            .total
+            .as_deref()
+            .and_then(|path| extract_jsonpath_value(page, path))
+            .and_then(|t| t.parse::<u64>().ok());
+        let short_page = self.page_size.is_some_and(|size| count < size);
+        let page_size = *self.page_size.get_or_insert(count);
+        let numbered = matches!(
+            p.pagination_type,
+            PaginationType::Offset | PaginationType::Page
+        );
+        if numbered && (count == 0 || short_page) {
+            return Ok(None);
+        }
+
+        match p.pagination_type {
+            PaginationType::Offset => {
+                let offset = number_var(&self.vars, p.offset_var()).unwrap_or(0) + count;
+                if total.is_some_and(|total| offset >= total) {
+                    return Ok(None);
+                }
+                self.vars
+                    .insert(p.offset_var().to_string(), offset.to_string());
+            }
+            PaginationType::Page => {
+                let page_number = number_var(&self.vars, p.page_var()).unwrap_or(1);
+                if total.is_some_and(|total| page_number * page_size >= total) {
+                    return Ok(None);
+                }
+                self.vars
+                    .insert(p.page_var().to_string(), (page_number + 1).to_string());
+            }
+            PaginationType::Cursor => {
+                let path = p.next_cursor.as_deref().unwrap_or_default();
+                let cursor = extract_jsonpath_value(page, path)
+                    .filter(|c| !c.is_empty() && c != "null" && c != "false");
+                let Some(cursor) = cursor else {
+                    return Ok(None);
+                };
+                if let Some(current) = self.vars.get(p.cursor_var()) {
+                    self.seen.insert(current.clone());
+                }
+                if self.seen.contains(&cursor) {
+                    return Ok(None);
+                }
+                self.vars.insert(p.cursor_var().to_string(), cursor);
+            }
+            PaginationType::Link => {
+                let next = headers
+                    .get_all(reqwest::header::LINK)
+                    .iter()
+                    .filter_map(|v| v.to_str().ok())
+                    .find_map(next_link);
+                let Some(next) = next else {
+                    return Ok(None);
+                };
+                let next = reqwest::Url::parse(url)
+                    .and_then(|base| base.join(&next))
+                    .with_context(|| format!("Invalid next link '{}'", next))?
+                    .to_string();
+                self.seen.insert(url.to_string());
+                if self.seen.contains(&next) {
+                    return Ok(None);
+                }
+                return Ok(Some(Next::Url(next)));
+            }
+        }
+        Ok(Some(Next::Vars(self.vars.clone())))
+    }
+}
+
+fn same_origin(a: &str, b: &str) -> bool {
+    match (reqwest::Url::parse(a), reqwest::Url::parse(b)) {
+        (Ok(a), Ok(b)) => a.origin() == b.origin(),
+        _ => false,
+    }
+}
+
+fn number_var(vars: &HashMap<String, String>, name: &str) -> Option<u64> {
+    vars.get(name).and_then(|v| v.trim().parse().ok())
+}
+
+/// The `rel="next"` target of a `Link` header value (RFC 5988), e.g.
+/// `<https://api.example.com/items?page=2>; rel="next", <...>; rel="last"`.
+pub fn next_link(header: &str) -> Option<String> {
+    header.split(',').find_map(|link| {
+        let mut parts = link.split(';');
+        let target = parts.next()?.trim();
+        let target = target.strip_prefix('<')?.strip_suffix('>')?;
+        let is_next = parts.any(|param| {
+            let Some((name, value)) = param.split_once('=') else {
+                return false;
+            };
+            name.trim().eq_ignore_ascii_case("rel")
+                && value
+                    .trim()
+                    .trim_matches('"')
+                    .split_whitespace()
+                    .any(|rel| rel.eq_ignore_ascii_case("next"))
+        });
+        is_next.then(|| target.to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn pagination(yaml: &str) -> Pagination {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    fn vars(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    fn next_vars(next: Option<Next>) -> HashMap<String, String> {
+        match next {
+            Some(Next::Vars(vars)) => vars,
+            other => panic!("expected variables, got {:?}", other),
+        }
+    }
+
+    // ==================== Link header tests ====================
+
+    #[test]
+    fn test_next_link() {
+        let header = r#"<https://api.example.com/items?page=3>; rel="next", <https://api.example.com/items?page=9>; rel="last""#;
+        assert_eq!(
+            next_link(header).as_deref(),
+            Some("https://api.example.com/items?page=3")
+        );
+        assert_eq!(
+            next_link(r#"</items?page=2>; title="x"; rel="prev next""#).as_deref(),
+            Some("/items?page=2")
+        );
+        assert_eq!(next_link(r#"<https://a/?page=1>; rel="prev""#), None);
+        assert_eq!(next_link(""), None);
+    }
+
+    // ==================== Pager tests ====================
+
+    #[test]
+    fn test_offset_pages_until_total() {
+        let p = pagination("{type: offset, offset_var: skip, total: '$.total'}");
+        let mut pager = Pager::new(&p, &vars(&[("limit", "2")]));
+        let headers = HeaderMap::new();
+        let page = json!({"total": 5});
+        let vars = next_vars(pager.advance(&page, &headers, 2, "u").unwrap());
+        assert_eq!(vars["skip"], "2");
+        assert_eq!(vars["limit"], "2");
+        let vars = next_vars(pager.advance(&page, &headers, 2, "u").unwrap());
+        assert_eq!(vars["skip"], "4");
+        // 4 + 1 reaches the total
+        assert_eq!(pager.advance(&page, &headers, 1, "u").unwrap(), None);
+    }
+
+    #[test]
+    fn test_offset_without_total_stops_on_short_page() {
+        let p = pagination("{type: offset}");
+        let mut pager = Pager::new(&p, &HashMap::new());
+        let headers = HeaderMap::new();
+        // The first page sets the page size
+        let vars = next_vars(pager.advance(&json!([]), &headers, 30, "u").unwrap());
+        assert_eq!(vars["offset"], "30");
+        assert!(pager
+            .advance(&json!([]), &headers, 30, "u")
+            .unwrap()
+            .is_some());
+        assert_eq!(pager.advance(&json!([]), &headers, 12, "u").unwrap(), None);
+    }
+
+    #[test]
+    fn test_page_numbers() {
+        let p = pagination("{type: page, total: '$.meta.total'}");
+        let mut pager = Pager::new(&p, &vars(&[("page", "2"), ("per_page", "10")]));
+        let headers = HeaderMap::new();
+        let page = json!({"meta": {"total": 35}});
+        let vars = next_vars(pager.advance(&page, &headers, 10, "u").unwrap());
+        assert_eq!(vars["page"], "3");
+        let vars = next_vars(pager.advance(&page, &headers, 10, "u").unwrap());
+        assert_eq!(vars["page"], "4");
+        // Page 4 of 10 covers items 31-40: the last one
+        assert_eq!(pager.advance(&page, &headers, 10, "u").unwrap(), None);
+        // An empty page always ends it
+        let mut pager = Pager::new(&p, &HashMap::new());
+        assert_eq!(pager.advance(&json!({}), &headers, 0, "u").unwrap(), None);
+    }
+
+    #[test]
+    fn test_cursor() {
+        let p = pagination("{type: cursor, cursor_var: after, next_cursor: '$.next'}");
+        let mut pager = Pager::new(&p, &HashMap::new());
+        let headers = HeaderMap::new();
+        let vars = next_vars(
+            pager
+                .advance(&json!({"next": "abc"}), &headers, 3, "u")
+                .unwrap(),
+        );
+        assert_eq!(vars["after"], "abc");
+        // Same cursor again, or none: done
+        assert_eq!(
+            pager
+                .advance(&json!({"next": "abc"}), &headers, 3, "u")
+                .unwrap(),
+            None
+        );
+        assert_eq!(
+            pager
+                .advance(&json!({"next": null}), &headers, 3, "u")
+                .unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_link_header() {
+        let p = pagination("{type: link}");
+        let mut pager = Pager::new(&p, &HashMap::new());
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            reqwest::header::LINK,
+            r#"</items?page=2>; rel="next""#.parse().unwrap(),
+        );
+        let next = pager
+            .advance(&json!([]), &headers, 3, "https://api.example.com/v1/items")
+            .unwrap();
+        assert_eq!(
+            next,
+            Some(Next::Url(
+                "https://api.example.com/items?page=2".to_string()
+            ))
+        );
+        let next = pager
+            .advance(
+                &json!([]),
+                &HeaderMap::new(),
+                3,
+                "https://api.example.com/x",
+            )
+            .unwrap();
+        assert_eq!(next, None);
+    }
+
+    #[test]
+    fn test_cycles_stop() {
+        let link = |target: &str| {
+            let mut headers = HeaderMap::new();
+            let value = format!("<{}>; rel=\"next\"", target);
+            headers.insert(reqwest::header::LINK, value.parse().unwrap());
+            headers
+        };
+        let p = pagination("{type: link}");
+        let mut pager = Pager::new(&p, &HashMap::new());
+        let (a, b) = ("https://api.example.com/a", "https://api.example.com/b");
+        let next = pager.advance(&json!([]), &link(b), 1, a).unwrap();
+        assert_eq!(next, Some(Next::Url(b.to_string())));
+        // B links back to A
+        assert_eq!(pager.advance(&json!([]), &link(a), 1, b).unwrap(), None);
+
+        let p = pagination("{type: cursor, next_cursor: '$.next'}");
+        let mut pager = Pager::new(&p, &HashMap::new());
+        let headers = HeaderMap::new();
+        for cursor in ["x", "y"] {
+            let page = json!({ "next": cursor });
+            let vars = next_vars(pager.advance(&page, &headers, 1, "u").unwrap());
+            assert_eq!(vars["cursor"], cursor);
+        }
+        let page = json!({"next": "x"});
+        assert_eq!(pager.advance(&page, &headers, 1, "u").unwrap(), None);
+    }
+
+    #[test]
+    fn test_link_to_another_host_gets_no_credentials() {
+        use crate::test_server::{Reply, TestServer};
+        let other = TestServer::start(|_| Reply::json(200, r#"[{"id":2}]"#));
+        let next = format!("<{}/feed?page=2>; rel=\"next\"", other.url);
+        let api = TestServer::start(move |_| {
+            Reply::json(200, r#"[{"id":1}]"#).with_header("Link", &next)
+        });
+
+        let cmd: crate::mapping::CommandSpec =
+            serde_yaml::from_str("{name: feed, method: GET, endpoint: /feed}").unwrap();
+        let spec = crate::try_build_request_from_command(
+            Some(api.url.clone()),
+            &cmd,
+            &HashMap::new(),
+            &HashSet::new(),
+            false,
+        )
+        .unwrap();
+        let crate::RequestSpec::Simple(mut first) = spec else {
+            panic!("expected a single request");
+        };
+        first.auth = vec![auth::RequestAuth::Bearer("s3cret".to_string())];
+        let mut config = ExecutionConfig::new("test");
+        config.output = OutputFormat::Quiet;
+
+        let code = execute_paginated(
+            &first,
+            &pagination("{type: link}"),
+            &HashMap::new(),
+            |_| unreachable!(),
+            &config,
+            None,
+        )
+        .unwrap();
+        assert_eq!(code, 0);
+        let first_request = &api.requests()[0];
+        assert_eq!(first_request.header("authorization"), Some("Bearer s3cret"));
+        let followed = other.requests();
+        assert_eq!(followed.len(), 1);
+        assert_eq!(followed[0].target, "/feed?page=2");
+        assert_eq!(followed[0].header("authorization"), None);
+    }
+
+    // ==================== combining tests ====================
+
+    #[test]
+    fn test_replace_rows_keeps_envelope() {
+        let list_path = "$.data.items";
+        let layout = TableLayout {
+            list_path: Some(list_path),
+            ..TableLayout::default()
+        };
+        let mut value = json!({"data": {"items": [{"id": 1}]}, "total": 3});
+        replace_rows(
+            &mut value,
+            &layout,
+            vec![json!({"id": 1}), json!({"id": 2}), json!({"id": 3})],
+        );
+        assert_eq!(
+            value,
+            json!({"data": {"items": [{"id": 1}, {"id": 2}, {"id": 3}]}, "total": 3})
+        );
+
+        let mut bare = json!([{"id": 1}]);
+        replace_rows(&mut bare, &TableLayout::default(), vec![json!(1), json!(2)]);
+        assert_eq!(bare, json!([1, 2]));
+    }
+}
@@ -0,0 +1,633 @@
+//! `stream: sse`: read a `text/event-stream` response incrementally and print each
+//! Server-Sent Event as it arrives.
+//!
+//! The response is read on a background thread so that Ctrl-C ([`interrupt`]) and
+//! `--max-events` end the stream promptly, even while the server is quiet. When the
+//! connection drops after the server has given events an `id:`, the request is sent again
+//! with the last one as `Last-Event-ID`, after the server's `retry:` delay. Streams without
+//! ids end when the connection closes, and a `204 No Content` reply ends any stream.
+
+use std::io::{BufRead, BufReader};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use reqwest::blocking::{Client, ClientBuilder};
+use serde_json::{json, Value};
+
+use crate::auth::{self, RequestAuth};
+use crate::{
+    build_http_request, build_url, journal, print_response_text, table, ExecutionConfig,
+    OutputFormat, RawRequestSpec, TableLayout,
+};
+
+/// Delay before reconnecting when the server has not sent `retry:`.
+const DEFAULT_RETRY: Duration = Duration::from_secs(3);
+
+/// Reconnects in a row that bring no new event before the stream is given up.
+const MAX_RECONNECTS: u32 = 5;
+
+/// How often the printing loop checks for an interrupt while waiting for events.
+const POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Exit code for a stream stopped with Ctrl-C, as shells report SIGINT.
+const EXIT_INTERRUPTED: i32 = 130;
+
+/// Set by [`interrupt`]; cleared when the last running stream ends.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+/// Streams being printed right now.
+static RUNNING: AtomicUsize = AtomicUsize::new(0);
+
+/// Ask the running event streams to stop, as Ctrl-C should. rclib leaves SIGINT to the
+/// application; its handler calls this and exits as usual when it returns `false`,
+/// i.e. when no stream is running or one was already asked to stop (a second Ctrl-C).
+pub fn interrupt() -> bool {
+    RUNNING.load(Ordering::SeqCst) > 0 && !INTERRUPTED.swap(true, Ordering::SeqCst)
+}
+
+/// Counts a stream as running while it lives.
+struct Running;
+
+impl Running {
+    fn start() -> Self {
+        RUNNING.fetch_add(1, Ordering::SeqCst);
+        Self
+    }
+}
+
+impl Drop for Running {
+    fn drop(&mut self) {
+        if RUNNING.fetch_sub(1, Ordering::SeqCst) == 1 {
+            INTERRUPTED.store(false, Ordering::SeqCst);
+        }
+    }
+}
+
+/// One dispatched event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SseEvent {
+    /// The `event:` field, `message` when absent
+    pub event: String,
+    /// The `data:` lines joined with newlines
+    pub data: String,
+    /// The last event id seen on the stream, if any
+    pub id: Option<String>,
+}
+
+/// Incremental `text/event-stream` parser following the WHATWG event stream rules:
+/// feed it one line at a time (without the line ending) and it returns an event at
+/// each blank line that ends a block with data.
+#[derive(Debug, Default)]
+pub struct SseParser {
+    event: String,
+    data: String,
+    last_id: Option<String>,
+    retry: Option<Duration>,
+}
+
+impl SseParser {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Process one line; returns the event a blank line completes.
+    pub fn line(&mut self, line: &str) -> Option<SseEvent> {
+        if line.is_empty() {
+            return self.dispatch();
+        }
+        if line.starts_with(':') {
+            // Comment, typically a keep-alive
+            return None;
+        }
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+        match field {
+            "event" => value.clone_into(&mut self.event),
+            "data" => {
+                self.data.push_str(value);
+                self.data.push('\n');
+            }
+            "id" if !value.contains('\0') => {
+                self.last_id = Some(value.to_string()).filter(|id| !id.is_empty());
+            }
+            "retry" if !value.is_empty() && value.bytes().all(|b| b.is_ascii_digit()) => {
+                self.retry = value.parse().ok().map(Duration::from_millis);
+            }
+            _ => {}
+        }
+        None
+    }
+
+    /// The id to resume from, sent as `Last-Event-ID` when reconnecting.
+    #[must_use]
+    pub fn last_event_id(&self) -> Option<&str> {
+        self.last_id.as_deref()
+    }
+
+    /// The reconnection delay the server asked for with `retry:`.
+    #[must_use]
+    pub fn retry(&self) -> Option<Duration> {
+        self.retry
+    }
+
+    /// Drop a half-received event, e.g. when the connection was lost in the middle of it.
+    pub fn discard_pending(&mut self) {
+        self.event.clear();
+        self.data.clear();
+    }
+
+    fn dispatch(&mut self) -> Option<SseEvent> {
+        let event = std::mem::take(&mut self.event);
+        if self.data.is_empty() {
+            return None;
+        }
+        let mut data = std::mem::take(&mut self.data);
+        data.pop();
+        Some(SseEvent {
+            event: if event.is_empty() {
+                "message".to_string()
+            } else {
+                event
+            },
+            data,
+            id: self.last_id.clone(),
+        })
+    }
+}
+
+/// What the reading thread reports to the printing loop.
+enum Message {
+    Event(SseEvent),
+    /// A non-2xx reply; the stream is over
+    Rejected {
+        status: u16,
+        body: String,
+    },
+    Failed(anyhow::Error),
+}
+
+/// Sets the shared stop flag when the printing loop returns, so the reader thread
+/// stops reconnecting.
+struct StopOnDrop(Arc<AtomicBool>);
+
+impl Drop for StopOnDrop {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+}
+
+/// Send `spec` and print its event stream until the server ends it, `max_events`
+/// events have been printed, or [`interrupt`] is called (exit code 130).
+///
+/// Each event's data is printed like a response body: pretty JSON or tables in the
+/// default output, the text as received with `-o raw`, filtered with `--query`. With
+/// `-o ndjson` every event is one line with its `event`, `id` and `data`.
+///
+/// Credentials work as for other requests: OAuth2 tokens are renewed before each
+/// connection if needed and once more after a 401, and `--log-file` records every
+/// connection (without the streamed body).
+pub fn execute_sse(
+    spec: &RawRequestSpec,
+    config: &ExecutionConfig<'_>,
+    max_events: Option<u64>,
+) -> Result<i32> {
+    // The request timeout would cut the stream off; only connecting is limited
+    let mut builder: ClientBuilder = Client::builder()
+        .user_agent(config.user_agent)
+        .timeout(None::<Duration>);
+    if let Some(secs) = config.conn_timeout_secs {
+        builder = builder.connect_timeout(Duration::from_secs_f64(secs));
+    }
+    let client = builder.build().context("Failed to build HTTP client")?;
+
+    let _running = Running::start();
+    let stop = StopOnDrop(Arc::new(AtomicBool::new(false)));
+    let (tx, rx) = mpsc::channel();
+    {
+        let spec = spec.clone();
+        let stop = Arc::clone(&stop.0);
+        let verbose = config.verbose;
+        thread::spawn(move || read_events(&client, &spec, &tx, &stop, verbose));
+    }
+
+    let layout = TableLayout::of(spec);
+    let mut count: u64 = 0;
+    loop {
+        if INTERRUPTED.load(Ordering::SeqCst) {
+            eprintln!("Interrupted after {} events", count);
+            return Ok(EXIT_INTERRUPTED);
+        }
+        match rx.recv_timeout(POLL_INTERVAL) {
+            Ok(Message::Event(event)) => {
+                print_event(&event, count == 0, config, layout)?;
+                count += 1;
+                if max_events.is_some_and(|max| count >= max) {
+                    return Ok(0);
+                }
+            }
+            Ok(Message::Rejected { status, body }) => {
+                print_response_text(&body, status, config.output, &config.filter, layout)?;
+                return Ok(1);
+            }
+            Ok(Message::Failed(e)) => return Err(e),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return Ok(0),
+        }
+    }
+}
+
+/// Connect, and reconnect while the stream allows it, sending every event to `tx`.
+/// Returns when the stream is over; dropping `tx` tells the printing loop so.
+fn read_events(
+    client: &Client,
+    spec: &RawRequestSpec,
+    tx: &Sender<Message>,
+    stop: &AtomicBool,
+    verbose: bool,
+) {
+    let mut parser = SseParser::new();
+    // Renewed tokens are kept for the reconnects
+    let mut auth = spec.auth.clone();
+    let mut connected = false;
+    let mut reconnects: u32 = 0;
+    loop {
+        let url = match build_url(&spec.base_url, &spec.endpoint) {
+            Ok(url) => url,
+            Err(e) => {
+                let _ = tx.send(Message::Failed(e));
+                return;
+            }
+        };
+        if verbose {
+            eprintln!("-> {} {}", spec.method, url);
+        }
+        let opened = open_stream(client, spec, &mut auth, parser.last_event_id(), verbose);
+        let outcome = match opened {
+            Ok(resp) if resp.status() == reqwest::StatusCode::NO_CONTENT => return,
+            Ok(resp) if !resp.status().is_success() => {
+                let status = resp.status().as_u16();
+                let body = resp.text().unwrap_or_default();
+                let _ = tx.send(Message::Rejected { status, body });
+                return;
+            }
+            Ok(resp) => {
+                if let Err(e) = check_content_type(&resp) {
+                    let _ = tx.send(Message::Failed(e));
+                    return;
+                }
+                connected = true;
+                match forward_events(resp, &mut parser, tx, stop) {
+                    Some(received) => received,
+                    // The printing loop is gone
+                    None => return,
+                }
+            }
+            Err(e) if !connected => {
+                let _ = tx.send(Message::Failed(e));
+                return;
+            }
+            Err(e) => Err(e),
+        };
+        parser.discard_pending();
+
+        // Only a stream with ids can be resumed without repeating events
+        let Some(last_id) = parser.last_event_id() else {
+            return;
+        };
+        reconnects = match outcome {
+            Ok(true) => 0,
+            _ => reconnects + 1,
+        };
+        if reconnects >= MAX_RECONNECTS {
+            let reason = outcome
+                .err()
+                .map_or_else(|| "no new events".to_string(), |e| format!("{:#}", e));
+            let _ = tx.send(Message::Failed(anyhow!(
+                "Gave up on the event stream after {} reconnects: {}",
+                reconnects,
+                reason
+            )));
+            return;
+        }
+        let delay = parser.retry().unwrap_or(DEFAULT_RETRY);
+        if verbose {
+            eprintln!(
+                "Stream closed; reconnecting in {} ms with Last-Event-ID: {}",
+                delay.as_millis(),
+                last_id
+            );
+        }
+        thread::sleep(delay);
+        if stop.load(Ordering::SeqCst) {
+            return;
+        }
+    }
+}
+
+/// Send the request with credentials that are renewed first if they have expired, and
+/// once more after a 401. Every attempt is journaled.
+fn open_stream(
+    client: &Client,
+    spec: &RawRequestSpec,
+    auth: &mut [RequestAuth],
+    last_event_id: Option<&str>,
+    verbose: bool,
+) -> Result<reqwest::blocking::Response> {
+    auth::prepare_auth(client, auth)?;
+    let send = |auth: &[RequestAuth]| {
+        journal::fetch_streaming(
+            client,
+            stream_request(client, spec, auth, last_event_id)?,
+            None,
+            &auth::secret_names(auth),
+        )
+        .context("Failed to open the event stream")
+    };
+    let resp = send(auth)?;
+    if resp.status() != reqwest::StatusCode::UNAUTHORIZED || !auth::renew_rejected(client, auth)? {
+        return Ok(resp);
+    }
+    if verbose {
+        eprintln!("<- 401; retrying with a renewed OAuth2 token");
+    }
+    send(auth)
+}
+
+fn stream_request(
+    client: &Client,
+    spec: &RawRequestSpec,
+    auth: &[RequestAuth],
+    last_event_id: Option<&str>,
+) -> Result<reqwest::blocking::RequestBuilder> {
+    let mut req = build_http_request(client, spec, auth)?;
+    let has_accept = spec.headers.iter().any(|h| {
+        h.split_once(':')
+            .is_some_and(|(name, _)| name.trim().eq_ignore_ascii_case("accept"))
+    });
+    if !has_accept {
+        req = req.header(reqwest::header::ACCEPT, "text/event-stream");
+    }
+    req = req.header(reqwest::header::CACHE_CONTROL, "no-cache");
+    if let Some(id) = last_event_id {
+        req = req.header("Last-Event-ID", id);
+    }
+    Ok(req)
+}
+
+fn check_content_type(resp: &reqwest::blocking::Response) -> Result<()> {
+    let content_type = resp
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("");
+    let mime = content_type.split(';').next().unwrap_or("").trim();
+    if mime.eq_ignore_ascii_case("text/event-stream") {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "Expected a text/event-stream response, got '{}'",
+            content_type
+        ))
+    }
+}
+
+/// Read one connection to its end. Returns whether any event arrived, or the read
+/// error that ended it; `None` once nobody is listening any more.
+fn forward_events(
+    resp: reqwest::blocking::Response,
+    parser: &mut SseParser,
+    tx: &Sender<Message>,
+    stop: &AtomicBool,
+) -> Option<Result<bool>> {
+    let mut reader = BufReader::new(resp);
+    let mut buf = Vec::new();
+    let mut received = false;
+    let mut first = true;
+    loop {
+        buf.clear();
+        match reader.read_until(b'\n', &mut buf) {
+            Ok(0) => return Some(Ok(received)),
+            Ok(_) => {}
+            Err(e) => {
+                return Some(Err(
+                    anyhow::Error::new(e).context("Event stream interrupted")
+                ))
+            }
+        }
+        if stop.load(Ordering::SeqCst) {
+            return None;
+        }
+        let text = String::from_utf8_lossy(&buf);
+        let mut text = text.strip_suffix('\n').unwrap_or(&text);
+        text = text.strip_suffix('\r').unwrap_or(text);
+        if first {
+            text = text.strip_prefix('\u{feff}').unwrap_or(text);
+            first = false;
+        }
+        // A lone CR also ends a line
+        for line in text.split('\r') {
+            if let Some(event) = parser.line(line) {
+                received = true;
+                if tx.send(Message::Event(event)).is_err() {
+                    return None;
+                }
+            }
+        }
+    }
+}
+
+/// Print one event's data as the output options ask, then flush so it shows at once.
+fn print_event(
+    event: &SseEvent,
+    first: bool,
+    config: &ExecutionConfig<'_>,
+    layout: TableLayout,
+) -> Result<()> {
+    use std::io::Write;
+
+    let parsed = serde_json::from_str::<Value>(&event.data);
+    match config.output {
+        OutputFormat::Quiet => {}
+        OutputFormat::Raw if config.filter.is_empty() => println!("{}", event.data),
+        OutputFormat::Ndjson => {
+            let data = match parsed {
+                Ok(value) if config.filter.is_empty() => value,
+                Ok(value) => config.filter.apply(value)?,
+                Err(_) => Value::String(event.data.clone()),
+            };
+            let line = json!({ "event": event.event, "id": event.id, "data": data });
+            println!("{}", serde_json::to_string(&line)?);
+        }
+        // A filter cannot apply to text data such as a closing "[DONE]"
+        _ if parsed.is_err() && !config.filter.is_empty() => {}
+        output => {
+            // Human views of consecutive events are set apart, named events under their name
+            if output == OutputFormat::Human && !config.filter.raw_value {
+                if !first {
+                    println!();
+                }
+                if event.event != "message" {
+                    println!(
+                        "{}",
+                        table::paint_heading(&event.event, table::settings().color)
+                    );
+                }
+            }
+            print_response_text(&event.data, 200, output, &config.filter, layout)?;
+        }
+    }
+    std::io::stdout().flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(stream: &str) -> (Vec<SseEvent>, SseParser) {
+        let mut parser = SseParser::new();
+        let events = stream.split('\n').filter_map(|l| parser.line(l)).collect();
+        (events, parser)
+    }
+
+    // ==================== connection tests ====================
+
+    #[test]
+    fn test_stream_gets_oauth2_token_and_is_journaled() {
+        use crate::auth::{OAuth2Client, OAuth2Endpoints, OAuth2Session, TokenCache};
+        use crate::test_server::{Reply, TestServer};
+        let server = TestServer::start(|req| match req.target.as_str() {
+            "/token" => Reply::json(200, r#"{"access_token":"fresh"}"#),
+            _ if req.header("authorization") == Some("Bearer fresh") => {
+                Reply::sse(200, "data: {\"n\":1}\n\n")
+            }
+            _ => Reply::json(401, "{}"),
+        });
+        let dir = std::env::temp_dir().join(format!("rclib-sse-{}", uuid::Uuid::new_v4()));
+        let cmd: crate::mapping::CommandSpec =
+            serde_yaml::from_str("{name: tail, method: GET, endpoint: /events, stream: sse}")
+                .unwrap();
+        let spec = crate::try_build_request_from_command(
+            Some(server.url.clone()),
+            &cmd,
+            &std::collections::HashMap::new(),
+            &std::collections::HashSet::new(),
+            false,
+        )
+        .unwrap();
+        let crate::RequestSpec::Simple(mut spec) = spec else {
+            panic!("expected a single request");
+        };
+        // Client credentials, no token yet
+        spec.auth = vec![RequestAuth::OAuth2(Box::new(OAuth2Session {
+            scheme: "oauth".to_string(),
+            endpoints: OAuth2Endpoints {
+                token_url: format!("{}/token", server.url),
+                refresh_url: None,
+                client_credentials: true,
+            },
+            client: OAuth2Client {
+                client_id: Some("app".to_string()),
+                client_secret: Some("s3".to_string()),
+                scopes: Vec::new(),
+            },
+            cache: TokenCache::new(dir.join("tokens.json").to_str().unwrap()),
+            token: None,
+        }))];
+        let mut config = ExecutionConfig::new("test");
+        config.output = OutputFormat::Quiet;
+
+        let log = dir.join("log.jsonl");
+        std::fs::create_dir_all(&dir).unwrap();
+        let installed =
+            journal::install_scoped(journal::Journal::open(log.to_str().unwrap(), "tail").unwrap());
+        assert_eq!(execute_sse(&spec, &config, None).unwrap(), 0);
+        drop(installed);
+
+        let targets: Vec<String> = server.requests().into_iter().map(|r| r.target).collect();
+        assert_eq!(targets, ["/token", "/events"]);
+        // Other tests may send requests while the journal is installed
+        let events_url = format!("{}/events", server.url);
+        let logged: Vec<Value> = std::fs::read_to_string(&log)
+            .unwrap()
+            .lines()
+            .map(|l| serde_json::from_str::<Value>(l).unwrap())
+            .filter(|l| l["request"]["url"] == events_url.as_str())
+            .collect();
+        assert_eq!(logged.len(), 1);
+        assert_eq!(
+            logged[0]["request"]["headers"]["authorization"],
+            "Bearer ****"
+        );
+        assert_eq!(logged[0]["response"]["status"], 200);
+        assert_eq!(logged[0]["response"]["body"], "<streamed body>");
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    // ==================== parser tests ====================
+
+    #[test]
+    fn test_parse_events() {
+        let (events, parser) = parse(
+            ": keep-alive\n\
+             data: first\n\
+             \n\
+             event: update\n\
+             id: 7\n\
+             data: {\"a\":1,\n\
+             data:  \"b\":2}\n\
+             \n\
+             data\n\
+             \n\
+             retry: 250\n\
+             \n",
+        );
+        assert_eq!(
+            events,
+            vec![
+                SseEvent {
+                    event: "message".to_string(),
+                    data: "first".to_string(),
+                    id: None,
+                },
+                SseEvent {
+                    event: "update".to_string(),
+                    data: "{\"a\":1,\n \"b\":2}".to_string(),
+                    id: Some("7".to_string()),
+                },
+                SseEvent {
+                    event: "message".to_string(),
+                    data: String::new(),
+                    id: Some("7".to_string()),
+                },
+            ]
+        );
+        assert_eq!(parser.last_event_id(), Some("7"));
+        assert_eq!(parser.retry(), Some(Duration::from_millis(250)));
+    }
+
+    #[test]
+    fn test_parse_ignores_incomplete_and_invalid_fields() {
+        // A block without data dispatches nothing, but its id still counts
+        let (events, parser) = parse("event: ping\nid: 3\n\nretry: soon\nunknown: x\n\n");
+        assert!(events.is_empty());
+        assert_eq!(parser.last_event_id(), Some("3"));
+        assert_eq!(parser.retry(), None);
+
+        // An empty id resets it; the event type does not leak into the next event
+        let (events, parser) = parse("event: a\ndata: 1\n\nid:\ndata: 2\n\n");
+        assert_eq!(events[1].event, "message");
+        assert_eq!(parser.last_event_id(), None);
+
+        let mut parser = SseParser::new();
+        parser.line("data: half");
+        parser.discard_pending();
+        assert_eq!(parser.line(""), None);
+    }
+}
@@ -0,0 +1,591 @@
+//! Text tables for the human-readable output.
+//!
+//! `drive_command` installs the command's settings (style, width, colors, column depth)
+//! for as long as it runs; outside a command, tables use [`TableSettings::default`].
+
+use std::borrow::Cow;
+use std::io::IsTerminal;
+use std::sync::{Arc, RwLock};
+
+use once_cell::sync::Lazy;
+use serde_json::{Map, Value};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+use crate::{scalar_to_string, table_columns};
+
+/// Nesting levels turned into columns by default: `dimensions.width`, not `a.b.c`.
+pub const DEFAULT_COLUMN_DEPTH: usize = 2;
+
+/// Columns are never squeezed below this width (or their header's longest word).
+const MIN_COLUMN_WIDTH: usize = 6;
+
+const ELLIPSIS: char = '…';
+
+const BOLD: &str = "\x1b[1m";
+const DIM: &str = "\x1b[2m";
+const GREEN: &str = "\x1b[32m";
+const RED: &str = "\x1b[31m";
+const RESET: &str = "\x1b[0m";
+
+static SETTINGS: Lazy<RwLock<Arc<TableSettings>>> =
+    Lazy::new(|| RwLock::new(Arc::new(TableSettings::default())));
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TableStyle {
+    /// `+---+` rules and `|` between columns
+    #[default]
+    Boxed,
+    /// No borders; columns separated by two spaces
+    Compact,
+}
+
+impl TableStyle {
+    /// Names accepted by `--table-style`.
+    pub const NAMES: &'static [&'static str] = &["boxed", "compact"];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "boxed" => Some(Self::Boxed),
+            "compact" => Some(Self::Compact),
+            _ => None,
+        }
+    }
+}
+
+/// How tables are laid out. The default is what tests and library callers get:
+/// boxed, unbounded width, no colors, nested objects flattened one level.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableSettings {
+    pub style: TableStyle,
+    /// Width of a whole line, in terminal columns; `None` leaves lines unbounded
+    pub max_width: Option<usize>,
+    /// Wrap long cells onto more lines instead of cutting them with an ellipsis
+    pub wrap: bool,
+    /// Bold headers, dimmed nulls, green/red booleans
+    pub color: bool,
+    /// How many levels of nested objects become columns when there is no table view:
+    /// 1 shows only top-level keys, 2 adds `dimensions.width`, and so on
+    pub column_depth: usize,
+}
+
+impl Default for TableSettings {
+    fn default() -> Self {
+        Self {
+            style: TableStyle::default(),
+            max_width: None,
+            wrap: false,
+            color: false,
+            column_depth: DEFAULT_COLUMN_DEPTH,
+        }
+    }
+}
+
+impl TableSettings {
+    /// Settings for printing to stdout. The width is `$COLUMNS` if set, else the
+    /// terminal's; output to a pipe or file is unbounded. Colors are on only for a
+    /// terminal, and never with `NO_COLOR` set or `no_color`.
+    pub fn for_stdout(style: TableStyle, wrap: bool, no_color: bool) -> Self {
+        let tty = std::io::stdout().is_terminal();
+        let max_width = std::env::var("COLUMNS")
+            .ok()
+            .and_then(|c| c.trim().parse::<usize>().ok())
+            .filter(|c| *c > 0)
+            .or_else(|| {
+                tty.then(terminal_size::terminal_size)
+                    .flatten()
+                    .map(|(w, _)| usize::from(w.0))
+            });
+        let no_color_env = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+        Self {
+            style,
+            max_width,
+            wrap,
+            color: tty && !no_color && !no_color_env,
+            ..Self::default()
+        }
+    }
+}
+
+/// Use `settings` until the returned guard is dropped, which puts back the settings that
+/// were installed before (if these are still the current ones).
+pub fn install_scoped(settings: TableSettings) -> Installed {
+    let settings = Arc::new(settings);
+    let previous = std::mem::replace(
+        &mut *SETTINGS.write().unwrap_or_else(|e| e.into_inner()),
+        Arc::clone(&settings),
+    );
+    Installed { settings, previous }
+}
+
+/// Keeps table settings installed; see [`install_scoped`].
+#[must_use = "the settings are reverted when the guard is dropped"]
+pub struct Installed {
+    settings: Arc<TableSettings>,
+    previous: Arc<TableSettings>,
+}
+
+impl Drop for Installed {
+    fn drop(&mut self) {
+        let mut current = SETTINGS.write().unwrap_or_else(|e| e.into_inner());
+        if Arc::ptr_eq(&current, &self.settings) {
+            *current = Arc::clone(&self.previous);
+        }
+    }
+}
+
+/// The installed settings.
+pub fn settings() -> TableSettings {
+    TableSettings::clone(&SETTINGS.read().unwrap_or_else(|e| e.into_inner()))
+}
+
+/// Width of `s` in terminal columns (CJK and emoji take two, combining marks none).
+pub fn display_width(s: &str) -> usize {
+    UnicodeWidthStr::width(s)
+}
+
+/// Cut `s` to at most `width` columns, ending it with `…` when anything was cut.
+pub fn truncate(s: &str, width: usize) -> String {
+    if display_width(s) <= width {
+        return s.to_string();
+    }
+    let mut out = String::new();
+    let mut used = 0;
+    for ch in s.chars() {
+        let w = ch.width().unwrap_or(0);
+        if used + w + 1 > width {
+            break;
+        }
+        out.push(ch);
+        used += w;
+    }
+    if width > 0 {
+        out.push(ELLIPSIS);
+    }
+    out
+}
+
+/// Break `s` into lines of at most `width` columns, at spaces where possible.
+pub fn wrap(s: &str, width: usize) -> Vec<String> {
+    let width = width.max(1);
+    let mut lines = Vec::new();
+    for paragraph in s.lines() {
+        let mut line = String::new();
+        for word in paragraph.split(' ') {
+            let sep = usize::from(!line.is_empty());
+            if display_width(&line) + sep + display_width(word) <= width {
+                if sep == 1 {
+                    line.push(' ');
+                }
+                line.push_str(word);
+                continue;
+            }
+            if !line.is_empty() {
+                lines.push(std::mem::take(&mut line));
+            }
+            // Words longer than a line are split wherever they run out of room
+            for ch in word.chars() {
+                if display_width(&line) + ch.width().unwrap_or(0) > width {
+                    lines.push(std::mem::take(&mut line));
+                }
+                line.push(ch);
+            }
+        }
+        lines.push(line);
+    }
+    if lines.is_empty() {
+        lines.push(String::new());
+    }
+    lines
+}
+
+/// `text` followed by spaces up to `width` columns, colored with `paint` if given.
+fn pad(text: &str, width: usize, paint: Option<&str>) -> String {
+    let fill = " ".repeat(width.saturating_sub(display_width(text)));
+    match paint {
+        Some(code) if !text.is_empty() => format!("{}{}{}{}", code, text, RESET, fill),
+        _ => format!("{}{}", text, fill),
+    }
+}
+
+/// `text` for `value`, colored if `color` is on and the value is null or a boolean.
+pub fn paint_value(value: &Value, text: &str, color: bool) -> String {
+    match value_paint(value, color) {
+        Some(code) => format!("{}{}{}", code, text, RESET),
+        None => text.to_string(),
+    }
+}
+
+/// `text` in bold if `color` is on, like table headers.
+pub fn paint_heading(text: &str, color: bool) -> String {
+    if color {
+        format!("{}{}{}", BOLD, text, RESET)
+    } else {
+        text.to_string()
+    }
+}
+
+/// The color for a cell holding `value`, if colors are on.
+fn value_paint(value: &Value, color: bool) -> Option<&'static str> {
+    if !color {
+        return None;
+    }
+    match value {
+        Value::Null => Some(DIM),
+        Value::Bool(true) => Some(GREEN),
+        Value::Bool(false) => Some(RED),
+        _ => None,
+    }
+}
+
+/// Shrink the widest columns, one column at a time, until their total fits `budget`
+/// or every column is at its minimum.
+fn fit_widths(widths: &mut [usize], mins: &[usize], budget: usize) {
+    while widths.iter().sum::<usize>() > budget {
+        let widest = (0..widths.len())
+            .filter(|&i| widths[i] > mins[i])
+            .max_by_key(|&i| widths[i]);
+        match widest {
+            Some(i) => widths[i] -= 1,
+            None => break,
+        }
+    }
+}
+
+const TOTAL_KEYS: &[&str] = &[
+    "total",
+    "totalCount",
+    "total_count",
+    "totalResults",
+    "total_results",
+];
+const OFFSET_KEYS: &[&str] = &["skip", "offset"];
+const LIMIT_KEYS: &[&str] = &["limit", "per_page", "perPage", "page_size", "pageSize"];
+const PAGE_KEYS: &[&str] = &["page"];
+
+/// Whether `key` is a list envelope field summarized by [`envelope_footer`].
+pub fn is_envelope_key(key: &str) -> bool {
+    [TOTAL_KEYS, OFFSET_KEYS, LIMIT_KEYS, PAGE_KEYS]
+        .iter()
+        .any(|keys| keys.contains(&key))
+}
+
+/// "Showing 1–30 of 194" for a page of `shown` items, from the envelope's total and
+/// skip/offset (or 1-based page times limit). `None` when the envelope has neither a
+/// total nor a position.
+pub fn envelope_footer(envelope: &Map<String, Value>, shown: usize) -> Option<String> {
+    let field = |keys: &[&str]| {
+        keys.iter().find_map(|k| match envelope.get(*k)? {
+            Value::Number(n) => n.as_u64(),
+            Value::String(s) => s.parse().ok(),
+            _ => None,
+        })
+    };
+    let total = field(TOTAL_KEYS);
+    let limit = field(LIMIT_KEYS);
+    let skip = field(OFFSET_KEYS).or_else(|| {
+        let page = field(PAGE_KEYS)?;
+        Some(page.saturating_sub(1) * limit.unwrap_or(shown as u64))
+    });
+    if total.is_none() && skip.is_none() {
+        return None;
+    }
+    if shown == 0 {
+        return Some(format!("Showing 0 of {}", total.unwrap_or(0)));
+    }
+    let first = skip.unwrap_or(0) + 1;
+    let last = skip.unwrap_or(0) + shown as u64;
+    Some(match total {
+        Some(total) => format!("Showing {}–{} of {}", first, last, total),
+        None => format!("Showing {}–{}", first, last),
+    })
+}
+
+/// Render a list as a table with a column per `table_view` entry (or derived column),
+/// one line per text line ending in `\n`.
+pub fn render(arr: &[Value], table_view: Option<&Vec<String>>, settings: &TableSettings) -> String {
+    if arr.is_empty() {
+        return "(empty)\n".to_string();
+    }
+
+    let col_specs = table_columns(arr, table_view, settings.column_depth);
+    if col_specs.is_empty() {
+        return arr
+            .iter()
+            .enumerate()
+            .map(|(i, item)| format!("{:<6} {}\n", i, scalar_to_string(item)))
+            .collect();
+    }
+
+    // Header labels are humanized and split onto one line per word
+    let header_lines: Vec<Vec<String>> = col_specs
+        .iter()
+        .map(|c| {
+            c.header()
+                .split_whitespace()
+                .map(|s| s.to_string())
+                .collect()
+        })
+        .collect();
+    let header_height = header_lines.iter().map(|v| v.len()).max().unwrap_or(1);
+
+    // Cell text and the value it came from (for colors), for each object row
+    let rows: Vec<Vec<(String, Cow<Value>)>> = arr
+        .iter()
+        .filter(|item| item.is_object())
+        .map(|item| {
+            col_specs
+                .iter()
+                .map(|c| {
+                    let value = c.value(item);
+                    (c.format(&value), value)
+                })
+                .collect()
+        })
+        .collect();
+
+    let header_word_widths: Vec<usize> = header_lines
+        .iter()
+        .map(|parts| parts.iter().map(|s| display_width(s)).max().unwrap_or(0))
+        .collect();
+    let mut widths = header_word_widths.clone();
+    for row in &rows {
+        for (i, (text, _)) in row.iter().enumerate() {
+            let longest = text.lines().map(display_width).max().unwrap_or(0);
+            widths[i] = widths[i].max(longest);
+        }
+    }
+    if let Some(max_width) = settings.max_width {
+        let n = widths.len();
+        let overhead = match settings.style {
+            TableStyle::Boxed => 3 * n + 1,
+            TableStyle::Compact => 2 * (n - 1),
+        };
+        let mins: Vec<usize> = widths
+            .iter()
+            .zip(&header_word_widths)
+            .map(|(w, h)| (*w).min(MIN_COLUMN_WIDTH.max(*h)))
+            .collect();
+        fit_widths(&mut widths, &mins, max_width.saturating_sub(overhead));
+    }
+
+    let fit = |text: &str, width: usize| -> Vec<String> {
+        if settings.wrap {
+            wrap(text, width)
+        } else {
+            let flat = text.replace(['\n', '\r', '\t'], " ");
+            vec![truncate(&flat, width)]
+        }
+    };
+    let line = |cells: Vec<String>| -> String {
+        match settings.style {
+            TableStyle::Boxed => format!("| {} |\n", cells.join(" | ")),
+            TableStyle::Compact => format!("{}\n", cells.join("  ").trim_end()),
+        }
+    };
+
+    let mut out = String::new();
+    let rule: String = match settings.style {
+        TableStyle::Boxed => {
+            let mut rule = String::from("+");
+            for w in &widths {
+                rule.push_str(&"-".repeat(w + 2));
+                rule.push('+');
+            }
+            rule.push('\n');
+            rule
+        }
+        TableStyle::Compact => String::new(),
+    };
+    out.push_str(&rule);
+
+    let header_paint = settings.color.then_some(BOLD);
+    for line_idx in 0..header_height {
+        let cells = header_lines
+            .iter()
+            .zip(&widths)
+            .map(|(parts, &w)| {
+                let word = parts.get(line_idx).map(String::as_str).unwrap_or("");
+                pad(&truncate(word, w), w, header_paint)
+            })
+            .collect();
+        out.push_str(&line(cells));
+    }
+    out.push_str(&rule);
+
+    for row in &rows {
+        let cells: Vec<(Vec<String>, Option<&str>)> = row
+            .iter()
+            .zip(&widths)
+            .map(|((text, value), &w)| (fit(text, w), value_paint(value, settings.color)))
+            .collect();
+        let height = cells
+            .iter()
+            .map(|(lines, _)| lines.len())
+            .max()
+            .unwrap_or(1);
+        for line_idx in 0..height {
+            let parts = cells
+                .iter()
+                .zip(&widths)
+                .map(|((lines, paint), &w)| {
+                    let text = lines.get(line_idx).map(String::as_str).unwrap_or("");
+                    pad(text, w, *paint)
+                })
+                .collect();
+            out.push_str(&line(parts));
+        }
+    }
+    out.push_str(&rule);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn plain() -> TableSettings {
+        TableSettings::default()
+    }
+
+    // ==================== width tests ====================
+
+    #[test]
+    fn test_display_width_and_truncate() {
+        assert_eq!(display_width("abc"), 3);
+        assert_eq!(display_width("日本"), 4);
+        assert_eq!(display_width("café"), 4);
+        assert_eq!(truncate("hello world", 20), "hello world");
+        assert_eq!(truncate("hello world", 6), "hello…");
+        assert_eq!(truncate("日本語テキスト", 6), "日本…");
+        assert_eq!(truncate("日本語", 4), "日…");
+    }
+
+    #[test]
+    fn test_wrap() {
+        assert_eq!(wrap("the quick brown fox", 10), ["the quick", "brown fox"]);
+        assert_eq!(wrap("abcdefghij", 4), ["abcd", "efgh", "ij"]);
+        assert_eq!(wrap("one\ntwo", 10), ["one", "two"]);
+        assert_eq!(wrap("", 5), [""]);
+    }
+
+    // ==================== render tests ====================
+
+    #[test]
+    fn test_render_boxed_unicode_alignment() {
+        let arr = vec![
+            json!({"id": 1, "name": "日本"}),
+            json!({"id": 22, "name": "ab"}),
+        ];
+        let out = render(&arr, None, &plain());
+        assert_eq!(
+            out,
+            "+----+------+\n\
+             | Id | Name |\n\
+             +----+------+\n\
+             | 1  | 日本 |\n\
+             | 22 | ab   |\n\
+             +----+------+\n"
+        );
+    }
+
+    #[test]
+    fn test_render_fits_max_width() {
+        let long = "a very long description that would not fit on a narrow terminal";
+        let arr = vec![json!({"id": 1, "description": long})];
+        let settings = TableSettings {
+            max_width: Some(30),
+            ..plain()
+        };
+        let out = render(&arr, None, &settings);
+        for line in out.lines() {
+            assert!(display_width(line) <= 30, "{:?}", line);
+        }
+        assert!(out.contains("| 1  | a very long descript… |"), "{}", out);
+
+        let wrapped = render(
+            &arr,
+            None,
+            &TableSettings {
+                wrap: true,
+                ..settings
+            },
+        );
+        assert!(
+            wrapped.contains("| 1  | a very long           |"),
+            "{}",
+            wrapped
+        );
+        assert!(
+            wrapped.contains("|    | description that      |"),
+            "{}",
+            wrapped
+        );
+        for line in wrapped.lines() {
+            assert!(display_width(line) <= 30, "{:?}", line);
+        }
+    }
+
+    #[test]
+    fn test_render_compact_and_colors() {
+        let arr = vec![json!({"id": 1, "active": true, "note": null})];
+        let compact = TableSettings {
+            style: TableStyle::Compact,
+            ..plain()
+        };
+        assert_eq!(
+            render(&arr, None, &compact),
+            "Id  Active  Note\n1   true    null\n"
+        );
+
+        let colored = render(
+            &arr,
+            None,
+            &TableSettings {
+                color: true,
+                ..compact
+            },
+        );
+        assert!(colored.contains("\x1b[1mId\x1b[0m"));
+        assert!(colored.contains("\x1b[32mtrue\x1b[0m"));
+        assert!(colored.contains("\x1b[2mnull\x1b[0m"));
+        // Padding stays outside the color codes
+        assert!(colored.starts_with("\x1b[1mId\x1b[0m  "));
+    }
+
+    #[test]
+    fn test_envelope_footer() {
+        let footer = |envelope: Value, shown| envelope_footer(envelope.as_object().unwrap(), shown);
+        assert_eq!(
+            footer(json!({"total": 194, "skip": 0, "limit": 30}), 30).as_deref(),
+            Some("Showing 1–30 of 194")
+        );
+        assert_eq!(
+            footer(json!({"total": 194, "skip": 180, "limit": 30}), 14).as_deref(),
+            Some("Showing 181–194 of 194")
+        );
+        assert_eq!(
+            footer(json!({"page": 3, "per_page": 10, "total_count": "95"}), 10).as_deref(),
+            Some("Showing 21–30 of 95")
+        );
+        assert_eq!(
+            footer(json!({"offset": 5}), 2).as_deref(),
+            Some("Showing 6–7")
+        );
+        assert_eq!(
+            footer(json!({"total": 0, "skip": 0}), 0).as_deref(),
+            Some("Showing 0 of 0")
+        );
+        assert_eq!(footer(json!({"limit": 30, "name": "x"}), 3), None);
+        assert!(is_envelope_key("totalCount") && !is_envelope_key("name"));
+    }
+
+    #[test]
+    fn test_render_empty_and_scalars() {
+        assert_eq!(render(&[], None, &plain()), "(empty)\n");
+        assert_eq!(
+            render(&[json!("a"), json!(2)], None, &plain()),
+            "0      a\n1      2\n"
+        );
+    }
+}
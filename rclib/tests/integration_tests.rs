@@ -8,7 +8,10 @@ use rclib::{
         build_cli, collect_subcommand_path, collect_vars_from_matches, validate_handlers,
         HandlerRegistry,
     },
-    mapping::{parse_mapping_root, MappingRoot},
+    mapping::{
+        generate_from_openapi, parse_mapping_root, validate_mapping, GenerateOptions, MappingRoot,
+    },
+    openapi::check_mapping_against_openapi,
     ExecutionConfig, OutputFormat, RequestSpec,
 };
 
@@ -385,6 +388,7 @@ fn test_execution_config_builder_pattern() {
         count: Some(10),
         duration_secs: 0,
         concurrency: 4,
+        filter: Default::default(),
     };
 
     assert_eq!(config.output, OutputFormat::Json);
@@ -473,3 +477,65 @@ commands:
         panic!("Expected Scenario request spec");
     }
 }
+
+// ==================== Mapping Validation Integration ====================
+
+#[test]
+fn test_example_mapping_passes_lint() {
+    let yaml = include_str!("../../dummyjson-cli/src/mapping.yaml");
+    let root = parse_mapping_root(yaml).unwrap();
+    let diags = validate_mapping(&root);
+    assert!(diags.is_empty(), "mapping.yaml has issues: {:?}", diags);
+}
+
+#[test]
+fn test_example_mapping_omits_unset_query_params() {
+    let root = parse_mapping_root(include_str!("../../dummyjson-cli/src/mapping.yaml")).unwrap();
+    let (app, path_map) = build_cli(&root, "https://dummyjson.com");
+    let matches = app
+        .try_get_matches_from(["dummyjson-cli", "products", "list", "--limit", "5"])
+        .unwrap();
+    let (path, leaf) = collect_subcommand_path(&matches);
+    let cmd = &path_map[&path];
+    let (vars, selected, _) = collect_vars_from_matches(cmd, leaf);
+
+    let RequestSpec::Simple(raw) = build_request_from_command(None, cmd, &vars, &selected) else {
+        panic!("Expected RequestSpec::Simple");
+    };
+    // --select has no default, so no empty `select=` is sent
+    assert_eq!(raw.endpoint, "/products?limit=5&skip=0&delay=0");
+}
+
+#[test]
+fn test_example_mapping_types_args_from_openapi() {
+    let api = rclib::parse_openapi(include_str!(
+        "../../dummyjson-cli/src/dummyjson-openapi-spec.yaml"
+    ))
+    .unwrap();
+    let root = parse_mapping_root(include_str!("../../dummyjson-cli/src/mapping.yaml")).unwrap();
+    let build = || rclib::cli::build_cli_with_openapi(&root, "https://dummyjson.com", Some(&api)).0;
+
+    // --limit is an integer and --delay is capped at 5000 in the spec
+    assert!(build()
+        .try_get_matches_from(["dummyjson-cli", "products", "list", "--limit", "abc"])
+        .is_err());
+    assert!(build()
+        .try_get_matches_from(["dummyjson-cli", "products", "list", "--delay", "9000"])
+        .is_err());
+    assert!(build()
+        .try_get_matches_from(["dummyjson-cli", "products", "list", "--limit", "5"])
+        .is_ok());
+}
+
+#[test]
+fn test_mapping_generated_from_example_spec_is_consistent() {
+    let api = rclib::parse_openapi(include_str!(
+        "../../dummyjson-cli/src/dummyjson-openapi-spec.yaml"
+    ))
+    .unwrap();
+    let spec = generate_from_openapi(&api, &GenerateOptions::default());
+    let yaml = serde_yaml::to_string(&spec).unwrap();
+    let root = parse_mapping_root(&yaml).unwrap();
+    assert!(validate_mapping(&root).is_empty());
+    assert!(check_mapping_against_openapi(&root, &api).is_empty());
+}